@@ -158,8 +158,9 @@ impl TiKVServer {
 
         let store_path = Path::new(&config.storage.data_dir).to_owned();
 
-        let (resolve_worker, resolver) = resolve::new_resolver(Arc::clone(&pd_client))
-            .unwrap_or_else(|e| fatal!("failed to start address resolver: {}", e));
+        let (resolve_worker, resolver) =
+            resolve::new_resolver(Arc::clone(&pd_client), config.server.resolve_cache_ttl.into())
+                .unwrap_or_else(|e| fatal!("failed to start address resolver: {}", e));
 
         // Initialize raftstore channels.
         let (router, system) = fsm::create_raft_batch_system(&config.raft_store);
@@ -355,7 +356,11 @@ impl TiKVServer {
         if let Some(encrypted_env) = encrypted_env {
             kv_db_opts.set_env(encrypted_env);
         }
-        let kv_cfs_opts = self.config.rocksdb.build_cf_opts(&block_cache);
+        let kv_cfs_opts = if self.config.storage.enable_ttl {
+            self.config.rocksdb.build_cf_opts_with_ttl(&block_cache)
+        } else {
+            self.config.rocksdb.build_cf_opts(&block_cache)
+        };
         let db_path = self
             .store_path
             .join(Path::new(storage::config::DEFAULT_ROCKSDB_SUB_DIR));
@@ -386,7 +391,9 @@ impl TiKVServer {
             Box::new(DBConfigManger::new(engines.raft.clone(), DBType::Raft)),
         );
 
-        let engine = RaftKv::new(raft_router.clone());
+        let mut engine = RaftKv::new(raft_router.clone());
+        engine.set_prefer_lease_read(self.config.server.prefer_lease_read);
+        engine.set_store_meta(store_meta.clone());
 
         self.engines = Some(Engines {
             engines,
@@ -582,13 +589,16 @@ impl TiKVServer {
         initial_metric(&self.config.metric, Some(node.id()));
 
         // Start auto gc
-        let auto_gc_config = AutoGcConfig::new(
-            self.pd_client.clone(),
-            self.region_info_accessor.clone(),
-            node.id(),
-        );
-        if let Err(e) = gc_worker.start_auto_gc(auto_gc_config) {
-            fatal!("failed to start auto_gc on storage, error: {}", e);
+        if self.config.gc.enable_auto_gc {
+            let mut auto_gc_config = AutoGcConfig::new(
+                self.pd_client.clone(),
+                self.region_info_accessor.clone(),
+                node.id(),
+            );
+            auto_gc_config.poll_safe_point_interval = self.config.gc.poll_safe_point_interval.into();
+            if let Err(e) = gc_worker.start_auto_gc(auto_gc_config) {
+                fatal!("failed to start auto_gc on storage, error: {}", e);
+            }
         }
 
         // Start CDC.
@@ -760,6 +770,9 @@ impl TiKVServer {
             .server
             .start(server_config, self.security_mgr.clone())
             .unwrap_or_else(|e| fatal!("failed to start server: {}", e));
+        // The node has been bootstrapped and raftstore is running, so the
+        // health service can report `SERVING` from now on.
+        server.server.mark_ready();
 
         // Create a status server.
         let status_enabled =
@@ -768,6 +781,7 @@ impl TiKVServer {
             let mut status_server = Box::new(StatusServer::new(
                 self.config.server.status_thread_pool_size,
                 server.pd_sender.clone(),
+                self.config.server.enable_debug_profiling,
             ));
             // Start the status server.
             if let Err(e) = status_server.start(
@@ -791,6 +805,10 @@ impl TiKVServer {
             .stop()
             .unwrap_or_else(|e| fatal!("failed to stop server: {}", e));
 
+        // The gRPC server is stopped, so no new downloads can start; wait for
+        // the in-flight ones and clean up their partial files.
+        servers.importer.shutdown();
+
         servers.node.stop();
         self.region_info_accessor.stop();
         if let Some(lm) = servers.lock_mgr.as_mut() {
@@ -9,7 +9,7 @@ use std::sync::*;
 use std::time::*;
 
 use engine::{IterOption, DATA_KEY_PREFIX_LEN, DB};
-use engine_traits::{name_to_cf, CfName};
+use engine_traits::{name_to_cf, CfName, SstCompressionType};
 use external_storage::*;
 use futures::channel::mpsc::*;
 use kvproto::backup::*;
@@ -47,6 +47,9 @@ struct Request {
     cancel: Arc<AtomicBool>,
     is_raw_kv: bool,
     cf: CfName,
+    compression_type: Option<SstCompressionType>,
+    use_range_file_names: bool,
+    write_manifest: bool,
 }
 
 /// Backup Task.
@@ -97,6 +100,7 @@ impl Task {
         let cf = name_to_cf(req.get_cf()).ok_or_else(|| crate::Error::InvalidCf {
             cf: req.get_cf().to_owned(),
         })?;
+        let compression_type = to_sst_compression_type(req.get_compression_type());
 
         // Check storage backend eagerly.
         create_storage(req.get_storage_backend())?;
@@ -112,6 +116,9 @@ impl Task {
                 cancel: cancel.clone(),
                 is_raw_kv: req.get_is_raw_kv(),
                 cf,
+                compression_type,
+                use_range_file_names: req.get_use_range_file_names(),
+                write_manifest: req.get_write_manifest(),
             },
             concurrency: req.get_concurrency(),
             resp,
@@ -264,14 +271,16 @@ impl BackupRange {
         file_name: String,
         backup_ts: TimeStamp,
         start_ts: TimeStamp,
+        compression_type: Option<SstCompressionType>,
     ) -> Result<(Vec<File>, Statistics)> {
-        let mut writer = match BackupWriter::new(db, &file_name, storage.limiter.clone()) {
-            Ok(w) => w,
-            Err(e) => {
-                error!("backup writer failed"; "error" => ?e);
-                return Err(e);
-            }
-        };
+        let mut writer =
+            match BackupWriter::new(db, &file_name, compression_type, storage.limiter.clone()) {
+                Ok(w) => w,
+                Err(e) => {
+                    error!("backup writer failed"; "error" => ?e);
+                    return Err(e);
+                }
+            };
         let stat = match self.backup(&mut writer, engine, backup_ts, start_ts) {
             Ok(s) => s,
             Err(e) => return Err(e),
@@ -293,8 +302,15 @@ impl BackupRange {
         storage: &LimitedStorage,
         file_name: String,
         cf: CfName,
+        compression_type: Option<SstCompressionType>,
     ) -> Result<(Vec<File>, Statistics)> {
-        let mut writer = match BackupRawKVWriter::new(db, &file_name, cf, storage.limiter.clone()) {
+        let mut writer = match BackupRawKVWriter::new(
+            db,
+            &file_name,
+            cf,
+            compression_type,
+            storage.limiter.clone(),
+        ) {
             Ok(w) => w,
             Err(e) => {
                 error!("backup writer failed"; "error" => ?e);
@@ -318,6 +334,50 @@ impl BackupRange {
 
 type BackupRes = (Vec<File>, Statistics);
 
+/// A simple counting semaphore that bounds how many region sub-tasks of one backup task are
+/// processed concurrently.
+struct Semaphore {
+    permits: Mutex<usize>,
+    cond: Condvar,
+}
+
+impl Semaphore {
+    fn new(permits: usize) -> Semaphore {
+        assert!(permits > 0);
+        Semaphore {
+            permits: Mutex::new(permits),
+            cond: Condvar::new(),
+        }
+    }
+
+    /// Blocks until a permit is available or the task is canceled. Returns false when canceled,
+    /// so that queued sub-tasks stop promptly instead of waiting for a permit.
+    fn acquire(&self, cancel: &AtomicBool) -> bool {
+        let mut permits = self.permits.lock().unwrap();
+        loop {
+            if cancel.load(Ordering::SeqCst) {
+                return false;
+            }
+            if *permits > 0 {
+                *permits -= 1;
+                return true;
+            }
+            // Wake up periodically to notice cancellation even when no permit is released.
+            let (p, _) = self
+                .cond
+                .wait_timeout(permits, Duration::from_millis(100))
+                .unwrap();
+            permits = p;
+        }
+    }
+
+    fn release(&self) {
+        let mut permits = self.permits.lock().unwrap();
+        *permits += 1;
+        self.cond.notify_one();
+    }
+}
+
 /// The endpoint of backup.
 ///
 /// It coordinates backup tasks and dispatches them to different workers.
@@ -326,6 +386,9 @@ pub struct Endpoint<E: Engine, R: RegionInfoProvider> {
     pool: RefCell<ControlThreadPool>,
     pool_idle_threshold: u64,
     db: Arc<DB>,
+    /// The maximum number of regions a backup task backs up in parallel. `0` means no extra
+    /// limit besides the task's own concurrency.
+    max_parallel_regions: usize,
 
     pub(crate) engine: E,
     pub(crate) region_info: R,
@@ -502,9 +565,16 @@ impl<E: Engine, R: RegionInfoProvider> Endpoint<E, R> {
             pool: RefCell::new(ControlThreadPool::new()),
             pool_idle_threshold: IDLE_THREADPOOL_DURATION,
             db,
+            max_parallel_regions: 0,
         }
     }
 
+    /// Limits how many regions are backed up in parallel per task, regardless of the
+    /// concurrency requested by the task itself.
+    pub fn set_max_parallel_regions(&mut self, limit: usize) {
+        self.max_parallel_regions = limit;
+    }
+
     pub fn new_timer(&self) -> Timer<()> {
         let mut timer = Timer::new(1);
         timer.add_task(Duration::from_millis(self.pool_idle_threshold), ());
@@ -515,6 +585,7 @@ impl<E: Engine, R: RegionInfoProvider> Endpoint<E, R> {
         &self,
         prs: Arc<Mutex<Progress<R>>>,
         request: Request,
+        semaphore: Arc<Semaphore>,
         tx: mpsc::Sender<(BackupRange, Result<BackupRes>)>,
     ) {
         let start_ts = request.start_ts;
@@ -550,23 +621,47 @@ impl<E: Engine, R: RegionInfoProvider> Endpoint<E, R> {
                     warn!("backup task has canceled"; "range" => ?brange);
                     return;
                 }
-                // TODO: make file_name unique and short
-                let key = brange.start_key.clone().and_then(|k| {
-                    // use start_key sha256 instead of start_key to avoid file name too long os error
-                    let input = if is_raw_kv {
-                        k.into_encoded()
-                    } else {
-                        k.into_raw().unwrap()
-                    };
-                    tikv_util::file::sha256(&input).ok().map(|b| hex::encode(b))
-                });
-                let name = backup_file_name(store_id, &brange.region, key);
+                let name = if request.use_range_file_names {
+                    range_backup_file_name(&brange, is_raw_kv)
+                } else {
+                    // TODO: make file_name unique and short
+                    let key = brange.start_key.clone().and_then(|k| {
+                        // use start_key sha256 instead of start_key to avoid file name too long os error
+                        let input = if is_raw_kv {
+                            k.into_encoded()
+                        } else {
+                            k.into_raw().unwrap()
+                        };
+                        tikv_util::file::sha256(&input).ok().map(|b| hex::encode(b))
+                    });
+                    backup_file_name(store_id, &brange.region, key)
+                };
 
+                if !semaphore.acquire(&request.cancel) {
+                    warn!("backup task has canceled"; "range" => ?brange);
+                    return;
+                }
                 let res = if is_raw_kv {
-                    brange.backup_raw_kv_to_file(&engine, db.clone(), &storage, name, cf)
+                    brange.backup_raw_kv_to_file(
+                        &engine,
+                        db.clone(),
+                        &storage,
+                        name,
+                        cf,
+                        request.compression_type,
+                    )
                 } else {
-                    brange.backup_to_file(&engine, db.clone(), &storage, name, backup_ts, start_ts)
+                    brange.backup_to_file(
+                        &engine,
+                        db.clone(),
+                        &storage,
+                        name,
+                        backup_ts,
+                        start_ts,
+                        request.compression_type,
+                    )
                 };
+                semaphore.release();
                 match res {
                     Err(e) => {
                         if let Err(e) = tx.send((brange, Err(e))) {
@@ -622,13 +717,29 @@ impl<E: Engine, R: RegionInfoProvider> Endpoint<E, R> {
         )));
         let concurrency = cmp::max(1, concurrency) as usize;
         self.pool.borrow_mut().adjust_with(concurrency);
+        let parallel_regions = if self.max_parallel_regions > 0 {
+            cmp::min(self.max_parallel_regions, concurrency)
+        } else {
+            concurrency
+        };
+        let semaphore = Arc::new(Semaphore::new(parallel_regions));
         for _ in 0..concurrency {
-            self.spawn_backup_worker(prs.clone(), request.clone(), res_tx.clone());
+            self.spawn_backup_worker(
+                prs.clone(),
+                request.clone(),
+                semaphore.clone(),
+                res_tx.clone(),
+            );
         }
 
         // Drop the extra sender so that for loop does not hang up.
         drop(res_tx);
         let mut summary = Statistics::default();
+        let mut manifest = Manifest {
+            start_version: request.start_ts.into_inner(),
+            end_version: request.end_ts.into_inner(),
+            files: vec![],
+        };
         for (brange, res) in res_rx {
             let start_key = if request.is_raw_kv {
                 brange
@@ -662,6 +773,18 @@ impl<E: Engine, R: RegionInfoProvider> Endpoint<E, R> {
                         file.set_start_version(request.start_ts.into_inner());
                         file.set_end_version(request.end_ts.into_inner());
                     }
+                    if request.write_manifest {
+                        for file in &files {
+                            manifest.files.push(ManifestFile {
+                                name: file.get_name().to_owned(),
+                                region_id: brange.region.get_id(),
+                                start_key: hex::encode(file.get_start_key()),
+                                end_key: hex::encode(file.get_end_key()),
+                                crc64xor: file.get_crc64xor(),
+                                size: file.get_size(),
+                            });
+                        }
+                    }
                     response.set_files(files.into());
                 }
                 Err(e) => {
@@ -680,6 +803,16 @@ impl<E: Engine, R: RegionInfoProvider> Endpoint<E, R> {
                 break;
             }
         }
+        if request.write_manifest {
+            // Workers finish regions in an arbitrary order; sort so the manifest
+            // is deterministic.
+            manifest.files.sort_by(|a, b| a.name.cmp(&b.name));
+            // Storage backend has been checked in `Task::new()`.
+            let storage = create_storage(&request.backend).unwrap();
+            if let Err(e) = manifest.save(storage.as_ref()) {
+                error!("backup failed to save manifest"; "error" => ?e);
+            }
+        }
         let duration = start.elapsed();
         BACKUP_REQUEST_HISTOGRAM.observe(duration.as_secs_f64());
         info!("backup finished";
@@ -710,6 +843,17 @@ impl<E: Engine, R: RegionInfoProvider> RunnableWithTimer<Task, ()> for Endpoint<
     }
 }
 
+/// Maps the compression type in a backup request to an SST compression type. `Unknown` keeps
+/// the writer's default compression unchanged.
+fn to_sst_compression_type(ct: CompressionType) -> Option<SstCompressionType> {
+    match ct {
+        CompressionType::Lz4 => Some(SstCompressionType::Lz4),
+        CompressionType::Snappy => Some(SstCompressionType::Snappy),
+        CompressionType::Zstd => Some(SstCompressionType::Zstd),
+        CompressionType::Unknown => None,
+    }
+}
+
 /// Get the min end key from the given `end_key` and `Region`'s end key.
 fn get_min_end_key(end_key: Option<&Key>, region: &Region) -> Option<Key> {
     let region_end = if region.get_end_key().is_empty() {
@@ -770,6 +914,29 @@ fn backup_file_name(store_id: u64, region: &Region, key: Option<String>) -> Stri
     }
 }
 
+/// Construct a backup file name of the form `<region_id>_<start_key_hex>_<end_key_hex>`,
+/// so that the covered range can be read back from the name. Keys are hex encoded,
+/// which keeps the name safe for file systems and object storages like S3; an
+/// unbounded key is encoded as an empty string. Names are unique per region since
+/// a region is backed up by exactly one range.
+fn range_backup_file_name(brange: &BackupRange, is_raw_kv: bool) -> String {
+    let key_hex = |key: Option<Key>| {
+        key.map_or_else(String::new, |k| {
+            if is_raw_kv {
+                hex::encode(k.into_encoded())
+            } else {
+                hex::encode(k.into_raw().unwrap())
+            }
+        })
+    };
+    format!(
+        "{}_{}_{}",
+        brange.region.get_id(),
+        key_hex(brange.start_key.clone()),
+        key_hex(brange.end_key.clone())
+    )
+}
+
 #[cfg(test)]
 pub mod tests {
     use super::*;
@@ -945,6 +1112,9 @@ pub mod tests {
                         cancel: Arc::default(),
                         is_raw_kv: false,
                         cf: engine_traits::CF_DEFAULT,
+                        compression_type: None,
+                        use_range_file_names: false,
+                        write_manifest: false,
                     },
                     resp: tx,
                     concurrency: 4,
@@ -1072,6 +1242,478 @@ pub mod tests {
         }
     }
 
+    #[test]
+    fn test_semaphore_limits_concurrency() {
+        let semaphore = Arc::new(Semaphore::new(2));
+        let cancel = Arc::new(AtomicBool::new(false));
+        let current = Arc::new(AtomicUsize::new(0));
+        let max_seen = Arc::new(AtomicUsize::new(0));
+        let mut handles = vec![];
+        for _ in 0..8 {
+            let semaphore = semaphore.clone();
+            let cancel = cancel.clone();
+            let current = current.clone();
+            let max_seen = max_seen.clone();
+            handles.push(thread::spawn(move || {
+                assert!(semaphore.acquire(&cancel));
+                let cur = current.fetch_add(1, Ordering::SeqCst) + 1;
+                let mut max = max_seen.load(Ordering::SeqCst);
+                while cur > max {
+                    match max_seen.compare_exchange(max, cur, Ordering::SeqCst, Ordering::SeqCst)
+                    {
+                        Ok(_) => break,
+                        Err(m) => max = m,
+                    }
+                }
+                thread::sleep(Duration::from_millis(20));
+                current.fetch_sub(1, Ordering::SeqCst);
+                semaphore.release();
+            }));
+        }
+        for h in handles {
+            h.join().unwrap();
+        }
+        let max_seen = max_seen.load(Ordering::SeqCst);
+        assert!(max_seen >= 1 && max_seen <= 2, "{}", max_seen);
+
+        // Canceled tasks must not wait for a permit, even when none is available.
+        assert!(semaphore.acquire(&cancel));
+        assert!(semaphore.acquire(&cancel));
+        cancel.store(true, Ordering::SeqCst);
+        assert!(!semaphore.acquire(&cancel));
+    }
+
+    #[test]
+    fn test_max_parallel_regions() {
+        let (tmp, mut endpoint) = new_endpoint();
+        endpoint.region_info.set_regions(vec![
+            (b"".to_vec(), b"1".to_vec(), 1),
+            (b"1".to_vec(), b"2".to_vec(), 2),
+            (b"2".to_vec(), b"3".to_vec(), 3),
+            (b"3".to_vec(), b"4".to_vec(), 4),
+            (b"4".to_vec(), b"".to_vec(), 5),
+        ]);
+        // Only one region may be backed up at a time, even though the task asks for four
+        // workers.
+        endpoint.set_max_parallel_regions(1);
+
+        let mut req = BackupRequest::default();
+        req.set_start_key(vec![]);
+        req.set_end_key(vec![]);
+        req.set_start_version(1);
+        req.set_end_version(1);
+        req.set_concurrency(4);
+        req.set_storage_backend(make_local_backend(&tmp.path().join("par")));
+        let (tx, rx) = unbounded();
+        let (task, _) = Task::new(req, tx).unwrap();
+        endpoint.handle_backup_task(task);
+        let resps: Vec<_> = block_on(rx.collect());
+        // All regions are eventually backed up.
+        assert_eq!(resps.len(), 5, "{:?}", resps);
+        for resp in resps {
+            assert!(!resp.has_error(), "{:?}", resp);
+        }
+    }
+
+    #[test]
+    fn test_handle_backup_task_range_file_names() {
+        let (tmp, endpoint) = new_endpoint();
+        let engine = endpoint.engine.clone();
+
+        endpoint.region_info.set_regions(vec![
+            (b"".to_vec(), b"1".to_vec(), 1),
+            (b"1".to_vec(), b"2".to_vec(), 2),
+            (b"2".to_vec(), b"".to_vec(), 3),
+        ]);
+
+        let mut ts = TimeStamp::new(1);
+        let mut alloc_ts = || *ts.incr();
+        for i in 0..3u8 {
+            let start = alloc_ts();
+            let commit = alloc_ts();
+            let key = format!("{}", i);
+            must_prewrite_put(&engine, key.as_bytes(), b"value", key.as_bytes(), start);
+            must_commit(&engine, key.as_bytes(), start, commit);
+        }
+        let backup_ts = alloc_ts();
+
+        let mut req = BackupRequest::default();
+        req.set_start_key(vec![]);
+        req.set_end_key(vec![]);
+        req.set_start_version(0);
+        req.set_end_version(backup_ts.into_inner());
+        req.set_concurrency(4);
+        req.set_use_range_file_names(true);
+        req.set_storage_backend(make_local_backend(&tmp.path().join("range")));
+        let (tx, rx) = unbounded();
+        let (task, _) = Task::new(req, tx).unwrap();
+        endpoint.handle_backup_task(task);
+        let resps: Vec<_> = block_on(rx.collect());
+        assert_eq!(resps.len(), 3, "{:?}", resps);
+
+        let mut seen = std::collections::HashSet::new();
+        for resp in resps {
+            assert!(!resp.has_error(), "{:?}", resp);
+            assert!(!resp.get_files().is_empty(), "{:?}", resp);
+            // The response does not carry the region id, but the range it covers
+            // identifies the region it was built from.
+            let region_id: u64 = match resp.get_start_key() {
+                b"" => 1,
+                b"1" => 2,
+                b"2" => 3,
+                other => panic!("unexpected start key {:?}", other),
+            };
+            let prefix = format!(
+                "{}_{}_{}_",
+                region_id,
+                hex::encode(resp.get_start_key()),
+                hex::encode(resp.get_end_key())
+            );
+            for file in resp.get_files() {
+                let name = file.get_name();
+                assert!(
+                    name.starts_with(&prefix) && name.ends_with(".sst"),
+                    "{} does not match {}<cf>.sst",
+                    name,
+                    prefix
+                );
+                // Names are unique per region, so the file must exist under them.
+                assert!(seen.insert(name.to_owned()), "duplicated name {}", name);
+                assert!(tmp.path().join("range").join(name).exists(), "{}", name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_handle_backup_task_manifest() {
+        let (tmp, endpoint) = new_endpoint();
+        let engine = endpoint.engine.clone();
+
+        endpoint.region_info.set_regions(vec![
+            (b"".to_vec(), b"2".to_vec(), 1),
+            (b"2".to_vec(), b"".to_vec(), 2),
+        ]);
+
+        let mut ts = TimeStamp::new(1);
+        let mut alloc_ts = || *ts.incr();
+        for i in 0..4u8 {
+            let start = alloc_ts();
+            let commit = alloc_ts();
+            let key = format!("{}", i);
+            must_prewrite_put(&engine, key.as_bytes(), b"value", key.as_bytes(), start);
+            must_commit(&engine, key.as_bytes(), start, commit);
+        }
+        let backup_ts = alloc_ts();
+
+        let mut req = BackupRequest::default();
+        req.set_start_key(vec![]);
+        req.set_end_key(vec![]);
+        req.set_start_version(0);
+        req.set_end_version(backup_ts.into_inner());
+        req.set_concurrency(4);
+        req.set_write_manifest(true);
+        req.set_storage_backend(make_local_backend(&tmp.path().join("manifest")));
+        let (tx, rx) = unbounded();
+        let (task, _) = Task::new(req, tx).unwrap();
+        endpoint.handle_backup_task(task);
+        let resps: Vec<_> = block_on(rx.collect());
+        assert_eq!(resps.len(), 2, "{:?}", resps);
+
+        let storage = create_storage(&make_local_backend(&tmp.path().join("manifest"))).unwrap();
+        let manifest = Manifest::load(storage.as_ref()).unwrap();
+        assert_eq!(manifest.start_version, 0);
+        assert_eq!(manifest.end_version, backup_ts.into_inner());
+
+        // The manifest must list every produced file with its region and range.
+        let mut expected = vec![];
+        for resp in resps {
+            assert!(!resp.has_error(), "{:?}", resp);
+            for file in resp.get_files() {
+                expected.push(ManifestFile {
+                    name: file.get_name().to_owned(),
+                    region_id: match resp.get_start_key() {
+                        b"" => 1,
+                        b"2" => 2,
+                        other => panic!("unexpected start key {:?}", other),
+                    },
+                    start_key: hex::encode(file.get_start_key()),
+                    end_key: hex::encode(file.get_end_key()),
+                    crc64xor: file.get_crc64xor(),
+                    size: file.get_size(),
+                });
+            }
+        }
+        expected.sort_by(|a, b| a.name.cmp(&b.name));
+        assert_eq!(manifest.files, expected);
+    }
+
+    #[test]
+    fn test_handle_backup_task_compression() {
+        let (tmp, endpoint) = new_endpoint();
+        let engine = endpoint.engine.clone();
+
+        endpoint
+            .region_info
+            .set_regions(vec![(b"".to_vec(), b"5".to_vec(), 1)]);
+
+        let mut ts = TimeStamp::new(1);
+        let mut alloc_ts = || *ts.incr();
+        // Long compressible values, so that they are stored in the default CF and the SST
+        // size is dominated by value compression.
+        for i in 0..10u8 {
+            let start = alloc_ts();
+            let commit = alloc_ts();
+            let key = format!("{}", i);
+            must_prewrite_put(
+                &engine,
+                key.as_bytes(),
+                &vec![i; SHORT_VALUE_MAX_LEN * 16],
+                key.as_bytes(),
+                start,
+            );
+            must_commit(&engine, key.as_bytes(), start, commit);
+        }
+        let backup_ts = alloc_ts();
+
+        let backup = |path: &str, compression: CompressionType| {
+            let mut req = BackupRequest::default();
+            req.set_start_key(vec![]);
+            req.set_end_key(vec![b'5']);
+            req.set_start_version(0);
+            req.set_end_version(backup_ts.into_inner());
+            req.set_concurrency(4);
+            req.set_compression_type(compression);
+            req.set_storage_backend(make_local_backend(&tmp.path().join(path)));
+            let (tx, rx) = unbounded();
+            let (task, _) = Task::new(req, tx).unwrap();
+            endpoint.handle_backup_task(task);
+            let (resp, _) = block_on(rx.into_future());
+            let resp = resp.unwrap();
+            assert!(!resp.has_error(), "{:?}", resp);
+            resp
+        };
+
+        // `Unknown` keeps the default compression unchanged.
+        let default_resp = backup("default", CompressionType::Unknown);
+        let zstd_resp = backup("zstd", CompressionType::Zstd);
+
+        let file_size = |path: &str, file: &File| {
+            tmp.path()
+                .join(path)
+                .join(file.get_name())
+                .metadata()
+                .unwrap()
+                .len()
+        };
+        let default_size: u64 = default_resp
+            .get_files()
+            .iter()
+            .map(|f| file_size("default", f))
+            .sum();
+        let zstd_size: u64 = zstd_resp
+            .get_files()
+            .iter()
+            .map(|f| file_size("zstd", f))
+            .sum();
+        assert!(
+            zstd_size < default_size,
+            "zstd {} >= default {}",
+            zstd_size,
+            default_size
+        );
+
+        // The recorded checksum and size must match the file written to storage.
+        for f in zstd_resp.get_files() {
+            let content = std::fs::read(tmp.path().join("zstd").join(f.get_name())).unwrap();
+            assert_eq!(f.get_size(), content.len() as u64);
+            let sha256 = tikv_util::file::sha256(&content).unwrap();
+            assert_eq!(f.get_sha256(), sha256.as_slice());
+        }
+    }
+
+    #[test]
+    fn test_handle_incremental_backup() {
+        use engine_rocks::Compat;
+        use engine_traits::Iterable;
+        use txn_types::{WriteRef, WriteType};
+
+        let (tmp, endpoint) = new_endpoint();
+        let engine = endpoint.engine.clone();
+
+        endpoint
+            .region_info
+            .set_regions(vec![(b"".to_vec(), b"5".to_vec(), 1)]);
+
+        let mut ts = TimeStamp::new(1);
+        let mut alloc_ts = || *ts.incr();
+
+        // Base data: keys 1, 2, 3.
+        for i in 1..4u8 {
+            let start = alloc_ts();
+            let commit = alloc_ts();
+            let key = format!("{}", i);
+            must_prewrite_put(&engine, key.as_bytes(), b"base", key.as_bytes(), start);
+            must_commit(&engine, key.as_bytes(), start, commit);
+        }
+        let full_ts = alloc_ts();
+
+        // Changes after the full backup: overwrite key 1 and delete key 2. Key 3 is untouched
+        // and must not show up in the incremental backup.
+        let start = alloc_ts();
+        let commit = alloc_ts();
+        must_prewrite_put(&engine, b"1", b"new", b"1", start);
+        must_commit(&engine, b"1", start, commit);
+        let start = alloc_ts();
+        let commit = alloc_ts();
+        must_prewrite_delete(&engine, b"2", b"2", start);
+        must_commit(&engine, b"2", start, commit);
+        let incremental_ts = alloc_ts();
+
+        let backup = |start_version: TimeStamp, end_version: TimeStamp, path: &str| {
+            let mut req = BackupRequest::default();
+            req.set_start_key(vec![]);
+            req.set_end_key(vec![b'5']);
+            req.set_start_version(start_version.into_inner());
+            req.set_end_version(end_version.into_inner());
+            req.set_concurrency(4);
+            req.set_storage_backend(make_local_backend(&tmp.path().join(path)));
+            let (tx, rx) = unbounded();
+            let (task, _) = Task::new(req, tx).unwrap();
+            endpoint.handle_backup_task(task);
+            let (resp, _) = block_on(rx.into_future());
+            let resp = resp.unwrap();
+            assert!(!resp.has_error(), "{:?}", resp);
+            resp
+        };
+
+        // The full backup contains all three keys. Values are short so everything ends up in
+        // a single write CF file.
+        let resp = backup(TimeStamp::zero(), full_ts, "full");
+        assert_eq!(resp.get_files().len(), 1, "{:?}", resp);
+        assert_eq!(resp.get_files()[0].get_total_kvs(), 3, "{:?}", resp);
+
+        // The incremental backup only contains versions committed in
+        // `(full_ts, incremental_ts]`, including the delete tombstone of key 2.
+        let resp = backup(full_ts, incremental_ts, "inc");
+        assert_eq!(resp.get_files().len(), 1, "{:?}", resp);
+        assert_eq!(resp.get_files()[0].get_total_kvs(), 2, "{:?}", resp);
+
+        // Ingest the incremental SST and make sure it carries exactly the overwrite of key 1
+        // and the tombstone of key 2, so that restore can apply the delete.
+        let sst_path = tmp.path().join("inc").join(resp.get_files()[0].get_name());
+        let restore_tmp = TempDir::new().unwrap();
+        let rocks = TestEngineBuilder::new()
+            .path(restore_tmp.path())
+            .cfs(&[engine_traits::CF_DEFAULT, engine_traits::CF_WRITE])
+            .build()
+            .unwrap();
+        let db = rocks.get_rocksdb();
+        let opt = engine::rocks::IngestExternalFileOptions::new();
+        let handle = db.cf_handle(engine_traits::CF_WRITE).unwrap();
+        db.ingest_external_file_cf(handle, &opt, &[sst_path.to_str().unwrap()])
+            .unwrap();
+
+        let mut restored = vec![];
+        db.c()
+            .scan_cf(
+                engine_traits::CF_WRITE,
+                keys::DATA_MIN_KEY,
+                keys::DATA_MAX_KEY,
+                false,
+                |key, value| {
+                    let user_key = Key::from_encoded(keys::origin_key(key).to_vec())
+                        .truncate_ts()
+                        .unwrap()
+                        .into_raw()
+                        .unwrap();
+                    let write_type = WriteRef::parse(value).unwrap().write_type;
+                    restored.push((user_key, write_type));
+                    Ok(true)
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            restored,
+            vec![
+                (b"1".to_vec(), WriteType::Put),
+                (b"2".to_vec(), WriteType::Delete)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_handle_backup_raw_task() {
+        use engine_rocks::Compat;
+        use engine_traits::Iterable;
+
+        let (tmp, endpoint) = new_endpoint();
+        let engine = endpoint.engine.clone();
+
+        endpoint
+            .region_info
+            .set_regions(vec![(b"".to_vec(), b"".to_vec(), 1)]);
+
+        // Seed raw keys. Raw KV has no MVCC versions, keys are stored in the raw CF as is.
+        let ctx = Context::default();
+        let kvs: Vec<(Vec<u8>, Vec<u8>)> = (0..10u8).map(|i| (vec![b'a' + i], vec![i; 8])).collect();
+        for (k, v) in &kvs {
+            engine
+                .put(&ctx, Key::from_encoded(k.clone()), v.clone())
+                .unwrap();
+        }
+
+        let mut req = BackupRequest::default();
+        req.set_start_key(vec![]);
+        req.set_end_key(vec![]);
+        req.set_is_raw_kv(true);
+        req.set_cf(engine_traits::CF_DEFAULT.to_owned());
+        req.set_concurrency(4);
+        req.set_storage_backend(make_local_backend(&tmp.path().join("raw")));
+
+        let (tx, rx) = unbounded();
+        let (task, _) = Task::new(req, tx).unwrap();
+        endpoint.handle_backup_task(task);
+        check_response(rx, |resp| {
+            let resp = resp.unwrap();
+            assert!(!resp.has_error(), "{:?}", resp);
+            assert_eq!(resp.get_files().len(), 1, "{:?}", resp);
+            // The response range follows the same semantics as the MVCC path: the
+            // (intersected) requested key range, without version filtering applied.
+            assert_eq!(resp.get_start_key(), &b""[..]);
+            assert_eq!(resp.get_end_key(), &b""[..]);
+
+            // Ingest the backup SST into a fresh engine and make sure identical raw keys
+            // come back.
+            let sst_path = tmp.path().join("raw").join(resp.get_files()[0].get_name());
+            let restore_tmp = TempDir::new().unwrap();
+            let rocks = TestEngineBuilder::new()
+                .path(restore_tmp.path())
+                .cfs(&[engine_traits::CF_DEFAULT])
+                .build()
+                .unwrap();
+            let db = rocks.get_rocksdb();
+            let opt = engine::rocks::IngestExternalFileOptions::new();
+            let handle = db.cf_handle(engine_traits::CF_DEFAULT).unwrap();
+            db.ingest_external_file_cf(handle, &opt, &[sst_path.to_str().unwrap()])
+                .unwrap();
+
+            let mut restored = vec![];
+            db.c()
+                .scan_cf(
+                    engine_traits::CF_DEFAULT,
+                    keys::DATA_MIN_KEY,
+                    keys::DATA_MAX_KEY,
+                    false,
+                    |key, value| {
+                        restored.push((keys::origin_key(key).to_vec(), value.to_vec()));
+                        Ok(true)
+                    },
+                )
+                .unwrap();
+            assert_eq!(restored, kvs);
+        });
+    }
+
     #[test]
     fn test_scan_error() {
         let (tmp, endpoint) = new_endpoint();
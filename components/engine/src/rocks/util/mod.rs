@@ -110,6 +110,23 @@ pub fn new_engine(
     new_engine_opt(path, db_opts, cf_opts)
 }
 
+/// Opens an existing db in RocksDB read-only mode.
+///
+/// Unlike `new_engine` nothing is created or dropped: the db must exist and
+/// all of its column families are opened as they are. Writes to a db opened
+/// this way fail, which makes it suitable for offline analysis tools that
+/// must not mutate production data.
+pub fn new_engine_read_only(path: &str, db_opts: Option<DBOptions>) -> Result<DB> {
+    let db_opt = db_opts.unwrap_or_else(DBOptions::new);
+    let cfs_list = DB::list_column_families(&db_opt, path)?;
+    let cfds = cfs_list
+        .iter()
+        .map(|cf| (cf.as_str(), ColumnFamilyOptions::new()))
+        .collect();
+    let db = DB::open_cf_for_read_only(db_opt, path, cfds, false)?;
+    Ok(db)
+}
+
 /// Turns "dynamic level size" off for the existing column family which was off before.
 /// Column families are small, HashMap isn't necessary.
 fn adjust_dynamic_level_bytes(
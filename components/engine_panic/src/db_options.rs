@@ -13,6 +13,12 @@ impl DBOptionsExt for PanicEngine {
     fn set_db_options(&self, options: &[(&str, &str)]) -> Result<()> {
         panic!()
     }
+    fn get_rate_limiter_bytes_per_sec(&self) -> Option<i64> {
+        panic!()
+    }
+    fn set_rate_limiter_bytes_per_sec(&self, rate_bytes_per_sec: i64) -> Result<()> {
+        panic!()
+    }
 }
 
 pub struct PanicDBOptions;
@@ -24,6 +24,17 @@ impl ImportExt for PanicEngine {
         panic!()
     }
 
+    fn ingest_external_file_in_range_cf(
+        &self,
+        cf: &Self::CFHandle,
+        opts: &Self::IngestExternalFileOptions,
+        files: &[&str],
+        start_key: &[u8],
+        end_key: &[u8],
+    ) -> Result<()> {
+        panic!()
+    }
+
     fn validate_sst_for_ingestion<P: AsRef<Path>>(
         &self,
         cf: &Self::CFHandle,
@@ -35,3 +35,5 @@ mod table_properties;
 pub use crate::table_properties::*;
 mod write_batch;
 pub use crate::write_batch::*;
+mod updates;
+pub use crate::updates::*;
@@ -1,7 +1,7 @@
 // Copyright 2020 TiKV Project Authors. Licensed under Apache-2.0.
 
 use crate::engine::PanicEngine;
-use engine_traits::{MiscExt, Range, Result};
+use engine_traits::{MiscExt, Range, Result, SstFileInfo};
 
 impl MiscExt for PanicEngine {
     fn flush_cf(&self, cf: &str, sync: bool) -> Result<()> {
@@ -21,4 +21,12 @@ impl MiscExt for PanicEngine {
     fn get_approximate_memtable_stats_cf(&self, cf: &str, range: &Range) -> Result<(u64, u64)> {
         panic!()
     }
+
+    fn get_cf_pending_compaction_bytes(&self, cf: &str) -> Result<u64> {
+        panic!()
+    }
+
+    fn get_cf_files(&self, cf: &str) -> Result<Vec<SstFileInfo>> {
+        panic!()
+    }
 }
@@ -16,6 +16,9 @@ impl Snapshot<PanicEngine> for PanicSnapshot {
     fn cf_names(&self) -> Vec<&str> {
         panic!()
     }
+    fn sequence_number(&self) -> u64 {
+        panic!()
+    }
     fn into_sync(self) -> Self::SyncSnapshot {
         panic!()
     }
@@ -3,7 +3,7 @@
 use crate::engine::PanicEngine;
 use engine_traits::{
     CfName, ExternalSstFileInfo, IterOptions, Iterable, Iterator, Result, SeekKey, SstExt,
-    SstReader, SstWriter, SstWriterBuilder,
+    SstCompressionType, SstReader, SstWriter, SstWriterBuilder,
 };
 use std::path::PathBuf;
 
@@ -105,6 +105,9 @@ impl SstWriterBuilder<PanicEngine> for PanicSstWriterBuilder {
     fn set_in_memory(self, in_memory: bool) -> Self {
         panic!()
     }
+    fn set_compression_type(self, compression: Option<SstCompressionType>) -> Self {
+        panic!()
+    }
     fn build(self, path: &str) -> Result<PanicSstWriter> {
         panic!()
     }
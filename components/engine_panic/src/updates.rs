@@ -0,0 +1,22 @@
+// Copyright 2020 TiKV Project Authors. Licensed under Apache-2.0.
+
+use crate::engine::PanicEngine;
+use engine_traits::{ChangeEvent, Result, UpdatesExt};
+
+impl UpdatesExt for PanicEngine {
+    type ChangeIter = PanicChangeIter;
+
+    fn scan_updates_since(&self, sequence_number: u64) -> Result<Self::ChangeIter> {
+        panic!()
+    }
+}
+
+pub struct PanicChangeIter;
+
+impl std::iter::Iterator for PanicChangeIter {
+    type Item = Result<ChangeEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        panic!()
+    }
+}
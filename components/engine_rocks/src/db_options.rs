@@ -19,6 +19,29 @@ impl DBOptionsExt for RocksEngine {
             .set_db_options(options)
             .map_err(|e| box_err!(e))
     }
+    fn get_rate_limiter_bytes_per_sec(&self) -> Option<i64> {
+        self.as_inner().get_db_options().get_rate_bytes_per_sec()
+    }
+    fn set_rate_limiter_bytes_per_sec(&self, rate_bytes_per_sec: i64) -> Result<()> {
+        let mut opts = self.as_inner().get_db_options();
+        // RocksDB cannot uninstall a rate limiter once one exists; an
+        // unbounded budget is equivalent to no throttling.
+        let rate = if rate_bytes_per_sec == 0 {
+            i64::max_value()
+        } else {
+            rate_bytes_per_sec
+        };
+        if opts.get_rate_bytes_per_sec().is_none() {
+            if rate_bytes_per_sec == 0 {
+                return Ok(());
+            }
+            // The engine was opened without a rate limiter; install one
+            // before it can be throttled.
+            opts.set_ratelimiter(rate);
+            return Ok(());
+        }
+        opts.set_rate_bytes_per_sec(rate).map_err(|e| box_err!(e))
+    }
 }
 
 pub struct RocksDBOptions(RawDBOptions);
@@ -70,3 +93,59 @@ impl TitanDBOptions for RocksTitanDBOptions {
         self.0.set_min_blob_size(size)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util;
+    use tempfile::Builder;
+
+    #[test]
+    fn test_rate_limiter_bytes_per_sec() {
+        // An engine opened without a rate limiter reports none; setting a
+        // budget installs one, and zero stays a no-op.
+        let path = Builder::new()
+            .prefix("test_rate_limiter_install")
+            .tempdir()
+            .unwrap();
+        let engine = util::new_engine(path.path().to_str().unwrap(), None, &["cf"], None).unwrap();
+        assert_eq!(engine.get_rate_limiter_bytes_per_sec(), None);
+        engine.set_rate_limiter_bytes_per_sec(0).unwrap();
+        engine
+            .set_rate_limiter_bytes_per_sec(10 * 1024 * 1024)
+            .unwrap();
+
+        // With a rate limiter installed at open time the budget can be
+        // adjusted dynamically and read back.
+        let path = Builder::new()
+            .prefix("test_rate_limiter_adjust")
+            .tempdir()
+            .unwrap();
+        let mut db_opts = RawDBOptions::new();
+        db_opts.set_ratelimiter(4 * 1024 * 1024);
+        let engine = util::new_engine(
+            path.path().to_str().unwrap(),
+            Some(RocksDBOptions::from_raw(db_opts)),
+            &["cf"],
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            engine.get_rate_limiter_bytes_per_sec(),
+            Some(4 * 1024 * 1024)
+        );
+        engine
+            .set_rate_limiter_bytes_per_sec(8 * 1024 * 1024)
+            .unwrap();
+        assert_eq!(
+            engine.get_rate_limiter_bytes_per_sec(),
+            Some(8 * 1024 * 1024)
+        );
+        // Zero disables throttling by lifting the budget.
+        engine.set_rate_limiter_bytes_per_sec(0).unwrap();
+        assert_eq!(
+            engine.get_rate_limiter_bytes_per_sec(),
+            Some(i64::max_value())
+        );
+    }
+}
@@ -41,6 +41,13 @@ impl RocksEngine {
         self.0.clone()
     }
 
+    fn check_mutable(&self) -> Result<()> {
+        if self.0.is_read_only() {
+            return Err(Error::Engine("cannot write to read-only engine".to_owned()));
+        }
+        Ok(())
+    }
+
     pub fn exists(path: &str) -> bool {
         let path = Path::new(path);
         if !path.exists() || !path.is_dir() {
@@ -93,21 +100,24 @@ impl Iterable for RocksEngine {
     type Iterator = RocksEngineIterator;
 
     fn iterator_opt(&self, opts: IterOptions) -> Result<Self::Iterator> {
+        let lower = opts.lower_bound().map(|b| b.to_vec());
+        let upper = opts.upper_bound().map(|b| b.to_vec());
         let opt: RocksReadOptions = opts.into();
-        Ok(RocksEngineIterator::from_raw(DBIterator::new(
-            self.0.clone(),
-            opt.into_raw(),
-        )))
+        Ok(
+            RocksEngineIterator::from_raw(DBIterator::new(self.0.clone(), opt.into_raw()))
+                .with_bounds(lower, upper),
+        )
     }
 
     fn iterator_cf_opt(&self, cf: &str, opts: IterOptions) -> Result<Self::Iterator> {
         let handle = get_cf_handle(&self.0, cf)?;
+        let lower = opts.lower_bound().map(|b| b.to_vec());
+        let upper = opts.upper_bound().map(|b| b.to_vec());
         let opt: RocksReadOptions = opts.into();
-        Ok(RocksEngineIterator::from_raw(DBIterator::new_cf(
-            self.0.clone(),
-            handle,
-            opt.into_raw(),
-        )))
+        Ok(
+            RocksEngineIterator::from_raw(DBIterator::new_cf(self.0.clone(), handle, opt.into_raw()))
+                .with_bounds(lower, upper),
+        )
     }
 }
 
@@ -135,24 +145,29 @@ impl Peekable for RocksEngine {
 
 impl SyncMutable for RocksEngine {
     fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.check_mutable()?;
         self.0.put(key, value).map_err(Error::Engine)
     }
 
     fn put_cf(&self, cf: &str, key: &[u8], value: &[u8]) -> Result<()> {
+        self.check_mutable()?;
         let handle = get_cf_handle(&self.0, cf)?;
         self.0.put_cf(handle, key, value).map_err(Error::Engine)
     }
 
     fn delete(&self, key: &[u8]) -> Result<()> {
+        self.check_mutable()?;
         self.0.delete(key).map_err(Error::Engine)
     }
 
     fn delete_cf(&self, cf: &str, key: &[u8]) -> Result<()> {
+        self.check_mutable()?;
         let handle = get_cf_handle(&self.0, cf)?;
         self.0.delete_cf(handle, key).map_err(Error::Engine)
     }
 
     fn delete_range_cf(&self, cf: &str, begin_key: &[u8], end_key: &[u8]) -> Result<()> {
+        self.check_mutable()?;
         let handle = get_cf_handle(&self.0, cf)?;
         self.0
             .delete_range_cf(handle, begin_key, end_key)
@@ -207,6 +222,157 @@ mod tests {
         assert!(b.is_none());
     }
 
+    #[test]
+    fn test_scan_limited() {
+        let path = Builder::new().prefix("var").tempdir().unwrap();
+        let cf = "cf";
+        let engine = RocksEngine::from_db(Arc::new(
+            util::new_engine(path.path().to_str().unwrap(), None, &[cf], None).unwrap(),
+        ));
+
+        for i in 0..5 {
+            engine
+                .put_cf(cf, format!("a{}", i).as_bytes(), b"v")
+                .unwrap();
+        }
+
+        let (pairs, has_more) = engine.scan_limited(cf, b"", &[0xFF, 0xFF], 3).unwrap();
+        assert_eq!(pairs.len(), 3);
+        assert!(has_more);
+        assert_eq!(pairs[0].0, b"a0".to_vec());
+        assert_eq!(pairs[2].0, b"a2".to_vec());
+
+        // The limit covering the whole range reports no remainder.
+        let (pairs, has_more) = engine.scan_limited(cf, b"", &[0xFF, 0xFF], 5).unwrap();
+        assert_eq!(pairs.len(), 5);
+        assert!(!has_more);
+
+        let (pairs, has_more) = engine.scan_limited(cf, b"", &[0xFF, 0xFF], 0).unwrap();
+        assert!(pairs.is_empty());
+        assert!(has_more);
+    }
+
+    #[test]
+    fn test_checksum_range() {
+        let cf = "cf";
+        let new_engine_at = |path: &tempfile::TempDir| {
+            RocksEngine::from_db(Arc::new(
+                util::new_engine(path.path().to_str().unwrap(), None, &[cf], None).unwrap(),
+            ))
+        };
+
+        let path1 = Builder::new().prefix("var").tempdir().unwrap();
+        let engine1 = new_engine_at(&path1);
+        for (k, v) in &[(b"a1", b"v1"), (b"a2", b"v2"), (b"a3", b"v3")] {
+            engine1.put_cf(cf, *k, *v).unwrap();
+        }
+
+        // The same data inserted in a different order checksums identically.
+        let path2 = Builder::new().prefix("var").tempdir().unwrap();
+        let engine2 = new_engine_at(&path2);
+        for (k, v) in &[(b"a3", b"v3"), (b"a1", b"v1"), (b"a2", b"v2")] {
+            engine2.put_cf(cf, *k, *v).unwrap();
+        }
+
+        let sum1 = engine1.checksum_range(cf, b"", &[0xFF, 0xFF]).unwrap();
+        let sum2 = engine2.checksum_range(cf, b"", &[0xFF, 0xFF]).unwrap();
+        assert_eq!(sum1, sum2);
+        let (_, kvs, bytes) = sum1;
+        assert_eq!(kvs, 3);
+        assert_eq!(bytes, 12);
+
+        // Different data produces a different checksum.
+        engine2.put_cf(cf, b"a4", b"v4").unwrap();
+        let sum3 = engine2.checksum_range(cf, b"", &[0xFF, 0xFF]).unwrap();
+        assert_ne!(sum1.0, sum3.0);
+    }
+
+    #[test]
+    fn test_iterator_bounds() {
+        use engine_traits::{collect, IterOptions, Iterator, SeekKey};
+
+        let path = Builder::new().prefix("var").tempdir().unwrap();
+        let cf = "cf";
+        let engine = RocksEngine::from_db(Arc::new(
+            util::new_engine(path.path().to_str().unwrap(), None, &[cf], None).unwrap(),
+        ));
+
+        engine.put_cf(cf, b"a1", b"v1").unwrap();
+        engine.put_cf(cf, b"a2", b"v2").unwrap();
+        engine.put_cf(cf, b"a3", b"v3").unwrap();
+
+        let opts = IterOptions::with_bounds(b"a1".to_vec(), b"a3".to_vec());
+        let mut iter = engine.iterator_cf_opt(cf, opts).unwrap();
+        iter.seek(SeekKey::Start).unwrap();
+        let data = collect(iter);
+        assert_eq!(
+            data,
+            vec![
+                (b"a1".to_vec(), b"v1".to_vec()),
+                (b"a2".to_vec(), b"v2".to_vec()),
+            ]
+        );
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "upper bound")]
+    fn test_iterator_bounds_assertion() {
+        use crate::options::RocksReadOptions;
+        use engine_traits::{IterOptions, Iterator, SeekKey};
+        use rocksdb::DBIterator;
+
+        let path = Builder::new().prefix("var").tempdir().unwrap();
+        let engine = RocksEngine::from_db(Arc::new(
+            util::new_engine(path.path().to_str().unwrap(), None, &["cf"], None).unwrap(),
+        ));
+
+        engine.put(b"a1", b"v1").unwrap();
+        engine.put(b"a3", b"v3").unwrap();
+
+        // Emulate a misbehaving iterator by recording bounds narrower than
+        // the ones the underlying iterator actually enforces.
+        let opt: RocksReadOptions = IterOptions::default().into();
+        let raw = DBIterator::new(engine.get_sync_db(), opt.into_raw());
+        let mut iter =
+            crate::RocksEngineIterator::from_raw(raw).with_bounds(None, Some(b"a2".to_vec()));
+        iter.seek(SeekKey::Key(b"a3")).unwrap();
+        iter.key();
+    }
+
+    #[test]
+    fn test_read_only() {
+        let path = Builder::new().prefix("var").tempdir().unwrap();
+        let path_str = path.path().to_str().unwrap();
+        let cf = "cf";
+        {
+            let engine = RocksEngine::from_db(Arc::new(
+                util::new_engine(path_str, None, &[cf], None).unwrap(),
+            ));
+            engine.put(b"k1", b"v1").unwrap();
+            engine.put_cf(cf, b"k1", b"v2").unwrap();
+        }
+
+        let engine = crate::util::new_engine_read_only(path_str, None).unwrap();
+        assert_eq!(&*engine.get_value(b"k1").unwrap().unwrap(), b"v1");
+        assert_eq!(&*engine.get_value_cf(cf, b"k1").unwrap().unwrap(), b"v2");
+
+        for r in &[
+            engine.put(b"k2", b"v2"),
+            engine.put_cf(cf, b"k2", b"v2"),
+            engine.delete(b"k1"),
+            engine.delete_cf(cf, b"k1"),
+            engine.delete_range_cf(cf, b"", &[0xFF, 0xFF]),
+        ] {
+            match r {
+                Err(engine_traits::Error::Engine(msg)) => {
+                    assert!(msg.contains("read-only"), "unexpected message: {}", msg)
+                }
+                r => panic!("unexpected write result: {:?}", r),
+            }
+        }
+    }
+
     #[test]
     fn test_peekable() {
         let path = Builder::new().prefix("var").tempdir().unwrap();
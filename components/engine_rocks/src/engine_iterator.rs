@@ -7,43 +7,104 @@ use rocksdb::{DBIterator, SeekKey as RawSeekKey, DB};
 
 // FIXME: Would prefer using &DB instead of Arc<DB>.  As elsewhere in
 // this crate, it would require generic associated types.
-pub struct RocksEngineIterator(DBIterator<Arc<DB>>);
+pub struct RocksEngineIterator {
+    iter: DBIterator<Arc<DB>>,
+    // In debug builds the iterator bounds are kept around so that keys
+    // yielded outside them are caught early instead of corrupting whatever
+    // consumed the over-read.
+    #[cfg(debug_assertions)]
+    lower_bound: Option<Vec<u8>>,
+    #[cfg(debug_assertions)]
+    upper_bound: Option<Vec<u8>>,
+}
 
 impl RocksEngineIterator {
     pub fn from_raw(iter: DBIterator<Arc<DB>>) -> RocksEngineIterator {
-        RocksEngineIterator(iter)
+        RocksEngineIterator {
+            iter,
+            #[cfg(debug_assertions)]
+            lower_bound: None,
+            #[cfg(debug_assertions)]
+            upper_bound: None,
+        }
+    }
+
+    /// Records the bounds the iterator is expected to respect. Keys outside
+    /// `[lower, upper)` trigger a panic in debug builds; release builds keep
+    /// nothing and check nothing.
+    #[cfg(debug_assertions)]
+    pub fn with_bounds(
+        mut self,
+        lower: Option<Vec<u8>>,
+        upper: Option<Vec<u8>>,
+    ) -> RocksEngineIterator {
+        self.lower_bound = lower;
+        self.upper_bound = upper;
+        self
+    }
+
+    #[cfg(not(debug_assertions))]
+    pub fn with_bounds(
+        self,
+        _lower: Option<Vec<u8>>,
+        _upper: Option<Vec<u8>>,
+    ) -> RocksEngineIterator {
+        self
+    }
+
+    #[cfg(debug_assertions)]
+    fn assert_in_bounds(&self, key: &[u8]) {
+        if let Some(lower) = &self.lower_bound {
+            assert!(
+                key >= lower.as_slice(),
+                "key {:?} is below iterator lower bound {:?}",
+                key,
+                lower
+            );
+        }
+        if let Some(upper) = &self.upper_bound {
+            assert!(
+                key < upper.as_slice(),
+                "key {:?} is not below iterator upper bound {:?}",
+                key,
+                upper
+            );
+        }
     }
 }
 
 impl engine_traits::Iterator for RocksEngineIterator {
     fn seek(&mut self, key: engine_traits::SeekKey) -> Result<bool> {
         let k: RocksSeekKey = key.into();
-        self.0.seek(k.into_raw()).map_err(Error::Engine)
+        self.iter.seek(k.into_raw()).map_err(Error::Engine)
     }
 
     fn seek_for_prev(&mut self, key: engine_traits::SeekKey) -> Result<bool> {
         let k: RocksSeekKey = key.into();
-        self.0.seek_for_prev(k.into_raw()).map_err(Error::Engine)
+        self.iter.seek_for_prev(k.into_raw()).map_err(Error::Engine)
     }
 
     fn prev(&mut self) -> Result<bool> {
-        self.0.prev().map_err(Error::Engine)
+        self.iter.prev().map_err(Error::Engine)
     }
 
     fn next(&mut self) -> Result<bool> {
-        self.0.next().map_err(Error::Engine)
+        self.iter.next().map_err(Error::Engine)
     }
 
     fn key(&self) -> &[u8] {
-        self.0.key()
+        let key = self.iter.key();
+        #[cfg(debug_assertions)]
+        self.assert_in_bounds(key);
+        key
     }
 
     fn value(&self) -> &[u8] {
-        self.0.value()
+        self.iter.value()
     }
 
     fn valid(&self) -> Result<bool> {
-        self.0.valid().map_err(Error::Engine)
+        self.iter.valid().map_err(Error::Engine)
     }
 }
 
@@ -45,6 +45,8 @@ mod table_properties;
 pub use crate::table_properties::*;
 mod write_batch;
 pub use crate::write_batch::*;
+mod updates;
+pub use crate::updates::*;
 
 mod engine_iterator;
 pub use crate::engine_iterator::*;
@@ -1,8 +1,9 @@
 // Copyright 2020 TiKV Project Authors. Licensed under Apache-2.0.
 
 use crate::engine::RocksEngine;
+use crate::rocks_metrics_defs::ROCKSDB_PENDING_COMPACTION_BYTES;
 use crate::util;
-use engine_traits::{MiscExt, Range, Result};
+use engine_traits::{MiscExt, Range, Result, SstFileInfo};
 
 impl MiscExt for RocksEngine {
     fn is_titan(&self) -> bool {
@@ -34,6 +35,33 @@ impl MiscExt for RocksEngine {
             .as_inner()
             .get_approximate_memtable_stats_cf(handle, &range))
     }
+
+    fn get_cf_pending_compaction_bytes(&self, cf: &str) -> Result<u64> {
+        let handle = util::get_cf_handle(self.as_inner(), cf)?;
+        Ok(self
+            .as_inner()
+            .get_property_int_cf(handle, ROCKSDB_PENDING_COMPACTION_BYTES)
+            .unwrap_or(0))
+    }
+
+    fn get_cf_files(&self, cf: &str) -> Result<Vec<SstFileInfo>> {
+        let handle = util::get_cf_handle(self.as_inner(), cf)?;
+        let cf_meta = self.as_inner().get_column_family_meta_data(handle);
+        let mut files = Vec::new();
+        for (level, level_meta) in cf_meta.get_levels().iter().enumerate() {
+            for f in level_meta.get_files() {
+                files.push(SstFileInfo {
+                    // The file name carries a leading '/'.
+                    path: format!("{}{}", self.as_inner().path(), f.get_name()),
+                    level,
+                    smallest_key: f.get_smallestkey().to_vec(),
+                    largest_key: f.get_largestkey().to_vec(),
+                    size: f.get_size(),
+                });
+            }
+        }
+        Ok(files)
+    }
 }
 
 #[cfg(test)]
@@ -125,6 +153,55 @@ mod tests {
         test_delete_all_in_range(false);
     }
 
+    fn test_delete_ranges_cfs(use_delete_files: bool) {
+        let path = Builder::new()
+            .prefix("engine_delete_ranges_cfs")
+            .tempdir()
+            .unwrap();
+        let path_str = path.path().to_str().unwrap();
+
+        let cfs_opts = ALL_CFS
+            .iter()
+            .map(|cf| CFOptions::new(cf, ColumnFamilyOptions::new()))
+            .collect();
+        let db = new_engine_opt(path_str, DBOptions::new(), cfs_opts).unwrap();
+        let db = Arc::new(db);
+        let db = RocksEngine::from_db(db);
+
+        let kvs: Vec<(&[u8], &[u8])> = vec![
+            (b"k1", b"value"),
+            (b"k2", b"value"),
+            (b"k3", b"value"),
+            (b"k4", b"value"),
+        ];
+        let kvs_left: Vec<(&[u8], &[u8])> = vec![(kvs[0].0, kvs[0].1), (kvs[3].0, kvs[3].1)];
+        for cf in ALL_CFS {
+            for &(k, v) in kvs.as_slice() {
+                db.put_cf(cf, k, v).unwrap();
+            }
+            db.flush_cf(cf, true).unwrap();
+        }
+        check_data(&db, ALL_CFS, kvs.as_slice());
+
+        // Clear ["k2", "k4") in every CF with a single call.
+        let ranges: Vec<(&str, &[u8], &[u8])> = ALL_CFS
+            .iter()
+            .map(|cf| (*cf, b"k2" as &[u8], b"k4" as &[u8]))
+            .collect();
+        db.delete_ranges_cfs(&ranges, use_delete_files).unwrap();
+        check_data(&db, ALL_CFS, kvs_left.as_slice());
+    }
+
+    #[test]
+    fn test_delete_ranges_cfs_use_delete_files() {
+        test_delete_ranges_cfs(true);
+    }
+
+    #[test]
+    fn test_delete_ranges_cfs_not_use_delete_files() {
+        test_delete_ranges_cfs(false);
+    }
+
     #[test]
     fn test_delete_all_files_in_range() {
         let path = Builder::new()
@@ -209,4 +286,66 @@ mod tests {
             .unwrap();
         check_data(&db, &[cf], kvs_left.as_slice());
     }
+
+    #[test]
+    fn test_get_cf_pending_compaction_bytes() {
+        let path = Builder::new()
+            .prefix("engine_pending_compaction_bytes")
+            .tempdir()
+            .unwrap();
+        let path_str = path.path().to_str().unwrap();
+
+        // Disable auto compactions and lower the L0 trigger so flushed files
+        // pile up as compaction debt instead of being compacted away.
+        let mut cf_opts = ColumnFamilyOptions::new();
+        cf_opts.set_disable_auto_compactions(true);
+        cf_opts.set_level_zero_file_num_compaction_trigger(1);
+        let cfs_opts = vec![CFOptions::new("default", cf_opts)];
+        let db = new_engine_opt(path_str, DBOptions::new(), cfs_opts).unwrap();
+        let db = Arc::new(db);
+        let db = RocksEngine::from_db(db);
+
+        assert_eq!(db.get_cf_pending_compaction_bytes("default").unwrap(), 0);
+
+        let value = vec![b'v'; 1024];
+        for i in 0..8 {
+            for j in 0..64 {
+                db.put(format!("k{}_{}", i, j).as_bytes(), &value).unwrap();
+            }
+            db.flush_cf("default", true).unwrap();
+        }
+
+        assert!(db.get_cf_pending_compaction_bytes("default").unwrap() > 0);
+    }
+
+    #[test]
+    fn test_get_cf_files() {
+        let path = Builder::new()
+            .prefix("engine_get_cf_files")
+            .tempdir()
+            .unwrap();
+        let path_str = path.path().to_str().unwrap();
+
+        let cfs_opts = vec![CFOptions::new("default", ColumnFamilyOptions::new())];
+        let db = new_engine_opt(path_str, DBOptions::new(), cfs_opts).unwrap();
+        let db = Arc::new(db);
+        let db = RocksEngine::from_db(db);
+
+        assert!(db.get_cf_files("default").unwrap().is_empty());
+
+        for &(k, v) in &[(b"k1", b"v1"), (b"k2", b"v2"), (b"k3", b"v3")] {
+            db.put(k, v).unwrap();
+        }
+        db.flush_cf("default", true).unwrap();
+
+        let files = db.get_cf_files("default").unwrap();
+        assert!(!files.is_empty());
+        for f in files {
+            assert!(f.path.starts_with(path_str), "{}", f.path);
+            assert!(std::path::Path::new(&f.path).exists(), "{}", f.path);
+            assert!(f.size > 0);
+            assert!(f.smallest_key.as_slice() <= b"k1" as &[u8]);
+            assert!(f.largest_key.as_slice() >= b"k3" as &[u8]);
+        }
+    }
 }
@@ -42,6 +42,10 @@ impl Snapshot<RocksEngine> for RocksSnapshot {
         self.db.cf_names()
     }
 
+    fn sequence_number(&self) -> u64 {
+        unsafe { self.snap.get_sequence_number() }
+    }
+
     fn into_sync(self) -> RocksSyncSnapshot {
         RocksSyncSnapshot(Arc::new(self))
     }
@@ -144,3 +148,27 @@ impl RocksSyncSnapshot {
 }
 
 impl SyncSnapshot<RocksSnapshot> for RocksSyncSnapshot {}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::new_engine;
+    use engine_traits::{KvEngine, Snapshot, SyncMutable};
+    use tempfile::Builder;
+
+    #[test]
+    fn test_sequence_number() {
+        let path = Builder::new()
+            .prefix("test_snapshot_sequence_number")
+            .tempdir()
+            .unwrap();
+        let engine = new_engine(path.path().to_str().unwrap(), None, &["cf"], None).unwrap();
+
+        let before = engine.snapshot().sequence_number();
+        engine.put(b"k1", b"v1").unwrap();
+        let after = engine.snapshot().sequence_number();
+        assert!(after > before, "{} > {}", after, before);
+
+        // No writes in between, so the sequence number must not move.
+        assert_eq!(engine.snapshot().sequence_number(), after);
+    }
+}
@@ -5,7 +5,7 @@ use crate::options::RocksReadOptions;
 use engine_traits::Error;
 use engine_traits::IterOptions;
 use engine_traits::{CfName, CF_DEFAULT};
-use engine_traits::{ExternalSstFileInfo, SstWriter, SstWriterBuilder};
+use engine_traits::{ExternalSstFileInfo, SstCompressionType, SstWriter, SstWriterBuilder};
 use engine_traits::{Iterable, Result, SstExt, SstReader};
 use engine_traits::{Iterator, SeekKey};
 use rocksdb::DBCompressionType;
@@ -107,6 +107,7 @@ pub struct RocksSstWriterBuilder {
     cf: Option<CfName>,
     db: Option<Arc<DB>>,
     in_memory: bool,
+    compression_type: Option<DBCompressionType>,
 }
 
 impl SstWriterBuilder<RocksEngine> for RocksSstWriterBuilder {
@@ -115,6 +116,7 @@ impl SstWriterBuilder<RocksEngine> for RocksSstWriterBuilder {
             cf: None,
             in_memory: false,
             db: None,
+            compression_type: None,
         }
     }
 
@@ -133,6 +135,11 @@ impl SstWriterBuilder<RocksEngine> for RocksSstWriterBuilder {
         self
     }
 
+    fn set_compression_type(mut self, compression: Option<SstCompressionType>) -> Self {
+        self.compression_type = compression.map(to_rocks_compression_type);
+        self
+    }
+
     fn build(self, path: &str) -> Result<RocksSstWriter> {
         let mut env = None;
         let mut io_options = if let Some(db) = self.db.as_ref() {
@@ -152,7 +159,10 @@ impl SstWriterBuilder<RocksEngine> for RocksSstWriterBuilder {
         } else if let Some(env) = env.as_ref() {
             io_options.set_env(env.clone());
         }
-        io_options.compression(get_fastest_supported_compression_type());
+        io_options.compression(
+            self.compression_type
+                .unwrap_or_else(get_fastest_supported_compression_type),
+        );
         // in rocksdb 5.5.1, SstFileWriter will try to use bottommost_compression and
         // compression_per_level first, so to make sure our specified compression type
         // being used, we must set them empty or disabled.
@@ -164,6 +174,14 @@ impl SstWriterBuilder<RocksEngine> for RocksSstWriterBuilder {
     }
 }
 
+fn to_rocks_compression_type(compression: SstCompressionType) -> DBCompressionType {
+    match compression {
+        SstCompressionType::Lz4 => DBCompressionType::Lz4,
+        SstCompressionType::Snappy => DBCompressionType::Snappy,
+        SstCompressionType::Zstd => DBCompressionType::Zstd,
+    }
+}
+
 pub struct RocksSstWriter {
     writer: SstFileWriter,
     env: Option<Arc<Env>>,
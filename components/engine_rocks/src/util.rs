@@ -6,6 +6,7 @@ use crate::engine::RocksEngine;
 use crate::rocks_metrics_defs::*;
 use engine::rocks::util::new_engine as new_engine_raw;
 use engine::rocks::util::new_engine_opt as new_engine_opt_raw;
+use engine::rocks::util::new_engine_read_only as new_engine_read_only_raw;
 use engine::rocks::util::CFOptions;
 use engine_traits::Range;
 use engine_traits::CF_DEFAULT;
@@ -110,6 +111,18 @@ pub fn new_engine(
     Ok(engine)
 }
 
+/// Opens an existing db in RocksDB read-only mode, so offline analysis tools
+/// cannot accidentally mutate it. Write operations on the returned engine
+/// fail with a "read-only engine" error.
+pub fn new_engine_read_only(path: &str, db_opts: Option<RocksDBOptions>) -> Result<RocksEngine> {
+    let db_opts = db_opts.map(RocksDBOptions::into_raw);
+    let engine =
+        new_engine_read_only_raw(path, db_opts).map_err(|e| Error::Other(box_err!(e)))?;
+    let engine = Arc::new(engine);
+    let engine = RocksEngine::from_db(engine);
+    Ok(engine)
+}
+
 pub fn new_engine_opt(
     path: &str,
     db_opt: RocksDBOptions,
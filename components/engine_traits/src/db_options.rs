@@ -8,6 +8,12 @@ pub trait DBOptionsExt {
 
     fn get_db_options(&self) -> Self::DBOptions;
     fn set_db_options(&self, options: &[(&str, &str)]) -> Result<()>;
+    /// Returns the rate limiter's bytes-per-second budget, or `None` if the
+    /// engine has no rate limiter installed.
+    fn get_rate_limiter_bytes_per_sec(&self) -> Option<i64>;
+    /// Adjusts the rate limiter's budget to `rate_bytes_per_sec`, installing
+    /// a rate limiter if the engine has none. Zero disables throttling.
+    fn set_rate_limiter_bytes_per_sec(&self, rate_bytes_per_sec: i64) -> Result<()>;
 }
 
 /// A handle to a database's options
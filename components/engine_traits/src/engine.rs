@@ -21,6 +21,7 @@ pub trait KvEngine:
     + SstExt
     + TablePropertiesExt
     + MiscExt
+    + UpdatesExt
     + Send
     + Sync
     + Clone
@@ -41,6 +41,13 @@ quick_error! {
             description("CF name not found")
             display("CF {} not found", name)
         }
+        WalTruncated(requested: u64, first_available: u64) {
+            description("WAL has been truncated")
+            display(
+                "WAL truncated: updates since {} requested but the oldest available is {}",
+                requested, first_available
+            )
+        }
         Codec(err: tikv_util::codec::Error) {
             from()
             cause(err)
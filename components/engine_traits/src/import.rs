@@ -20,6 +20,18 @@ pub trait ImportExt: CFHandleExt {
         files: &[&str],
     ) -> Result<()>;
 
+    /// Clears `[start_key, end_key)` in the cf and ingests `files` into it as
+    /// one atomic write, so readers and crash recovery never observe the
+    /// range cleared but not yet refilled.
+    fn ingest_external_file_in_range_cf(
+        &self,
+        cf: &Self::CFHandle,
+        opt: &Self::IngestExternalFileOptions,
+        files: &[&str],
+        start_key: &[u8],
+        end_key: &[u8],
+    ) -> Result<()>;
+
     fn validate_sst_for_ingestion<P: AsRef<Path>>(
         &self,
         cf: &Self::CFHandle,
@@ -75,6 +75,53 @@ pub trait Iterable {
         scan_impl(self.iterator_cf_opt(cf, iter_opt)?, start_key, f)
     }
 
+    // Like `scan_cf`, but collects at most `limit` key-value pairs and
+    // reports whether the range contains more beyond them. Useful for
+    // paginated scans.
+    fn scan_limited(
+        &self,
+        cf: &str,
+        start_key: &[u8],
+        end_key: &[u8],
+        limit: usize,
+    ) -> Result<(Vec<(Vec<u8>, Vec<u8>)>, bool)> {
+        let mut pairs = Vec::with_capacity(limit);
+        let mut has_more = false;
+        self.scan_cf(cf, start_key, end_key, false, |key, value| {
+            if pairs.len() == limit {
+                has_more = true;
+                return Ok(false);
+            }
+            pairs.push((key.to_vec(), value.to_vec()));
+            Ok(true)
+        })?;
+        Ok((pairs, has_more))
+    }
+
+    // Computes a crc64 checksum together with the key-value count and total
+    // byte size over `[start_key, end_key)` of the given column family. The
+    // range is iterated in key order, so the checksum only depends on the
+    // data, not on how it was written. Useful for consistency checks between
+    // replicas.
+    fn checksum_range(
+        &self,
+        cf: &str,
+        start_key: &[u8],
+        end_key: &[u8],
+    ) -> Result<(u64, u64, u64)> {
+        let mut digest = crc64fast::Digest::new();
+        let mut kvs = 0;
+        let mut bytes = 0;
+        self.scan_cf(cf, start_key, end_key, false, |key, value| {
+            digest.write(key);
+            digest.write(value);
+            kvs += 1;
+            bytes += (key.len() + value.len()) as u64;
+            Ok(true)
+        })?;
+        Ok((digest.sum64(), kvs, bytes))
+    }
+
     // Seek the first key >= given key, if not found, return None.
     fn seek(&self, key: &[u8]) -> Result<Option<(Vec<u8>, Vec<u8>)>> {
         let mut iter = self.iterator()?;
@@ -233,6 +233,8 @@ mod table_properties;
 pub use crate::table_properties::*;
 mod write_batch;
 pub use crate::write_batch::*;
+mod updates;
+pub use crate::updates::*;
 mod encryption;
 pub use crate::encryption::*;
 mod properties;
@@ -264,6 +266,8 @@ pub use crate::range::*;
 pub mod metrics_flusher;
 pub mod util;
 pub use crate::metrics_flusher::*;
+pub mod wal_syncer;
+pub use crate::wal_syncer::*;
 pub mod compact;
 pub use compact::*;
 
@@ -19,6 +19,16 @@ use tikv_util::keybuilder::KeyBuilder;
 // FIXME: Find somewhere else to put this?
 pub const MAX_DELETE_BATCH_SIZE: usize = 32 * 1024;
 
+/// Metadata of a live SST file, as reported by the engine.
+#[derive(Clone, Debug)]
+pub struct SstFileInfo {
+    pub path: String,
+    pub level: usize,
+    pub smallest_key: Vec<u8>,
+    pub largest_key: Vec<u8>,
+    pub size: u64,
+}
+
 pub trait MiscExt: Iterable + WriteBatchExt + CFNamesExt {
     fn is_titan(&self) -> bool {
         false
@@ -91,6 +101,46 @@ pub trait MiscExt: Iterable + WriteBatchExt + CFNamesExt {
         Ok(())
     }
 
+    /// Deletes all keys in `ranges`, each a `(cf, start_key, end_key)`
+    /// triple. The deletions go down in a single write batch so a crash
+    /// cannot leave only some of the ranges cleared. When `use_delete_files`
+    /// is set, SST files entirely inside a range are dropped first via
+    /// `delete_files_in_range_cf`, which is much faster for large ranges.
+    fn delete_ranges_cfs(
+        &self,
+        ranges: &[(&str, &[u8], &[u8])],
+        use_delete_files: bool,
+    ) -> Result<()> {
+        let mut wb = self.write_batch();
+        for &(cf, start_key, end_key) in ranges {
+            if start_key >= end_key {
+                continue;
+            }
+            if use_delete_files {
+                self.delete_files_in_range_cf(cf, start_key, end_key, false)?;
+            }
+            if cf == CF_LOCK {
+                // DeleteRange on the lock CF hurts its point-lookup
+                // performance, so delete its keys one by one instead.
+                let start = KeyBuilder::from_slice(start_key, 0, 0);
+                let end = KeyBuilder::from_slice(end_key, 0, 0);
+                let iter_opt = IterOptions::new(Some(start), Some(end), false);
+                let mut it = self.iterator_cf_opt(cf, iter_opt)?;
+                let mut it_valid = it.seek(start_key.into())?;
+                while it_valid {
+                    wb.delete_cf(cf, it.key())?;
+                    it_valid = it.next()?;
+                }
+            } else {
+                wb.delete_range_cf(cf, start_key, end_key)?;
+            }
+        }
+        if wb.count() > 0 {
+            self.write(&wb)?;
+        }
+        Ok(())
+    }
+
     fn delete_all_files_in_range(&self, start_key: &[u8], end_key: &[u8]) -> Result<()> {
         if start_key >= end_key {
             return Ok(());
@@ -105,4 +155,12 @@ pub trait MiscExt: Iterable + WriteBatchExt + CFNamesExt {
 
     /// Return the approximate number of records and size in the range of memtables of the cf.
     fn get_approximate_memtable_stats_cf(&self, cf: &str, range: &Range) -> Result<(u64, u64)>;
+
+    /// Returns the engine's estimate of bytes pending compaction in the cf,
+    /// or zero when the cf has no compaction backlog.
+    fn get_cf_pending_compaction_bytes(&self, cf: &str) -> Result<u64>;
+
+    /// Lists the live SST files of the cf, with the level, key bounds, and
+    /// size of each.
+    fn get_cf_files(&self, cf: &str) -> Result<Vec<SstFileInfo>>;
 }
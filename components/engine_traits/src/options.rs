@@ -63,6 +63,17 @@ pub struct IterOptions {
 }
 
 impl IterOptions {
+    /// Creates options bounded to the key range `[lower, upper)`.
+    ///
+    /// Prefer this over setting bounds after the fact: forgetting one of the
+    /// bounds lets iterators silently step across region boundaries.
+    pub fn with_bounds(lower: Vec<u8>, upper: Vec<u8>) -> IterOptions {
+        let mut opts = IterOptions::default();
+        opts.set_vec_lower_bound(lower);
+        opts.set_vec_upper_bound(upper);
+        opts
+    }
+
     pub fn new(
         lower_bound: Option<KeyBuilder>,
         upper_bound: Option<KeyBuilder>,
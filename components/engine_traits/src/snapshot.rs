@@ -15,6 +15,9 @@ where
 
     fn cf_names(&self) -> Vec<&str>;
 
+    /// Returns the engine sequence number this snapshot was taken at.
+    fn sequence_number(&self) -> u64;
+
     fn into_sync(self) -> Self::SyncSnapshot;
 
     fn get_db(&self) -> &E;
@@ -59,10 +59,22 @@ where
     /// Set it to true, the builder builds a in-memory SST builder.
     fn set_in_memory(self, in_memory: bool) -> Self;
 
+    /// Set the compression type of the SST file. The engine specific default compression is
+    /// used when it is not set.
+    fn set_compression_type(self, compression: Option<SstCompressionType>) -> Self;
+
     /// Builder a SstWriter.
     fn build(self, path: &str) -> Result<E::SstWriter>;
 }
 
+/// Compression types supported for SST files.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SstCompressionType {
+    Lz4,
+    Snappy,
+    Zstd,
+}
+
 pub trait ExternalSstFileInfo {
     fn new() -> Self;
     fn file_path(&self) -> PathBuf;
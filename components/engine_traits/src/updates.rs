@@ -0,0 +1,21 @@
+// Copyright 2020 TiKV Project Authors. Licensed under Apache-2.0.
+
+use crate::errors::Result;
+
+/// A single change replayed from the engine's write-ahead log.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChangeEvent {
+    Put { key: Vec<u8>, value: Vec<u8> },
+    Delete { key: Vec<u8> },
+}
+
+/// A trait for engines that can replay committed changes from their
+/// write-ahead log.
+pub trait UpdatesExt {
+    type ChangeIter: std::iter::Iterator<Item = Result<ChangeEvent>>;
+
+    /// Replays all changes committed after `sequence_number`, in commit
+    /// order. Returns `Error::WalTruncated` when the log no longer reaches
+    /// back to `sequence_number`; callers must fall back to a full scan.
+    fn scan_updates_since(&self, sequence_number: u64) -> Result<Self::ChangeIter>;
+}
@@ -0,0 +1,114 @@
+// Copyright 2020 TiKV Project Authors. Licensed under Apache-2.0.
+
+use std::io;
+use std::sync::mpsc::{self, Sender};
+use std::thread::{Builder as ThreadBuilder, JoinHandle};
+use std::time::Duration;
+
+use rand::Rng;
+
+use crate::*;
+
+/// A callback invoked with the engine name ("kv" or "raft") when a WAL sync
+/// fails.
+pub type SyncErrorCallback = Box<dyn Fn(&str, Error) + Send>;
+
+/// Periodically syncs the WAL of both engines on a background thread.
+///
+/// Each cycle waits `interval` plus a random duration up to `jitter` before
+/// syncing. The jitter de-synchronizes fsync across the nodes of a cluster,
+/// avoiding storms of simultaneous disk flushes. A zero jitter preserves
+/// exact intervals.
+pub struct PeriodicWalSyncer<K: KvEngine, R: KvEngine> {
+    engines: KvEngines<K, R>,
+    interval: Duration,
+    jitter: Duration,
+    handle: Option<JoinHandle<()>>,
+    sender: Option<Sender<bool>>,
+}
+
+impl<K: KvEngine, R: KvEngine> PeriodicWalSyncer<K, R> {
+    pub fn new(engines: KvEngines<K, R>, interval: Duration, jitter: Duration) -> Self {
+        PeriodicWalSyncer {
+            engines,
+            interval,
+            jitter,
+            handle: None,
+            sender: None,
+        }
+    }
+
+    pub fn start(&mut self, on_error: SyncErrorCallback) -> Result<(), io::Error> {
+        let engines = self.engines.clone();
+        let (interval, jitter) = (self.interval, self.jitter);
+        let (tx, rx) = mpsc::channel();
+        self.sender = Some(tx);
+        let h = ThreadBuilder::new()
+            .name("wal-syncer".to_owned())
+            .spawn(move || {
+                let mut rng = rand::thread_rng();
+                loop {
+                    let timeout = next_sync_timeout(&mut rng, interval, jitter);
+                    match rx.recv_timeout(timeout) {
+                        Err(mpsc::RecvTimeoutError::Timeout) => {
+                            if let Err(e) = engines.sync_kv() {
+                                on_error("kv", e);
+                            }
+                            if let Err(e) = engines.sync_raft() {
+                                on_error("raft", e);
+                            }
+                        }
+                        _ => return,
+                    }
+                }
+            })?;
+
+        self.handle = Some(h);
+        Ok(())
+    }
+
+    pub fn stop(&mut self) {
+        let h = self.handle.take();
+        if h.is_none() {
+            return;
+        }
+        drop(self.sender.take().unwrap());
+        if let Err(e) = h.unwrap().join() {
+            error!("join wal syncer failed"; "err" => ?e);
+            return;
+        }
+    }
+}
+
+fn next_sync_timeout<T: Rng>(rng: &mut T, interval: Duration, jitter: Duration) -> Duration {
+    if jitter == Duration::from_secs(0) {
+        return interval;
+    }
+    interval + Duration::from_millis(rng.gen_range(0, jitter.as_millis() as u64 + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_sync_timeout() {
+        let mut rng = rand::thread_rng();
+        let interval = Duration::from_millis(100);
+        let jitter = Duration::from_millis(20);
+        for _ in 0..100 {
+            let timeout = next_sync_timeout(&mut rng, interval, jitter);
+            assert!(timeout >= interval, "timeout {:?} below interval", timeout);
+            assert!(
+                timeout <= interval + jitter,
+                "timeout {:?} above jittered window",
+                timeout
+            );
+        }
+        // Zero jitter preserves exact intervals.
+        for _ in 0..10 {
+            let timeout = next_sync_timeout(&mut rng, interval, Duration::from_secs(0));
+            assert_eq!(timeout, interval);
+        }
+    }
+}
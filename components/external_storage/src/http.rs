@@ -0,0 +1,189 @@
+// Copyright 2020 TiKV Project Authors. Licensed under Apache-2.0.
+
+use std::io::{Error, ErrorKind, Result};
+use std::marker::Unpin;
+
+use futures_io::AsyncRead;
+use futures_util::{
+    future::{Either, FutureExt},
+    stream::TryStreamExt,
+};
+use hyper::client::HttpConnector;
+use hyper::{Client, StatusCode, Uri};
+use hyper_tls::HttpsConnector;
+
+use super::{util::error_stream, ExternalStorage};
+
+/// A read-only storage that fetches files over HTTP(S).
+///
+/// Files are fetched with `GET {base_url}/{prefix}/{name}`. It is meant for
+/// restoring from backups served by a plain file server; writes are not
+/// supported.
+#[derive(Clone)]
+pub struct HttpStorage {
+    base_url: String,
+    prefix: String,
+    client: Client<HttpsConnector<HttpConnector>>,
+}
+
+impl HttpStorage {
+    /// Create a new HTTP storage rooted at the given base URL. The prefix may
+    /// be empty.
+    pub fn new(base_url: &str, prefix: &str) -> Result<HttpStorage> {
+        let url = url::Url::parse(base_url)
+            .map_err(|e| Error::new(ErrorKind::InvalidInput, format!("invalid url: {}", e)))?;
+        match url.scheme() {
+            "http" | "https" => {}
+            scheme => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("unsupported scheme {} in url {}", scheme, base_url),
+                ));
+            }
+        }
+        info!("create http storage"; "base_url" => base_url, "prefix" => prefix);
+        Ok(HttpStorage {
+            base_url: base_url.trim_end_matches('/').to_owned(),
+            prefix: prefix.trim_matches('/').to_owned(),
+            client: Client::builder().build(HttpsConnector::new()),
+        })
+    }
+
+    fn url_of(&self, name: &str) -> String {
+        if self.prefix.is_empty() {
+            format!("{}/{}", self.base_url, name)
+        } else {
+            format!("{}/{}/{}", self.base_url, self.prefix, name)
+        }
+    }
+}
+
+impl ExternalStorage for HttpStorage {
+    fn write(
+        &self,
+        name: &str,
+        _reader: Box<dyn AsyncRead + Send + Unpin>,
+        _content_length: u64,
+    ) -> Result<()> {
+        Err(Error::new(
+            ErrorKind::Other,
+            format!("cannot write [{}]: http storage is read-only", name),
+        ))
+    }
+
+    fn read(&self, name: &str) -> Box<dyn AsyncRead + Unpin + '_> {
+        let url = self.url_of(name);
+        debug!("read file from http storage"; "url" => %url);
+        let uri: Uri = match url.parse() {
+            Ok(uri) => uri,
+            Err(e) => {
+                let e = Error::new(ErrorKind::InvalidInput, format!("invalid url {}: {}", url, e));
+                return Box::new(error_stream(e).into_async_read()) as _;
+            }
+        };
+        Box::new(
+            self.client
+                .get(uri)
+                .map(move |res| match res {
+                    Ok(resp) if resp.status() == StatusCode::NOT_FOUND => {
+                        Either::Left(error_stream(Error::new(
+                            ErrorKind::NotFound,
+                            format!("no file at {}", url),
+                        )))
+                    }
+                    Ok(resp) if !resp.status().is_success() => {
+                        Either::Left(error_stream(Error::new(
+                            ErrorKind::Other,
+                            format!("failed to get {}: status {}", url, resp.status()),
+                        )))
+                    }
+                    Ok(resp) => Either::Right(resp.into_body().map_err(|e| {
+                        Error::new(ErrorKind::Other, format!("failed to read body: {}", e))
+                    })),
+                    Err(e) => Either::Left(error_stream(Error::new(
+                        ErrorKind::Other,
+                        format!("failed to get {}: {}", url, e),
+                    ))),
+                })
+                .flatten_stream()
+                .into_async_read(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::block_on_external_io;
+    use futures_util::io::AsyncReadExt;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    // Serves `connections` HTTP/1.1 requests, replying 200 with `content` for
+    // `path` and 404 for anything else.
+    fn spawn_mock_server(path: &'static str, content: &'static [u8], connections: usize) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for _ in 0..connections {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut req = Vec::new();
+                let mut buf = [0; 1024];
+                while !req.windows(4).any(|w| w == b"\r\n\r\n") {
+                    let n = stream.read(&mut buf).unwrap();
+                    req.extend_from_slice(&buf[..n]);
+                }
+                let req = String::from_utf8(req).unwrap();
+                let target = req.split_whitespace().nth(1).unwrap();
+                let resp = if target == path {
+                    let mut resp = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        content.len()
+                    )
+                    .into_bytes();
+                    resp.extend_from_slice(content);
+                    resp
+                } else {
+                    b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                        .to_vec()
+                };
+                stream.write_all(&resp).unwrap();
+            }
+        });
+        format!("http://{}", addr)
+    }
+
+    #[test]
+    fn test_http_storage() {
+        let magic_contents: &[u8] = b"5678";
+        let base_url = spawn_mock_server("/backup/a.sst", magic_contents, 2);
+        let hs = HttpStorage::new(&base_url, "backup").unwrap();
+
+        // A successful read streams the body back.
+        let mut buf = vec![];
+        block_on_external_io(hs.read("a.sst").read_to_end(&mut buf)).unwrap();
+        assert_eq!(buf, magic_contents);
+
+        // A 404 maps to a not-found error.
+        let err = block_on_external_io(hs.read("b.sst").read_to_end(&mut vec![])).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+
+        // Writes are rejected.
+        hs.write("a.sst", Box::new(magic_contents), magic_contents.len() as u64)
+            .unwrap_err();
+    }
+
+    #[test]
+    fn test_http_storage_url_of() {
+        let hs = HttpStorage::new("http://example.com/base/", "prefix").unwrap();
+        assert_eq!(hs.url_of("a.sst"), "http://example.com/base/prefix/a.sst");
+
+        let hs = HttpStorage::new("https://example.com", "").unwrap();
+        assert_eq!(hs.url_of("a.sst"), "https://example.com/a.sst");
+
+        // Only http(s) urls are accepted.
+        HttpStorage::new("ftp://example.com", "").unwrap_err();
+        HttpStorage::new("not a url", "").unwrap_err();
+    }
+}
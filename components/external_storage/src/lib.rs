@@ -5,6 +5,8 @@
 //! This crate define an abstraction of external storage. Currently, it
 //! supports local storage.
 
+#[macro_use]
+extern crate serde_derive;
 #[macro_use]
 extern crate slog_global;
 #[allow(unused_extern_crates)]
@@ -16,6 +18,7 @@ use std::path::Path;
 use std::sync::Arc;
 
 use futures_io::AsyncRead;
+use futures_util::stream::TryStreamExt;
 #[cfg(feature = "prost-codec")]
 use kvproto::backup::storage_backend::Backend;
 #[cfg(feature = "protobuf-codec")]
@@ -23,14 +26,20 @@ use kvproto::backup::StorageBackend_oneof_backend as Backend;
 #[cfg_attr(feature = "protobuf-codec", allow(unused_imports))]
 use kvproto::backup::{Local, Noop, StorageBackend, S3};
 
+mod http;
+pub use http::HttpStorage;
 mod local;
 pub use local::LocalStorage;
+mod manifest;
+pub use manifest::{Manifest, ManifestFile, MANIFEST_NAME};
+mod memory;
+pub use memory::InMemoryStorage;
 mod noop;
 pub use noop::NoopStorage;
 mod s3;
-pub use s3::S3Storage;
+pub use s3::{ConfigError as S3ConfigError, S3Storage};
 mod util;
-pub use util::block_on_external_io;
+pub use util::{block_on_external_io, ProgressReader};
 
 /// Create a new storage from the given storage backend description.
 pub fn create_storage(backend: &StorageBackend) -> io::Result<Arc<dyn ExternalStorage>> {
@@ -52,6 +61,20 @@ pub fn create_storage(backend: &StorageBackend) -> io::Result<Arc<dyn ExternalSt
     }
 }
 
+/// Returns the kind of the storage backend as a static label for metrics.
+///
+/// The cardinality is bounded by the known backend variants, so the label is
+/// safe to attach to metric vectors.
+pub fn backend_label(backend: &StorageBackend) -> &'static str {
+    match &backend.backend {
+        Some(Backend::Local(_)) => "local",
+        Some(Backend::Noop(_)) => "noop",
+        Some(Backend::S3(_)) => "s3",
+        Some(Backend::Gcs(_)) => "gcs",
+        None => "unknown",
+    }
+}
+
 /// Formats the storage backend as a URL.
 pub fn url_of_backend(backend: &StorageBackend) -> url::Url {
     let mut u = url::Url::parse("unknown:///").unwrap();
@@ -93,6 +116,15 @@ pub fn make_local_backend(path: &Path) -> StorageBackend {
     }
 }
 
+/// Creates an in-memory storage for tests.
+///
+/// Unlike the other factories it returns the storage itself instead of a `StorageBackend`,
+/// because there is no protobuf representation for it: in-memory storages never leave the
+/// process that created them.
+pub fn make_memory_backend() -> Arc<dyn ExternalStorage> {
+    Arc::new(InMemoryStorage::default())
+}
+
 /// Creates a noop `StorageBackend`.
 pub fn make_noop_backend() -> StorageBackend {
     let noop = Noop::default();
@@ -136,8 +168,37 @@ pub trait ExternalStorage: 'static {
         reader: Box<dyn AsyncRead + Send + Unpin>,
         content_length: u64,
     ) -> io::Result<()>;
+    /// Like `write`, but invokes `progress` with the total bytes sent so far
+    /// as chunks of the content are consumed.
+    fn write_with_progress(
+        &self,
+        name: &str,
+        reader: Box<dyn AsyncRead + Send + Unpin>,
+        content_length: u64,
+        progress: Box<dyn Fn(u64) + Send>,
+    ) -> io::Result<()> {
+        self.write(
+            name,
+            Box::new(ProgressReader::new(reader, progress)),
+            content_length,
+        )
+    }
     /// Read all contents of the given path.
     fn read(&self, name: &str) -> Box<dyn AsyncRead + Unpin + '_>;
+    /// Read `len` bytes of the given path starting at `offset`.
+    ///
+    /// Ranges past the end of the file are truncated, so an out-of-range
+    /// offset reads back empty. Backends without range support return an
+    /// error stream.
+    fn read_range(&self, name: &str, _offset: u64, _len: u64) -> Box<dyn AsyncRead + Unpin + '_> {
+        Box::new(
+            util::error_stream(io::Error::new(
+                io::ErrorKind::Other,
+                format!("range read of [{}] is not supported", name),
+            ))
+            .into_async_read(),
+        )
+    }
 }
 
 impl ExternalStorage for Arc<dyn ExternalStorage> {
@@ -152,6 +213,9 @@ impl ExternalStorage for Arc<dyn ExternalStorage> {
     fn read(&self, name: &str) -> Box<dyn AsyncRead + Unpin + '_> {
         (**self).read(name)
     }
+    fn read_range(&self, name: &str, offset: u64, len: u64) -> Box<dyn AsyncRead + Unpin + '_> {
+        (**self).read_range(name, offset, len)
+    }
 }
 
 #[cfg(test)]
@@ -170,6 +234,55 @@ mod tests {
         assert!(create_storage(&backend).is_err());
     }
 
+    #[test]
+    fn test_write_with_progress() {
+        use futures_util::io::AllowStdIo;
+        use std::sync::Mutex;
+
+        let content = vec![7u8; 42];
+        let temp_dir = tempfile::Builder::new().tempdir().unwrap();
+        let storages: Vec<Arc<dyn ExternalStorage>> = vec![
+            make_memory_backend(),
+            Arc::new(LocalStorage::new(temp_dir.path()).unwrap()),
+        ];
+        for storage in storages {
+            let reports = Arc::new(Mutex::new(Vec::new()));
+            let recorder = Arc::clone(&reports);
+            storage
+                .write_with_progress(
+                    "file",
+                    Box::new(AllowStdIo::new(io::Cursor::new(content.clone()))),
+                    content.len() as u64,
+                    Box::new(move |written| recorder.lock().unwrap().push(written)),
+                )
+                .unwrap();
+
+            // The reported byte counts grow monotonically up to the content
+            // length.
+            let reports = reports.lock().unwrap();
+            assert!(!reports.is_empty());
+            for w in reports.windows(2) {
+                assert!(w[0] < w[1], "{:?}", *reports);
+            }
+            assert_eq!(*reports.last().unwrap(), content.len() as u64);
+        }
+    }
+
+    #[test]
+    fn test_backend_label() {
+        let backend = make_local_backend(Path::new("/tmp/a"));
+        assert_eq!(backend_label(&backend), "local");
+
+        let backend = make_noop_backend();
+        assert_eq!(backend_label(&backend), "noop");
+
+        let backend = make_s3_backend(S3::default());
+        assert_eq!(backend_label(&backend), "s3");
+
+        let backend = StorageBackend::default();
+        assert_eq!(backend_label(&backend), "unknown");
+    }
+
     #[test]
     fn test_url_of_backend() {
         use kvproto::backup::S3;
@@ -1,7 +1,7 @@
 // Copyright 2019 TiKV Project Authors. Licensed under Apache-2.0.
 
 use std::fs::{self, File};
-use std::io;
+use std::io::{self, Read, Seek, SeekFrom};
 use std::marker::Unpin;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
@@ -34,6 +34,7 @@ pub struct LocalStorage {
     base: PathBuf,
     base_dir: Arc<File>,
     tmp: PathBuf,
+    sharded: bool,
 }
 
 impl LocalStorage {
@@ -47,9 +48,62 @@ impl LocalStorage {
             base: base.to_owned(),
             base_dir,
             tmp: tmp_dir,
+            sharded: false,
         })
     }
 
+    /// Create a new local storage which shards files into two-level subdirectories by a hash
+    /// of the file name. This avoids huge flat directories when a backup consists of tens of
+    /// thousands of SSTs, which is slow on some file systems.
+    pub fn new_sharded(base: &Path) -> io::Result<LocalStorage> {
+        let mut storage = LocalStorage::new(base)?;
+        storage.sharded = true;
+        Ok(storage)
+    }
+
+    /// The path of the given file relative to the storage base. `read` and `write` both go
+    /// through this, so they always agree on where a file lives.
+    fn file_path(&self, name: &str) -> PathBuf {
+        if self.sharded {
+            // CRC32 is a specified hash, so the shard of a name stays stable across
+            // processes and releases. `DefaultHasher` must not be used here: its
+            // algorithm is explicitly unspecified and may change between toolchains,
+            // which would make existing shard paths unreachable.
+            let mut digest = crc32fast::Hasher::new();
+            digest.update(name.as_bytes());
+            let hash = digest.finalize();
+            let mut path = PathBuf::new();
+            path.push(format!("{:02x}", (hash >> 8) as u8));
+            path.push(format!("{:02x}", hash as u8));
+            path.push(name);
+            path
+        } else {
+            Path::new(name).to_owned()
+        }
+    }
+
+    /// Lists the names of all files in the storage, no matter which shard they live in.
+    pub fn list(&self) -> io::Result<Vec<String>> {
+        let mut names = Vec::new();
+        let mut dirs = vec![self.base.clone()];
+        while let Some(dir) = dirs.pop() {
+            for entry in fs::read_dir(dir)? {
+                let entry = entry?;
+                let path = entry.path();
+                if path == self.tmp {
+                    continue;
+                }
+                if entry.file_type()?.is_dir() {
+                    dirs.push(path);
+                } else if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+                    names.push(name.to_owned());
+                }
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
     fn tmp_path(&self, path: &Path) -> PathBuf {
         let uid: u64 = rand::thread_rng().gen();
         let tmp_suffix = format!("{}{:016x}", LOCAL_STORAGE_TMP_FILE_SUFFIX, uid);
@@ -76,7 +130,8 @@ impl ExternalStorage for LocalStorage {
             ));
         }
         // Sanitize check, do not save file if it is already exist.
-        if fs::metadata(self.base.join(name)).is_ok() {
+        let file_path = self.base.join(self.file_path(name));
+        if fs::metadata(&file_path).is_ok() {
             return Err(io::Error::new(
                 io::ErrorKind::AlreadyExists,
                 format!("[{}] is already exists in {}", name, self.base.display()),
@@ -90,7 +145,10 @@ impl ExternalStorage for LocalStorage {
         tmp_f.sync_all()?;
         debug!("save file to local storage";
             "name" => %name, "base" => %self.base.display());
-        fs::rename(tmp_path, self.base.join(name))?;
+        if let Some(parent) = file_path.parent() {
+            maybe_create_dir(parent)?;
+        }
+        fs::rename(tmp_path, file_path)?;
         // Fsync the base dir.
         self.base_dir.sync_all()
     }
@@ -98,11 +156,27 @@ impl ExternalStorage for LocalStorage {
     fn read(&self, name: &str) -> Box<dyn AsyncRead + Unpin + '_> {
         debug!("read file from local storage";
             "name" => %name, "base" => %self.base.display());
-        match File::open(self.base.join(name)) {
+        match File::open(self.base.join(self.file_path(name))) {
             Ok(file) => Box::new(AllowStdIo::new(file)) as _,
             Err(e) => Box::new(error_stream(e).into_async_read()) as _,
         }
     }
+
+    fn read_range(&self, name: &str, offset: u64, len: u64) -> Box<dyn AsyncRead + Unpin + '_> {
+        debug!("read range from local storage";
+            "name" => %name, "base" => %self.base.display(),
+            "offset" => offset, "len" => len);
+        let mut file = match File::open(self.base.join(self.file_path(name))) {
+            Ok(file) => file,
+            Err(e) => return Box::new(error_stream(e).into_async_read()) as _,
+        };
+        // Seeking past the end of the file is allowed, the `take` below simply
+        // reads back empty then.
+        if let Err(e) = file.seek(SeekFrom::Start(offset)) {
+            return Box::new(error_stream(e).into_async_read()) as _;
+        }
+        Box::new(AllowStdIo::new(file.take(len))) as _
+    }
 }
 
 #[cfg(test)]
@@ -145,4 +219,87 @@ mod tests {
         ls.write("/", Box::new(magic_contents), content_length)
             .unwrap_err();
     }
+
+    #[test]
+    fn test_local_storage_read_range() {
+        use futures_util::io::AsyncReadExt;
+
+        let temp_dir = Builder::new().tempdir().unwrap();
+        let ls = LocalStorage::new(temp_dir.path()).unwrap();
+        let contents: &[u8] = b"0123456789";
+        ls.write("a.log", Box::new(contents), contents.len() as u64)
+            .unwrap();
+
+        let read_range = |offset, len| {
+            let mut buf = vec![];
+            block_on(ls.read_range("a.log", offset, len).read_to_end(&mut buf)).unwrap();
+            buf
+        };
+        // A middle slice.
+        assert_eq!(read_range(2, 4), b"2345");
+        // A range past the end of the file is truncated.
+        assert_eq!(read_range(8, 100), b"89");
+        // An out-of-range offset reads back empty.
+        assert_eq!(read_range(100, 4), b"");
+
+        // A missing name still results in an error.
+        let err = block_on(ls.read_range("b.log", 0, 4).read_to_end(&mut vec![])).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_local_storage_sharded() {
+        use futures_util::io::AsyncReadExt;
+
+        let temp_dir = Builder::new().tempdir().unwrap();
+        let path = temp_dir.path();
+        let ls = LocalStorage::new_sharded(path).unwrap();
+
+        let mut names = vec![];
+        for i in 0..50 {
+            let name = format!("{}.sst", i);
+            let content = name.clone().into_bytes();
+            ls.write(
+                &name,
+                Box::new(AllowStdIo::new(io::Cursor::new(content))),
+                name.len() as u64,
+            )
+            .unwrap();
+            names.push(name);
+        }
+
+        for name in &names {
+            // Files land in two-level shard directories instead of the flat base.
+            assert!(fs::metadata(path.join(name)).is_err());
+            let sharded_path = path.join(ls.file_path(name));
+            assert_eq!(
+                sharded_path.components().count(),
+                path.components().count() + 3
+            );
+            assert!(fs::metadata(&sharded_path).is_ok());
+
+            // ... and are still read back by name.
+            let mut buf = vec![];
+            block_on(ls.read(name).read_to_end(&mut buf)).unwrap();
+            assert_eq!(buf, name.as_bytes());
+        }
+
+        // `list` sees every file, no matter which shard it lives in.
+        let mut expected = names.clone();
+        expected.sort();
+        assert_eq!(ls.list().unwrap(), expected);
+
+        // The shard of a name is derived from CRC32, so it never changes across
+        // releases. If this assertion fails, files written by older versions
+        // have become unreachable.
+        assert_eq!(ls.file_path("a.sst"), Path::new("38/f4/a.sst"));
+
+        // Duplicated names are still rejected in sharded mode.
+        ls.write(
+            &names[0],
+            Box::new(AllowStdIo::new(io::Cursor::new(vec![]))),
+            0,
+        )
+        .unwrap_err();
+    }
 }
@@ -0,0 +1,133 @@
+// Copyright 2020 TiKV Project Authors. Licensed under Apache-2.0.
+
+use std::collections::HashMap;
+use std::io;
+use std::marker::Unpin;
+use std::sync::{Arc, Mutex};
+
+use futures_executor::block_on;
+use futures_io::AsyncRead;
+use futures_util::{
+    io::{copy, AllowStdIo},
+    stream::TryStreamExt,
+};
+
+use super::{util::error_stream, ExternalStorage};
+
+/// A storage that saves files in memory.
+///
+/// It has no protobuf representation, so it cannot be created through `create_storage`.
+/// It is mainly used to speed up unit tests of code paths built on `ExternalStorage`,
+/// which would otherwise write temporary files to disk.
+#[derive(Clone, Default)]
+pub struct InMemoryStorage {
+    files: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+}
+
+impl InMemoryStorage {
+    /// Lists the names of all files in the storage.
+    pub fn list(&self) -> Vec<String> {
+        let files = self.files.lock().unwrap();
+        let mut names: Vec<_> = files.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Checks whether a file with the given name exists.
+    pub fn exists(&self, name: &str) -> bool {
+        self.files.lock().unwrap().contains_key(name)
+    }
+}
+
+impl ExternalStorage for InMemoryStorage {
+    fn write(
+        &self,
+        name: &str,
+        reader: Box<dyn AsyncRead + Send + Unpin>,
+        _content_length: u64,
+    ) -> io::Result<()> {
+        let mut content = AllowStdIo::new(Vec::new());
+        block_on(copy(reader, &mut content))?;
+        let mut files = self.files.lock().unwrap();
+        if files.contains_key(name) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("[{}] is already exists in memory storage", name),
+            ));
+        }
+        files.insert(name.to_owned(), content.into_inner());
+        Ok(())
+    }
+
+    fn read(&self, name: &str) -> Box<dyn AsyncRead + Unpin + '_> {
+        match self.files.lock().unwrap().get(name) {
+            Some(content) => Box::new(AllowStdIo::new(io::Cursor::new(content.clone()))) as _,
+            None => {
+                let e = io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("[{}] not found in memory storage", name),
+                );
+                Box::new(error_stream(e).into_async_read()) as _
+            }
+        }
+    }
+
+    fn read_range(&self, name: &str, offset: u64, len: u64) -> Box<dyn AsyncRead + Unpin + '_> {
+        match self.files.lock().unwrap().get(name) {
+            Some(content) => {
+                let start = std::cmp::min(offset as usize, content.len());
+                let end = std::cmp::min(start.saturating_add(len as usize), content.len());
+                let slice = content[start..end].to_vec();
+                Box::new(AllowStdIo::new(io::Cursor::new(slice))) as _
+            }
+            None => {
+                let e = io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("[{}] not found in memory storage", name),
+                );
+                Box::new(error_stream(e).into_async_read()) as _
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::io::AsyncReadExt;
+
+    #[test]
+    fn test_memory_storage() {
+        let ms = InMemoryStorage::default();
+
+        let magic_contents: &[u8] = b"5678";
+        ms.write("a.log", Box::new(magic_contents), magic_contents.len() as u64)
+            .unwrap();
+        assert!(ms.exists("a.log"));
+        assert_eq!(ms.list(), vec!["a.log".to_owned()]);
+
+        let mut buf = vec![];
+        block_on(ms.read("a.log").read_to_end(&mut buf)).unwrap();
+        assert_eq!(buf, magic_contents);
+
+        // Duplicated names are rejected.
+        ms.write("a.log", Box::new(magic_contents), magic_contents.len() as u64)
+            .unwrap_err();
+
+        // Reading a missing name results in a not-found error.
+        assert!(!ms.exists("b.log"));
+        let err = block_on(ms.read("b.log").read_to_end(&mut vec![])).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+        // Range reads are truncated to the file length.
+        let mut buf = vec![];
+        block_on(ms.read_range("a.log", 1, 2).read_to_end(&mut buf)).unwrap();
+        assert_eq!(buf, b"67");
+        let mut buf = vec![];
+        block_on(ms.read_range("a.log", 2, 100).read_to_end(&mut buf)).unwrap();
+        assert_eq!(buf, b"78");
+        let mut buf = vec![];
+        block_on(ms.read_range("a.log", 100, 4).read_to_end(&mut buf)).unwrap();
+        assert_eq!(buf, b"");
+    }
+}
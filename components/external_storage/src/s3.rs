@@ -2,6 +2,7 @@
 
 use std::io::{Error, ErrorKind, Result};
 use std::marker::PhantomData;
+use std::time::Duration;
 
 use futures_io::AsyncRead;
 use futures_util::{future::FutureExt, stream::TryStreamExt};
@@ -12,7 +13,7 @@ use rusoto_core::{
 };
 use rusoto_s3::*;
 
-use rusoto_util::new_client;
+use rusoto_util::{new_client, timeout_from_secs};
 
 use super::{
     util::{block_on_external_io, error_stream, AsyncReadAsSyncStreamOfBytes},
@@ -22,11 +23,45 @@ use kvproto::backup::S3 as Config;
 
 const READ_BUF_SIZE: usize = 1024 * 1024 * 2;
 
+/// A configuration problem that prevents the S3 client from being built.
+///
+/// The surfaced `io::Error` keeps `ErrorKind::InvalidInput`; callers that
+/// need the precise classification can downcast its source to this type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigError {
+    MissingBucket,
+    InvalidRegion,
+    MissingCredentials,
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::MissingBucket => write!(f, "missing bucket name"),
+            ConfigError::InvalidRegion => write!(f, "invalid region"),
+            ConfigError::MissingCredentials => {
+                write!(f, "access_key and secret_access_key must be set together")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<ConfigError> for Error {
+    fn from(e: ConfigError) -> Error {
+        Error::new(ErrorKind::InvalidInput, e)
+    }
+}
+
 /// S3 compatible storage
 #[derive(Clone)]
 pub struct S3Storage {
     config: Config,
     client: S3Client,
+    // Time limit for a single dispatched request, including reading the
+    // response headers. `None` means no limit.
+    request_timeout: Option<Duration>,
     // The current implementation (rosoto 0.43.0 + hyper 0.13.3) is not `Send`
     // in practical. See more https://github.com/tikv/tikv/issues/7236.
     // FIXME: remove it.
@@ -41,6 +76,7 @@ impl S3Storage {
         Ok(S3Storage {
             config: config.clone(),
             client,
+            request_timeout: timeout_from_secs(config.request_timeout),
             _not_send: PhantomData::default(),
         })
     }
@@ -54,13 +90,35 @@ impl S3Storage {
         Ok(S3Storage {
             config: config.clone(),
             client,
+            request_timeout: timeout_from_secs(config.request_timeout),
             _not_send: PhantomData::default(),
         })
     }
 
     fn check_config(config: &Config) -> Result<()> {
         if config.bucket.is_empty() {
-            return Err(Error::new(ErrorKind::InvalidInput, "missing bucket name"));
+            return Err(ConfigError::MissingBucket.into());
+        }
+        // A custom endpoint accepts any region name (e.g. minio), but without
+        // one the region must be a real AWS region.
+        if config.endpoint.is_empty() && config.region.parse::<rusoto_core::Region>().is_err() {
+            return Err(ConfigError::InvalidRegion.into());
+        }
+        // Both halves of a static key pair are required; leaving both empty
+        // falls back to the default credentials provider chain.
+        if config.access_key.is_empty() != config.secret_access_key.is_empty() {
+            return Err(ConfigError::MissingCredentials.into());
+        }
+        // The bundled client always builds path-style URIs ("/bucket/key"): it
+        // cannot move the bucket into the host name, so virtual-hosted style
+        // addressing is not available. Warn instead of silently sending
+        // requests a virtual-hosted-only gateway would reject.
+        if !config.endpoint.is_empty() && !config.force_path_style {
+            warn!(
+                "s3 client only supports path-style addressing, \
+                 treating the custom endpoint as path-style";
+                "endpoint" => &config.endpoint,
+            );
         }
         Ok(())
     }
@@ -99,26 +157,71 @@ impl ExternalStorage for S3Storage {
             acl: get_var(&self.config.acl),
             server_side_encryption: get_var(&self.config.sse),
             storage_class: get_var(&self.config.storage_class),
+            content_type: get_var(&self.config.content_type),
+            metadata: if self.config.metadata.is_empty() {
+                None
+            } else {
+                Some(self.config.metadata.clone())
+            },
             ..Default::default()
         };
-        block_on_external_io(self.client.put_object(req))
+        let put = self.client.put_object(req);
+        block_on_external_io(async move {
+            match self.request_timeout {
+                Some(t) => tokio::time::timeout(t, put).await.map_err(|_| {
+                    Error::new(
+                        ErrorKind::TimedOut,
+                        format!("put object timed out after {:?}", t),
+                    )
+                })?,
+                None => put.await,
+            }
             .map(|_| ())
             .map_err(|e| Error::new(ErrorKind::Other, format!("failed to put object {}", e)))
+        })
     }
 
     fn read(&self, name: &str) -> Box<dyn AsyncRead + Unpin + '_> {
+        self.get_object(name, None)
+    }
+
+    fn read_range(&self, name: &str, offset: u64, len: u64) -> Box<dyn AsyncRead + Unpin + '_> {
+        if len == 0 {
+            return Box::new(futures_util::io::empty()) as _;
+        }
+        // HTTP byte ranges are inclusive on both ends.
+        self.get_object(name, Some(format!("bytes={}-{}", offset, offset + len - 1)))
+    }
+}
+
+impl S3Storage {
+    fn get_object(&self, name: &str, range: Option<String>) -> Box<dyn AsyncRead + Unpin + '_> {
         let key = self.maybe_prefix_key(name);
         let bucket = self.config.bucket.clone();
-        debug!("read file from s3 storage"; "key" => %key);
+        debug!("read file from s3 storage"; "key" => %key, "range" => ?range);
         let req = GetObjectRequest {
             key,
             bucket: bucket.clone(),
+            range,
             ..Default::default()
         };
+        let get = self.client.get_object(req);
+        let request_timeout = self.request_timeout;
         Box::new(
-            self.client
-                .get_object(req)
-                .map(move |future| match future {
+            async move {
+                let res = match request_timeout {
+                    Some(t) => match tokio::time::timeout(t, get).await {
+                        Ok(res) => res,
+                        Err(_) => {
+                            return ByteStream::new(error_stream(Error::new(
+                                ErrorKind::TimedOut,
+                                format!("get object timed out after {:?}", t),
+                            )));
+                        }
+                    },
+                    None => get.await,
+                };
+                match res {
                     Ok(out) => out.body.unwrap(),
                     Err(RusotoError::Service(GetObjectError::NoSuchKey(key))) => {
                         ByteStream::new(error_stream(Error::new(
@@ -130,9 +233,10 @@ impl ExternalStorage for S3Storage {
                         ErrorKind::Other,
                         format!("failed to get object {}", e),
                     ))),
-                })
-                .flatten_stream()
-                .into_async_read(),
+                }
+            }
+            .flatten_stream()
+            .into_async_read(),
         )
     }
 }
@@ -144,6 +248,17 @@ mod tests {
     use rusoto_core::signature::SignedRequest;
     use rusoto_mock::MockRequestDispatcher;
 
+    fn check_config_err(config: Config, expected: ConfigError) {
+        let err = S3Storage::new(&config).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        let inner = err
+            .get_ref()
+            .unwrap()
+            .downcast_ref::<ConfigError>()
+            .unwrap();
+        assert_eq!(*inner, expected);
+    }
+
     #[test]
     fn test_s3_config() {
         let config = Config {
@@ -154,18 +269,46 @@ mod tests {
             secret_access_key: "xyz".to_string(),
             ..Default::default()
         };
-        let cases = vec![
-            // bucket is empty
+        assert!(S3Storage::new(&config).is_ok());
+
+        check_config_err(
             Config {
                 bucket: "".to_owned(),
                 ..config.clone()
             },
-        ];
-        for case in cases {
-            let r = S3Storage::new(&case);
-            assert!(r.is_err());
-        }
-        assert!(S3Storage::new(&config).is_ok());
+            ConfigError::MissingBucket,
+        );
+        check_config_err(
+            Config {
+                region: "not-a-region".to_owned(),
+                ..config.clone()
+            },
+            ConfigError::InvalidRegion,
+        );
+        // An unknown region is fine when a custom endpoint names the gateway.
+        assert!(S3Storage::new(&Config {
+            region: "minio".to_owned(),
+            endpoint: "http://minio.local:9000".to_owned(),
+            force_path_style: true,
+            ..config.clone()
+        })
+        .is_ok());
+        // Half a static key pair is a misconfiguration, not a fallback to the
+        // provider chain.
+        check_config_err(
+            Config {
+                secret_access_key: "".to_owned(),
+                ..config.clone()
+            },
+            ConfigError::MissingCredentials,
+        );
+        check_config_err(
+            Config {
+                access_key: "".to_owned(),
+                ..config
+            },
+            ConfigError::MissingCredentials,
+        );
     }
 
     #[test]
@@ -201,6 +344,148 @@ mod tests {
         assert!(buf.is_empty());
     }
 
+    #[test]
+    fn test_s3_storage_read_range() {
+        let config = Config {
+            region: "ap-southeast-2".to_string(),
+            bucket: "mybucket".to_string(),
+            prefix: "myprefix".to_string(),
+            access_key: "abc".to_string(),
+            secret_access_key: "xyz".to_string(),
+            ..Default::default()
+        };
+        let dispatcher = MockRequestDispatcher::with_status(200)
+            .with_body("2345")
+            .with_request_checker(move |req: &SignedRequest| {
+                assert_eq!(req.path(), "/mybucket/myprefix/mykey");
+                // HTTP byte ranges are inclusive on both ends.
+                assert_eq!(
+                    req.headers().get("range").unwrap(),
+                    &vec![b"bytes=2-5".to_vec()]
+                );
+            });
+        let s = S3Storage::with_request_dispatcher(&config, dispatcher).unwrap();
+        let mut buf = Vec::new();
+        block_on_external_io(s.read_range("mykey", 2, 4).read_to_end(&mut buf)).unwrap();
+        assert_eq!(buf, b"2345");
+
+        // A zero-length range never hits the network.
+        let mut buf = Vec::new();
+        block_on_external_io(s.read_range("mykey", 2, 0).read_to_end(&mut buf)).unwrap();
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_s3_storage_content_type_and_metadata() {
+        let magic_contents = "5678";
+        let mut metadata = std::collections::HashMap::new();
+        metadata.insert("origin".to_string(), "tikv-backup".to_string());
+        let config = Config {
+            region: "ap-southeast-2".to_string(),
+            bucket: "mybucket".to_string(),
+            access_key: "abc".to_string(),
+            secret_access_key: "xyz".to_string(),
+            content_type: "application/octet-stream".to_string(),
+            metadata,
+            ..Default::default()
+        };
+        let dispatcher = MockRequestDispatcher::with_status(200).with_request_checker(
+            move |req: &SignedRequest| {
+                let headers = req.headers();
+                assert_eq!(
+                    headers.get("content-type").unwrap(),
+                    &vec![b"application/octet-stream".to_vec()]
+                );
+                // Custom metadata is sent as x-amz-meta-* headers.
+                assert_eq!(
+                    headers.get("x-amz-meta-origin").unwrap(),
+                    &vec![b"tikv-backup".to_vec()]
+                );
+            },
+        );
+        let s = S3Storage::with_request_dispatcher(&config, dispatcher).unwrap();
+        s.write(
+            "mykey",
+            Box::new(magic_contents.as_bytes()),
+            magic_contents.len() as u64,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_s3_storage_path_style() {
+        let magic_contents = "5678";
+        let config = Config {
+            region: "minio".to_string(),
+            bucket: "mybucket".to_string(),
+            prefix: "myprefix".to_string(),
+            endpoint: "http://minio.local:9000".to_string(),
+            access_key: "abc".to_string(),
+            secret_access_key: "xyz".to_string(),
+            force_path_style: true,
+            ..Default::default()
+        };
+        let dispatcher = MockRequestDispatcher::with_status(200).with_request_checker(
+            move |req: &SignedRequest| {
+                // Path-style addressing: the bucket stays in the path, not the
+                // host name of the custom endpoint.
+                assert_eq!(req.hostname(), "minio.local:9000");
+                assert_eq!(req.path(), "/mybucket/myprefix/mykey");
+            },
+        );
+        let s = S3Storage::with_request_dispatcher(&config, dispatcher).unwrap();
+        s.write(
+            "mykey",
+            Box::new(magic_contents.as_bytes()),
+            magic_contents.len() as u64,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_s3_storage_request_timeout() {
+        use rusoto_core::request::{HttpDispatchError, HttpResponse};
+
+        // A dispatcher that stalls for a while before delegating, simulating a
+        // hanging endpoint.
+        struct DelayedDispatcher<D> {
+            inner: D,
+            delay: Duration,
+        }
+
+        #[async_trait::async_trait]
+        impl<D: DispatchSignedRequest + Send + Sync> DispatchSignedRequest for DelayedDispatcher<D> {
+            async fn dispatch(
+                &self,
+                request: SignedRequest,
+                timeout: Option<Duration>,
+            ) -> std::result::Result<HttpResponse, HttpDispatchError> {
+                tokio::time::delay_for(self.delay).await;
+                self.inner.dispatch(request, timeout).await
+            }
+        }
+
+        let config = Config {
+            region: "ap-southeast-2".to_string(),
+            bucket: "mybucket".to_string(),
+            access_key: "abc".to_string(),
+            secret_access_key: "xyz".to_string(),
+            request_timeout: 1,
+            ..Default::default()
+        };
+        let dispatcher = DelayedDispatcher {
+            inner: MockRequestDispatcher::with_status(200),
+            delay: Duration::from_secs(2),
+        };
+        let s = S3Storage::with_request_dispatcher(&config, dispatcher).unwrap();
+
+        // Both writes and reads fail with a timeout error instead of hanging.
+        let err = s.write("mykey", Box::new("5678".as_bytes()), 4).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+        let err = block_on_external_io(s.read("mykey").read_to_end(&mut Vec::new())).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+    }
+
     #[test]
     #[cfg(FALSE)]
     // FIXME: enable this (or move this to an integration test) if we've got a
@@ -54,6 +54,42 @@ pub fn error_stream(e: io::Error) -> impl Stream<Item = io::Result<Bytes>> + Unp
     stream::iter(iter::once(Err(e)))
 }
 
+/// Wrapper of an `AsyncRead` instance that reports the total bytes consumed
+/// so far to a callback after every successful read.
+pub struct ProgressReader<R> {
+    reader: R,
+    consumed: u64,
+    progress: Box<dyn Fn(u64) + Send>,
+}
+
+impl<R> ProgressReader<R> {
+    pub fn new(reader: R, progress: Box<dyn Fn(u64) + Send>) -> Self {
+        ProgressReader {
+            reader,
+            consumed: 0,
+            progress,
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for ProgressReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        match Pin::new(&mut this.reader).poll_read(cx, buf) {
+            Poll::Ready(Ok(n)) if n > 0 => {
+                this.consumed += n as u64;
+                (this.progress)(this.consumed);
+                Poll::Ready(Ok(n))
+            }
+            other => other,
+        }
+    }
+}
+
 /// Runs a future on the current thread involving external storage.
 // FIXME: get rid of this function, so that futures_executor::block_on is sufficient.
 pub fn block_on_external_io<F: Future>(f: F) -> F::Output {
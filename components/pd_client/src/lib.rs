@@ -279,3 +279,12 @@ pub fn take_peer_address(store: &mut metapb::Store) -> String {
         store.take_address()
     }
 }
+
+/// Takes the advertised address (for client-facing traffic) from a store.
+pub fn take_advertised_address(store: &mut metapb::Store) -> String {
+    if !store.get_address().is_empty() {
+        store.take_address()
+    } else {
+        store.take_peer_address()
+    }
+}
@@ -95,6 +95,14 @@ pub struct Config {
     /// Minimum percentage of tombstones to trigger manual compaction.
     /// Should between 1 and 100.
     pub region_compact_tombstones_percent: u64,
+    /// Hours of the local day within which manual compactions may run,
+    /// `[start, end)`. The window may wrap around midnight. Equal values
+    /// disable the restriction; compactions requested outside the window
+    /// are queued until it opens.
+    #[config(skip)]
+    pub compaction_window_start_hour: u32,
+    #[config(skip)]
+    pub compaction_window_end_hour: u32,
     pub pd_heartbeat_tick_interval: ReadableDuration,
     pub pd_store_heartbeat_tick_interval: ReadableDuration,
     pub snap_mgr_gc_tick_interval: ReadableDuration,
@@ -215,6 +223,8 @@ impl Default for Config {
             region_compact_check_step: 100,
             region_compact_min_tombstones: 10000,
             region_compact_tombstones_percent: 30,
+            compaction_window_start_hour: 0,
+            compaction_window_end_hour: 0,
             pd_heartbeat_tick_interval: ReadableDuration::minutes(1),
             pd_store_heartbeat_tick_interval: ReadableDuration::secs(10),
             notify_capacity: 40960,
@@ -450,6 +460,14 @@ impl Config {
             })
         }
 
+        if self.compaction_window_start_hour > 23 || self.compaction_window_end_hour > 23 {
+            return Err(box_err!(
+                "compaction window hours must between 0 and 23, current window is [{}, {})",
+                self.compaction_window_start_hour,
+                self.compaction_window_end_hour
+            ));
+        }
+
         if self.local_read_batch_size == 0 {
             rollback_or!(rb_collector, local_read_batch_size, {
                 Err(box_err!("local-read-batch-size must be greater than 0"))
@@ -560,6 +578,12 @@ impl Config {
         CONFIG_RAFTSTORE_GAUGE
             .with_label_values(&["region_compact_tombstones_percent"])
             .set(self.region_compact_tombstones_percent as f64);
+        CONFIG_RAFTSTORE_GAUGE
+            .with_label_values(&["compaction_window_start_hour"])
+            .set(f64::from(self.compaction_window_start_hour));
+        CONFIG_RAFTSTORE_GAUGE
+            .with_label_values(&["compaction_window_end_hour"])
+            .set(f64::from(self.compaction_window_end_hour));
         CONFIG_RAFTSTORE_GAUGE
             .with_label_values(&["pd_heartbeat_tick_interval"])
             .set(self.pd_heartbeat_tick_interval.as_secs() as f64);
@@ -1161,7 +1161,11 @@ impl RaftBatchSystem {
         let raftlog_gc_runner = RaftlogGcRunner::new(None);
         box_try!(workers.raftlog_gc_worker.start(raftlog_gc_runner));
 
-        let compact_runner = CompactRunner::new(Arc::clone(&engines.kv));
+        let mut compact_runner = CompactRunner::new(Arc::clone(&engines.kv));
+        compact_runner.set_compaction_window(
+            cfg.compaction_window_start_hour,
+            cfg.compaction_window_end_hour,
+        );
         let cleanup_sst_runner = CleanupSSTRunner::new(
             store.get_id(),
             self.router.clone(),
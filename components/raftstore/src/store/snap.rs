@@ -1149,6 +1149,86 @@ impl SnapManager {
         Ok(v)
     }
 
+    /// Removes temporary snapshot files left behind by failed or canceled
+    /// transfers. A file is swept only if its last modification is at least
+    /// `max_age` old and its snapshot is not registered by any ongoing
+    /// transfer, so in-progress files are never touched. Returns the number
+    /// of bytes reclaimed.
+    pub fn delete_stale_tmp_files(&self, max_age: time::Duration) -> u64 {
+        let core = self.core.rl();
+        let path = Path::new(&core.base);
+        let read_dir = match fs::read_dir(path) {
+            Ok(read_dir) => read_dir,
+            Err(e) => {
+                error!(
+                    "failed to list content of directory";
+                    "directory" => %core.base,
+                    "err" => ?e,
+                );
+                return 0;
+            }
+        };
+        let now = time::SystemTime::now();
+        let mut reclaimed = 0;
+        for p in read_dir.filter_map(|p| p.ok()) {
+            if !p.file_type().map(|t| t.is_file()).unwrap_or(false) {
+                continue;
+            }
+            let file_name = p.file_name();
+            let name = match file_name.to_str() {
+                Some(name) => name,
+                None => continue,
+            };
+            if !name.ends_with(TMP_FILE_SUFFIX) {
+                continue;
+            }
+            // Skip files of registered snapshots, their transfers are still
+            // in progress.
+            let numbers: Vec<u64> = name.split('.').next().map_or_else(
+                || vec![],
+                |s| {
+                    s.split('_')
+                        .skip(1)
+                        .filter_map(|s| s.parse().ok())
+                        .collect()
+                },
+            );
+            if numbers.len() == 3 {
+                let snap_key = SnapKey::new(numbers[0], numbers[1], numbers[2]);
+                if core.registry.contains_key(&snap_key) {
+                    continue;
+                }
+            }
+            let meta = match p.metadata() {
+                Ok(meta) => meta,
+                Err(_) => continue,
+            };
+            let modified = match meta.modified() {
+                Ok(modified) => modified,
+                Err(_) => continue,
+            };
+            match now.duration_since(modified) {
+                Ok(age) if age >= max_age => {}
+                _ => continue,
+            }
+            if let Err(e) = fs::remove_file(p.path()) {
+                warn!(
+                    "failed to remove stale snapshot tmp file";
+                    "file" => %p.path().display(),
+                    "err" => ?e,
+                );
+                continue;
+            }
+            info!(
+                "removed stale snapshot tmp file";
+                "file" => %p.path().display(),
+                "size" => meta.len(),
+            );
+            reclaimed += meta.len();
+        }
+        reclaimed
+    }
+
     #[inline]
     pub fn has_registered(&self, key: &SnapKey) -> bool {
         self.core.rl().registry.contains_key(key)
@@ -1439,6 +1519,7 @@ pub mod tests {
     use std::path::Path;
     use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
     use std::sync::Arc;
+    use std::time;
 
     use engine::rocks;
     use engine::rocks::util::CFOptions;
@@ -2270,6 +2351,44 @@ pub mod tests {
         assert!(mgr.init().is_err());
     }
 
+    #[test]
+    fn test_delete_stale_tmp_files() {
+        let temp_dir = Builder::new()
+            .prefix("test-delete-stale-tmp")
+            .tempdir()
+            .unwrap();
+        let path = temp_dir.path().to_str().unwrap().to_owned();
+        let mgr = SnapManager::new(path, None);
+        mgr.init().unwrap();
+
+        // A partial file left behind by a failed transfer.
+        let stale_path = temp_dir.path().join("rev_1_2_3_default.sst.tmp");
+        fs::write(&stale_path, b"stale data").unwrap();
+
+        // A file of an in-progress transfer.
+        let active_key = SnapKey::new(4, 5, 6);
+        mgr.register(active_key.clone(), SnapEntry::Receiving);
+        let active_path = temp_dir.path().join("rev_4_5_6_default.sst.tmp");
+        fs::write(&active_path, b"active data").unwrap();
+
+        // Young files are not swept regardless of registration.
+        assert_eq!(mgr.delete_stale_tmp_files(time::Duration::from_secs(3600)), 0);
+        assert!(stale_path.exists());
+
+        // With a zero age the stale file is reclaimed, but the registered
+        // one is never touched.
+        let reclaimed = mgr.delete_stale_tmp_files(time::Duration::from_secs(0));
+        assert_eq!(reclaimed, b"stale data".len() as u64);
+        assert!(!stale_path.exists());
+        assert!(active_path.exists());
+
+        // Once the transfer is done its leftover file becomes sweepable.
+        mgr.deregister(&active_key, &SnapEntry::Receiving);
+        let reclaimed = mgr.delete_stale_tmp_files(time::Duration::from_secs(0));
+        assert_eq!(reclaimed, b"active data".len() as u64);
+        assert!(!active_path.exists());
+    }
+
     #[test]
     fn test_snap_mgr_v2() {
         let temp_dir = Builder::new().prefix("test-snap-mgr-v2").tempdir().unwrap();
@@ -89,11 +89,56 @@ quick_error! {
 
 pub struct Runner {
     engine: Arc<DB>,
+    // Hours of the local day within which compactions may run. `None` means
+    // no restriction. The window may wrap around midnight, e.g. `(22, 6)`.
+    window: Option<(u32, u32)>,
+    // Tasks that arrived outside the window, waiting for it to open.
+    pending: VecDeque<Task>,
+    clock: Box<dyn Fn() -> u32 + Send>,
 }
 
 impl Runner {
     pub fn new(engine: Arc<DB>) -> Runner {
-        Runner { engine }
+        Runner {
+            engine,
+            window: None,
+            pending: VecDeque::new(),
+            clock: Box::new(|| time::now().tm_hour as u32),
+        }
+    }
+
+    /// Restricts compactions to `[start_hour, end_hour)` of the local day.
+    /// Equal hours disable the restriction. Tasks arriving outside the window
+    /// are queued and run once the window opens.
+    pub fn set_compaction_window(&mut self, start_hour: u32, end_hour: u32) {
+        if start_hour != end_hour {
+            self.window = Some((start_hour % 24, end_hour % 24));
+        }
+    }
+
+    #[cfg(test)]
+    fn set_clock(&mut self, clock: Box<dyn Fn() -> u32 + Send>) {
+        self.clock = clock;
+    }
+
+    fn in_window(&self) -> bool {
+        match self.window {
+            None => true,
+            Some((start, end)) => {
+                let hour = (self.clock)();
+                if start < end {
+                    hour >= start && hour < end
+                } else {
+                    hour >= start || hour < end
+                }
+            }
+        }
+    }
+
+    fn drain_pending(&mut self) {
+        while let Some(task) = self.pending.pop_front() {
+            self.execute(task);
+        }
     }
 
     /// Sends a compact range command to RocksDB to compact the range of the cf.
@@ -128,8 +173,8 @@ impl Runner {
     }
 }
 
-impl Runnable<Task> for Runner {
-    fn run(&mut self, task: Task) {
+impl Runner {
+    fn execute(&mut self, task: Task) {
         match task {
             Task::Compact {
                 cf_name,
@@ -177,6 +222,24 @@ impl Runnable<Task> for Runner {
     }
 }
 
+impl Runnable<Task> for Runner {
+    fn run(&mut self, task: Task) {
+        if !self.in_window() {
+            info!("compaction out of the allowed window, queued"; "task" => %task);
+            self.pending.push_back(task);
+            return;
+        }
+        self.drain_pending();
+        self.execute(task);
+    }
+
+    fn on_tick(&mut self) {
+        if self.in_window() {
+            self.drain_pending();
+        }
+    }
+}
+
 fn need_compact(
     num_entires: u64,
     num_versions: u64,
@@ -251,6 +314,7 @@ fn collect_ranges_need_compact(
 
 #[cfg(test)]
 mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
     use std::thread::sleep;
     use std::time::Duration;
 
@@ -325,6 +389,62 @@ mod tests {
         assert!(old_sst_files_size > new_sst_files_size);
     }
 
+    #[test]
+    fn test_compaction_window() {
+        let path = Builder::new()
+            .prefix("compact-window-test")
+            .tempdir()
+            .unwrap();
+        let db = new_engine(path.path().to_str().unwrap(), None, &[CF_DEFAULT], None).unwrap();
+        let db = Arc::new(db);
+
+        let mut runner = Runner::new(Arc::clone(&db));
+        runner.set_compaction_window(2, 4);
+        let hour = Arc::new(AtomicU32::new(10));
+        let mocked_hour = Arc::clone(&hour);
+        runner.set_clock(Box::new(move || mocked_hour.load(Ordering::SeqCst)));
+
+        let handle = get_cf_handle(&db, CF_DEFAULT).unwrap();
+
+        // Generate two overlapping SST files so a manual compaction has
+        // something to merge.
+        for _ in 0..2 {
+            let mut wb = db.c().write_batch();
+            for i in 0..1000 {
+                let k = format!("key_{}", i);
+                wb.put_cf(CF_DEFAULT, k.as_bytes(), b"whatever content")
+                    .unwrap();
+            }
+            db.c().write(&wb).unwrap();
+            db.flush_cf(handle, true).unwrap();
+        }
+        let old_sst_files_size = db
+            .get_property_int_cf(handle, ROCKSDB_TOTAL_SST_FILES_SIZE)
+            .unwrap();
+
+        // Outside the window the task is queued instead of executed.
+        runner.run(Task::Compact {
+            cf_name: String::from(CF_DEFAULT),
+            start_key: None,
+            end_key: None,
+        });
+        assert_eq!(runner.pending.len(), 1);
+        let sst_files_size = db
+            .get_property_int_cf(handle, ROCKSDB_TOTAL_SST_FILES_SIZE)
+            .unwrap();
+        assert_eq!(old_sst_files_size, sst_files_size);
+
+        // Once the window opens the queued task fires.
+        hour.store(3, Ordering::SeqCst);
+        runner.on_tick();
+        assert!(runner.pending.is_empty());
+        sleep(Duration::from_secs(5));
+        let new_sst_files_size = db
+            .get_property_int_cf(handle, ROCKSDB_TOTAL_SST_FILES_SIZE)
+            .unwrap();
+        assert!(old_sst_files_size > new_sst_files_size);
+    }
+
     fn mvcc_put(db: &DB, k: &[u8], v: &[u8], start_ts: TimeStamp, commit_ts: TimeStamp) {
         let cf = get_cf_handle(db, CF_WRITE).unwrap();
         let k = Key::from_encoded(data_key(k)).append_ts(commit_ts);
@@ -2,7 +2,10 @@
 
 use std::env::{var, VarError};
 use std::io::{self, Error, ErrorKind};
+use std::time::Duration;
 
+use hyper::client::HttpConnector;
+use hyper_tls::HttpsConnector;
 use rusoto_core::{
     region::Region,
     request::{HttpClient, HttpConfig},
@@ -20,7 +23,8 @@ const READ_BUF_SIZE: usize = 1024 * 1024 * 2;
 #[macro_export]
 macro_rules! new_client {
     ($client: ty, $config: ident) => {{
-        let http_client = $crate::new_http_client()?;
+        let connect_timeout = $crate::timeout_from_secs($config.connect_timeout);
+        let http_client = $crate::new_http_client(connect_timeout)?;
         new_client!($client, $config, http_client)
     }};
     ($client: ty, $config: ident, $dispatcher: ident) => {{
@@ -33,18 +37,38 @@ macro_rules! new_client {
     }};
 }
 
-pub fn new_http_client() -> io::Result<HttpClient> {
+/// Converts a timeout in seconds from a config into a `Duration`. Zero means
+/// the timeout is not set, so it maps to `None` instead of a `Duration` that
+/// would expire immediately.
+pub fn timeout_from_secs(secs: u64) -> Option<Duration> {
+    if secs == 0 {
+        None
+    } else {
+        Some(Duration::from_secs(secs))
+    }
+}
+
+pub fn new_http_client(connect_timeout: Option<Duration>) -> io::Result<HttpClient> {
     let mut http_config = HttpConfig::new();
     // This can greatly improve performance dealing with payloads greater
     // than 100MB. See https://github.com/rusoto/rusoto/pull/1227
     // for more information.
     http_config.read_buf_size(READ_BUF_SIZE);
-    HttpClient::new_with_config(http_config).map_err(|e| {
+    let mut connector = HttpConnector::new();
+    // The HTTPS connector needs the inner connector to pass https URIs through.
+    connector.enforce_http(false);
+    connector.set_connect_timeout(connect_timeout);
+    let tls = native_tls::TlsConnector::new().map_err(|e| {
         Error::new(
             ErrorKind::Other,
-            format!("create aws http client error: {}", e),
+            format!("create aws tls connector error: {}", e),
         )
-    })
+    })?;
+    let connector = HttpsConnector::from((connector, tls.into()));
+    Ok(HttpClient::from_connector_with_config(
+        connector,
+        http_config,
+    ))
 }
 
 pub fn get_region(region: &str, endpoint: &str) -> io::Result<Region> {
@@ -65,13 +89,36 @@ pub fn get_region(region: &str, endpoint: &str) -> io::Result<Region> {
     }
 }
 
-pub enum CredentialsProvider {
-    Default(Box<AutoRefreshingProvider<DefaultCredentialsProvider>>),
+/// The credentials used to sign requests.
+///
+/// Static keys from the config never expire and keep being used as-is. When
+/// no static keys are given the default sources are wrapped in an
+/// `AutoRefreshingProvider`, so temporary credentials (e.g. rotated STS
+/// tokens) are re-fetched once they expire instead of failing long-running
+/// transfers.
+pub enum CredentialsProvider<P = DefaultCredentialsProvider> {
+    Default(Box<AutoRefreshingProvider<P>>),
     Static(StaticProvider),
 }
 
 impl CredentialsProvider {
     pub fn new(access_key: &str, secret_access_key: &str) -> io::Result<CredentialsProvider> {
+        CredentialsProvider::with_provider(
+            access_key,
+            secret_access_key,
+            DefaultCredentialsProvider::default(),
+        )
+    }
+}
+
+impl<P: ProvideAwsCredentials + Send + Sync + 'static> CredentialsProvider<P> {
+    /// Like `new`, but wraps the given provider instead of the default
+    /// credential sources when no static keys are configured.
+    fn with_provider(
+        access_key: &str,
+        secret_access_key: &str,
+        provider: P,
+    ) -> io::Result<CredentialsProvider<P>> {
         let cred_provider = if !access_key.is_empty() && !secret_access_key.is_empty() {
             CredentialsProvider::Static(StaticProvider::new(
                 access_key.to_owned(),
@@ -80,23 +127,23 @@ impl CredentialsProvider {
                 None, /* valid_for*/
             ))
         } else {
-            CredentialsProvider::Default(Box::new(
-                AutoRefreshingProvider::new(DefaultCredentialsProvider::default()).map_err(
-                    |e| {
-                        Error::new(
-                            ErrorKind::Other,
-                            format!("create aws credentials provider error: {}", e),
-                        )
-                    },
-                )?,
-            ))
+            CredentialsProvider::Default(Box::new(AutoRefreshingProvider::new(provider).map_err(
+                |e| {
+                    Error::new(
+                        ErrorKind::Other,
+                        format!("create aws credentials provider error: {}", e),
+                    )
+                },
+            )?))
         };
         Ok(cred_provider)
     }
 }
 
 #[async_trait]
-impl ProvideAwsCredentials for CredentialsProvider {
+impl<P: ProvideAwsCredentials + Send + Sync + 'static> ProvideAwsCredentials
+    for CredentialsProvider<P>
+{
     async fn credentials(&self) -> Result<AwsCredentials, CredentialsError> {
         match self {
             CredentialsProvider::Default(default_provider) => default_provider.credentials().await,
@@ -166,3 +213,67 @@ impl ProvideAwsCredentials for DefaultCredentialsProvider {
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration as ChronoDuration, Utc};
+    use futures::executor::block_on;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Hands out a credential that is already expired on the first fetch and
+    /// a long-lived one afterwards, counting every fetch.
+    struct ExpiringProvider {
+        fetches: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl ProvideAwsCredentials for ExpiringProvider {
+        async fn credentials(&self) -> Result<AwsCredentials, CredentialsError> {
+            let n = self.fetches.fetch_add(1, Ordering::SeqCst);
+            let expiry = if n == 0 {
+                Utc::now() - ChronoDuration::seconds(1)
+            } else {
+                Utc::now() + ChronoDuration::hours(1)
+            };
+            Ok(AwsCredentials::new(
+                format!("key{}", n),
+                "secret",
+                None,
+                Some(expiry),
+            ))
+        }
+    }
+
+    #[test]
+    fn test_credentials_refreshed_after_expiry() {
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let provider = CredentialsProvider::with_provider(
+            "",
+            "",
+            ExpiringProvider {
+                fetches: Arc::clone(&fetches),
+            },
+        )
+        .unwrap();
+
+        // Without static keys the provider must take the refreshing path.
+        if let CredentialsProvider::Static(_) = provider {
+            panic!("expected the auto refreshing provider");
+        }
+
+        // The first credential expires immediately, so the next request must
+        // trigger a refresh and succeed with the new key.
+        let creds = block_on(provider.credentials()).unwrap();
+        assert_eq!(creds.aws_access_key_id(), "key0");
+        let creds = block_on(provider.credentials()).unwrap();
+        assert_eq!(creds.aws_access_key_id(), "key1");
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+
+        // The refreshed credential is still valid and stays cached.
+        let creds = block_on(provider.credentials()).unwrap();
+        assert_eq!(creds.aws_access_key_id(), "key1");
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+    }
+}
@@ -9,6 +9,10 @@ use std::result::Result;
 pub struct Config {
     pub num_threads: usize,
     pub stream_channel_window: usize,
+    /// Whether an ingest request that fails with an epoch mismatch is retried
+    /// once with the current epoch of the region before the error is returned
+    /// to the client.
+    pub auto_refresh_region_epoch: bool,
 }
 
 impl Default for Config {
@@ -16,6 +20,7 @@ impl Default for Config {
         Config {
             num_threads: 8,
             stream_channel_window: 128,
+            auto_refresh_region_epoch: true,
         }
     }
 }
@@ -28,6 +28,8 @@ pub fn error_inc(err: &Error) {
         Error::CannotReadExternalStorage(..) => "read_external_storage",
         Error::WrongKeyPrefix(..) => "wrong_prefix",
         Error::BadFormat(..) => "bad_format",
+        Error::EmptySstFile(..) => "empty_sst",
+        Error::ManifestCoverage(..) => "manifest_coverage",
         _ => return,
     };
     IMPORTER_ERROR_VEC.with_label_values(&[label]).inc();
@@ -98,6 +100,15 @@ quick_error! {
         BadFormat(msg: String) {
             display("bad format {}", msg)
         }
+        EmptySstFile(path: PathBuf) {
+            display("SST file {:?} contains no keys and cannot be ingested", path)
+        }
+        ManifestCoverage(reason: String) {
+            display("manifest does not cover the requested span: {}", reason)
+        }
+        ShuttingDown {
+            display("importer is shutting down")
+        }
     }
 }
 
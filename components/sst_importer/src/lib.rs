@@ -19,6 +19,7 @@ pub mod metrics;
 #[macro_use]
 pub mod service;
 pub mod import_mode;
+pub mod manifest;
 pub mod sst_importer;
 
 pub use self::config::Config;
@@ -25,7 +25,7 @@ lazy_static! {
     pub static ref IMPORTER_DOWNLOAD_DURATION: HistogramVec = register_histogram_vec!(
         "tikv_import_download_duration",
         "Bucketed histogram of importer download duration",
-        &["type"],
+        &["type", "backend"],
         exponential_buckets(0.001, 2.0, 20).unwrap()
     )
     .unwrap();
@@ -6,16 +6,18 @@ use std::fs::{self, File, OpenOptions};
 use std::io::Write;
 use std::ops::Bound;
 use std::path::{Path, PathBuf};
+use std::sync::{Condvar, Mutex};
 use std::time::Instant;
 
 use kvproto::backup::StorageBackend;
 use kvproto::import_sstpb::*;
+use kvproto::metapb::RegionEpoch;
 use uuid::{Builder as UuidBuilder, Uuid};
 
 use engine_traits::{IngestExternalFileOptions, KvEngine};
 use engine_traits::{Iterator, CF_WRITE};
 use engine_traits::{SeekKey, SstReader, SstWriter};
-use external_storage::{block_on_external_io, create_storage, url_of_backend};
+use external_storage::{backend_label, block_on_external_io, create_storage, url_of_backend};
 use futures_util::io::{copy, AllowStdIo};
 use keys;
 use tikv_util::time::Limiter;
@@ -27,15 +29,60 @@ use crate::metrics::*;
 /// SSTImporter manages SST files that are waiting for ingesting.
 pub struct SSTImporter {
     dir: ImportDir,
+    // Tracks in-flight downloads so that `shutdown` can wait for them and
+    // clean up whatever partial files they leave behind.
+    download_state: Mutex<DownloadState>,
+    download_cond: Condvar,
+}
+
+#[derive(Default)]
+struct DownloadState {
+    shutdown: bool,
+    running: usize,
 }
 
 impl SSTImporter {
     pub fn new<P: AsRef<Path>>(root: P) -> Result<SSTImporter> {
         Ok(SSTImporter {
             dir: ImportDir::new(root)?,
+            download_state: Mutex::new(DownloadState::default()),
+            download_cond: Condvar::new(),
         })
     }
 
+    fn start_download(&self) -> Result<()> {
+        let mut state = self.download_state.lock().unwrap();
+        if state.shutdown {
+            return Err(Error::ShuttingDown);
+        }
+        state.running += 1;
+        Ok(())
+    }
+
+    fn finish_download(&self) {
+        let mut state = self.download_state.lock().unwrap();
+        state.running -= 1;
+        if state.running == 0 {
+            self.download_cond.notify_all();
+        }
+    }
+
+    /// Shuts the importer down gracefully: new downloads are rejected, the
+    /// in-flight ones are waited for, and any partial files they left in the
+    /// temp directory are removed.
+    pub fn shutdown(&self) {
+        let mut state = self.download_state.lock().unwrap();
+        state.shutdown = true;
+        while state.running > 0 {
+            state = self.download_cond.wait(state).unwrap();
+        }
+        drop(state);
+        info!("importer shut down");
+        if let Err(e) = self.dir.clear_temp() {
+            warn!("clean up temp dir failed"; "err" => %e);
+        }
+    }
+
     pub fn get_path(&self, meta: &SstMeta) -> PathBuf {
         let path = self.dir.join(meta).unwrap();
         path.save
@@ -80,6 +127,19 @@ impl SSTImporter {
         }
     }
 
+    /// Rebinds the SST to a new region epoch and returns the updated meta.
+    ///
+    /// The region epoch is part of the file name, so the underlying file is
+    /// renamed accordingly. This is used to retry an ingest after the region
+    /// epoch changed (e.g. by a split that left the SST range untouched).
+    pub fn update_region_epoch(&self, meta: &SstMeta, epoch: RegionEpoch) -> Result<SstMeta> {
+        let mut new_meta = meta.clone();
+        new_meta.set_region_epoch(epoch);
+        self.dir.rename(meta, &new_meta)?;
+        info!("update region epoch"; "meta" => ?meta, "new_meta" => ?new_meta);
+        Ok(new_meta)
+    }
+
     // Downloads an SST file from an external storage.
     //
     // This method is blocking. It performs the following transformations before
@@ -112,7 +172,11 @@ impl SSTImporter {
             "rewrite_rule" => ?rewrite_rule,
             "speed_limit" => speed_limiter.speed_limit(),
         );
-        match self.do_download::<E>(meta, backend, name, rewrite_rule, speed_limiter, sst_writer) {
+        self.start_download()?;
+        let res =
+            self.do_download::<E>(meta, backend, name, rewrite_rule, speed_limiter, sst_writer);
+        self.finish_download();
+        match res {
             Ok(r) => {
                 info!("download"; "meta" => ?meta, "range" => ?r);
                 Ok(r)
@@ -136,6 +200,7 @@ impl SSTImporter {
         let start = Instant::now();
         let path = self.dir.join(meta)?;
         let url = url_of_backend(backend);
+        let backend_label = backend_label(backend);
 
         // prepare to download the file from the external_storage
         let ext_storage = create_storage(backend)?;
@@ -234,7 +299,7 @@ impl SSTImporter {
             fs::rename(&path.temp, &path.save)?;
             let duration = start.elapsed();
             IMPORTER_DOWNLOAD_DURATION
-                .with_label_values(&["rename"])
+                .with_label_values(&["rename", backend_label])
                 .observe(duration.as_secs_f64());
             return Ok(Some(range));
         }
@@ -301,7 +366,7 @@ impl SSTImporter {
 
         let duration = start.elapsed();
         IMPORTER_DOWNLOAD_DURATION
-            .with_label_values(&["rewrite"])
+            .with_label_values(&["rewrite", backend_label])
             .observe(duration.as_secs_f64());
 
         if let Some(start_key) = first_key {
@@ -392,9 +457,24 @@ impl ImportDir {
         Ok(path)
     }
 
+    fn rename(&self, old_meta: &SstMeta, new_meta: &SstMeta) -> Result<()> {
+        let old_path = self.join(old_meta)?;
+        let new_path = self.join(new_meta)?;
+        fs::rename(&old_path.save, &new_path.save)?;
+        Ok(())
+    }
+
     fn ingest<E: KvEngine>(&self, meta: &SstMeta, engine: &E) -> Result<()> {
         let start = Instant::now();
         let path = self.join(meta)?;
+        // `download` does not create a file at all when the requested range
+        // turns out to contain no keys (an empty SST cannot even be written).
+        // Ingesting such a meta right after the download would fail with an
+        // opaque RocksDB-level error; report it distinctly instead so that
+        // clients can simply skip the SST.
+        if !path.save.exists() {
+            return Err(Error::EmptySstFile(path.save));
+        }
         let cf = meta.get_cf_name();
         let cf = engine.cf_handle(cf).expect("bad cf name");
         engine.prepare_sst_for_ingestion(&path.save, &path.clone)?;
@@ -417,6 +497,16 @@ impl ImportDir {
         Ok(())
     }
 
+    fn clear_temp(&self) -> Result<()> {
+        for e in fs::read_dir(&self.temp_dir)? {
+            let e = e?;
+            if e.file_type()?.is_file() {
+                fs::remove_file(e.path())?;
+            }
+        }
+        Ok(())
+    }
+
     fn list_ssts(&self) -> Result<Vec<SstMeta>> {
         let mut ssts = Vec::new();
         for e in fs::read_dir(&self.root_dir)? {
@@ -607,6 +697,9 @@ mod tests {
     use test_sst_importer::*;
 
     use std::f64::INFINITY;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
 
     use engine_traits::{collect, name_to_cf, Iterable, Iterator, SeekKey, CF_DEFAULT, DATA_CFS};
     use engine_traits::{Error as TraitError, SstWriterBuilder, TablePropertiesExt};
@@ -1266,6 +1359,31 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_download_duration_backend_label() {
+        let (_ext_sst_dir, backend, meta) = create_sample_external_sst_file().unwrap();
+        let importer_dir = tempfile::tempdir().unwrap();
+        let importer = SSTImporter::new(&importer_dir).unwrap();
+        let sst_writer = create_sst_writer_with_db(&importer, &meta).unwrap();
+
+        importer
+            .download::<TestEngine>(
+                &meta,
+                &backend,
+                "sample.sst",
+                &RewriteRule::default(),
+                Limiter::new(INFINITY),
+                sst_writer,
+            )
+            .unwrap()
+            .unwrap();
+
+        // The download duration is recorded under the backend kind of the
+        // storage it went through.
+        let hist = IMPORTER_DOWNLOAD_DURATION.with_label_values(&["rename", "local"]);
+        assert!(hist.get_sample_count() >= 1);
+    }
+
     #[test]
     fn test_download_sst_empty() {
         let (_ext_sst_dir, backend, mut meta) = create_sample_external_sst_file().unwrap();
@@ -1290,6 +1408,39 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_download_sst_empty_then_ingest() {
+        let (_ext_sst_dir, backend, mut meta) = create_sample_external_sst_file().unwrap();
+        let importer_dir = tempfile::tempdir().unwrap();
+        let importer = SSTImporter::new(&importer_dir).unwrap();
+        let sst_writer = create_sst_writer_with_db(&importer, &meta).unwrap();
+        // An empty range: nothing in the sample SST is inside it.
+        meta.mut_range().set_start(vec![b'x']);
+        meta.mut_range().set_end(vec![b'y']);
+
+        let result = importer.download::<TestEngine>(
+            &meta,
+            &backend,
+            "sample.sst",
+            &RewriteRule::default(),
+            Limiter::new(INFINITY),
+            sst_writer,
+        );
+        match result {
+            Ok(None) => {}
+            _ => panic!("unexpected download result: {:?}", result),
+        }
+
+        // Ingesting the empty download result fails with the dedicated error
+        // instead of an opaque RocksDB-level failure.
+        let ingest_dir = tempfile::tempdir().unwrap();
+        let db = new_test_engine(ingest_dir.path().to_str().unwrap(), DATA_CFS);
+        match importer.ingest(&meta, &db) {
+            Err(Error::EmptySstFile(_)) => {}
+            r => panic!("unexpected ingest result: {:?}", r),
+        }
+    }
+
     #[test]
     fn test_download_sst_wrong_key_prefix() {
         let (_ext_sst_dir, backend, meta) = create_sample_external_sst_file().unwrap();
@@ -1314,4 +1465,35 @@ mod tests {
             _ => panic!("unexpected download result: {:?}", result),
         }
     }
+
+    #[test]
+    fn test_shutdown_cleans_partial_downloads() {
+        let importer_dir = tempfile::tempdir().unwrap();
+        let importer = Arc::new(SSTImporter::new(&importer_dir).unwrap());
+
+        // Simulate an in-flight download that has written a partial file.
+        let mut meta = SstMeta::default();
+        meta.set_uuid(Uuid::new_v4().as_bytes().to_vec());
+        let path = importer.dir.join(&meta).unwrap();
+        fs::write(&path.temp, b"partial").unwrap();
+        importer.start_download().unwrap();
+
+        let running = Arc::clone(&importer);
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            running.finish_download();
+        });
+
+        // `shutdown` must wait for the download to finish and then remove the
+        // partial file it left behind.
+        importer.shutdown();
+        handle.join().unwrap();
+        assert!(!path.temp.exists());
+
+        // New downloads are rejected once the importer is shut down.
+        match importer.start_download() {
+            Err(Error::ShuttingDown) => {}
+            r => panic!("unexpected start_download result: {:?}", r),
+        }
+    }
 }
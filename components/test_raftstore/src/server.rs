@@ -146,7 +146,9 @@ impl Simulator for ServerCluster {
         let raft_router = ServerRaftStoreRouter::new(router.clone(), local_reader);
         let sim_router = SimulateTransport::new(raft_router.clone());
 
-        let raft_engine = RaftKv::new(sim_router.clone());
+        let mut raft_engine = RaftKv::new(sim_router.clone());
+        raft_engine.set_prefer_lease_read(cfg.server.prefer_lease_read);
+        raft_engine.set_store_meta(store_meta.clone());
 
         // Create coprocessor.
         let mut coprocessor_host = CoprocessorHost::new(router.clone());
@@ -167,7 +169,9 @@ impl Simulator for ServerCluster {
             raft_engine.clone(),
         ));
 
-        let engine = RaftKv::new(sim_router.clone());
+        let mut engine = RaftKv::new(sim_router.clone());
+        engine.set_prefer_lease_read(cfg.server.prefer_lease_read);
+        engine.set_store_meta(store_meta.clone());
 
         let mut gc_worker = GcWorker::new(
             engine.clone(),
@@ -220,7 +224,11 @@ impl Simulator for ServerCluster {
         let deadlock_service = lock_mgr.deadlock_service(security_mgr.clone());
 
         // Create pd client, snapshot manager, server.
-        let (worker, resolver) = resolve::new_resolver(Arc::clone(&self.pd_client)).unwrap();
+        let (worker, resolver) = resolve::new_resolver(
+            Arc::clone(&self.pd_client),
+            cfg.server.resolve_cache_ttl.into(),
+        )
+        .unwrap();
         let snap_mgr = SnapManager::new(tmp_str, Some(router.clone()));
         let server_cfg = Arc::new(cfg.server.clone());
         let cop_read_pool = ReadPool::from(coprocessor::readpool_impl::build_read_pool_for_test(
@@ -611,13 +611,15 @@ impl<E: Engine> AssertionStorage<E> {
             .unwrap();
     }
 
+    /// Batch-resolves the locks of two transactions and returns the number of
+    /// locks that were resolved.
     pub fn resolve_lock_batch_ok(
         &self,
         start_ts_1: impl Into<TimeStamp>,
         commit_ts_1: impl Into<TimeStamp>,
         start_ts_2: impl Into<TimeStamp>,
         commit_ts_2: impl Into<TimeStamp>,
-    ) {
+    ) -> u64 {
         self.store
             .resolve_lock_batch(
                 self.ctx.clone(),
@@ -626,7 +628,7 @@ impl<E: Engine> AssertionStorage<E> {
                     (start_ts_2.into(), commit_ts_2.into()),
                 ],
             )
-            .unwrap();
+            .unwrap()
     }
 
     pub fn resolve_lock_with_illegal_tso(
@@ -684,7 +686,50 @@ impl<E: Engine> AssertionStorage<E> {
     ) {
         let result: Vec<KvPair> = self
             .store
-            .raw_scan(self.ctx.clone(), cf, start_key, None, limit)
+            .raw_scan(self.ctx.clone(), cf, start_key, None, limit, None)
+            .unwrap()
+            .into_iter()
+            .map(|x| x.unwrap())
+            .collect();
+        let expect: Vec<KvPair> = expect
+            .into_iter()
+            .map(|(k, v)| (k.to_vec(), v.to_vec()))
+            .collect();
+        assert_eq!(result, expect);
+    }
+
+    pub fn raw_scan_prefix_ok(
+        &self,
+        cf: String,
+        start_key: Vec<u8>,
+        limit: usize,
+        key_prefix: Vec<u8>,
+        expect: Vec<(&[u8], &[u8])>,
+    ) {
+        let result: Vec<KvPair> = self
+            .store
+            .raw_scan(self.ctx.clone(), cf, start_key, None, limit, Some(key_prefix))
+            .unwrap()
+            .into_iter()
+            .map(|x| x.unwrap())
+            .collect();
+        let expect: Vec<KvPair> = expect
+            .into_iter()
+            .map(|(k, v)| (k.to_vec(), v.to_vec()))
+            .collect();
+        assert_eq!(result, expect);
+    }
+
+    pub fn raw_reverse_scan_ok(
+        &self,
+        cf: String,
+        start_key: Vec<u8>,
+        limit: usize,
+        expect: Vec<(&[u8], &[u8])>,
+    ) {
+        let result: Vec<KvPair> = self
+            .store
+            .reverse_raw_scan(self.ctx.clone(), cf, start_key, None, limit)
             .unwrap()
             .into_iter()
             .map(|x| x.unwrap())
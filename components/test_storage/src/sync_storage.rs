@@ -232,6 +232,20 @@ impl<E: Engine> SyncTestStorage<E> {
         .unwrap()
     }
 
+    pub fn mvcc_version_count(
+        &self,
+        ctx: Context,
+        key: Key,
+        start_ts: impl Into<TimeStamp>,
+        end_ts: impl Into<TimeStamp>,
+    ) -> Result<(u64, TimeStamp, TimeStamp)> {
+        wait_op!(|cb| self.store.sched_txn_command(
+            commands::MvccVersionCount::new(key, start_ts.into(), end_ts.into(), ctx),
+            cb,
+        ))
+        .unwrap()
+    }
+
     pub fn resolve_lock(
         &self,
         ctx: Context,
@@ -254,10 +268,10 @@ impl<E: Engine> SyncTestStorage<E> {
         &self,
         ctx: Context,
         txns: Vec<(TimeStamp, TimeStamp)>,
-    ) -> Result<()> {
+    ) -> Result<u64> {
         let txn_status: HashMap<TimeStamp, TimeStamp> = txns.into_iter().collect();
         wait_op!(|cb| self.store.sched_txn_command(
-            commands::ResolveLock::new(txn_status, None, vec![], ctx),
+            commands::ResolveLockBatch::new(txn_status, None, vec![], 0, ctx),
             cb,
         ))
         .unwrap()
@@ -272,7 +286,7 @@ impl<E: Engine> SyncTestStorage<E> {
     }
 
     pub fn raw_put(&self, ctx: Context, cf: String, key: Vec<u8>, value: Vec<u8>) -> Result<()> {
-        wait_op!(|cb| self.store.raw_put(ctx, cf, key, value, cb)).unwrap()
+        wait_op!(|cb| self.store.raw_put(ctx, cf, key, value, 0, cb)).unwrap()
     }
 
     pub fn raw_delete(&self, ctx: Context, cf: String, key: Vec<u8>) -> Result<()> {
@@ -286,9 +300,10 @@ impl<E: Engine> SyncTestStorage<E> {
         start_key: Vec<u8>,
         end_key: Option<Vec<u8>>,
         limit: usize,
+        key_prefix: Option<Vec<u8>>,
     ) -> Result<Vec<Result<KvPair>>> {
         self.store
-            .raw_scan(ctx, cf, start_key, end_key, limit, false, false)
+            .raw_scan(ctx, cf, start_key, end_key, limit, key_prefix, false, false)
             .wait()
     }
 
@@ -301,7 +316,7 @@ impl<E: Engine> SyncTestStorage<E> {
         limit: usize,
     ) -> Result<Vec<Result<KvPair>>> {
         self.store
-            .raw_scan(ctx, cf, start_key, end_key, limit, false, true)
+            .raw_reverse_scan(ctx, cf, start_key, end_key, limit, false)
             .wait()
     }
 }
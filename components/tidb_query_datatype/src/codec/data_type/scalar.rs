@@ -178,6 +178,24 @@ impl<'a> ScalarValueRef<'a> {
         }
     }
 
+    #[inline]
+    pub fn is_none(&self) -> bool {
+        match_template_evaluable! {
+            TT, match self {
+                ScalarValueRef::TT(v) => v.is_none(),
+            }
+        }
+    }
+
+    #[inline]
+    pub fn is_some(&self) -> bool {
+        match_template_evaluable! {
+            TT, match self {
+                ScalarValueRef::TT(v) => v.is_some(),
+            }
+        }
+    }
+
     /// Encodes into binary format.
     pub fn encode(
         &self,
@@ -1,9 +1,13 @@
 // Copyright 2017 TiKV Project Authors. Licensed under Apache-2.0.
 
 use std::cmp::Ordering;
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
 use std::mem;
 use std::sync::Arc;
 
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
 use tipb::{Aggregation, Expr, ExprType};
 
 use indexmap::map::Entry as OrderMapEntry;
@@ -20,6 +24,93 @@ use tidb_query_normal_expr::Expression;
 
 const SINGLE_GROUP: &[u8] = b"SingleGroup";
 
+/// Approximate bookkeeping cost of one group in the aggregation map, covering
+/// the map entry and the aggregate function states, in addition to the group
+/// key itself.
+const SPILL_GROUP_OVERHEAD: usize = 64;
+
+/// Spills rows of overflow groups to a temporary file when the in-memory group
+/// map is estimated to exceed a memory budget.
+///
+/// Once the budget is exceeded no new groups are created in memory: rows whose
+/// group is not resident are appended to the spill file instead, so every
+/// group is aggregated either fully in memory or fully from the spill file.
+/// The spilled rows are aggregated after all resident groups have been handed
+/// out, when the map no longer holds live entries.
+struct SpillState {
+    budget: usize,
+    mem_usage: usize,
+    writer: Option<BufWriter<File>>,
+    spilled_rows: usize,
+}
+
+impl SpillState {
+    fn new(budget: usize) -> SpillState {
+        SpillState {
+            budget,
+            mem_usage: 0,
+            writer: None,
+            spilled_rows: 0,
+        }
+    }
+
+    /// Checks whether creating one more group with the given key length would
+    /// exceed the budget.
+    fn over_budget(&self, key_len: usize) -> bool {
+        self.mem_usage + key_len + SPILL_GROUP_OVERHEAD > self.budget
+    }
+
+    fn on_new_group(&mut self, key_len: usize) {
+        self.mem_usage += key_len + SPILL_GROUP_OVERHEAD;
+    }
+
+    fn write_row(
+        &mut self,
+        ctx: &mut EvalContext,
+        group_key: &[u8],
+        cols: &[Datum],
+    ) -> Result<()> {
+        if self.writer.is_none() {
+            let file = box_try!(tempfile::tempfile());
+            self.writer = Some(BufWriter::new(file));
+        }
+        let value = box_try!(datum::encode_value(ctx, cols));
+        let writer = self.writer.as_mut().unwrap();
+        box_try!(writer.write_u32::<LittleEndian>(group_key.len() as u32));
+        box_try!(writer.write_all(group_key));
+        box_try!(writer.write_u32::<LittleEndian>(value.len() as u32));
+        box_try!(writer.write_all(&value));
+        self.spilled_rows += 1;
+        Ok(())
+    }
+
+    /// Reads all spilled rows back. Consumes the state so the temporary file
+    /// is closed and removed afterwards.
+    fn into_rows(self) -> Result<Vec<(Vec<u8>, Vec<Datum>)>> {
+        let mut rows = Vec::with_capacity(self.spilled_rows);
+        let writer = match self.writer {
+            Some(w) => w,
+            None => return Ok(rows),
+        };
+        let mut file = box_try!(writer.into_inner());
+        box_try!(file.seek(SeekFrom::Start(0)));
+        let mut buf = Vec::new();
+        box_try!(file.read_to_end(&mut buf));
+        let mut data = buf.as_slice();
+        while !data.is_empty() {
+            let key_len = box_try!(data.read_u32::<LittleEndian>()) as usize;
+            let mut key = vec![0; key_len];
+            box_try!(data.read_exact(&mut key));
+            let value_len = box_try!(data.read_u32::<LittleEndian>()) as usize;
+            let (mut value, rest) = data.split_at(value_len);
+            data = rest;
+            let cols = box_try!(datum::decode(&mut value));
+            rows.push((key, cols));
+        }
+        Ok(rows)
+    }
+}
+
 struct AggFuncExpr {
     args: Vec<Expression>,
     tp: ExprType,
@@ -155,6 +246,7 @@ pub struct HashAggExecutor<Src: Executor> {
     inner: AggExecutor<Src>,
     group_key_aggrs: OrderMap<Vec<u8>, Vec<Box<dyn AggrFunc>>>,
     cursor: usize,
+    spill: Option<SpillState>,
 }
 
 impl<Src: Executor> HashAggExecutor<Src> {
@@ -166,9 +258,17 @@ impl<Src: Executor> HashAggExecutor<Src> {
             inner,
             group_key_aggrs: OrderMap::new(),
             cursor: 0,
+            spill: None,
         })
     }
 
+    /// Enables spilling rows of overflow groups to a temporary file once the
+    /// in-memory group map is estimated to use more than `budget` bytes. The
+    /// estimation is approximate; see [`SpillState`].
+    pub fn enable_spill(&mut self, budget: usize) {
+        self.spill = Some(SpillState::new(budget));
+    }
+
     fn get_group_key(&mut self, row: &[Datum]) -> Result<Vec<u8>> {
         let group_by_cols = self.inner.get_group_by_cols(row)?;
         if group_by_cols.is_empty() {
@@ -185,24 +285,45 @@ impl<Src: Executor> HashAggExecutor<Src> {
     fn aggregate(&mut self) -> Result<()> {
         while let Some(cols) = self.inner.next()? {
             let group_key = self.get_group_key(&cols)?;
-            match self.group_key_aggrs.entry(group_key) {
-                OrderMapEntry::Vacant(e) => {
-                    let mut aggrs = Vec::with_capacity(self.inner.aggr_func.len());
-                    for expr in &mut self.inner.aggr_func {
-                        let mut aggr = aggregate::build_aggr_func(expr.tp)?;
-                        aggr.update_with_expr(&mut self.inner.ctx, expr, &cols)?;
-                        aggrs.push(aggr);
-                    }
-                    e.insert(aggrs);
+            self.update_group(group_key, &cols)?;
+        }
+        Ok(())
+    }
+
+    fn update_group(&mut self, group_key: Vec<u8>, cols: &[Datum]) -> Result<()> {
+        let key_len = group_key.len();
+        let resident = self.group_key_aggrs.contains_key(&group_key);
+        let should_spill = !resident
+            && self
+                .spill
+                .as_ref()
+                .map_or(false, |spill| spill.over_budget(key_len));
+        if should_spill {
+            let spill = self.spill.as_mut().unwrap();
+            return spill.write_row(&mut self.inner.ctx, &group_key, cols);
+        }
+        match self.group_key_aggrs.entry(group_key) {
+            OrderMapEntry::Vacant(e) => {
+                let mut aggrs = Vec::with_capacity(self.inner.aggr_func.len());
+                for expr in &mut self.inner.aggr_func {
+                    let mut aggr = aggregate::build_aggr_func(expr.tp)?;
+                    aggr.update_with_expr(&mut self.inner.ctx, expr, cols)?;
+                    aggrs.push(aggr);
                 }
-                OrderMapEntry::Occupied(e) => {
-                    let aggrs = e.into_mut();
-                    for (expr, aggr) in self.inner.aggr_func.iter_mut().zip(aggrs) {
-                        aggr.update_with_expr(&mut self.inner.ctx, expr, &cols)?;
-                    }
+                e.insert(aggrs);
+            }
+            OrderMapEntry::Occupied(e) => {
+                let aggrs = e.into_mut();
+                for (expr, aggr) in self.inner.aggr_func.iter_mut().zip(aggrs) {
+                    aggr.update_with_expr(&mut self.inner.ctx, expr, cols)?;
                 }
             }
         }
+        if !resident {
+            if let Some(spill) = self.spill.as_mut() {
+                spill.on_new_group(key_len);
+            }
+        }
         Ok(())
     }
 }
@@ -235,7 +356,23 @@ impl<Src: Executor> Executor for HashAggExecutor<Src> {
                     Ok(Some(Row::agg(aggr_cols, Vec::default())))
                 }
             }
-            None => Ok(None),
+            None => {
+                // Merge the groups that were spilled to disk during the scan.
+                // They are disjoint from the groups handed out above, so the
+                // map can be reused for them now that its entries are done.
+                if let Some(spill) = self.spill.take() {
+                    let rows = spill.into_rows()?;
+                    if !rows.is_empty() {
+                        self.group_key_aggrs.clear();
+                        self.cursor = 0;
+                        for (group_key, cols) in rows {
+                            self.update_group(group_key, &cols)?;
+                        }
+                        return self.next();
+                    }
+                }
+                Ok(None)
+            }
         }
     }
 
@@ -816,4 +953,62 @@ mod tests {
         aggr_ect.collect_exec_stats(&mut exec_stats);
         assert_eq!(expected_counts, exec_stats.scanned_rows_per_range);
     }
+
+    #[test]
+    fn test_hash_agg_spill() {
+        // prepare data and store
+        let tid = 1;
+        let cis = vec![
+            new_col_info(1, FieldTypeTp::LongLong),
+            new_col_info(2, FieldTypeTp::VarChar),
+            new_col_info(3, FieldTypeTp::NewDecimal),
+        ];
+        let mut raw_data = Vec::new();
+        for i in 0..50 {
+            raw_data.push(vec![
+                Datum::I64(i),
+                Datum::Bytes(format!("group-{}", i % 17).into_bytes()),
+                Datum::Dec((i % 17).into()),
+            ]);
+        }
+
+        // init aggregation meta
+        let mut aggregation = Aggregation::default();
+        let group_by = build_group_by(&[1]);
+        aggregation.set_group_by(group_by.into());
+        let aggr_funcs = build_aggr_func(&[(ExprType::Count, 0), (ExprType::Sum, 2)]);
+        aggregation.set_agg_func(aggr_funcs.into());
+
+        let collect_rows = |aggr_ect: &mut HashAggExecutor<_>| {
+            let mut row_data = Vec::new();
+            while let Some(Row::Agg(row)) = aggr_ect.next().unwrap() {
+                row_data.push(row.get_binary(&mut EvalContext::default()).unwrap());
+            }
+            row_data
+        };
+
+        // The in-memory path is the reference.
+        let key_ranges = vec![get_range(tid, i64::MIN, i64::MAX)];
+        let ts_ect = gen_table_scan_executor(tid, cis.clone(), &raw_data, Some(key_ranges));
+        let mut aggr_ect =
+            HashAggExecutor::new(aggregation.clone(), Arc::new(EvalConfig::default()), ts_ect)
+                .unwrap();
+        let mut expect_rows = collect_rows(&mut aggr_ect);
+        assert_eq!(expect_rows.len(), 17);
+        expect_rows.sort();
+
+        // Budgets forcing everything, some groups and nothing to spill must
+        // all produce the same result set.
+        for budget in &[1, 300, 1024 * 1024] {
+            let key_ranges = vec![get_range(tid, i64::MIN, i64::MAX)];
+            let ts_ect = gen_table_scan_executor(tid, cis.clone(), &raw_data, Some(key_ranges));
+            let mut aggr_ect =
+                HashAggExecutor::new(aggregation.clone(), Arc::new(EvalConfig::default()), ts_ect)
+                    .unwrap();
+            aggr_ect.enable_spill(*budget);
+            let mut row_data = collect_rows(&mut aggr_ect);
+            row_data.sort();
+            assert_eq!(row_data, expect_rows, "budget = {}", budget);
+        }
+    }
 }
@@ -17,6 +17,9 @@ pub struct SortRow {
     pub data: OriginCols,
     pub key: Vec<Datum>,
     order_cols: Arc<Vec<ByItem>>,
+    /// Per-sort-key NULLS FIRST / NULLS LAST flags. `None` keeps the default
+    /// placement, where NULL is the smallest value.
+    nulls_first: Option<Arc<Vec<bool>>>,
     eval_ctx: Arc<RefCell<EvalContext>>,
     err: Arc<RefCell<Option<String>>>,
 }
@@ -26,6 +29,7 @@ impl SortRow {
         data: OriginCols,
         key: Vec<Datum>,
         order_cols: Arc<Vec<ByItem>>,
+        nulls_first: Option<Arc<Vec<bool>>>,
         ctx: Arc<RefCell<EvalContext>>,
         err: Arc<RefCell<Option<String>>>,
     ) -> SortRow {
@@ -33,6 +37,7 @@ impl SortRow {
             data,
             key,
             order_cols,
+            nulls_first,
             eval_ctx: ctx,
             err,
         }
@@ -43,7 +48,21 @@ impl SortRow {
         self.check_err()?;
         let values = self.key.iter().zip(right.key.iter());
         let mut ctx = self.eval_ctx.borrow_mut();
-        for (col, (v1, v2)) in self.order_cols.as_ref().iter().zip(values) {
+        for (idx, (col, (v1, v2))) in self.order_cols.as_ref().iter().zip(values).enumerate() {
+            // NULL placement is absolute when requested: it is not affected by
+            // the descending flag of the sort key.
+            if let Some(flags) = &self.nulls_first {
+                let v1_null = *v1 == Datum::Null;
+                let v2_null = *v2 == Datum::Null;
+                if v1_null != v2_null {
+                    let null_ord = if flags[idx] {
+                        Ordering::Less
+                    } else {
+                        Ordering::Greater
+                    };
+                    return Ok(if v1_null { null_ord } else { null_ord.reverse() });
+                }
+            }
             match v1.cmp(&mut ctx, v2) {
                 Ok(Ordering::Equal) => {
                     continue;
@@ -79,6 +98,7 @@ impl SortRow {
 pub struct TopNHeap {
     pub rows: BinaryHeap<SortRow>,
     limit: usize,
+    nulls_first: Option<Arc<Vec<bool>>>,
     err: Arc<RefCell<Option<String>>>,
     ctx: Arc<RefCell<EvalContext>>,
 }
@@ -92,11 +112,19 @@ impl TopNHeap {
         Ok(TopNHeap {
             rows: BinaryHeap::with_capacity(cap),
             limit,
+            nulls_first: None,
             err: Arc::new(RefCell::new(None)),
             ctx,
         })
     }
 
+    /// Overrides the NULL placement of each sort key: `true` sorts NULLs before
+    /// all other values, `false` after them. Without this, NULL is simply
+    /// treated as the smallest value.
+    pub fn set_nulls_first(&mut self, flags: Vec<bool>) {
+        self.nulls_first = Some(Arc::new(flags));
+    }
+
     #[inline]
     pub fn check_err(&self) -> Result<()> {
         if let Some(ref err_msg) = *self.err.as_ref().borrow() {
@@ -118,6 +146,7 @@ impl TopNHeap {
             data,
             values,
             order_cols,
+            self.nulls_first.clone(),
             Arc::clone(&self.ctx),
             Arc::clone(&self.err),
         );
@@ -445,6 +474,58 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_topn_heap_nulls_first_last() {
+        let order_cols = Arc::new(vec![new_order_by(0, false)]);
+        for &nulls_first in &[true, false] {
+            let mut topn_heap =
+                TopNHeap::new(10, Arc::new(RefCell::new(EvalContext::default()))).unwrap();
+            topn_heap.set_nulls_first(vec![nulls_first]);
+            let data = vec![
+                Datum::I64(2),
+                Datum::Null,
+                Datum::I64(1),
+                Datum::Null,
+                Datum::I64(3),
+            ];
+            for (handle, key) in data.into_iter().enumerate() {
+                let row_data = RowColsDict::new(HashMap::default(), b"data".to_vec());
+                topn_heap
+                    .try_add_row(
+                        OriginCols::new(handle as i64, row_data, Arc::default()),
+                        vec![key],
+                        Arc::clone(&order_cols),
+                    )
+                    .unwrap();
+            }
+            let result = topn_heap.into_sorted_vec().unwrap();
+            let keys: Vec<_> = result.into_iter().map(|row| row.key[0].clone()).collect();
+            if nulls_first {
+                assert_eq!(
+                    keys,
+                    vec![
+                        Datum::Null,
+                        Datum::Null,
+                        Datum::I64(1),
+                        Datum::I64(2),
+                        Datum::I64(3)
+                    ]
+                );
+            } else {
+                assert_eq!(
+                    keys,
+                    vec![
+                        Datum::I64(1),
+                        Datum::I64(2),
+                        Datum::I64(3),
+                        Datum::Null,
+                        Datum::Null
+                    ]
+                );
+            }
+        }
+    }
+
     #[test]
     fn test_topn_limit_oom() {
         let topn_heap = TopNHeap::new(
@@ -15,7 +15,7 @@ use tidb_query_common::storage::{IntervalRange, Storage};
 use tidb_query_common::Result;
 use tidb_query_datatype::codec::batch::{LazyBatchColumn, LazyBatchColumnVec};
 use tidb_query_datatype::codec::table::{check_index_key, MAX_OLD_ENCODED_VALUE_LEN};
-use tidb_query_datatype::codec::{datum, table};
+use tidb_query_datatype::codec::{datum, table, Datum};
 use tidb_query_datatype::expr::{EvalConfig, EvalContext};
 
 pub struct BatchIndexScanExecutor<S: Storage>(ScanExecutor<S, IndexScanExecutorImpl>);
@@ -82,6 +82,38 @@ impl<S: Storage> BatchIndexScanExecutor<S> {
     }
 }
 
+/// Builds the narrowest `[start, end)` index key range for a query that has equality predicates
+/// on the leading index columns plus a range on the next column.
+///
+/// `range_low` is the inclusive lower bound of the column right after the equality prefix and
+/// `range_high` is its exclusive upper bound. Pass `Datum::Min` / `Datum::Max` for an unbounded
+/// side. Compared with scanning everything under the equality prefix, the produced range skips
+/// all keys whose next column falls outside of the bounds.
+///
+/// Key order is independent of scan direction, so the same range serves descending index scans:
+/// bounds are always given in ascending value order and the executor is constructed with
+/// `is_backward: true` to emit rows in descending order.
+pub fn build_prefix_bounded_range(
+    ctx: &mut EvalContext,
+    table_id: i64,
+    index_id: i64,
+    eq_prefix: &[Datum],
+    range_low: Datum,
+    range_high: Datum,
+) -> Result<KeyRange> {
+    let mut bound_datums = Vec::with_capacity(eq_prefix.len() + 1);
+    bound_datums.extend_from_slice(eq_prefix);
+    bound_datums.push(range_low);
+    let start_data = datum::encode_key(ctx, &bound_datums)?;
+    *bound_datums.last_mut().unwrap() = range_high;
+    let end_data = datum::encode_key(ctx, &bound_datums)?;
+
+    let mut range = KeyRange::default();
+    range.set_start(table::encode_index_seek_key(table_id, index_id, &start_data));
+    range.set_end(table::encode_index_seek_key(table_id, index_id, &end_data));
+    Ok(range)
+}
+
 impl<S: Storage> BatchExecutor for BatchIndexScanExecutor<S> {
     type StorageStats = S::Statistics;
 
@@ -598,4 +630,104 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_prefix_bounded_range() {
+        const TABLE_ID: i64 = 7;
+        const INDEX_ID: i64 = 61;
+        let mut ctx = EvalContext::default();
+
+        // Index schema: (INT, INT). Rows are `a in {1, 2, 3}` x `b in 0..10`, so each equality
+        // prefix `a = x` covers 10 keys.
+        let store = {
+            let mut kv = Vec::new();
+            let mut handle = 0;
+            for a in 1..=3 {
+                for b in 0..10 {
+                    let datums = [Datum::I64(a), Datum::I64(b), Datum::I64(handle)];
+                    let index_data = datum::encode_key(&mut ctx, &datums).unwrap();
+                    let key = table::encode_index_seek_key(TABLE_ID, INDEX_ID, &index_data);
+                    kv.push((key, vec![]));
+                    handle += 1;
+                }
+            }
+            FixtureStorage::from(kv)
+        };
+
+        let columns_info = vec![
+            {
+                let mut ci = ColumnInfo::default();
+                ci.as_mut_accessor().set_tp(FieldTypeTp::LongLong);
+                ci
+            },
+            {
+                let mut ci = ColumnInfo::default();
+                ci.as_mut_accessor().set_tp(FieldTypeTp::LongLong);
+                ci
+            },
+        ];
+
+        // Runs `WHERE a = 2 AND b in [low, high)` over the given range and returns the values of
+        // column `b` together with the number of scanned keys.
+        let scan = |key_ranges: Vec<KeyRange>, is_backward: bool| {
+            let mut executor = BatchIndexScanExecutor::new(
+                store.clone(),
+                Arc::new(EvalConfig::default()),
+                columns_info.clone(),
+                key_ranges,
+                is_backward,
+                false,
+            )
+            .unwrap();
+
+            let mut ctx = EvalContext::default();
+            let mut result = executor.next_batch(100);
+            assert!(result.is_drained.as_ref().unwrap());
+            result.physical_columns[1]
+                .ensure_all_decoded_for_test(&mut ctx, &FieldTypeTp::LongLong.into())
+                .unwrap();
+            let values = result.physical_columns[1].decoded().as_int_slice().to_vec();
+
+            let mut stats = ExecuteStats::new(0);
+            executor.collect_exec_stats(&mut stats);
+            let scanned_keys: usize = stats.scanned_rows_per_range.iter().sum();
+            (values, scanned_keys)
+        };
+
+        // The naive range scans everything under the equality prefix `a = 2` and relies on a
+        // downstream filter for `b`.
+        let naive_range = build_prefix_bounded_range(
+            &mut ctx,
+            TABLE_ID,
+            INDEX_ID,
+            &[Datum::I64(2)],
+            Datum::Min,
+            Datum::Max,
+        )
+        .unwrap();
+        let (naive_values, naive_scanned) = scan(vec![naive_range], false);
+        assert_eq!(naive_values.len(), 10);
+        assert_eq!(naive_scanned, 10);
+
+        // The prefix-bounded range pushes `b in [3, 7)` into the scanned keys.
+        let bounded_range = build_prefix_bounded_range(
+            &mut ctx,
+            TABLE_ID,
+            INDEX_ID,
+            &[Datum::I64(2)],
+            Datum::I64(3),
+            Datum::I64(7),
+        )
+        .unwrap();
+        let (values, scanned) = scan(vec![bounded_range.clone()], false);
+        assert_eq!(values, vec![Some(3), Some(4), Some(5), Some(6)]);
+        assert!(scanned < naive_scanned);
+        assert_eq!(scanned, 4);
+
+        // A descending scan uses the very same range with `is_backward` and emits rows in
+        // reverse order without scanning extra keys.
+        let (values, scanned) = scan(vec![bounded_range], true);
+        assert_eq!(values, vec![Some(6), Some(5), Some(4), Some(3)]);
+        assert_eq!(scanned, 4);
+    }
 }
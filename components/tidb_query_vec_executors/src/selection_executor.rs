@@ -9,9 +9,10 @@ use tipb::Selection;
 use crate::interface::*;
 use tidb_query_common::storage::IntervalRange;
 use tidb_query_common::Result;
+use tidb_query_datatype::codec::batch::LazyBatchColumnVec;
 use tidb_query_datatype::codec::data_type::*;
-use tidb_query_datatype::expr::{EvalConfig, EvalContext};
-use tidb_query_vec_expr::RpnStackNode;
+use tidb_query_datatype::expr::{EvalConfig, EvalContext, EvalWarnings};
+use tidb_query_vec_expr::{RpnExpressionNode, RpnStackNode};
 use tidb_query_vec_expr::{RpnExpression, RpnExpressionBuilder};
 
 pub struct BatchSelectionExecutor<Src: BatchExecutor> {
@@ -19,6 +20,10 @@ pub struct BatchSelectionExecutor<Src: BatchExecutor> {
     src: Src,
 
     conditions: Vec<RpnExpression>,
+
+    /// Whether some top-level predicate is provably false, in which case the result is
+    /// empty and the child does not need to be scanned at all.
+    always_false: bool,
 }
 
 // We assign a dummy type `Box<dyn BatchExecutor<StorageStats = ()>>` so that we can omit the type
@@ -42,24 +47,30 @@ impl<Src: BatchExecutor> BatchSelectionExecutor<Src> {
             context: EvalContext::default(),
             src,
             conditions,
+            always_false: false,
         }
     }
 
     pub fn new(config: Arc<EvalConfig>, src: Src, conditions_def: Vec<Expr>) -> Result<Self> {
         let mut conditions = Vec::with_capacity(conditions_def.len());
+        let mut always_false = false;
         let mut ctx = EvalContext::new(config);
         for def in conditions_def {
-            conditions.push(RpnExpressionBuilder::build_from_expr_tree(
-                def,
-                &mut ctx,
-                src.schema().len(),
-            )?);
+            let condition =
+                RpnExpressionBuilder::build_from_expr_tree(def, &mut ctx, src.schema().len())?;
+            match fold_constant_condition(&mut ctx, src.schema(), &condition) {
+                // An always true predicate filters nothing and can be skipped entirely.
+                Some(true) => {}
+                Some(false) => always_false = true,
+                None => conditions.push(condition),
+            }
         }
 
         Ok(Self {
             context: ctx,
             src,
             conditions,
+            always_false,
         })
     }
 
@@ -117,6 +128,43 @@ impl<Src: BatchExecutor> BatchSelectionExecutor<Src> {
     }
 }
 
+/// Tries to evaluate a predicate that references no columns at build time.
+///
+/// Returns `None` when the predicate references columns or fails to evaluate. Evaluation
+/// failures must not be folded away: the normal per-batch path only evaluates predicates
+/// when there are rows, which matches MySQL semantics for erroneous predicates (e.g. a
+/// division error) over empty results.
+fn fold_constant_condition(
+    ctx: &mut EvalContext,
+    schema: &[FieldType],
+    condition: &RpnExpression,
+) -> Option<bool> {
+    let references_column = condition.as_ref().iter().any(|node| match node {
+        RpnExpressionNode::ColumnRef { .. } => true,
+        _ => false,
+    });
+    if references_column {
+        return None;
+    }
+    let mut columns = LazyBatchColumnVec::empty();
+    match condition.eval(ctx, schema, &mut columns, &[], 1) {
+        Ok(RpnStackNode::Scalar { value, .. }) => value.as_mysql_bool(ctx).ok(),
+        Ok(RpnStackNode::Vector { value, .. }) => {
+            let logical_rows = value.logical_rows();
+            if logical_rows.is_empty() {
+                return None;
+            }
+            let physical_index = logical_rows[0];
+            match_template_evaluable! {
+                TT, match value.as_ref() {
+                    VectorValue::TT(v) => v[physical_index].as_mysql_bool(ctx).ok(),
+                }
+            }
+        }
+        Err(_) => None,
+    }
+}
+
 fn update_logical_rows_by_scalar_value(
     logical_rows: &mut Vec<usize>,
     ctx: &mut EvalContext,
@@ -170,6 +218,15 @@ impl<Src: BatchExecutor> BatchExecutor for BatchSelectionExecutor<Src> {
 
     #[inline]
     fn next_batch(&mut self, scan_rows: usize) -> BatchExecuteResult {
+        if self.always_false {
+            return BatchExecuteResult {
+                physical_columns: LazyBatchColumnVec::empty(),
+                logical_rows: Vec::new(),
+                warnings: EvalWarnings::default(),
+                is_drained: Ok(true),
+            };
+        }
+
         let mut src_result = self.src.next_batch(scan_rows);
 
         if let Err(e) = self.handle_src_result(&mut src_result) {
@@ -650,4 +707,72 @@ mod tests {
         assert!(r.logical_rows.is_empty());
         assert!(r.is_drained.is_err());
     }
+
+    /// Tests the scenario that a constant predicate is provably false, e.g. `WHERE 1 = 0`.
+    /// The executor should return empty drained results without scanning the child at all.
+    #[test]
+    fn test_constant_false_short_circuit() {
+        use tipb::ScalarFuncSig;
+        use tipb_helper::ExprDefBuilder;
+
+        // The mock executor panics if `next_batch` is called more times than the number of
+        // results it holds, so giving it a single result proves the child is never scanned.
+        let src_exec = MockExecutor::new(
+            vec![FieldTypeTp::LongLong.into()],
+            vec![BatchExecuteResult {
+                physical_columns: LazyBatchColumnVec::from(vec![VectorValue::Int(vec![Some(1)])]),
+                logical_rows: vec![0],
+                warnings: EvalWarnings::default(),
+                is_drained: Ok(true),
+            }],
+        );
+
+        // WHERE 1 = 0
+        let condition = ExprDefBuilder::scalar_func(ScalarFuncSig::EqInt, FieldTypeTp::LongLong)
+            .push_child(ExprDefBuilder::constant_int(1))
+            .push_child(ExprDefBuilder::constant_int(0))
+            .build();
+        let mut exec =
+            BatchSelectionExecutor::new(Arc::new(EvalConfig::default()), src_exec, vec![condition])
+                .unwrap();
+        assert!(exec.always_false);
+
+        // Repeated calls always succeed with empty drained results. If the child were scanned
+        // the mock executor would have panicked on the second call.
+        for _ in 0..3 {
+            let r = exec.next_batch(1);
+            assert!(r.logical_rows.is_empty());
+            assert_eq!(r.physical_columns.rows_len(), 0);
+            assert!(r.is_drained.unwrap());
+        }
+    }
+
+    /// Tests the scenario that a constant predicate is provably true. It filters nothing and
+    /// should be dropped at build time, while other predicates are kept.
+    #[test]
+    fn test_constant_true_is_skipped() {
+        use tipb_helper::ExprDefBuilder;
+
+        let src_exec = make_src_executor_using_fixture_1();
+
+        let condition = ExprDefBuilder::constant_int(1).build();
+        let mut exec =
+            BatchSelectionExecutor::new(Arc::new(EvalConfig::default()), src_exec, vec![condition])
+                .unwrap();
+        assert!(!exec.always_false);
+        assert!(exec.conditions.is_empty());
+
+        // Data passes through unfiltered.
+        let r = exec.next_batch(1);
+        assert_eq!(&r.logical_rows, &[2, 0]);
+        assert!(!r.is_drained.unwrap());
+
+        let r = exec.next_batch(1);
+        assert!(r.logical_rows.is_empty());
+        assert!(!r.is_drained.unwrap());
+
+        let r = exec.next_batch(1);
+        assert_eq!(&r.logical_rows, &[1]);
+        assert!(r.is_drained.unwrap());
+    }
 }
@@ -53,8 +53,15 @@ pub struct BatchTopNExecutor<Src: BatchExecutor> {
     /// Whether or not it is descending order for each order by column.
     order_is_desc: Box<[bool]>,
 
+    /// Whether NULL values are placed first for each order by column, regardless of the
+    /// ascending / descending flag.
+    order_nulls_first: Box<[bool]>,
+
     n: usize,
 
+    /// The number of leading rows to discard after ordering, before handing out `n` rows.
+    offset: usize,
+
     context: EvalContext,
     src: Src,
     is_ended: bool,
@@ -88,8 +95,33 @@ impl<Src: BatchExecutor> BatchTopNExecutor<Src> {
         order_exprs: Vec<RpnExpression>,
         order_is_desc: Vec<bool>,
         n: usize,
+    ) -> Self {
+        Self::new_for_test_with_offset(src, order_exprs, order_is_desc, n, 0)
+    }
+
+    #[cfg(test)]
+    pub fn new_for_test_with_offset(
+        src: Src,
+        order_exprs: Vec<RpnExpression>,
+        order_is_desc: Vec<bool>,
+        n: usize,
+        offset: usize,
+    ) -> Self {
+        let order_nulls_first = order_is_desc.iter().map(|desc| !desc).collect();
+        Self::new_for_test_with_options(src, order_exprs, order_is_desc, order_nulls_first, n, offset)
+    }
+
+    #[cfg(test)]
+    pub fn new_for_test_with_options(
+        src: Src,
+        order_exprs: Vec<RpnExpression>,
+        order_is_desc: Vec<bool>,
+        order_nulls_first: Vec<bool>,
+        n: usize,
+        offset: usize,
     ) -> Self {
         assert_eq!(order_exprs.len(), order_is_desc.len());
+        assert_eq!(order_exprs.len(), order_nulls_first.len());
 
         let order_exprs_field_type: Vec<FieldType> = order_exprs
             .iter()
@@ -102,7 +134,9 @@ impl<Src: BatchExecutor> BatchTopNExecutor<Src> {
             order_exprs: order_exprs.into_boxed_slice(),
             order_exprs_field_type: order_exprs_field_type.into_boxed_slice(),
             order_is_desc: order_is_desc.into_boxed_slice(),
+            order_nulls_first: order_nulls_first.into_boxed_slice(),
             n,
+            offset,
 
             context: EvalContext::default(),
             src,
@@ -116,8 +150,47 @@ impl<Src: BatchExecutor> BatchTopNExecutor<Src> {
         order_exprs_def: Vec<Expr>,
         order_is_desc: Vec<bool>,
         n: usize,
+    ) -> Result<Self> {
+        Self::new_with_offset(config, src, order_exprs_def, order_is_desc, n, 0)
+    }
+
+    /// Builds a top-n executor that additionally discards the first `offset` rows after
+    /// ordering, for pushed down `ORDER BY ... LIMIT offset, n` queries.
+    pub fn new_with_offset(
+        config: std::sync::Arc<EvalConfig>,
+        src: Src,
+        order_exprs_def: Vec<Expr>,
+        order_is_desc: Vec<bool>,
+        n: usize,
+        offset: usize,
+    ) -> Result<Self> {
+        // NULL is the smallest value in the default ordering, i.e. NULLS FIRST when
+        // ascending and NULLS LAST when descending.
+        let order_nulls_first = order_is_desc.iter().map(|desc| !desc).collect();
+        Self::new_with_options(
+            config,
+            src,
+            order_exprs_def,
+            order_is_desc,
+            order_nulls_first,
+            n,
+            offset,
+        )
+    }
+
+    /// Builds a top-n executor with full control over the ordering: per-sort-key
+    /// descending and NULLS FIRST / NULLS LAST flags, a limit and an offset.
+    pub fn new_with_options(
+        config: std::sync::Arc<EvalConfig>,
+        src: Src,
+        order_exprs_def: Vec<Expr>,
+        order_is_desc: Vec<bool>,
+        order_nulls_first: Vec<bool>,
+        n: usize,
+        offset: usize,
     ) -> Result<Self> {
         assert_eq!(order_exprs_def.len(), order_is_desc.len());
+        assert_eq!(order_exprs_def.len(), order_nulls_first.len());
 
         let mut order_exprs: Vec<RpnExpression> = Vec::with_capacity(order_exprs_def.len());
         let mut ctx = EvalContext::new(config.clone());
@@ -135,13 +208,15 @@ impl<Src: BatchExecutor> BatchTopNExecutor<Src> {
 
         Ok(Self {
             // Avoid large N causing OOM
-            heap: BinaryHeap::with_capacity(n.min(1024)),
+            heap: BinaryHeap::with_capacity(n.saturating_add(offset).min(1024)),
             // Simply large enough to avoid repeated allocations
             eval_columns_buffer_unsafe: Box::new(Vec::with_capacity(512)),
             order_exprs: order_exprs.into_boxed_slice(),
             order_exprs_field_type: order_exprs_field_type.into_boxed_slice(),
             order_is_desc: order_is_desc.into_boxed_slice(),
+            order_nulls_first: order_nulls_first.into_boxed_slice(),
             n,
+            offset,
 
             context: EvalContext::new(config),
             src,
@@ -149,6 +224,13 @@ impl<Src: BatchExecutor> BatchTopNExecutor<Src> {
         })
     }
 
+    /// Returns how many rows the heap needs to keep: the top `offset + n` rows. Saturating so
+    /// that a huge offset plus limit does not overflow.
+    #[inline]
+    fn heap_rows(&self) -> usize {
+        self.offset.saturating_add(self.n)
+    }
+
     #[inline]
     fn handle_next_batch(&mut self) -> Result<Option<LazyBatchColumnVec>> {
         // Use max batch size from the beginning because top N
@@ -205,6 +287,7 @@ impl<Src: BatchExecutor> BatchTopNExecutor<Src> {
         for logical_row_index in 0..pinned_source_data.logical_rows.len() {
             let row = HeapItemUnsafe {
                 order_is_desc_ptr: (&*self.order_is_desc).into(),
+                order_nulls_first_ptr: (&*self.order_nulls_first).into(),
                 order_exprs_field_type_ptr: (&*self.order_exprs_field_type).into(),
                 source_data: pinned_source_data.clone(),
                 eval_columns_buffer_ptr: (&*self.eval_columns_buffer_unsafe).into(),
@@ -218,7 +301,7 @@ impl<Src: BatchExecutor> BatchTopNExecutor<Src> {
     }
 
     fn heap_add_row(&mut self, row: HeapItemUnsafe) -> Result<()> {
-        if self.heap.len() < self.n {
+        if self.heap.len() < self.heap_rows() {
             // HeapItemUnsafe must be checked valid to compare in advance, or else it may
             // panic inside BinaryHeap.
             row.cmp_sort_key(&row)?;
@@ -239,7 +322,10 @@ impl<Src: BatchExecutor> BatchTopNExecutor<Src> {
     #[allow(clippy::clone_on_copy)]
     fn heap_take_all(&mut self) -> LazyBatchColumnVec {
         let heap = std::mem::replace(&mut self.heap, BinaryHeap::default());
-        let sorted_items = heap.into_sorted_vec();
+        let all_items = heap.into_sorted_vec();
+        // Discard the first `offset` rows.
+        let begin = self.offset.min(all_items.len());
+        let sorted_items = &all_items[begin..];
         if sorted_items.is_empty() {
             return LazyBatchColumnVec::empty();
         }
@@ -363,6 +449,9 @@ struct HeapItemUnsafe {
     /// A pointer to the `order_is_desc` field in `BatchTopNExecutor`.
     order_is_desc_ptr: NonNull<[bool]>,
 
+    /// A pointer to the `order_nulls_first` field in `BatchTopNExecutor`.
+    order_nulls_first_ptr: NonNull<[bool]>,
+
     /// A pointer to the `order_exprs_field_type` field in `order_exprs`.
     order_exprs_field_type_ptr: NonNull<[FieldType]>,
 
@@ -386,6 +475,10 @@ impl HeapItemUnsafe {
         unsafe { self.order_is_desc_ptr.as_ref() }
     }
 
+    fn get_order_nulls_first(&self) -> &[bool] {
+        unsafe { self.order_nulls_first_ptr.as_ref() }
+    }
+
     fn get_order_exprs_field_type(&self) -> &[FieldType] {
         unsafe { self.order_exprs_field_type_ptr.as_ref() }
     }
@@ -402,6 +495,7 @@ impl HeapItemUnsafe {
         debug_assert_eq!(self.get_order_is_desc(), other.get_order_is_desc());
 
         let order_is_desc = self.get_order_is_desc();
+        let order_nulls_first = self.get_order_nulls_first();
         let order_exprs_field_type = self.get_order_exprs_field_type();
         let columns_len = order_is_desc.len();
         let eval_columns_lhs = self.get_eval_columns(columns_len);
@@ -413,6 +507,23 @@ impl HeapItemUnsafe {
             let lhs = lhs_node.get_logical_scalar_ref(self.logical_row_index);
             let rhs = rhs_node.get_logical_scalar_ref(other.logical_row_index);
 
+            // NULLS FIRST / NULLS LAST placement is absolute: it is not affected by the
+            // ascending / descending flag of the sort key.
+            let lhs_is_none = lhs.is_none();
+            let rhs_is_none = rhs.is_none();
+            if lhs_is_none != rhs_is_none {
+                let null_ord = if order_nulls_first[column_idx] {
+                    Ordering::Less
+                } else {
+                    Ordering::Greater
+                };
+                return Ok(if lhs_is_none {
+                    null_ord
+                } else {
+                    null_ord.reverse()
+                });
+            }
+
             // There is panic inside, but will never panic, since the data type of corresponding
             // column should be consistent for each `HeapItemUnsafe`.
             let ord = lhs.cmp_sort_key(&rhs, &order_exprs_field_type[column_idx])?;
@@ -679,6 +790,127 @@ mod tests {
         assert!(r.is_drained.unwrap());
     }
 
+    #[test]
+    fn test_with_offset() {
+        // The full sort reference, ordered by col2 (see `test_integration_1`):
+        //
+        // col0: -1, -10, NULL, -10, NULL, -10, NULL
+        // col1: NULL, NULL, -1, -10, NULL, 10, 1
+        // col2: NULL, -5, -1, 0, 2, 3, 4
+        let reference_col2 = vec![
+            None,
+            Real::new(-5.0).ok(),
+            Real::new(-1.0).ok(),
+            Real::new(0.0).ok(),
+            Real::new(2.0).ok(),
+            Real::new(3.0).ok(),
+            Real::new(4.0).ok(),
+        ];
+
+        // (offset, n) cases, including an offset beyond the data and huge values that
+        // would overflow `offset + n`.
+        let cases = vec![
+            (0, 100),
+            (2, 3),
+            (5, 100),
+            (6, 1),
+            (10, 3),
+            (std::usize::MAX, 3),
+            (3, std::usize::MAX),
+        ];
+
+        for (offset, n) in cases {
+            let src_exec = make_src_executor();
+            let mut exec = BatchTopNExecutor::new_for_test_with_offset(
+                src_exec,
+                vec![RpnExpressionBuilder::new_for_test()
+                    .push_column_ref_for_test(2)
+                    .build_for_test()],
+                vec![false],
+                n,
+                offset,
+            );
+
+            let r = exec.next_batch(1);
+            assert!(r.logical_rows.is_empty());
+            assert!(!r.is_drained.unwrap());
+
+            let r = exec.next_batch(1);
+            assert!(r.logical_rows.is_empty());
+            assert!(!r.is_drained.unwrap());
+
+            let r = exec.next_batch(1);
+            let begin = offset.min(reference_col2.len());
+            let end = begin.saturating_add(n).min(reference_col2.len());
+            let expected = &reference_col2[begin..end];
+            assert_eq!(r.physical_columns.rows_len(), expected.len());
+            if !expected.is_empty() {
+                assert_eq!(
+                    r.physical_columns[2].decoded().as_real_slice(),
+                    expected,
+                    "offset = {}, n = {}",
+                    offset,
+                    n
+                );
+            }
+            assert!(r.is_drained.unwrap());
+        }
+    }
+
+    #[test]
+    fn test_nulls_first_last() {
+        // Order by col2 with explicit NULL placement. The full sort reference of col2 with
+        // NULLs smallest is: NULL, -5, -1, 0, 2, 3, 4 (see `test_integration_1`).
+        let non_null_asc = vec![
+            Real::new(-5.0).ok(),
+            Real::new(-1.0).ok(),
+            Real::new(0.0).ok(),
+            Real::new(2.0).ok(),
+            Real::new(3.0).ok(),
+            Real::new(4.0).ok(),
+        ];
+
+        let build_expected = |desc: bool, nulls_first: bool| -> Vec<Option<Real>> {
+            let mut values = non_null_asc.clone();
+            if desc {
+                values.reverse();
+            }
+            if nulls_first {
+                values.insert(0, None);
+            } else {
+                values.push(None);
+            }
+            values
+        };
+
+        for &(desc, nulls_first) in &[(false, true), (false, false), (true, true), (true, false)] {
+            let src_exec = make_src_executor();
+            let mut exec = BatchTopNExecutor::new_for_test_with_options(
+                src_exec,
+                vec![RpnExpressionBuilder::new_for_test()
+                    .push_column_ref_for_test(2)
+                    .build_for_test()],
+                vec![desc],
+                vec![nulls_first],
+                100,
+                0,
+            );
+
+            exec.next_batch(1);
+            exec.next_batch(1);
+            let r = exec.next_batch(1);
+            assert_eq!(r.physical_columns.rows_len(), 7);
+            assert_eq!(
+                r.physical_columns[2].decoded().as_real_slice(),
+                build_expected(desc, nulls_first).as_slice(),
+                "desc = {}, nulls_first = {}",
+                desc,
+                nulls_first
+            );
+            assert!(r.is_drained.unwrap());
+        }
+    }
+
     #[test]
     fn test_integration_2() {
         // Order by multiple columns, data len == n.
@@ -3,7 +3,7 @@
 use std::fs::{self, File, OpenOptions};
 use std::io::{self, Error, ErrorKind, Write};
 use std::path::{Path, PathBuf};
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant, SystemTime};
 
 use chrono::{DateTime, Local};
 
@@ -53,6 +53,34 @@ pub struct RotatingFileLogger {
     file: File,
     rename: Box<dyn Send + Fn(&Path) -> io::Result<PathBuf>>,
     rotators: Vec<Box<dyn Rotator>>,
+    sync_on_flush: bool,
+    sync_interval: Option<Duration>,
+    last_sync: Instant,
+    // `Some` enables line-aware mode: a partial line is buffered here until
+    // its newline arrives, so rotation never splits a line across files.
+    line_buffer: Option<Vec<u8>>,
+}
+
+impl RotatingFileLogger {
+    fn sync(&mut self) -> io::Result<()> {
+        self.file.sync_data()?;
+        self.last_sync = Instant::now();
+        Ok(())
+    }
+
+    fn write_to_file(&mut self, bytes: &[u8]) -> io::Result<()> {
+        // Updates all roators' states.
+        for rotator in self.rotators.iter_mut() {
+            rotator.on_write(bytes)?;
+        }
+        self.file.write_all(bytes)?;
+        if let Some(interval) = self.sync_interval {
+            if self.last_sync.elapsed() >= interval {
+                self.sync()?;
+            }
+        }
+        Ok(())
+    }
 }
 
 /// Builder for `RotatingFileLogger`.
@@ -60,6 +88,9 @@ pub struct RotatingFileLoggerBuilder {
     rotators: Vec<Box<dyn Rotator>>,
     path: PathBuf,
     rename: Box<dyn Send + Fn(&Path) -> io::Result<PathBuf>>,
+    sync_on_flush: bool,
+    sync_interval: Option<Duration>,
+    line_aware: bool,
 }
 
 impl RotatingFileLoggerBuilder {
@@ -71,6 +102,9 @@ impl RotatingFileLoggerBuilder {
             path: path.as_ref().to_path_buf(),
             rotators: vec![],
             rename: Box::new(rename),
+            sync_on_flush: false,
+            sync_interval: None,
+            line_aware: false,
         }
     }
 
@@ -81,6 +115,35 @@ impl RotatingFileLoggerBuilder {
         self
     }
 
+    /// Fsyncs the log file on every `flush` call, so that already flushed
+    /// lines survive a crash of the process. Disabled by default: the cheap
+    /// path leaves durability to the OS.
+    pub fn sync_on_flush(mut self, enabled: bool) -> Self {
+        self.sync_on_flush = enabled;
+        self
+    }
+
+    /// Buffers a partial line until its newline arrives, so that rotation
+    /// never splits a line -- e.g. a JSON record -- across two files. The
+    /// buffered tail is written out when completed or when the logger is
+    /// dropped; `flush` deliberately leaves it in place.
+    pub fn line_aware(mut self, enabled: bool) -> Self {
+        self.line_aware = enabled;
+        self
+    }
+
+    /// Additionally fsyncs during writes, at most once per `interval`. This
+    /// bounds how much logging a crash can lose even if `flush` is never
+    /// called. A zero interval disables it.
+    pub fn sync_interval(mut self, interval: ReadableDuration) -> Self {
+        self.sync_interval = if interval.is_zero() {
+            None
+        } else {
+            Some(interval.0)
+        };
+        self
+    }
+
     pub fn build(mut self) -> io::Result<RotatingFileLogger> {
         let file = open_log_file(&self.path)?;
 
@@ -93,26 +156,58 @@ impl RotatingFileLoggerBuilder {
             path: self.path,
             rename: self.rename,
             file,
+            sync_on_flush: self.sync_on_flush,
+            sync_interval: self.sync_interval,
+            last_sync: Instant::now(),
+            line_buffer: if self.line_aware { Some(vec![]) } else { None },
         })
     }
 }
 
 impl Write for RotatingFileLogger {
     fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
-        // Updates all roators' states.
-        for rotator in self.rotators.iter_mut() {
-            rotator.on_write(bytes)?;
+        if self.line_buffer.is_some() {
+            let mut buf = self.line_buffer.take().unwrap();
+            buf.extend_from_slice(bytes);
+            // Write out everything up to the last complete line; the partial
+            // tail stays buffered until its newline arrives, so it goes to
+            // whichever file is current by then, in one piece.
+            if let Some(pos) = buf.iter().rposition(|b| *b == b'\n') {
+                let rest = buf.split_off(pos + 1);
+                self.write_to_file(&buf)?;
+                self.line_buffer = Some(rest);
+            } else {
+                self.line_buffer = Some(buf);
+            }
+            return Ok(bytes.len());
         }
-        self.file.write(bytes)
+        self.write_to_file(bytes)?;
+        Ok(bytes.len())
     }
 
     fn flush(&mut self) -> io::Result<()> {
         for rotator in self.rotators.iter() {
             if rotator.should_rotate() {
                 self.file.flush()?;
+                if self.sync_on_flush {
+                    self.sync()?;
+                }
 
                 let new_path = (self.rename)(&self.path)?;
-                fs::rename(&self.path, &new_path)?;
+                // Rotate without ever leaving the base path absent, so that
+                // log shippers tailing it always find a file: publish the old
+                // content under the rotated name via a hard link, then swap
+                // in a fresh file atomically.
+                let _ = fs::remove_file(&new_path);
+                fs::hard_link(&self.path, &new_path)?;
+                let mut tmp_path = self.path.clone().into_os_string();
+                tmp_path.push(".tmp");
+                let tmp_path = PathBuf::from(tmp_path);
+                let _ = fs::remove_file(&tmp_path);
+                drop(open_log_file(&tmp_path)?);
+                fs::rename(&tmp_path, &self.path)?;
+                // Reopen immediately; the old handle now points at the
+                // rotated file and must not be written to any more.
                 self.file = open_log_file(&self.path)?;
 
                 // Updates all roators' states.
@@ -123,12 +218,22 @@ impl Write for RotatingFileLogger {
                 return Ok(());
             }
         }
-        self.file.flush()
+        self.file.flush()?;
+        if self.sync_on_flush {
+            self.sync()?;
+        }
+        Ok(())
     }
 }
 
 impl Drop for RotatingFileLogger {
     fn drop(&mut self) {
+        // A buffered partial line has nowhere else to go; write it out as is.
+        if let Some(buf) = self.line_buffer.take() {
+            if !buf.is_empty() {
+                let _ = self.write_to_file(&buf);
+            }
+        }
         let _ = self.file.flush();
     }
 }
@@ -443,6 +548,134 @@ mod tests {
         assert!(file_exists(new_path));
     }
 
+    #[test]
+    fn test_rotation_keeps_base_path_present() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+        use std::thread;
+
+        let tmp_dir = TempDir::new().unwrap();
+        let path = tmp_dir.path().join("test_always_present.log");
+        let suffix = ".backup";
+
+        let mut logger = RotatingFileLoggerBuilder::new(path.clone(), move |path| {
+            rename_with_subffix(path, suffix)
+        })
+        .add_rotator(RotateBySize::new(ReadableSize::kb(1)))
+        .build()
+        .unwrap();
+
+        // A concurrent reader keeps checking that the base path exists, like
+        // a log shipper re-opening the file would.
+        let stop = Arc::new(AtomicBool::new(false));
+        let reader_path = path.clone();
+        let reader_stop = stop.clone();
+        let reader = thread::spawn(move || {
+            let mut misses = 0u64;
+            while !reader_stop.load(Ordering::Relaxed) {
+                if File::open(&reader_path).is_err() {
+                    misses += 1;
+                }
+            }
+            misses
+        });
+
+        for _ in 0..50 {
+            logger.write_all(&[b'x'; 1025]).unwrap();
+            // Triggers a rotation every time.
+            logger.flush().unwrap();
+        }
+        stop.store(true, Ordering::Relaxed);
+        assert_eq!(reader.join().unwrap(), 0);
+
+        let mut new_path = PathBuf::from(path).into_os_string();
+        new_path.push(suffix);
+        assert!(file_exists(new_path));
+    }
+
+    #[test]
+    fn test_line_aware_rotation() {
+        let tmp_dir = TempDir::new().unwrap();
+        let path = tmp_dir.path().join("test_line_aware.log");
+        let suffix = ".backup";
+
+        let mut logger = RotatingFileLoggerBuilder::new(path.clone(), move |path| {
+            rename_with_subffix(path, suffix)
+        })
+        .add_rotator(RotateBySize::new(ReadableSize::kb(1)))
+        .line_aware(true)
+        .build()
+        .unwrap();
+
+        let mut new_path = PathBuf::from(path.clone()).into_os_string();
+        new_path.push(suffix);
+
+        // Fill the file over the rotation size with one complete line.
+        logger.write_all(&[b'x'; 2047]).unwrap();
+        logger.write_all(b"\n").unwrap();
+
+        // Start a line, trigger rotation, then complete the line.
+        logger.write_all(b"{\"half\":").unwrap();
+        logger.flush().unwrap();
+        assert!(file_exists(new_path.clone()));
+        logger.write_all(b"1}\n").unwrap();
+        drop(logger);
+
+        // The rotated file holds only the filler line; the record that was
+        // in flight across the rollover lands complete in the current file.
+        let rotated = fs::read(new_path).unwrap();
+        assert_eq!(rotated.len(), 2048);
+        assert!(!rotated.contains(&b'{'));
+        let current = fs::read(&path).unwrap();
+        assert_eq!(current, b"{\"half\":1}\n");
+    }
+
+    #[test]
+    fn test_sync_on_flush_is_durable() {
+        let tmp_dir = TempDir::new().unwrap();
+        let path = tmp_dir.path().join("test_sync_on_flush.log");
+        let suffix = ".backup";
+
+        let mut logger = RotatingFileLoggerBuilder::new(path.clone(), move |path| {
+            rename_with_subffix(path, suffix)
+        })
+        .sync_on_flush(true)
+        .build()
+        .unwrap();
+
+        logger.write_all(b"must survive a crash").unwrap();
+        logger.flush().unwrap();
+        // Simulate a crash: the logger is never dropped, so nothing is
+        // flushed afterwards.
+        std::mem::forget(logger);
+
+        // Reopen and read; the flushed data must be there.
+        let content = fs::read(&path).unwrap();
+        assert_eq!(content, b"must survive a crash");
+    }
+
+    #[test]
+    fn test_sync_interval() {
+        let tmp_dir = TempDir::new().unwrap();
+        let path = tmp_dir.path().join("test_sync_interval.log");
+        let suffix = ".backup";
+
+        let mut logger = RotatingFileLoggerBuilder::new(path.clone(), move |path| {
+            rename_with_subffix(path, suffix)
+        })
+        .sync_interval(ReadableDuration(Duration::from_millis(1)))
+        .build()
+        .unwrap();
+
+        std::thread::sleep(Duration::from_millis(10));
+        // The interval has elapsed, so this write syncs without any flush.
+        logger.write_all(b"synced by timer").unwrap();
+        std::mem::forget(logger);
+
+        let content = fs::read(&path).unwrap();
+        assert_eq!(content, b"synced by timer");
+    }
+
     #[test]
     fn test_failing_to_rotate_file_will_not_cause_panic() {
         let tmp_dir = TempDir::new().unwrap();
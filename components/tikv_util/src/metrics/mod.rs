@@ -62,10 +62,19 @@ pub fn run_prometheus(
 }
 
 pub fn dump() -> String {
+    dump_filtered("")
+}
+
+/// Like `dump`, but only encodes metric families whose names start with the
+/// given prefix. An empty prefix keeps everything.
+pub fn dump_filtered(prefix: &str) -> String {
     let mut buffer = vec![];
     let encoder = TextEncoder::new();
     let metric_families = prometheus::gather();
     for mf in metric_families {
+        if !mf.get_name().starts_with(prefix) {
+            continue;
+        }
         if let Err(e) = encoder.encode(&[mf], &mut buffer) {
             warn!("prometheus encoding error"; "err" => ?e);
         }
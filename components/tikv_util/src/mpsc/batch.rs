@@ -135,6 +135,11 @@ impl<T> Sender<T> {
         self.sender.as_ref().map_or(false, |s| s.is_empty())
     }
 
+    /// The number of messages in the channel that are not received yet.
+    pub fn len(&self) -> usize {
+        self.sender.as_ref().map_or(0, |s| s.len())
+    }
+
     #[inline]
     pub fn send(&self, t: T) -> Result<(), SendError<T>> {
         self.sender.as_ref().unwrap().send(t)?;
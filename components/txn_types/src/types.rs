@@ -97,6 +97,20 @@ impl Key {
         Key(encoded)
     }
 
+    /// Creates a new key by appending a `u64` timestamp to this key, failing
+    /// if the key already carries a timestamp.
+    ///
+    /// The memcomparable encoding produces keys made of `ENC_GROUP_SIZE + 1`
+    /// byte groups, so a key whose encoded length is not a multiple of the
+    /// group size must already have a timestamp appended to it.
+    #[inline]
+    pub fn append_ts_checked(self, ts: TimeStamp) -> Result<Key, codec::Error> {
+        if self.0.is_empty() || self.0.len() % (bytes::ENC_GROUP_SIZE + 1) != 0 {
+            return Err(codec::Error::KeyLength);
+        }
+        Ok(self.append_ts(ts))
+    }
+
     /// Gets the timestamp contained in this key.
     ///
     /// Preconditions: the caller must ensure this is actually a timestamped
@@ -129,6 +143,17 @@ impl Key {
         }
     }
 
+    /// Splits a ts encoded key, returning the raw user key and the timestamp.
+    ///
+    /// Unlike `split_on_ts_for`, the user key part is decoded back into its
+    /// raw representation.
+    #[inline]
+    pub fn split_on_ts(self) -> Result<(Vec<u8>, TimeStamp), codec::Error> {
+        let ts = self.decode_ts()?;
+        let raw = self.truncate_ts()?.into_raw()?;
+        Ok((raw, ts))
+    }
+
     /// Split a ts encoded key, return the user key and timestamp.
     #[inline]
     pub fn split_on_ts_for(key: &[u8]) -> Result<(&[u8], TimeStamp), codec::Error> {
@@ -352,6 +377,31 @@ mod tests {
         assert_eq!(false, eq(b"abcdeffhijk87654321", b"abcdefghijk"));
     }
 
+    #[test]
+    fn test_split_on_ts() {
+        for raw_len in 0..=24 {
+            let raw: Vec<u8> = (0..raw_len).collect();
+            for &ts in &[TimeStamp::zero(), 1.into(), 256.into(), TimeStamp::max()] {
+                let key = Key::from_raw(&raw).append_ts(ts);
+                let (split_raw, split_ts) = key.split_on_ts().unwrap();
+                assert_eq!(split_raw, raw);
+                assert_eq!(split_ts, ts);
+            }
+        }
+        // A key without a timestamp has no valid user key to split off.
+        assert!(Key::from_raw(b"k").split_on_ts().is_err());
+    }
+
+    #[test]
+    fn test_append_ts_checked() {
+        let key = Key::from_raw(b"foo");
+        let ts_key = key.clone().append_ts_checked(7.into()).unwrap();
+        assert_eq!(ts_key, key.append_ts(7.into()));
+        // Appending another timestamp to a timestamped key must be rejected.
+        assert!(ts_key.append_ts_checked(8.into()).is_err());
+        assert!(Key::from_encoded(vec![]).append_ts_checked(7.into()).is_err());
+    }
+
     #[test]
     fn test_is_encoded_from() {
         for raw_len in 0..=24 {
@@ -8,6 +8,7 @@
 use std::cmp::{self, Ord, Ordering};
 use std::collections::HashMap;
 use std::error::Error;
+use std::ffi::CString;
 use std::fs;
 use std::i32;
 use std::io::Error as IoError;
@@ -24,9 +25,9 @@ use configuration::{
     RollbackCollector,
 };
 use engine::rocks::{
-    BlockBasedOptions, Cache, ColumnFamilyOptions, CompactionPriority, DBCompactionStyle,
-    DBCompressionType, DBOptions, DBRateLimiterMode, DBRecoveryMode, LRUCacheOptions,
-    TitanDBOptions,
+    BlockBasedOptions, Cache, ColumnFamilyOptions, CompactionFilterFactory, CompactionPriority,
+    DBCompactionStyle, DBCompressionType, DBOptions, DBRateLimiterMode, DBRecoveryMode,
+    LRUCacheOptions, TitanDBOptions,
 };
 use slog;
 
@@ -36,6 +37,7 @@ use crate::server::lock_manager::Config as PessimisticTxnConfig;
 use crate::server::Config as ServerConfig;
 use crate::server::CONFIG_ROCKSDB_GAUGE;
 use crate::storage::config::{Config as StorageConfig, DEFAULT_DATA_DIR, DEFAULT_ROCKSDB_SUB_DIR};
+use crate::storage::raw_ttl::TTLCompactionFilterFactory;
 use engine::rocks::util::config::{self as rocks_config, BlobRunMode, CompressionType};
 use engine::rocks::util::{
     db_exist, get_cf_handle, CFOptions, FixedPrefixSliceTransform, FixedSuffixSliceTransform,
@@ -495,6 +497,19 @@ impl DefaultCfConfig {
         cf_opts.set_titandb_options(&self.titan.build_opts());
         cf_opts
     }
+
+    /// Like [`build_opt`](DefaultCfConfig::build_opt), but attaches the
+    /// compaction filter that purges expired TTL-encoded raw keys.
+    pub fn build_ttl_opt(&self, cache: &Option<Cache>) -> ColumnFamilyOptions {
+        let mut cf_opts = self.build_opt(cache);
+        let name = CString::new("ttl_compaction_filter_factory").unwrap();
+        let factory =
+            Box::new(TTLCompactionFilterFactory) as Box<dyn CompactionFilterFactory>;
+        cf_opts
+            .set_compaction_filter_factory(name, factory)
+            .unwrap();
+        cf_opts
+    }
 }
 
 cf_config!(WriteCfConfig);
@@ -910,6 +925,19 @@ impl DbConfig {
         ]
     }
 
+    /// Like [`build_cf_opts`](DbConfig::build_cf_opts), but the default column
+    /// family purges expired TTL-encoded raw keys during compactions. Used
+    /// when `storage.enable-ttl` is set.
+    pub fn build_cf_opts_with_ttl(&self, cache: &Option<Cache>) -> Vec<CFOptions<'_>> {
+        vec![
+            CFOptions::new(CF_DEFAULT, self.defaultcf.build_ttl_opt(cache)),
+            CFOptions::new(CF_LOCK, self.lockcf.build_opt(cache)),
+            CFOptions::new(CF_WRITE, self.writecf.build_opt(cache)),
+            // TODO: remove CF_RAFT.
+            CFOptions::new(CF_RAFT, self.raftcf.build_opt(cache)),
+        ]
+    }
+
     pub fn build_cf_opts_v2(&self, cache: &Option<Cache>) -> Vec<CFOptions<'_>> {
         vec![
             CFOptions::new(CF_DEFAULT, self.defaultcf.build_opt(cache)),
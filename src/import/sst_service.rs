@@ -18,6 +18,7 @@ use crate::server::CONFIG_ROCKSDB_GAUGE;
 use engine_rocks::RocksEngine;
 use engine_traits::{SstExt, SstWriterBuilder};
 use raftstore::router::RaftStoreRouter;
+use raftstore::store::util::check_key_in_region;
 use raftstore::store::Callback;
 use sst_importer::send_rpc_response;
 use tikv_util::future::paired_future_callback;
@@ -262,7 +263,7 @@ impl<Router: RaftStoreRouter> ImportSst for ImportSSTService<Router> {
         cmd.mut_requests().push(ingest);
 
         let (cb, future) = paired_future_callback();
-        if let Err(e) = self.router.send_command(cmd, Callback::Write(cb)) {
+        if let Err(e) = self.router.send_command(cmd.clone(), Callback::Write(cb)) {
             let mut resp = IngestResponse::default();
             resp.set_error(e.into());
             ctx.spawn(sink.success(resp).map_err(|e| {
@@ -271,12 +272,101 @@ impl<Router: RaftStoreRouter> ImportSst for ImportSSTService<Router> {
             return;
         }
 
+        let router = self.router.clone();
+        let importer = Arc::clone(&self.importer);
+        let auto_refresh_epoch = self.cfg.auto_refresh_region_epoch;
+        ctx.spawn(
+            future
+                .map_err(Error::from)
+                .and_then(
+                    move |mut res| -> Box<
+                        dyn Future<Item = IngestResponse, Error = Error> + Send,
+                    > {
+                        let mut header = res.response.take_header();
+                        if auto_refresh_epoch && header.get_error().has_epoch_not_match() {
+                            // A region epoch change (e.g. a split) between upload
+                            // and ingest does not necessarily invalidate the SST.
+                            // Refresh the epoch from the error and retry exactly
+                            // once before bothering the client.
+                            let epoch_not_match = header.get_error().get_epoch_not_match();
+                            if let Some(cmd) =
+                                refresh_ingest_cmd_epoch(&cmd, epoch_not_match, &importer)
+                            {
+                                let (cb, retry) = paired_future_callback();
+                                if router.send_command(cmd, Callback::Write(cb)).is_ok() {
+                                    return Box::new(retry.map_err(Error::from).map(
+                                        |mut res| {
+                                            let mut resp = IngestResponse::default();
+                                            let mut header = res.response.take_header();
+                                            if header.has_error() {
+                                                resp.set_error(header.take_error());
+                                            }
+                                            resp
+                                        },
+                                    ));
+                                }
+                            }
+                        }
+                        let mut resp = IngestResponse::default();
+                        if header.has_error() {
+                            resp.set_error(header.take_error());
+                        }
+                        Box::new(future::ok(resp))
+                    },
+                )
+                .then(move |res| send_rpc_response!(res, sink, label, timer)),
+        )
+    }
+
+    /// Removes all keys in the given range within a region.
+    ///
+    /// The range is dropped with RocksDB range deletions instead of point
+    /// deletes, which is far cheaper for wiping out a bulk-restored range.
+    /// Raftstore validates that the range is fully contained in the region
+    /// and reports a region error otherwise.
+    fn delete_range(
+        &mut self,
+        ctx: RpcContext<'_>,
+        mut req: DeleteRangeRequest,
+        sink: UnarySink<DeleteRangeResponse>,
+    ) {
+        if !check_common_name(self.security_mgr.cert_allowed_cn(), &ctx) {
+            return;
+        }
+        let label = "delete_range";
+        let timer = Instant::now_coarse();
+
+        // Make delete range command.
+        let mut delete = Request::default();
+        delete.set_cmd_type(CmdType::DeleteRange);
+        delete.mut_delete_range().set_cf(req.take_cf());
+        delete.mut_delete_range().set_start_key(req.take_start_key());
+        delete.mut_delete_range().set_end_key(req.take_end_key());
+        let mut context = req.take_context();
+        let mut header = RaftRequestHeader::default();
+        header.set_peer(context.take_peer());
+        header.set_region_id(context.get_region_id());
+        header.set_region_epoch(context.take_region_epoch());
+        let mut cmd = RaftCmdRequest::default();
+        cmd.set_header(header);
+        cmd.mut_requests().push(delete);
+
+        let (cb, future) = paired_future_callback();
+        if let Err(e) = self.router.send_command(cmd, Callback::Write(cb)) {
+            let mut resp = DeleteRangeResponse::default();
+            resp.set_error(e.into());
+            ctx.spawn(sink.success(resp).map_err(|e| {
+                warn!("send rpc failed"; "err" => %e);
+            }));
+            return;
+        }
+
         ctx.spawn(
             future
                 .map_err(Error::from)
                 .then(|res| match res {
                     Ok(mut res) => {
-                        let mut resp = IngestResponse::default();
+                        let mut resp = DeleteRangeResponse::default();
                         let mut header = res.response.take_header();
                         if header.has_error() {
                             resp.set_error(header.take_error());
@@ -365,3 +455,34 @@ impl<Router: RaftStoreRouter> ImportSst for ImportSSTService<Router> {
         )
     }
 }
+
+// Rebuilds an ingest command against the current epoch carried in an
+// `EpochNotMatch` error. Returns `None` if the request cannot be retried
+// as-is, e.g. the SST range is no longer fully contained in the region.
+fn refresh_ingest_cmd_epoch(
+    cmd: &RaftCmdRequest,
+    epoch_not_match: &errorpb::EpochNotMatch,
+    importer: &SSTImporter,
+) -> Option<RaftCmdRequest> {
+    let region_id = cmd.get_header().get_region_id();
+    let region = epoch_not_match
+        .get_current_regions()
+        .iter()
+        .find(|r| r.get_id() == region_id)?;
+    let sst = cmd.get_requests()[0].get_ingest_sst().get_sst();
+    let range = sst.get_range();
+    if check_key_in_region(range.get_start(), region).is_err()
+        || check_key_in_region(range.get_end(), region).is_err()
+    {
+        return None;
+    }
+    // The SST meta embeds the region epoch as well and raftstore revalidates
+    // it at apply time, so the importer has to rebind the file to the new
+    // epoch before the retry.
+    let epoch = region.get_region_epoch().clone();
+    let sst = importer.update_region_epoch(sst, epoch.clone()).ok()?;
+    let mut cmd = cmd.clone();
+    cmd.mut_header().set_region_epoch(epoch);
+    cmd.mut_requests()[0].mut_ingest_sst().set_sst(sst);
+    Some(cmd)
+}
@@ -21,6 +21,8 @@ const DEFAULT_GRPC_CONCURRENT_STREAM: i32 = 1024;
 const DEFAULT_GRPC_RAFT_CONN_NUM: usize = 1;
 const DEFAULT_GRPC_MEMORY_POOL_QUOTA: u64 = isize::MAX as u64;
 const DEFAULT_GRPC_STREAM_INITIAL_WINDOW_SIZE: u64 = 2 * 1024 * 1024;
+const DEFAULT_RAFT_MSG_MAX_BATCH_SIZE: usize = 128;
+const DEFAULT_RAFT_CLIENT_QUEUE_SIZE: usize = 8192;
 
 // Number of rows in each chunk.
 const DEFAULT_ENDPOINT_BATCH_ROW_LIMIT: usize = 64;
@@ -61,16 +63,32 @@ pub struct Config {
     // These are related to TiKV status.
     pub status_addr: String,
     pub status_thread_pool_size: usize,
+    /// Whether the status server serves on-demand CPU profiles.
+    pub enable_debug_profiling: bool,
 
     // TODO: use CompressionAlgorithms instead once it supports traits like Clone etc.
     pub grpc_compression_type: GrpcCompressionType,
     pub grpc_concurrency: usize,
     pub grpc_concurrent_stream: i32,
+    #[serde(alias = "raft-client-conn-count")]
     pub grpc_raft_conn_num: usize,
+    /// Max number of raft messages coalesced into one stream write. The flush
+    /// delay is bounded by `heavy-load-wait-duration`.
+    pub raft_msg_max_batch_size: usize,
+    /// Max number of raft messages queued per connection towards a store.
+    /// Non-critical messages beyond the limit are dropped; raft re-sends them
+    /// anyway. 0 means unbounded.
+    pub raft_client_queue_size: usize,
     pub grpc_memory_pool_quota: ReadableSize,
     pub grpc_stream_initial_window_size: ReadableSize,
     pub grpc_keepalive_time: ReadableDuration,
     pub grpc_keepalive_timeout: ReadableDuration,
+    /// Connections older than this are asked to close gracefully. 0 means no limit.
+    pub grpc_max_connection_age: ReadableDuration,
+    /// Max message size the server sends/receives. Oversized messages get
+    /// `RESOURCE_EXHAUSTED` instead of breaking the connection. 0 means no limit.
+    pub max_grpc_send_msg_len: ReadableSize,
+    pub max_grpc_recv_msg_len: ReadableSize,
     /// How many snapshots can be sent concurrently.
     pub concurrent_send_snap_limit: usize,
     /// How many snapshots can be recv concurrently.
@@ -84,6 +102,13 @@ pub struct Config {
     pub end_point_max_concurrency: usize,
     pub snap_max_write_bytes_per_sec: ReadableSize,
     pub snap_max_total_size: ReadableSize,
+    /// Bandwidth cap on outgoing snapshot streams. 0 means unlimited.
+    pub snap_send_bytes_per_sec: ReadableSize,
+    /// Bandwidth cap on incoming snapshot streams. 0 means unlimited.
+    pub snap_recv_bytes_per_sec: ReadableSize,
+    /// How old a leftover partial snapshot file must be before the periodic
+    /// sweep reclaims it.
+    pub snap_stale_file_age: ReadableDuration,
     pub stats_concurrency: usize,
     pub heavy_load_threshold: usize,
     pub heavy_load_wait_duration: ReadableDuration,
@@ -92,6 +117,14 @@ pub struct Config {
     pub request_batch_enable_cross_command: bool,
     // Wait duration before each request batch is processed.
     pub request_batch_wait_duration: ReadableDuration,
+    /// How long a resolved store address stays valid in the resolver cache.
+    pub resolve_cache_ttl: ReadableDuration,
+    /// Whether reads try the lease-based local read path first. When disabled
+    /// every read takes a read index through the raft quorum.
+    pub prefer_lease_read: bool,
+    /// Requests slower than this are counted and logged (sampled) with their
+    /// type and region. 0 disables slow-request logging.
+    pub slow_request_threshold: ReadableDuration,
 
     // Server labels to specify some attributes about this server.
     pub labels: HashMap<String, String>,
@@ -122,16 +155,22 @@ impl Default for Config {
             advertise_addr: DEFAULT_ADVERTISE_LISTENING_ADDR.to_owned(),
             status_addr: DEFAULT_STATUS_ADDR.to_owned(),
             status_thread_pool_size: 1,
+            enable_debug_profiling: false,
             grpc_compression_type: GrpcCompressionType::None,
             grpc_concurrency: DEFAULT_GRPC_CONCURRENCY,
             grpc_concurrent_stream: DEFAULT_GRPC_CONCURRENT_STREAM,
             grpc_raft_conn_num: DEFAULT_GRPC_RAFT_CONN_NUM,
+            raft_msg_max_batch_size: DEFAULT_RAFT_MSG_MAX_BATCH_SIZE,
+            raft_client_queue_size: DEFAULT_RAFT_CLIENT_QUEUE_SIZE,
             grpc_stream_initial_window_size: ReadableSize(DEFAULT_GRPC_STREAM_INITIAL_WINDOW_SIZE),
             grpc_memory_pool_quota: ReadableSize(DEFAULT_GRPC_MEMORY_POOL_QUOTA),
             // There will be a heartbeat every secs, it's weird a connection will be idle for more
             // than 10 senconds.
             grpc_keepalive_time: ReadableDuration::secs(10),
             grpc_keepalive_timeout: ReadableDuration::secs(3),
+            grpc_max_connection_age: ReadableDuration::secs(0),
+            max_grpc_send_msg_len: ReadableSize(0),
+            max_grpc_recv_msg_len: ReadableSize(0),
             concurrent_send_snap_limit: 32,
             concurrent_recv_snap_limit: 32,
             end_point_concurrency: None, // deprecated
@@ -148,6 +187,9 @@ impl Default for Config {
             end_point_max_concurrency: cpu_num,
             snap_max_write_bytes_per_sec: ReadableSize(DEFAULT_SNAP_MAX_BYTES_PER_SEC),
             snap_max_total_size: ReadableSize(0),
+            snap_send_bytes_per_sec: ReadableSize(0),
+            snap_recv_bytes_per_sec: ReadableSize(0),
+            snap_stale_file_age: ReadableDuration::hours(1),
             stats_concurrency: 1,
             // 300 means gRPC threads are under heavy load if their total CPU usage
             // is greater than 300%.
@@ -157,6 +199,9 @@ impl Default for Config {
             enable_request_batch: true,
             request_batch_enable_cross_command: true,
             request_batch_wait_duration: ReadableDuration::millis(1),
+            resolve_cache_ttl: ReadableDuration::secs(60),
+            prefer_lease_read: true,
+            slow_request_threshold: ReadableDuration::secs(0),
         }
     }
 }
@@ -190,6 +235,8 @@ impl Config {
             ));
         }
         let non_zero_entries = vec![
+            // The gRPC environment needs at least one completion queue thread.
+            ("grpc-concurrency", self.grpc_concurrency),
             (
                 "concurrent-send-snap-limit",
                 self.concurrent_send_snap_limit,
@@ -217,6 +264,28 @@ impl Config {
             ));
         }
 
+        if self.grpc_raft_conn_num == 0 {
+            return Err(box_err!("server.grpc-raft-conn-num can not be 0."));
+        }
+
+        if self.raft_msg_max_batch_size == 0 {
+            return Err(box_err!("server.raft-msg-max-batch-size can not be 0."));
+        }
+
+        if self.max_grpc_send_msg_len.0 > i32::MAX as u64 {
+            return Err(box_err!("server.max-grpc-send-msg-len is too large."));
+        }
+
+        if self.max_grpc_recv_msg_len.0 > i32::MAX as u64 {
+            return Err(box_err!("server.max-grpc-recv-msg-len is too large."));
+        }
+
+        if self.grpc_keepalive_timeout.as_millis() >= self.grpc_keepalive_time.as_millis() {
+            return Err(box_err!(
+                "server.grpc-keepalive-timeout should be less than server.grpc-keepalive-time."
+            ));
+        }
+
         if self.grpc_stream_initial_window_size.0 > i32::MAX as u64 {
             return Err(box_err!(
                 "server.grpc_stream_initial_window_size is too large."
@@ -284,6 +353,10 @@ mod tests {
         cfg.validate().unwrap();
         assert_eq!(cfg.addr, cfg.advertise_addr);
 
+        let mut invalid_cfg = cfg.clone();
+        invalid_cfg.grpc_concurrency = 0;
+        assert!(invalid_cfg.validate().is_err());
+
         let mut invalid_cfg = cfg.clone();
         invalid_cfg.concurrent_send_snap_limit = 0;
         assert!(invalid_cfg.validate().is_err());
@@ -315,6 +388,11 @@ mod tests {
         invalid_cfg.grpc_stream_initial_window_size = ReadableSize(i32::MAX as u64 + 1);
         assert!(invalid_cfg.validate().is_err());
 
+        let mut invalid_cfg = cfg.clone();
+        invalid_cfg.grpc_keepalive_time = ReadableDuration::secs(1);
+        invalid_cfg.grpc_keepalive_timeout = ReadableDuration::secs(2);
+        assert!(invalid_cfg.validate().is_err());
+
         cfg.labels.insert("k1".to_owned(), "v1".to_owned());
         cfg.validate().unwrap();
         cfg.labels.insert("k2".to_owned(), "v2?".to_owned());
@@ -6,6 +6,7 @@ use std::path::Path;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::thread::{Builder as ThreadBuilder, JoinHandle};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use std::{error, result};
 
 use engine::rocks::util::get_cf_handle;
@@ -20,7 +21,9 @@ use engine_traits::{
     Iterable, Mutable, Peekable, TableProperties, TablePropertiesCollection, TablePropertiesExt,
     WriteBatch, WriteOptions,
 };
-use engine_traits::{WriteBatchExt, CF_DEFAULT, CF_LOCK, CF_RAFT, CF_WRITE};
+use engine_traits::{
+    WriteBatchExt, CF_DEFAULT, CF_LOCK, CF_RAFT, CF_WRITE, DATA_CFS, DATA_KEY_PREFIX_LEN,
+};
 use kvproto::debugpb::{self, Db as DBType, Module};
 use kvproto::kvrpcpb::{MvccInfo, MvccLock, MvccValue, MvccWrite, Op};
 use kvproto::metapb::{Peer, Region};
@@ -232,6 +235,53 @@ impl Debugger {
         }
     }
 
+    /// Compares the raft state in the raft engine against the apply state in
+    /// the kv engine and reports every mismatch found. Nothing is modified;
+    /// an empty result means the two engines agree on this region.
+    pub fn check_region_consistency(&self, region_id: u64) -> Result<Vec<String>> {
+        let raft_state_key = keys::raft_state_key(region_id);
+        let raft_state = box_try!(self
+            .engines
+            .raft
+            .c()
+            .get_msg::<RaftLocalState>(&raft_state_key))
+        .ok_or_else(|| Error::NotFound(format!("raft state for region {}", region_id)))?;
+
+        let apply_state_key = keys::apply_state_key(region_id);
+        let apply_state = box_try!(self
+            .engines
+            .kv
+            .c()
+            .get_msg_cf::<RaftApplyState>(CF_RAFT, &apply_state_key))
+        .ok_or_else(|| Error::NotFound(format!("apply state for region {}", region_id)))?;
+
+        let mut mismatches = Vec::new();
+        let last_index = raft_state.get_last_index();
+        let commit_index = raft_state.get_hard_state().get_commit();
+        let applied_index = apply_state.get_applied_index();
+        let truncated_index = apply_state.get_truncated_state().get_index();
+
+        if applied_index > last_index {
+            mismatches.push(format!(
+                "applied index {} is beyond raft last index {}",
+                applied_index, last_index
+            ));
+        }
+        if applied_index > commit_index {
+            mismatches.push(format!(
+                "applied index {} is beyond raft commit index {}",
+                applied_index, commit_index
+            ));
+        }
+        if truncated_index > applied_index {
+            mismatches.push(format!(
+                "truncated index {} is beyond applied index {}",
+                truncated_index, applied_index
+            ));
+        }
+        Ok(mismatches)
+    }
+
     pub fn region_size<T: AsRef<str>>(
         &self,
         region_id: u64,
@@ -283,6 +333,127 @@ impl Debugger {
         MvccInfoIterator::new(&self.engines.kv, start, end, limit)
     }
 
+    /// Dumps all MVCC versions of a user key: the lock (if any), every write
+    /// record with its start/commit ts and type, and the default values.
+    /// Returns an empty `MvccInfo` when the key has no data at all.
+    pub fn dump_mvcc(&self, key: &[u8]) -> Result<MvccInfo> {
+        let from = keys::data_key(Key::from_raw(key).as_encoded());
+        let mut iter = MvccInfoIterator::new(&self.engines.kv, &from, &[], 1)?;
+        match iter.next().transpose()? {
+            // The iterator starts at the first key >= `from`, which may
+            // belong to another user key when this one has no data.
+            Some((prefix, mvcc_info)) if prefix == from => Ok(mvcc_info),
+            _ => Ok(MvccInfo::default()),
+        }
+    }
+
+    /// Scans the lock CF for locks whose transactions started at least `min_age` ago. Returns the
+    /// primary key and age of each matched lock, at most `limit` entries (0 means no limit).
+    ///
+    /// This helps operators find stuck transactions whose locks were left behind.
+    pub fn orphan_locks(
+        &self,
+        min_age: Duration,
+        limit: usize,
+    ) -> Result<Vec<(Vec<u8>, Duration)>> {
+        let db = &self.engines.kv;
+        let now_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("clock went backwards")
+            .as_millis() as u64;
+        let min_age_ms = min_age.as_millis() as u64;
+
+        let mut locks = Vec::new();
+        box_try!(db.c().scan_cf(
+            CF_LOCK,
+            keys::DATA_MIN_KEY,
+            keys::DATA_MAX_KEY,
+            false,
+            |_, value| {
+                let lock = box_try!(Lock::parse(value));
+                let age_ms = now_ms.saturating_sub(lock.ts.physical());
+                if age_ms >= min_age_ms {
+                    locks.push((lock.primary, Duration::from_millis(age_ms)));
+                    if limit > 0 && locks.len() >= limit {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+        ));
+        Ok(locks)
+    }
+
+    /// Scans the kv engine for data keys not covered by any region range in
+    /// the local region metadata. Such "orphan" keys can be left behind by
+    /// botched splits. `start` and `end` are data keys (must start with `z`);
+    /// at most `limit` keys are returned (0 means no limit).
+    pub fn scan_orphan_keys(
+        &self,
+        start: &[u8],
+        end: &[u8],
+        limit: usize,
+    ) -> Result<Vec<Vec<u8>>> {
+        if !start.starts_with(keys::DATA_PREFIX_KEY)
+            || (!end.is_empty() && !end.starts_with(keys::DATA_PREFIX_KEY))
+        {
+            return Err(Error::InvalidArgument(
+                "start and end should start with \"z\"".to_owned(),
+            ));
+        }
+
+        // Collect the key ranges of all live regions on this store.
+        let mut ranges = Vec::new();
+        box_try!(self.engines.kv.c().scan_cf(
+            CF_RAFT,
+            keys::REGION_META_MIN_KEY,
+            keys::REGION_META_MAX_KEY,
+            false,
+            |key, value| {
+                let (_, suffix) = box_try!(keys::decode_region_meta_key(key));
+                if suffix != keys::REGION_STATE_SUFFIX {
+                    return Ok(true);
+                }
+                let mut region_state = RegionLocalState::default();
+                box_try!(region_state.merge_from_bytes(value));
+                if region_state.get_state() != PeerState::Tombstone {
+                    let region = region_state.get_region();
+                    ranges.push((region.get_start_key().to_vec(), region.get_end_key().to_vec()));
+                }
+                Ok(true)
+            }
+        ));
+
+        let end = if end.is_empty() {
+            keys::DATA_MAX_KEY.to_vec()
+        } else {
+            end.to_vec()
+        };
+        let mut orphans = Vec::new();
+        for cf in DATA_CFS {
+            let mut done = false;
+            box_try!(self.engines.kv.c().scan_cf(cf, start, &end, false, |key, _| {
+                // Region boundaries don't carry the data key prefix.
+                let origin = &key[DATA_KEY_PREFIX_LEN..];
+                let covered = ranges.iter().any(|(s, e)| {
+                    origin >= s.as_slice() && (e.is_empty() || origin < e.as_slice())
+                });
+                if !covered {
+                    orphans.push(key.to_vec());
+                    if limit > 0 && orphans.len() >= limit {
+                        done = true;
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }));
+            if done {
+                break;
+            }
+        }
+        Ok(orphans)
+    }
+
     /// Scan raw keys for given range `[start, end)` in given cf.
     pub fn raw_scan(
         &self,
@@ -337,6 +508,16 @@ impl Debugger {
         Ok(())
     }
 
+    /// Forces a WAL sync of both engines. Useful for crash-consistency
+    /// testing, where an fsync needs to be triggered from outside before the
+    /// process is killed.
+    pub fn sync_wal(&self) -> Result<()> {
+        box_try!(self.engines.sync_kv());
+        box_try!(self.engines.sync_raft());
+        info!("Debugger synced WAL of both engines");
+        Ok(())
+    }
+
     /// Set regions to tombstone by manual, and apply other status(such as
     /// peers, version, and key range) from `region` which comes from PD normally.
     pub fn set_region_tombstone(&self, regions: Vec<Region>) -> Result<Vec<(u64, Error)>> {
@@ -620,6 +801,66 @@ impl Debugger {
         Ok(())
     }
 
+    /// Forces a region to a single healthy peer during disaster recovery.
+    /// The persisted region state retains only the peer on
+    /// `keep_peer_store_id` and the conf version is bumped by the number of
+    /// removed peers so the new configuration wins.
+    pub fn recover_region(&self, region_id: u64, keep_peer_store_id: u64) -> Result<()> {
+        let store_id = self.get_store_id()?;
+        if store_id != keep_peer_store_id {
+            let msg = format!(
+                "Store {} is not the store to keep, must run on store {}",
+                store_id, keep_peer_store_id
+            );
+            return Err(Error::Other(msg.into()));
+        }
+
+        let kv = &self.engines.kv;
+        let key = keys::region_state_key(region_id);
+        let mut region_state = box_try!(kv.c().get_msg_cf::<RegionLocalState>(CF_RAFT, &key))
+            .ok_or_else(|| {
+                Error::Other(format!("No such region {} on the store", region_id).into())
+            })?;
+        if region_state.get_state() == PeerState::Tombstone {
+            let msg = format!("Region {} is tombstone", region_id);
+            return Err(Error::Other(msg.into()));
+        }
+        let keep_peer = region_state
+            .get_region()
+            .get_peers()
+            .iter()
+            .find(|p| p.get_store_id() == keep_peer_store_id)
+            .cloned()
+            .ok_or_else(|| {
+                Error::Other(
+                    format!(
+                        "Region {} has no peer on store {}",
+                        region_id, keep_peer_store_id
+                    )
+                    .into(),
+                )
+            })?;
+
+        let old_peers = region_state.mut_region().take_peers();
+        info!(
+            "recover region to a single peer";
+            "region_id" => region_id,
+            "old_peers" => ?old_peers,
+            "keep_peer" => ?keep_peer,
+        );
+        let removed = old_peers.len() as u64 - 1;
+        region_state.mut_region().set_peers(vec![keep_peer].into());
+        let epoch = region_state.mut_region().mut_region_epoch();
+        epoch.set_conf_ver(epoch.get_conf_ver() + removed);
+
+        let mut wb = kv.c().write_batch();
+        box_try!(wb.put_msg_cf(CF_RAFT, &key, &region_state));
+        let mut write_opts = WriteOptions::new();
+        write_opts.set_sync(true);
+        box_try!(kv.c().write_opt(&wb, &write_opts));
+        Ok(())
+    }
+
     pub fn recreate_region(&self, region: Region) -> Result<()> {
         let region_id = region.get_id();
         let kv = &self.engines.kv;
@@ -1570,6 +1811,93 @@ mod tests {
             .unwrap()
     }
 
+    #[test]
+    fn test_orphan_locks() {
+        let debugger = new_debugger();
+        let engine = &debugger.engines.kv;
+        let lock_cf = engine.cf_handle(CF_LOCK).unwrap();
+
+        let now_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        // (key, physical time of the lock's start_ts)
+        let cf_lock_data: Vec<(&[u8], u64)> = vec![
+            (b"k1", now_ms - 3_600_000),
+            (b"k2", now_ms - 120_000),
+            (b"k3", now_ms),
+        ];
+        for &(prefix, physical) in &cf_lock_data {
+            let encoded_key = Key::from_raw(prefix);
+            let key = keys::data_key(encoded_key.as_encoded().as_slice());
+            let lock = Lock::new(
+                LockType::Put,
+                prefix.to_vec(),
+                TimeStamp::compose(physical, 0),
+                0,
+                None,
+                TimeStamp::zero(),
+                0,
+                TimeStamp::zero(),
+            );
+            engine
+                .put_cf(lock_cf, key.as_slice(), lock.to_bytes().as_slice())
+                .unwrap();
+        }
+
+        // Only locks older than the threshold are reported.
+        let locks = debugger.orphan_locks(Duration::from_secs(60), 0).unwrap();
+        assert_eq!(locks.len(), 2);
+        assert_eq!(locks[0].0, b"k1".to_vec());
+        assert!(locks[0].1 >= Duration::from_secs(3600));
+        assert_eq!(locks[1].0, b"k2".to_vec());
+        assert!(locks[1].1 >= Duration::from_secs(120));
+
+        // The limit is honored.
+        let locks = debugger.orphan_locks(Duration::from_secs(60), 1).unwrap();
+        assert_eq!(locks.len(), 1);
+        assert_eq!(locks[0].0, b"k1".to_vec());
+    }
+
+    #[test]
+    fn test_scan_orphan_keys() {
+        let debugger = new_debugger();
+        let engine = &debugger.engines.kv;
+
+        // A single region covering [k1, k3).
+        let mut region = init_region_state(engine, 1, &[100]);
+        region.set_start_key(b"k1".to_vec());
+        region.set_end_key(b"k3".to_vec());
+        let mut region_state = get_region_state(engine, 1);
+        region_state.set_region(region);
+        engine
+            .c()
+            .put_msg_cf(CF_RAFT, &keys::region_state_key(1), &region_state)
+            .unwrap();
+
+        for k in &[b"k0", b"k1", b"k2", b"k3"] {
+            engine.put(&keys::data_key(*k), b"v").unwrap();
+        }
+
+        // k0 and k3 are not covered by any region.
+        let orphans = debugger
+            .scan_orphan_keys(keys::DATA_MIN_KEY, keys::DATA_MAX_KEY, 0)
+            .unwrap();
+        assert_eq!(
+            orphans,
+            vec![keys::data_key(b"k0"), keys::data_key(b"k3")]
+        );
+
+        // The limit is honored.
+        let orphans = debugger
+            .scan_orphan_keys(keys::DATA_MIN_KEY, keys::DATA_MAX_KEY, 1)
+            .unwrap();
+        assert_eq!(orphans, vec![keys::data_key(b"k0")]);
+
+        // Start and end must be data keys.
+        debugger.scan_orphan_keys(b"k0", b"k3", 0).unwrap_err();
+    }
+
     #[test]
     fn test_region_overlap() {
         let new_region = |start: &[u8], end: &[u8]| -> Region {
@@ -1688,6 +2016,18 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_sync_wal() {
+        let debugger = new_debugger();
+        let engine = &debugger.engines.kv;
+        engine.put(b"k", b"v").unwrap();
+
+        // The data written before the sync must be durable afterwards, so a
+        // crash right after a successful call cannot lose it.
+        debugger.sync_wal().unwrap();
+        assert_eq!(&*engine.get(b"k").unwrap().unwrap(), b"v");
+    }
+
     #[test]
     fn test_get() {
         let debugger = new_debugger();
@@ -1791,6 +2131,67 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_check_region_consistency() {
+        let debugger = new_debugger();
+        let raft_engine = &debugger.engines.raft;
+        let kv_engine = &debugger.engines.kv;
+        let region_id = 1;
+
+        // A missing region reports not found instead of a mismatch.
+        match debugger.check_region_consistency(region_id) {
+            Err(Error::NotFound(_)) => (),
+            res => panic!("expect Error::NotFound(_), but got {:?}", res),
+        }
+
+        let raft_state_key = keys::raft_state_key(region_id);
+        let mut raft_state = RaftLocalState::default();
+        raft_state.set_last_index(42);
+        raft_state.mut_hard_state().set_commit(42);
+        raft_engine
+            .c()
+            .put_msg(&raft_state_key, &raft_state)
+            .unwrap();
+
+        let apply_state_key = keys::apply_state_key(region_id);
+        let mut apply_state = RaftApplyState::default();
+        apply_state.set_applied_index(40);
+        apply_state.mut_truncated_state().set_index(30);
+        kv_engine
+            .c()
+            .put_msg_cf(CF_RAFT, &apply_state_key, &apply_state)
+            .unwrap();
+
+        // Both engines agree.
+        assert!(debugger
+            .check_region_consistency(region_id)
+            .unwrap()
+            .is_empty());
+
+        // Desync the engines: the kv engine claims to have applied entries
+        // the raft engine never stored.
+        apply_state.set_applied_index(50);
+        kv_engine
+            .c()
+            .put_msg_cf(CF_RAFT, &apply_state_key, &apply_state)
+            .unwrap();
+        let mismatches = debugger.check_region_consistency(region_id).unwrap();
+        assert_eq!(mismatches.len(), 2);
+        assert!(mismatches[0].contains("raft last index"));
+        assert!(mismatches[1].contains("raft commit index"));
+
+        // A truncated state beyond the applied index is flagged as well.
+        apply_state.set_applied_index(40);
+        apply_state.mut_truncated_state().set_index(41);
+        kv_engine
+            .c()
+            .put_msg_cf(CF_RAFT, &apply_state_key, &apply_state)
+            .unwrap();
+        let mismatches = debugger.check_region_consistency(region_id).unwrap();
+        assert_eq!(mismatches.len(), 1);
+        assert!(mismatches[0].contains("truncated index"));
+    }
+
     #[test]
     fn test_region_size() {
         let debugger = new_debugger();
@@ -1894,6 +2295,69 @@ mod tests {
         assert!(debugger.scan_mvcc(b"z", b"x", 3).is_err());
     }
 
+    #[test]
+    fn test_dump_mvcc() {
+        let debugger = new_debugger();
+        let engine = &debugger.engines.kv;
+
+        // Two committed versions of "k1" plus a pending lock.
+        let cf_default_data: Vec<(&[u8], &[u8], TimeStamp)> =
+            vec![(b"k1", b"v1", 5.into()), (b"k1", b"v2", 15.into())];
+        for &(prefix, value, ts) in &cf_default_data {
+            let encoded_key = Key::from_raw(prefix).append_ts(ts);
+            let key = keys::data_key(encoded_key.as_encoded().as_slice());
+            engine.put(key.as_slice(), value).unwrap();
+        }
+
+        let write_cf = engine.cf_handle(CF_WRITE).unwrap();
+        let cf_write_data: Vec<(&[u8], WriteType, TimeStamp, TimeStamp)> = vec![
+            (b"k1", WriteType::Put, 5.into(), 10.into()),
+            (b"k1", WriteType::Put, 15.into(), 20.into()),
+        ];
+        for &(prefix, tp, start_ts, commit_ts) in &cf_write_data {
+            let encoded_key = Key::from_raw(prefix).append_ts(commit_ts);
+            let key = keys::data_key(encoded_key.as_encoded().as_slice());
+            let write = Write::new(tp, start_ts, None);
+            engine
+                .put_cf(write_cf, key.as_slice(), write.as_ref().to_bytes().as_slice())
+                .unwrap();
+        }
+
+        let lock_cf = engine.cf_handle(CF_LOCK).unwrap();
+        let encoded_key = Key::from_raw(b"k1");
+        let key = keys::data_key(encoded_key.as_encoded().as_slice());
+        let lock = Lock::new(
+            LockType::Put,
+            b"k1".to_vec(),
+            25.into(),
+            0,
+            None,
+            TimeStamp::zero(),
+            0,
+            TimeStamp::zero(),
+        );
+        engine
+            .put_cf(lock_cf, key.as_slice(), lock.to_bytes().as_slice())
+            .unwrap();
+
+        let mvcc_info = debugger.dump_mvcc(b"k1").unwrap();
+        assert!(mvcc_info.has_lock());
+        assert_eq!(mvcc_info.get_lock().get_start_ts(), 25);
+        assert_eq!(mvcc_info.get_writes().len(), 2);
+        // Writes are returned in decreasing commit ts order.
+        assert_eq!(mvcc_info.get_writes()[0].get_commit_ts(), 20);
+        assert_eq!(mvcc_info.get_writes()[0].get_start_ts(), 15);
+        assert_eq!(mvcc_info.get_writes()[1].get_commit_ts(), 10);
+        assert_eq!(mvcc_info.get_writes()[1].get_start_ts(), 5);
+        assert_eq!(mvcc_info.get_values().len(), 2);
+
+        // A key without any data dumps as an empty MvccInfo.
+        let empty = debugger.dump_mvcc(b"k0").unwrap();
+        assert!(!empty.has_lock());
+        assert!(empty.get_writes().is_empty());
+        assert!(empty.get_values().is_empty());
+    }
+
     #[test]
     fn test_tombstone_regions() {
         let debugger = new_debugger();
@@ -2009,6 +2473,32 @@ mod tests {
         debugger.remove_failed_stores(vec![100], None).unwrap_err();
     }
 
+    #[test]
+    fn test_recover_region() {
+        let debugger = new_debugger();
+        debugger.set_store_id(12);
+        let engine = &debugger.engines.kv;
+
+        // region 1 with peers at stores 11, 12 and 13.
+        init_region_state(engine, 1, &[11, 12, 13]);
+
+        // Must be run on the store that keeps the peer.
+        debugger.recover_region(1, 11).unwrap_err();
+        // The store must actually hold the region.
+        debugger.recover_region(2, 12).unwrap_err();
+
+        debugger.recover_region(1, 12).unwrap();
+        let region_state = get_region_state(engine, 1);
+        let peers = region_state.get_region().get_peers();
+        assert_eq!(peers.len(), 1);
+        assert_eq!(peers[0].get_store_id(), 12);
+        // The conf version is bumped by the number of removed peers.
+        assert_eq!(
+            region_state.get_region().get_region_epoch().get_conf_ver(),
+            2
+        );
+    }
+
     #[test]
     fn test_bad_regions() {
         let debugger = new_debugger();
@@ -2,12 +2,17 @@
 
 use configuration::{rollback_or, ConfigChange, ConfigManager, Configuration, RollbackCollector};
 use std::sync::Arc;
-use tikv_util::config::{ReadableSize, VersionTrack};
+use tikv_util::config::{ReadableDuration, ReadableSize, VersionTrack};
 
 const DEFAULT_GC_RATIO_THRESHOLD: f64 = 1.1;
 pub const DEFAULT_GC_BATCH_KEYS: usize = 512;
 // No limit
 const DEFAULT_GC_MAX_WRITE_BYTES_PER_SEC: u64 = 0;
+// No limit
+const DEFAULT_GC_MAX_WRITE_BATCH_SIZE: u64 = 0;
+// No limit
+const DEFAULT_GC_MAX_CONCURRENT_TASKS: usize = 0;
+const DEFAULT_AUTO_GC_POLL_SAFE_POINT_INTERVAL_SECS: u64 = 60;
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Configuration)]
 #[serde(default)]
@@ -16,6 +21,16 @@ pub struct GcConfig {
     pub ratio_threshold: f64,
     pub batch_keys: usize,
     pub max_write_bytes_per_sec: ReadableSize,
+    /// Max bytes of deletions written to the engine in one write batch. 0 means no limit, i.e.
+    /// all deletions collected in a GC round go out in a single batch. When set, the WAL is
+    /// synced between batches so only a bounded amount of data is in flight at a time.
+    pub max_write_batch_size: ReadableSize,
+    /// Max number of GC tasks being executed at the same time. 0 means no limit.
+    pub max_concurrent_tasks: usize,
+    /// Whether to automatically poll the GC safe point from PD and do GC up to it.
+    pub enable_auto_gc: bool,
+    /// How often the GC safe point is polled from PD in auto GC mode.
+    pub poll_safe_point_interval: ReadableDuration,
 }
 
 impl Default for GcConfig {
@@ -24,6 +39,12 @@ impl Default for GcConfig {
             ratio_threshold: DEFAULT_GC_RATIO_THRESHOLD,
             batch_keys: DEFAULT_GC_BATCH_KEYS,
             max_write_bytes_per_sec: ReadableSize(DEFAULT_GC_MAX_WRITE_BYTES_PER_SEC),
+            max_write_batch_size: ReadableSize(DEFAULT_GC_MAX_WRITE_BATCH_SIZE),
+            max_concurrent_tasks: DEFAULT_GC_MAX_CONCURRENT_TASKS,
+            enable_auto_gc: true,
+            poll_safe_point_interval: ReadableDuration::secs(
+                DEFAULT_AUTO_GC_POLL_SAFE_POINT_INTERVAL_SECS,
+            ),
         }
     }
 }
@@ -42,6 +63,11 @@ impl GcConfig {
                 Err(("gc.batch_keys should not be 0.").into())
             })
         }
+        if self.poll_safe_point_interval.is_zero() {
+            rollback_or!(rb_collector, poll_safe_point_interval, {
+                Err(("gc.poll-safe-point-interval should not be 0.").into())
+            })
+        }
         Ok(())
     }
 }
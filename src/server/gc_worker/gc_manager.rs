@@ -353,7 +353,7 @@ impl<S: GcSafePointProvider, R: RegionInfoProvider> GcManager<S, R> {
             }
             Ordering::Equal => false,
             Ordering::Greater => {
-                debug!("gc_worker: update safe point"; "safe_point" => safe_point);
+                info!("gc_worker: gc safe point advanced"; "safe_point" => safe_point);
                 self.safe_point = safe_point;
                 AUTO_GC_SAFE_POINT_GAUGE.set(safe_point.into_inner() as i64);
                 true
@@ -529,7 +529,12 @@ impl<S: GcSafePointProvider, R: RegionInfoProvider> GcManager<S, R> {
             "trying gc"; "region_id" => ctx.get_region_id(), "region_epoch" => ?ctx.region_epoch.as_ref(),
             "end_key" => next_key.as_ref().map(DisplayValue)
         );
-        if let Err(e) = sync_gc(&self.worker_scheduler, ctx.clone(), self.safe_point) {
+        if let Err(e) = sync_gc(
+            &self.worker_scheduler,
+            ctx.clone(),
+            self.safe_point,
+            GcReason::auto,
+        ) {
             error!(
                 "failed gc"; "region_id" => ctx.get_region_id(), "region_epoch" => ?ctx.region_epoch.as_ref(),
                 "end_key" => next_key.as_ref().map(DisplayValue),
@@ -622,6 +627,9 @@ mod tests {
             GcTask::Gc {
                 ref mut callback, ..
             } => callback,
+            GcTask::GcRange {
+                ref mut callback, ..
+            } => callback,
             GcTask::UnsafeDestroyRange {
                 ref mut callback, ..
             } => callback,
@@ -833,6 +841,45 @@ mod tests {
         assert_eq!(gc_manager.safe_point, 5.into());
     }
 
+    #[test]
+    fn test_no_gc_when_safe_point_not_advanced() {
+        let mut r = metapb::Region::default();
+        r.set_id(1);
+        r.mut_peers().push(new_peer(1, 1));
+        let info = RegionInfo::new(r, StateRole::Leader);
+        let mut regions = BTreeMap::new();
+        regions.insert(b"".to_vec(), info);
+
+        let mut test_util = GcManagerTestUtil::new(regions);
+        test_util.add_next_safe_point(233);
+        let mut gc_manager = test_util.gc_manager.take().unwrap();
+        gc_manager.initialize();
+        assert_eq!(gc_manager.safe_point, 233.into());
+
+        // The safe point stays where it is, so no GC should be triggered.
+        test_util.add_next_safe_point(233);
+        assert!(!gc_manager.try_update_safe_point());
+        assert!(test_util.collect_scheduled_tasks().is_empty());
+
+        // Advancing the safe point triggers a round of GC with the new timestamp.
+        test_util.add_next_safe_point(234);
+        assert!(gc_manager.try_update_safe_point());
+        gc_manager.gc_a_round().unwrap();
+        test_util.stop();
+
+        let tasks: Vec<_> = test_util
+            .collect_scheduled_tasks()
+            .iter()
+            .map(|task| match task {
+                GcTask::Gc {
+                    ctx, safe_point, ..
+                } => (ctx.get_region_id(), *safe_point),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(tasks, vec![(1, 234.into())]);
+    }
+
     #[test]
     fn test_auto_gc_a_round_without_rewind() {
         // First region starts with empty and last region ends with empty.
@@ -4,13 +4,13 @@ use std::f64::INFINITY;
 use std::fmt::{self, Display, Formatter};
 use std::mem;
 use std::sync::mpsc;
-use std::sync::{atomic, Arc, Mutex};
+use std::sync::{atomic, Arc, Condvar, Mutex};
 use std::time::{Duration, Instant};
 
 use engine::rocks::util::get_cf_handle;
 use engine::rocks::DB;
-use engine_rocks::{Compat, RocksEngine};
-use engine_traits::{MiscExt, TablePropertiesExt};
+use engine_rocks::{Compat, RangeProperties, RocksEngine};
+use engine_traits::{MiscExt, TableProperties, TablePropertiesCollection, TablePropertiesExt};
 use engine_traits::{CF_DEFAULT, CF_LOCK, CF_WRITE};
 use futures::Future;
 use kvproto::kvrpcpb::{Context, IsolationLevel, LockInfo};
@@ -19,7 +19,7 @@ use tokio_core::reactor::Handle;
 
 use crate::server::metrics::*;
 use crate::storage::kv::{
-    Engine, Error as EngineError, ErrorInner as EngineErrorInner, ScanMode, Statistics,
+    Engine, Error as EngineError, ErrorInner as EngineErrorInner, Modify, ScanMode, Statistics,
 };
 use crate::storage::mvcc::{check_need_gc, Error as MvccError, MvccReader, MvccTxn};
 use pd_client::PdClient;
@@ -28,6 +28,7 @@ use raftstore::router::ServerRaftStoreRouter;
 use raftstore::store::msg::StoreMsg;
 use raftstore::store::RegionSnapshot;
 use tikv_util::config::{Tracker, VersionTrack};
+use tikv_util::threadpool::{DefaultContext, ThreadPool, ThreadPoolBuilder};
 use tikv_util::time::{duration_to_sec, Limiter, SlowTimer};
 use tikv_util::worker::{
     FutureRunnable, FutureScheduler, FutureWorker, Stopped as FutureWorkerStopped,
@@ -68,10 +69,82 @@ impl<T: PdClient + 'static> GcSafePointProvider for Arc<T> {
     }
 }
 
+/// Limits how many GC tasks can be executed at the same time. A limit of 0 means no limit, which
+/// keeps the previous unbounded behavior.
+///
+/// The number of tasks holding a permit is exposed via the `tikv_gcworker_in_flight_tasks` gauge.
+#[derive(Clone)]
+pub struct GcTaskLimiter {
+    state: Arc<(Mutex<LimiterState>, Condvar)>,
+}
+
+struct LimiterState {
+    limit: usize,
+    in_flight: usize,
+}
+
+impl GcTaskLimiter {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            state: Arc::new((
+                Mutex::new(LimiterState {
+                    limit,
+                    in_flight: 0,
+                }),
+                Condvar::new(),
+            )),
+        }
+    }
+
+    /// Updates the limit. Tasks already holding a permit are not affected.
+    pub fn set_limit(&self, limit: usize) {
+        let (lock, cvar) = &*self.state;
+        lock.lock().unwrap().limit = limit;
+        cvar.notify_all();
+    }
+
+    /// Blocks until a permit is available, then takes one. The permit is released when the
+    /// returned guard is dropped.
+    pub fn acquire(&self) -> GcTaskPermit {
+        let (lock, cvar) = &*self.state;
+        let mut state = lock.lock().unwrap();
+        while state.limit > 0 && state.in_flight >= state.limit {
+            state = cvar.wait(state).unwrap();
+        }
+        state.in_flight += 1;
+        GC_IN_FLIGHT_TASKS_GAUGE.set(state.in_flight as i64);
+        GcTaskPermit {
+            state: Arc::clone(&self.state),
+        }
+    }
+}
+
+pub struct GcTaskPermit {
+    state: Arc<(Mutex<LimiterState>, Condvar)>,
+}
+
+impl Drop for GcTaskPermit {
+    fn drop(&mut self) {
+        let (lock, cvar) = &*self.state;
+        let mut state = lock.lock().unwrap();
+        state.in_flight -= 1;
+        GC_IN_FLIGHT_TASKS_GAUGE.set(state.in_flight as i64);
+        cvar.notify_one();
+    }
+}
+
 pub enum GcTask {
     Gc {
         ctx: Context,
         safe_point: TimeStamp,
+        reason: GcReason,
+        callback: Callback<()>,
+    },
+    GcRange {
+        ctx: Context,
+        start_key: Key,
+        end_key: Key,
+        safe_point: TimeStamp,
         callback: Callback<()>,
     },
     UnsafeDestroyRange {
@@ -95,6 +168,7 @@ impl GcTask {
     pub fn get_enum_label(&self) -> GcCommandKind {
         match self {
             GcTask::Gc { .. } => GcCommandKind::gc,
+            GcTask::GcRange { .. } => GcCommandKind::gc_range,
             GcTask::UnsafeDestroyRange { .. } => GcCommandKind::unsafe_destroy_range,
             GcTask::PhysicalScanLock { .. } => GcCommandKind::physical_scan_lock,
             #[cfg(any(test, feature = "testexport"))]
@@ -116,6 +190,17 @@ impl Display for GcTask {
                     .field("safe_point", safe_point)
                     .finish()
             }
+            GcTask::GcRange {
+                start_key,
+                end_key,
+                safe_point,
+                ..
+            } => f
+                .debug_struct("GcRange")
+                .field("start_key", &format!("{}", start_key))
+                .field("end_key", &format!("{}", end_key))
+                .field("safe_point", safe_point)
+                .finish(),
             GcTask::UnsafeDestroyRange {
                 start_key, end_key, ..
             } => f
@@ -133,8 +218,11 @@ impl Display for GcTask {
     }
 }
 
-/// Used to perform GC operations on the engine.
-struct GcRunner<E: Engine> {
+/// The per-task part of the GC runner. It is cloned for every incoming task,
+/// so tasks can execute concurrently on the worker pool. The limiters are
+/// shared between the clones; `cfg` and `stats` are task-local.
+#[derive(Clone)]
+struct GcRunnerCore<E: Engine> {
     engine: E,
     local_storage: Option<Arc<DB>>,
     raft_store_router: Option<ServerRaftStoreRouter>,
@@ -143,12 +231,28 @@ struct GcRunner<E: Engine> {
     /// Used to limit the write flow of GC.
     limiter: Limiter,
 
+    /// Used to limit how many GC tasks are executed at the same time.
+    task_limiter: GcTaskLimiter,
+
     cfg: GcConfig,
-    cfg_tracker: Tracker<GcConfig>,
 
     stats: Statistics,
 }
 
+/// Used to perform GC operations on the engine.
+struct GcRunner<E: Engine> {
+    core: GcRunnerCore<E>,
+
+    /// Tasks are executed on this pool so that a slow task doesn't block the
+    /// others. `check_is_busy` caps the number of outstanding tasks at
+    /// `GC_MAX_EXECUTING_TASKS`, so a pool of the same size never queues a
+    /// task behind a long-running one; `task_limiter` further restricts how
+    /// many of the threads may run at the same time.
+    pool: ThreadPool<DefaultContext>,
+
+    cfg_tracker: Tracker<GcConfig>,
+}
+
 impl<E: Engine> GcRunner<E> {
     pub fn new(
         engine: E,
@@ -163,18 +267,41 @@ impl<E: Engine> GcRunner<E> {
         } else {
             INFINITY
         });
+        let task_limiter = GcTaskLimiter::new(cfg.max_concurrent_tasks);
+        let pool = ThreadPoolBuilder::with_default_factory("gc-worker-pool".to_owned())
+            .thread_count(GC_MAX_EXECUTING_TASKS)
+            .build();
         Self {
-            engine,
-            local_storage,
-            raft_store_router,
-            region_info_accessor,
-            limiter,
-            cfg,
+            core: GcRunnerCore {
+                engine,
+                local_storage,
+                raft_store_router,
+                region_info_accessor,
+                limiter,
+                task_limiter,
+                cfg,
+                stats: Statistics::default(),
+            },
+            pool,
             cfg_tracker,
-            stats: Statistics::default(),
         }
     }
 
+    fn refresh_cfg(&mut self) {
+        if let Some(incoming) = self.cfg_tracker.any_new() {
+            let limit = incoming.max_write_bytes_per_sec.0;
+            self.core
+                .limiter
+                .set_speed_limit(if limit > 0 { limit as f64 } else { INFINITY });
+            self.core
+                .task_limiter
+                .set_limit(incoming.max_concurrent_tasks);
+            self.core.cfg = incoming.clone();
+        }
+    }
+}
+
+impl<E: Engine> GcRunnerCore<E> {
     fn get_snapshot(&self, ctx: &mut Context) -> Result<E::Snap> {
         let timeout = Duration::from_secs(GC_SNAPSHOT_TIMEOUT_SECS);
         match wait_op!(|cb| self.engine.async_snapshot(ctx, cb), timeout) {
@@ -190,31 +317,20 @@ impl<E: Engine> GcRunner<E> {
         .map_err(Error::from)
     }
 
-    /// Check need gc without getting snapshot.
-    /// If this is not supported or any error happens, returns true to do further check after
-    /// getting snapshot.
-    fn need_gc(&self, ctx: &Context, safe_point: TimeStamp) -> bool {
+    /// Looks up the data range of the region from `region_info_accessor`. Returns `None` if the
+    /// accessor is not set, the region is not found or any error happens.
+    fn find_region_range(&self, region_id: u64) -> Option<(Vec<u8>, Vec<u8>)> {
         let region_info_accessor = match &self.region_info_accessor {
             Some(r) => r,
             None => {
-                info!(
-                    "region_info_accessor not set. cannot check need_gc without getting snapshot"
-                );
-                return true;
-            }
-        };
-
-        let db = match &self.local_storage {
-            Some(db) => db,
-            None => {
-                info!("local_storage not set. cannot check need_gc without getting snapshot");
-                return true;
+                info!("region_info_accessor not set. cannot find the range of the region");
+                return None;
             }
         };
 
         let (tx, rx) = mpsc::channel();
         if let Err(e) = region_info_accessor.find_region_by_id(
-            ctx.get_region_id(),
+            region_id,
             Box::new(move |region| match tx.send(region) {
                 Ok(()) => (),
                 Err(e) => error!(
@@ -225,27 +341,62 @@ impl<E: Engine> GcRunner<E> {
         ) {
             error!(
                 "failed to find_region_by_id from region_info_accessor";
-                "region_id" => ctx.get_region_id(),
+                "region_id" => region_id,
                 "err" => ?e
             );
-            return true;
+            return None;
         }
 
         let region_info = match rx.recv() {
-            Ok(None) => return true,
+            Ok(None) => return None,
             Ok(Some(r)) => r,
             Err(e) => {
                 error!(
                     "failed to find_region_by_id from region_info_accessor";
-                    "region_id" => ctx.get_region_id(),
+                    "region_id" => region_id,
                     "err" => ?e
                 );
-                return true;
+                return None;
             }
         };
 
         let start_key = keys::data_key(region_info.region.get_start_key());
         let end_key = keys::data_end_key(region_info.region.get_end_key());
+        Some((start_key, end_key))
+    }
+
+    /// Estimates the on-disk size of `[start_key, end_key)` in the default and write CFs by
+    /// aggregating table properties. Returns `None` if `local_storage` is not set or the
+    /// properties cannot be read.
+    fn estimate_range_size(&self, start_key: &[u8], end_key: &[u8]) -> Option<u64> {
+        let db = self.local_storage.as_ref()?;
+        let mut size = 0;
+        for cf in &[CF_DEFAULT, CF_WRITE] {
+            let collection = db.c().get_range_properties_cf(cf, start_key, end_key).ok()?;
+            for (_, v) in collection.iter() {
+                let props = RangeProperties::decode(&v.user_collected_properties()).ok()?;
+                size += props.get_approximate_size_in_range(start_key, end_key);
+            }
+        }
+        Some(size)
+    }
+
+    /// Check need gc without getting snapshot.
+    /// If this is not supported or any error happens, returns true to do further check after
+    /// getting snapshot.
+    fn need_gc(&self, ctx: &Context, safe_point: TimeStamp) -> bool {
+        let db = match &self.local_storage {
+            Some(db) => db,
+            None => {
+                info!("local_storage not set. cannot check need_gc without getting snapshot");
+                return true;
+            }
+        };
+
+        let (start_key, end_key) = match self.find_region_range(ctx.get_region_id()) {
+            Some(range) => range,
+            None => return true,
+        };
 
         let collection = match db
             .c()
@@ -314,6 +465,7 @@ impl<E: Engine> GcRunner<E> {
         safe_point: TimeStamp,
         keys: Vec<Key>,
         mut next_scan_key: Option<Key>,
+        reason: GcReason,
     ) -> Result<Option<Key>> {
         let snapshot = self.get_snapshot(ctx)?;
         let mut txn = MvccTxn::for_scan(
@@ -322,8 +474,10 @@ impl<E: Engine> GcRunner<E> {
             TimeStamp::zero(),
             !ctx.get_not_fill_cache(),
         );
+        let mut deleted_versions = 0;
         for k in keys {
             let gc_info = txn.gc(k.clone(), safe_point)?;
+            deleted_versions += gc_info.deleted_versions;
 
             if gc_info.found_versions >= GC_LOG_FOUND_VERSION_THRESHOLD {
                 debug!(
@@ -350,18 +504,38 @@ impl<E: Engine> GcRunner<E> {
             }
         }
         self.stats.add(&txn.take_statistics());
+        GC_RECLAIMED_KEYS_COUNTER_STATIC
+            .get(reason)
+            .inc_by(deleted_versions as i64);
 
         let write_size = txn.write_size();
         let modifies = txn.into_modifies();
         if !modifies.is_empty() {
-            self.refresh_cfg();
             self.limiter.blocking_consume(write_size);
-            self.engine.write(ctx, modifies)?;
+            let max_batch_size = self.cfg.max_write_batch_size.0 as usize;
+            if max_batch_size == 0 {
+                self.engine.write(ctx, modifies)?;
+            } else {
+                let batches = chunk_modifies(modifies, max_batch_size);
+                let last = batches.len() - 1;
+                for (i, batch) in batches.into_iter().enumerate() {
+                    self.engine.write(ctx, batch)?;
+                    // Sync the WAL between batches so only a bounded amount of
+                    // data is in flight at a time.
+                    if i < last {
+                        if let Some(db) = &self.local_storage {
+                            if let Err(e) = db.sync_wal() {
+                                warn!("gc failed to sync WAL between write batches"; "err" => ?e);
+                            }
+                        }
+                    }
+                }
+            }
         }
         Ok(next_scan_key)
     }
 
-    fn gc(&mut self, ctx: &mut Context, safe_point: TimeStamp) -> Result<()> {
+    fn gc(&mut self, ctx: &mut Context, safe_point: TimeStamp, reason: GcReason) -> Result<()> {
         debug!(
             "start doing GC";
             "region_id" => ctx.get_region_id(),
@@ -387,7 +561,7 @@ impl<E: Engine> GcRunner<E> {
             }
 
             // Does the GC operation on all scanned keys
-            next_key = self.gc_keys(ctx, safe_point, keys, next).map_err(|e| {
+            next_key = self.gc_keys(ctx, safe_point, keys, next, reason).map_err(|e| {
                 warn!("gc gc_keys failed"; "region_id" => ctx.get_region_id(), "safe_point" => safe_point, "err" => ?e);
                 e
             })?;
@@ -404,6 +578,62 @@ impl<E: Engine> GcRunner<E> {
         Ok(())
     }
 
+    /// Cleans up outdated data in `[start_key, end_key)` regardless of region boundaries, up to
+    /// the given safe point. The range is processed in chunks of at most `batch_keys` keys so
+    /// `GcRange` also honors `max_concurrent_tasks`: the task permit is re-acquired for every
+    /// chunk instead of being held for the whole task, so a long range gives concurrently
+    /// running region GC tasks a chance to take the permit in between.
+    fn gc_range(
+        &mut self,
+        ctx: &mut Context,
+        safe_point: TimeStamp,
+        start_key: &Key,
+        end_key: &Key,
+    ) -> Result<()> {
+        debug!(
+            "start doing GC on a key range";
+            "start_key" => %start_key,
+            "end_key" => %end_key,
+            "safe_point" => safe_point
+        );
+
+        let unbounded_end = end_key.as_encoded().is_empty();
+        let mut next_key = Some(start_key.clone());
+        while let Some(from) = next_key {
+            let _permit = self.task_limiter.acquire();
+
+            let (mut keys, mut next) = self.scan_keys(ctx, safe_point, Some(from)).map_err(|e| {
+                warn!("gc_range scan_keys failed"; "start_key" => %start_key, "end_key" => %end_key, "err" => ?e);
+                e
+            })?;
+            // Discard keys beyond the end of the requested range.
+            if !unbounded_end {
+                if let Some(pos) = keys.iter().position(|k| k >= end_key) {
+                    keys.truncate(pos);
+                    next = None;
+                }
+            }
+            if keys.is_empty() {
+                break;
+            }
+
+            next_key = self
+                .gc_keys(ctx, safe_point, keys, next, GcReason::range)
+                .map_err(|e| {
+                    warn!("gc_range gc_keys failed"; "start_key" => %start_key, "end_key" => %end_key, "err" => ?e);
+                    e
+                })?;
+        }
+
+        debug!(
+            "gc on a key range has finished";
+            "start_key" => %start_key,
+            "end_key" => %end_key,
+            "safe_point" => safe_point
+        );
+        Ok(())
+    }
+
     fn unsafe_destroy_range(&self, _: &Context, start_key: &Key, end_key: &Key) -> Result<()> {
         info!(
             "unsafe destroy range started";
@@ -531,19 +761,7 @@ impl<E: Engine> GcRunner<E> {
         }
     }
 
-    fn refresh_cfg(&mut self) {
-        if let Some(incoming) = self.cfg_tracker.any_new() {
-            let limit = incoming.max_write_bytes_per_sec.0;
-            self.limiter
-                .set_speed_limit(if limit > 0 { limit as f64 } else { INFINITY });
-            self.cfg = incoming.clone();
-        }
-    }
-}
-
-impl<E: Engine> FutureRunnable<GcTask> for GcRunner<E> {
-    #[inline]
-    fn run(&mut self, task: GcTask, _handle: &Handle) {
+    fn handle_task(&mut self, task: GcTask) {
         let enum_label = task.get_enum_label();
 
         GC_GCTASK_COUNTER_STATIC.get(enum_label).inc();
@@ -559,16 +777,26 @@ impl<E: Engine> FutureRunnable<GcTask> for GcRunner<E> {
             }
         };
 
-        // Refresh config before handle task
-        self.refresh_cfg();
-
         match task {
             GcTask::Gc {
                 mut ctx,
                 safe_point,
+                reason,
                 callback,
             } => {
-                let res = self.gc(&mut ctx, safe_point);
+                let _permit = self.task_limiter.acquire();
+                let range = self.find_region_range(ctx.get_region_id());
+                let size_before = range
+                    .as_ref()
+                    .and_then(|(start, end)| self.estimate_range_size(start, end));
+                let res = self.gc(&mut ctx, safe_point, reason);
+                if let (Some(before), Some((start, end))) = (size_before, range.as_ref()) {
+                    if let Some(after) = self.estimate_range_size(start, end) {
+                        GC_RECLAIMED_BYTES_COUNTER_STATIC
+                            .get(reason)
+                            .inc_by(before.saturating_sub(after) as i64);
+                    }
+                }
                 update_metrics(res.is_err());
                 callback(res);
                 self.update_statistics_metrics();
@@ -580,6 +808,35 @@ impl<E: Engine> FutureRunnable<GcTask> for GcRunner<E> {
                     safe_point
                 );
             }
+            GcTask::GcRange {
+                mut ctx,
+                start_key,
+                end_key,
+                safe_point,
+                callback,
+            } => {
+                let data_start = keys::data_key(start_key.as_encoded());
+                let data_end = keys::data_end_key(end_key.as_encoded());
+                let size_before = self.estimate_range_size(&data_start, &data_end);
+                let res = self.gc_range(&mut ctx, safe_point, &start_key, &end_key);
+                if let Some(before) = size_before {
+                    if let Some(after) = self.estimate_range_size(&data_start, &data_end) {
+                        GC_RECLAIMED_BYTES_COUNTER_STATIC
+                            .get(GcReason::range)
+                            .inc_by(before.saturating_sub(after) as i64);
+                    }
+                }
+                update_metrics(res.is_err());
+                callback(res);
+                self.update_statistics_metrics();
+                slow_log!(
+                    T timer,
+                    "GcRange start_key {:?}, end_key {:?}, safe_point {}",
+                    start_key,
+                    end_key,
+                    safe_point
+                );
+            }
             GcTask::UnsafeDestroyRange {
                 ctx,
                 start_key,
@@ -622,6 +879,44 @@ impl<E: Engine> FutureRunnable<GcTask> for GcRunner<E> {
     }
 }
 
+impl<E: Engine> FutureRunnable<GcTask> for GcRunner<E> {
+    #[inline]
+    fn run(&mut self, task: GcTask, _handle: &Handle) {
+        // Refresh config before dispatching, so the task below sees the
+        // latest limits.
+        self.refresh_cfg();
+        let mut core = self.core.clone();
+        self.pool.execute(move |_| core.handle_task(task));
+    }
+
+    fn shutdown(&mut self) {
+        if let Err(e) = self.pool.stop() {
+            error!("failed to stop gc-worker pool"; "err" => %e);
+        }
+    }
+}
+
+/// Splits `modifies` into batches of at most `max_batch_size` bytes each. A single modify
+/// larger than the limit gets a batch of its own.
+fn chunk_modifies(modifies: Vec<Modify>, max_batch_size: usize) -> Vec<Vec<Modify>> {
+    let mut batches = Vec::new();
+    let mut current = Vec::new();
+    let mut current_size = 0;
+    for modify in modifies {
+        let size = modify.size();
+        if !current.is_empty() && current_size + size > max_batch_size {
+            batches.push(mem::replace(&mut current, Vec::new()));
+            current_size = 0;
+        }
+        current_size += size;
+        current.push(modify);
+    }
+    if !current.is_empty() {
+        batches.push(current);
+    }
+    batches
+}
+
 /// When we failed to schedule a `GcTask` to `GcRunner`, use this to handle the `ScheduleError`.
 fn handle_gc_task_schedule_error(e: FutureWorkerStopped<GcTask>) -> Result<()> {
     error!("failed to schedule gc task: {:?}", e);
@@ -633,12 +928,14 @@ fn schedule_gc(
     scheduler: &FutureScheduler<GcTask>,
     ctx: Context,
     safe_point: TimeStamp,
+    reason: GcReason,
     callback: Callback<()>,
 ) -> Result<()> {
     scheduler
         .schedule(GcTask::Gc {
             ctx,
             safe_point,
+            reason,
             callback,
         })
         .or_else(handle_gc_task_schedule_error)
@@ -649,8 +946,9 @@ pub fn sync_gc(
     scheduler: &FutureScheduler<GcTask>,
     ctx: Context,
     safe_point: TimeStamp,
+    reason: GcReason,
 ) -> Result<()> {
-    wait_op!(|callback| schedule_gc(scheduler, ctx, safe_point, callback)).unwrap_or_else(|| {
+    wait_op!(|callback| schedule_gc(scheduler, ctx, safe_point, reason, callback)).unwrap_or_else(|| {
         error!("failed to receive result of gc");
         Err(box_err!("gc_worker: failed to receive result of gc"))
     })
@@ -824,6 +1122,32 @@ impl<E: Engine> GcWorker<E> {
                 .schedule(GcTask::Gc {
                     ctx,
                     safe_point,
+                    reason: GcReason::manual,
+                    callback,
+                })
+                .or_else(handle_gc_task_schedule_error)
+        })
+    }
+
+    /// Does GC on all keys in `[start_key, end_key)` up to the given safe point, regardless of
+    /// region boundaries. Unlike `unsafe_destroy_range`, this only removes outdated MVCC versions,
+    /// so the range is still accessible afterwards.
+    pub fn gc_range(
+        &self,
+        ctx: Context,
+        start_key: Key,
+        end_key: Key,
+        safe_point: TimeStamp,
+        callback: Callback<()>,
+    ) -> Result<()> {
+        GC_COMMAND_COUNTER_VEC_STATIC.gc_range.inc();
+        self.check_is_busy(callback).map_or(Ok(()), |callback| {
+            self.worker_scheduler
+                .schedule(GcTask::GcRange {
+                    ctx,
+                    start_key,
+                    end_key,
+                    safe_point,
                     callback,
                 })
                 .or_else(handle_gc_task_schedule_error)
@@ -929,6 +1253,7 @@ mod tests {
     use std::collections::BTreeMap;
     use std::sync::mpsc::channel;
     use tikv_util::codec::number::NumberEncoder;
+    use tikv_util::config::ReadableSize;
     use tikv_util::future::paired_future_callback;
     use txn_types::Mutation;
 
@@ -1177,6 +1502,164 @@ mod tests {
         .unwrap();
     }
 
+    #[test]
+    fn test_gc_range() {
+        let engine = TestEngineBuilder::new().build().unwrap();
+        let storage = TestStorageBuilder::from_engine(engine.clone())
+            .build()
+            .unwrap();
+        let db = engine.get_rocksdb();
+        let mut gc_worker = GcWorker::new(engine, Some(db), None, None, GcConfig::default());
+        gc_worker.start().unwrap();
+
+        let init_keys: Vec<Vec<u8>> = vec![
+            b"key1".to_vec(),
+            b"key2".to_vec(),
+            b"key3".to_vec(),
+            b"key4".to_vec(),
+            b"key5".to_vec(),
+        ];
+
+        // Write an old version for every key...
+        let mutations: Vec<_> = init_keys
+            .iter()
+            .map(|key| {
+                let mut value = b"value-".to_vec();
+                value.extend_from_slice(key);
+                Mutation::Put((Key::from_raw(key), value))
+            })
+            .collect();
+        let primary = init_keys[0].clone();
+        wait_op!(|cb| storage.sched_txn_command(
+            commands::Prewrite::with_defaults(mutations, primary.clone(), 5.into()),
+            cb,
+        ))
+        .unwrap()
+        .unwrap();
+        let keys: Vec<_> = init_keys.iter().map(|k| Key::from_raw(k)).collect();
+        wait_op!(|cb| storage.sched_txn_command(
+            commands::Commit::new(keys.clone(), 5.into(), 10.into(), Context::default()),
+            cb
+        ))
+        .unwrap()
+        .unwrap();
+
+        // ...then delete every key, so GC can physically remove the old versions.
+        let mutations: Vec<_> = init_keys
+            .iter()
+            .map(|key| Mutation::Delete(Key::from_raw(key)))
+            .collect();
+        wait_op!(|cb| storage.sched_txn_command(
+            commands::Prewrite::with_defaults(mutations, primary, 15.into()),
+            cb,
+        ))
+        .unwrap()
+        .unwrap();
+        wait_op!(|cb| storage.sched_txn_command(
+            commands::Commit::new(keys, 15.into(), 20.into(), Context::default()),
+            cb
+        ))
+        .unwrap()
+        .unwrap();
+
+        // GC `[key2, key4)` with a safe point above all commits.
+        wait_op!(|cb| gc_worker.gc_range(
+            Context::default(),
+            Key::from_raw(b"key2"),
+            Key::from_raw(b"key4"),
+            30.into(),
+            cb
+        ))
+        .unwrap()
+        .unwrap();
+
+        // Old versions in the range are gone; those outside the range are untouched.
+        for key in &init_keys {
+            let value = storage
+                .get(Context::default(), Key::from_raw(key), 14.into())
+                .wait()
+                .unwrap();
+            let in_range = key.as_slice() >= b"key2".as_ref() && key.as_slice() < b"key4".as_ref();
+            if in_range {
+                assert!(value.is_none(), "{:?} should have been GC-ed", key);
+            } else {
+                let mut expected = b"value-".to_vec();
+                expected.extend_from_slice(key);
+                assert_eq!(value.unwrap(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_gc_task_limiter() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::thread;
+
+        const LIMIT: usize = 2;
+        const TASKS: usize = 8;
+
+        let limiter = GcTaskLimiter::new(LIMIT);
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_in_flight = Arc::new(Mutex::new(0));
+
+        let handles: Vec<_> = (0..TASKS)
+            .map(|_| {
+                let limiter = limiter.clone();
+                let in_flight = Arc::clone(&in_flight);
+                let max_in_flight = Arc::clone(&max_in_flight);
+                thread::spawn(move || {
+                    let _permit = limiter.acquire();
+                    let cur = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    let mut max = max_in_flight.lock().unwrap();
+                    if cur > *max {
+                        *max = cur;
+                    }
+                    drop(max);
+                    thread::sleep(Duration::from_millis(10));
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                })
+            })
+            .collect();
+        for h in handles {
+            h.join().unwrap();
+        }
+        assert!(*max_in_flight.lock().unwrap() <= LIMIT);
+
+        // A limit of 0 means unbounded, so acquiring never blocks.
+        let limiter = GcTaskLimiter::new(0);
+        let _p1 = limiter.acquire();
+        let _p2 = limiter.acquire();
+    }
+
+    #[test]
+    fn test_gc_tasks_run_concurrently() {
+        use std::sync::Barrier;
+
+        let engine = TestEngineBuilder::new().build().unwrap();
+        let db = engine.get_rocksdb();
+        let mut gc_worker = GcWorker::new(engine, Some(db), None, None, GcConfig::default());
+        gc_worker.start().unwrap();
+
+        // Both tasks block on the same barrier, so neither can finish unless
+        // they are executed on the pool at the same time.
+        let barrier = Arc::new(Barrier::new(2));
+        let (tx, rx) = channel();
+        for _ in 0..2 {
+            let barrier = Arc::clone(&barrier);
+            let tx = tx.clone();
+            gc_worker
+                .scheduler()
+                .schedule(GcTask::Validate(Box::new(move |_, _| {
+                    barrier.wait();
+                    tx.send(()).unwrap();
+                })))
+                .unwrap();
+        }
+        for _ in 0..2 {
+            rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        }
+    }
+
     #[test]
     fn test_physical_scan_lock() {
         let engine = TestEngineBuilder::new().build().unwrap();
@@ -1245,4 +1728,162 @@ mod tests {
         // expected_locks[3] is the key 4.
         assert_eq!(res[..], expected_lock_info[3..9]);
     }
+
+    #[test]
+    fn test_gc_reclaimed_keys_metric() {
+        let engine = TestEngineBuilder::new().build().unwrap();
+        let storage = TestStorageBuilder::from_engine(engine.clone())
+            .build()
+            .unwrap();
+        let mut gc_worker = GcWorker::new(engine, None, None, None, GcConfig::default());
+        gc_worker.start().unwrap();
+
+        // Write several versions of the same key so there is garbage to collect.
+        let key = b"gc_reclaim_key";
+        for i in 0..5u64 {
+            let start_ts = TimeStamp::from(10 + i * 10);
+            let commit_ts = TimeStamp::from(11 + i * 10);
+            wait_op!(|cb| storage.sched_txn_command(
+                commands::Prewrite::with_defaults(
+                    vec![Mutation::Put((Key::from_raw(key), b"value".to_vec()))],
+                    key.to_vec(),
+                    start_ts,
+                ),
+                cb,
+            ))
+            .unwrap()
+            .unwrap()
+            .into_iter()
+            .for_each(|r| r.unwrap());
+            wait_op!(|cb| storage.sched_txn_command(
+                commands::Commit::new(
+                    vec![Key::from_raw(key)],
+                    start_ts,
+                    commit_ts,
+                    Context::default()
+                ),
+                cb,
+            ))
+            .unwrap()
+            .unwrap();
+        }
+
+        let reclaimed_before = GC_RECLAIMED_KEYS_COUNTER_VEC
+            .with_label_values(&["manual"])
+            .get();
+        wait_op!(|cb| gc_worker.gc(Context::default(), 100.into(), cb))
+            .unwrap()
+            .unwrap();
+        let reclaimed_after = GC_RECLAIMED_KEYS_COUNTER_VEC
+            .with_label_values(&["manual"])
+            .get();
+        // 4 of the 5 versions are before the safe point and overwritten, so GC
+        // deletes at least these.
+        assert!(reclaimed_after >= reclaimed_before + 4);
+    }
+
+    #[test]
+    fn test_chunk_modifies() {
+        let modifies: Vec<_> = (0..10u8)
+            .map(|i| Modify::Delete(CF_WRITE, Key::from_raw(&[i])))
+            .collect();
+        let size = modifies[0].size();
+
+        // No chunking needed when everything fits in one batch.
+        assert_eq!(chunk_modifies(modifies.clone(), size * 10).len(), 1);
+
+        // 10 modifies with room for 3 each: 3 + 3 + 3 + 1.
+        let batches = chunk_modifies(modifies.clone(), size * 3);
+        assert_eq!(batches.len(), 4);
+        assert!(batches[..3].iter().all(|b| b.len() == 3));
+        assert_eq!(batches[3].len(), 1);
+
+        // A modify larger than the limit still gets a batch of its own.
+        let batches = chunk_modifies(modifies, size / 2);
+        assert_eq!(batches.len(), 10);
+    }
+
+    /// A wrapper of engine counting how many write batches go to the underlying engine.
+    #[derive(Clone)]
+    struct CountingEngine {
+        inner: kv::RocksEngine,
+        write_count: Arc<atomic::AtomicUsize>,
+    }
+
+    impl Engine for CountingEngine {
+        type Snap = <kv::RocksEngine as Engine>::Snap;
+
+        fn async_write(
+            &self,
+            ctx: &Context,
+            batch: Vec<Modify>,
+            callback: EngineCallback<()>,
+        ) -> EngineResult<()> {
+            self.write_count.fetch_add(1, atomic::Ordering::SeqCst);
+            self.inner.async_write(ctx, batch, callback)
+        }
+
+        fn async_snapshot(
+            &self,
+            ctx: &Context,
+            callback: EngineCallback<Self::Snap>,
+        ) -> EngineResult<()> {
+            self.inner.async_snapshot(ctx, callback)
+        }
+    }
+
+    #[test]
+    fn test_gc_write_batch_size_limit() {
+        let engine = CountingEngine {
+            inner: TestEngineBuilder::new().build().unwrap(),
+            write_count: Arc::new(atomic::AtomicUsize::new(0)),
+        };
+        let storage = TestStorageBuilder::from_engine(engine.clone())
+            .build()
+            .unwrap();
+        let mut cfg = GcConfig::default();
+        // Force every modify into its own write batch.
+        cfg.max_write_batch_size = ReadableSize(1);
+        let mut gc_worker = GcWorker::new(engine.clone(), None, None, None, cfg);
+        gc_worker.start().unwrap();
+
+        // 3 keys with 3 versions each: GC deletes the 2 overwritten versions of
+        // every key, 6 deletions in total.
+        for key in &[b"batch_k1", b"batch_k2", b"batch_k3"] {
+            for i in 0..3u64 {
+                let start_ts = TimeStamp::from(10 + i * 10);
+                let commit_ts = TimeStamp::from(11 + i * 10);
+                wait_op!(|cb| storage.sched_txn_command(
+                    commands::Prewrite::with_defaults(
+                        vec![Mutation::Put((Key::from_raw(*key), b"v".to_vec()))],
+                        key.to_vec(),
+                        start_ts,
+                    ),
+                    cb,
+                ))
+                .unwrap()
+                .unwrap()
+                .into_iter()
+                .for_each(|r| r.unwrap());
+                wait_op!(|cb| storage.sched_txn_command(
+                    commands::Commit::new(
+                        vec![Key::from_raw(*key)],
+                        start_ts,
+                        commit_ts,
+                        Context::default()
+                    ),
+                    cb,
+                ))
+                .unwrap()
+                .unwrap();
+            }
+        }
+
+        let writes_before = engine.write_count.load(atomic::Ordering::SeqCst);
+        wait_op!(|cb| gc_worker.gc(Context::default(), 100.into(), cb))
+            .unwrap()
+            .unwrap();
+        let writes_after = engine.write_count.load(atomic::Ordering::SeqCst);
+        assert_eq!(writes_after - writes_before, 6);
+    }
 }
@@ -1,6 +1,5 @@
 // Copyright 2018 TiKV Project Authors. Licensed under Apache-2.0.
 
-use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::time::Instant;
 
@@ -92,8 +91,7 @@ impl ThreadLoadStatistics {
             if cpu_usage > self.tids.len() * 100 {
                 cpu_usage = self.tids.len() * 100;
             }
-            self.thread_load.load.store(cpu_usage, Ordering::Relaxed);
-            self.thread_load.term.fetch_add(1, Ordering::Relaxed);
+            self.thread_load.update(cpu_usage);
         }
     }
 }
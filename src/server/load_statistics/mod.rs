@@ -1,12 +1,56 @@
 // Copyright 2018 TiKV Project Authors. Licensed under Apache-2.0.
 
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use prometheus::*;
+
+lazy_static! {
+    pub static ref LOAD_BYTES_READ_COUNTER_VEC: IntCounterVec = register_int_counter_vec!(
+        "tikv_server_load_bytes_read",
+        "Total bytes read from the engine per column family.",
+        &["cf"]
+    )
+    .unwrap();
+    pub static ref LOAD_BYTES_WRITTEN_COUNTER_VEC: IntCounterVec = register_int_counter_vec!(
+        "tikv_server_load_bytes_written",
+        "Total bytes written to the engine per column family.",
+        &["cf"]
+    )
+    .unwrap();
+}
+
+/// Accounts `bytes` read from column family `cf` into the per-CF load counters.
+pub fn record_read_bytes(cf: &str, bytes: usize) {
+    LOAD_BYTES_READ_COUNTER_VEC
+        .with_label_values(&[cf])
+        .inc_by(bytes as i64);
+}
+
+/// Accounts `bytes` written to column family `cf` into the per-CF load counters.
+pub fn record_write_bytes(cf: &str, bytes: usize) {
+    LOAD_BYTES_WRITTEN_COUNTER_VEC
+        .with_label_values(&[cf])
+        .inc_by(bytes as i64);
+}
+
+/// The weight of a new sample in the smoothed load, i.e. the smoothed load
+/// moves 1/4 of the way towards every new sample. Larger factors react slower
+/// but flap less on short spikes.
+const LOAD_EWMA_FACTOR: usize = 4;
+
+/// A callback invoked when the smoothed load crosses the threshold. The
+/// argument is `true` when entering overload and `false` when leaving it.
+pub type ThresholdEventCallback = Box<dyn Fn(bool) + Send + Sync>;
 
 /// A load metric for all threads.
 pub struct ThreadLoad {
     term: AtomicUsize,
     load: AtomicUsize,
+    smoothed: AtomicUsize,
+    overloaded: AtomicBool,
     threshold: usize,
+    listeners: Mutex<Vec<ThresholdEventCallback>>,
 }
 
 impl ThreadLoad {
@@ -15,7 +59,10 @@ impl ThreadLoad {
         ThreadLoad {
             term: AtomicUsize::new(0),
             load: AtomicUsize::new(0),
+            smoothed: AtomicUsize::new(0),
+            overloaded: AtomicBool::new(false),
             threshold,
+            listeners: Mutex::new(Vec::new()),
         }
     }
 
@@ -42,6 +89,44 @@ impl ThreadLoad {
     pub fn load(&self) -> usize {
         self.load.load(Ordering::Relaxed)
     }
+
+    /// Gets the load normalized against the threshold, smoothed with an EWMA
+    /// to filter out short spikes. 1.0 means the smoothed load is right at the
+    /// threshold.
+    #[allow(dead_code)]
+    pub fn score(&self) -> f64 {
+        if self.threshold == 0 {
+            return 0.0;
+        }
+        self.smoothed.load(Ordering::Relaxed) as f64 / self.threshold as f64
+    }
+
+    /// Registers a callback fired every time the smoothed load crosses the
+    /// threshold, so that raftstore can e.g. shed leaders on sustained
+    /// overload without reacting to transient spikes.
+    pub fn subscribe_threshold_event(&self, cb: ThresholdEventCallback) {
+        self.listeners.lock().unwrap().push(cb);
+    }
+
+    /// Updates the load with a new sample and notifies listeners if the
+    /// smoothed load crosses the threshold.
+    pub fn update(&self, load: usize) {
+        self.load.store(load, Ordering::Relaxed);
+        self.term.fetch_add(1, Ordering::Relaxed);
+
+        // Only the statistics collector thread calls this, so a plain
+        // load/store pair is enough here.
+        let old = self.smoothed.load(Ordering::Relaxed);
+        let smoothed = (old * (LOAD_EWMA_FACTOR - 1) + load) / LOAD_EWMA_FACTOR;
+        self.smoothed.store(smoothed, Ordering::Relaxed);
+
+        let overloaded = smoothed > self.threshold;
+        if self.overloaded.swap(overloaded, Ordering::Relaxed) != overloaded {
+            for cb in self.listeners.lock().unwrap().iter() {
+                cb(overloaded);
+            }
+        }
+    }
 }
 
 #[cfg(target_os = "linux")]
@@ -71,3 +156,41 @@ mod other_os {
 }
 #[cfg(not(target_os = "linux"))]
 pub use self::other_os::ThreadLoadStatistics;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_threshold_event_on_sustained_overload() {
+        let load = ThreadLoad::with_threshold(100);
+        let (tx, rx) = mpsc::channel();
+        load.subscribe_threshold_event(Box::new(move |overloaded| tx.send(overloaded).unwrap()));
+
+        // A single spike must not trigger the event.
+        load.update(300);
+        load.update(0);
+        assert!(rx.try_recv().is_err());
+
+        // Sustained overload pushes the smoothed load over the threshold and
+        // fires the event exactly once.
+        for _ in 0..8 {
+            load.update(300);
+        }
+        assert!(rx.try_recv().unwrap());
+        assert!(rx.try_recv().is_err());
+        assert!(load.score() > 1.0);
+
+        // Recovering fires the event again with the opposite state, also only
+        // after the smoothed load settles below the threshold.
+        load.update(0);
+        assert!(rx.try_recv().is_err());
+        for _ in 0..8 {
+            load.update(0);
+        }
+        assert!(!rx.try_recv().unwrap());
+        assert!(rx.try_recv().is_err());
+        assert!(load.score() < 1.0);
+    }
+}
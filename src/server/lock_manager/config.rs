@@ -91,10 +91,14 @@ impl LockManagerConfigManager {
 
 impl ConfigManager for LockManagerConfigManager {
     fn dispatch(&mut self, mut change: ConfigChange) -> Result<(), Box<dyn Error>> {
-        match (
-            change.remove("wait_for_lock_timeout").map(Into::into),
-            change.remove("wake_up_delay_duration").map(Into::into),
-        ) {
+        let timeout: Option<ReadableDuration> =
+            change.remove("wait_for_lock_timeout").map(Into::into);
+        let delay: Option<ReadableDuration> =
+            change.remove("wake_up_delay_duration").map(Into::into);
+        if timeout.map_or(false, |t| t.is_zero()) {
+            return Err("pessimistic-txn.wait-for-lock-timeout can not be 0".into());
+        }
+        match (timeout, delay) {
             (timeout @ Some(_), delay) => {
                 self.waiter_mgr_scheduler.change_config(timeout, delay);
                 self.detector_scheduler.change_ttl(timeout.unwrap().into());
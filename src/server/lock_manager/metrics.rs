@@ -66,6 +66,11 @@ lazy_static! {
         &["type"]
     )
     .unwrap();
+    pub static ref WAITER_MEMORY_GAUGE: IntGauge = register_int_gauge!(
+        "tikv_lock_manager_waiter_memory_bytes",
+        "Estimated memory held by waiters in the wait table"
+    )
+    .unwrap();
     pub static ref DETECTOR_LEADER_GAUGE: IntGauge = register_int_gauge!(
         "tikv_lock_manager_detector_leader_heartbeat",
         "Heartbeat of the leader of the deadlock detector"
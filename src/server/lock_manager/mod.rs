@@ -22,6 +22,7 @@ use crate::server::resolve::StoreAddrResolver;
 use crate::server::{Error, Result};
 use crate::storage::{
     lock_manager::{Lock, LockManager as LockManagerTrait, WaitTimeout},
+    metrics::CommandKind,
     ProcessResult, StorageCallback,
 };
 use raftstore::coprocessor::CoprocessorHost;
@@ -223,6 +224,7 @@ impl LockManagerTrait for LockManager {
         lock: Lock,
         is_first_lock: bool,
         timeout: Option<WaitTimeout>,
+        tag: CommandKind,
     ) {
         let timeout = match timeout {
             Some(t) => t,
@@ -236,7 +238,7 @@ impl LockManagerTrait for LockManager {
         // but the waiter_mgr haven't processed it, subsequent WakeUp msgs may be lost.
         self.waiter_count.fetch_add(1, Ordering::SeqCst);
         self.waiter_mgr_scheduler
-            .wait_for(start_ts, cb, pr, lock, timeout);
+            .wait_for(start_ts, cb, pr, lock, timeout, tag);
 
         // If it is the first lock the transaction tries to lock, it won't cause deadlock.
         if !is_first_lock {
@@ -278,6 +280,7 @@ mod tests {
     use self::metrics::*;
     use self::waiter_manager::tests::*;
     use super::*;
+    use crate::storage::metrics::SCHED_LOCK_WAIT_HISTOGRAM_VEC;
     use raftstore::coprocessor::RegionChangeEvent;
     use tikv_util::config::ReadableDuration;
     use tikv_util::security::SecurityConfig;
@@ -336,6 +339,7 @@ mod tests {
             waiter.lock,
             true,
             Some(WaitTimeout::Default),
+            CommandKind::acquire_pessimistic_lock,
         );
         assert!(lock_mgr.has_waiter());
         assert_elapsed(
@@ -361,6 +365,7 @@ mod tests {
             waiter.lock,
             true,
             Some(WaitTimeout::Default),
+            CommandKind::acquire_pessimistic_lock,
         );
         assert!(lock_mgr.has_waiter());
         lock_mgr.wake_up(lock.ts, Some(vec![lock.hash]), 30.into(), false);
@@ -380,6 +385,7 @@ mod tests {
             waiter1.lock,
             false,
             Some(WaitTimeout::Default),
+            CommandKind::acquire_pessimistic_lock,
         );
         assert!(lock_mgr.has_waiter());
         let (waiter2, lock_info2, f2) = new_test_waiter(20.into(), 10.into(), 10);
@@ -390,6 +396,7 @@ mod tests {
             waiter2.lock,
             false,
             Some(WaitTimeout::Default),
+            CommandKind::acquire_pessimistic_lock,
         );
         assert!(lock_mgr.has_waiter());
         assert_elapsed(
@@ -417,6 +424,7 @@ mod tests {
                 waiter.lock,
                 *is_first_lock,
                 Some(WaitTimeout::Default),
+                CommandKind::acquire_pessimistic_lock,
             );
             assert!(lock_mgr.has_waiter());
             assert_eq!(lock_mgr.remove_from_detected(30.into()), !is_first_lock);
@@ -456,6 +464,7 @@ mod tests {
             waiter.lock,
             false,
             None,
+            CommandKind::acquire_pessimistic_lock,
         );
         assert_elapsed(
             || expect_key_is_locked(f.wait().unwrap().unwrap(), lock_info),
@@ -468,6 +477,40 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_lock_wait_histogram() {
+        let lock_mgr = start_lock_manager();
+        let histogram = SCHED_LOCK_WAIT_HISTOGRAM_VEC.get(CommandKind::acquire_pessimistic_lock);
+        let prev_count = histogram.get_sample_count();
+        let prev_sum = histogram.get_sample_sum();
+
+        // A waiter blocked on a lock for a while records a non-zero wait.
+        let (waiter, lock_info, f) = new_test_waiter(10.into(), 20.into(), 20);
+        let lock = waiter.lock;
+        lock_mgr.wait_for(
+            waiter.start_ts,
+            waiter.cb,
+            waiter.pr,
+            waiter.lock,
+            true,
+            Some(WaitTimeout::Default),
+            CommandKind::acquire_pessimistic_lock,
+        );
+        assert!(lock_mgr.has_waiter());
+        thread::sleep(Duration::from_millis(100));
+        lock_mgr.wake_up(lock.ts, Some(vec![lock.hash]), 30.into(), false);
+        assert_elapsed(
+            || expect_write_conflict(f.wait().unwrap(), 10.into(), lock_info, 30.into()),
+            0,
+            200,
+        );
+        // The wait duration is observed when the waiter is destroyed, which
+        // happens asynchronously after the callback is called.
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(histogram.get_sample_count(), prev_count + 1);
+        assert!(histogram.get_sample_sum() - prev_sum >= 0.05);
+    }
+
     #[bench]
     fn bench_lock_mgr_clone(b: &mut test::Bencher) {
         let lock_mgr = LockManager::new();
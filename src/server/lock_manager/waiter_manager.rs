@@ -4,6 +4,7 @@ use super::config::Config;
 use super::deadlock::Scheduler as DetectorScheduler;
 use super::metrics::*;
 use crate::storage::lock_manager::{Lock, WaitTimeout};
+use crate::storage::metrics::{CommandKind, SCHED_LOCK_WAIT_HISTOGRAM_VEC};
 use crate::storage::mvcc::{Error as MvccError, ErrorInner as MvccErrorInner, TimeStamp};
 use crate::storage::txn::{Error as TxnError, ErrorInner as TxnErrorInner};
 use crate::storage::{
@@ -101,6 +102,7 @@ pub enum Task {
         pr: ProcessResult,
         lock: Lock,
         timeout: WaitTimeout,
+        tag: CommandKind,
     },
     WakeUp {
         // lock info
@@ -171,6 +173,8 @@ pub(crate) struct Waiter {
     pub(crate) lock: Lock,
     delay: Delay,
     _lifetime_timer: HistogramTimer,
+    // Records the time the command is blocked on the lock, labeled by command type.
+    _lock_wait_timer: HistogramTimer,
 }
 
 impl Waiter {
@@ -180,6 +184,7 @@ impl Waiter {
         pr: ProcessResult,
         lock: Lock,
         deadline: Instant,
+        tag: CommandKind,
     ) -> Self {
         Self {
             start_ts,
@@ -188,6 +193,7 @@ impl Waiter {
             lock,
             delay: Delay::new(deadline),
             _lifetime_timer: WAITER_LIFETIME_HISTOGRAM.start_coarse_timer(),
+            _lock_wait_timer: SCHED_LOCK_WAIT_HISTOGRAM_VEC.get(tag).start_coarse_timer(),
         }
     }
 
@@ -250,6 +256,26 @@ impl Waiter {
         };
     }
 
+    /// Roughly estimates the memory held by the `Waiter`, for the memory usage gauge.
+    fn estimated_memory(&self) -> usize {
+        let key_bytes = match &self.pr {
+            ProcessResult::PessimisticLockRes { res } => match res {
+                Err(StorageError(box StorageErrorInner::Txn(TxnError(
+                    box TxnErrorInner::Mvcc(MvccError(box MvccErrorInner::KeyIsLocked(info))),
+                )))) => info.get_key().len() + info.get_primary_lock().len(),
+                _ => 0,
+            },
+            ProcessResult::Failed { err } => match err {
+                StorageError(box StorageErrorInner::Txn(TxnError(box TxnErrorInner::Mvcc(
+                    MvccError(box MvccErrorInner::WriteConflict { key, primary, .. }),
+                )))) => key.len() + primary.len(),
+                _ => 0,
+            },
+            _ => 0,
+        };
+        std::mem::size_of::<Waiter>() + key_bytes
+    }
+
     /// Extracts key and primary key from `ProcessResult`.
     fn extract_key_info(&mut self) -> (Vec<u8>, Vec<u8>) {
         match &mut self.pr {
@@ -285,6 +311,9 @@ struct WaitTable {
     // Map lock hash to waiters.
     wait_table: HashMap<u64, Waiters>,
     waiter_count: Arc<AtomicUsize>,
+    /// Estimated memory held by all waiters in the table. It backs
+    /// `WAITER_MEMORY_GAUGE`.
+    memory_bytes: usize,
 }
 
 impl WaitTable {
@@ -292,9 +321,11 @@ impl WaitTable {
         Self {
             wait_table: HashMap::default(),
             waiter_count,
+            memory_bytes: 0,
         }
     }
 
+
     #[cfg(test)]
     fn count(&self) -> usize {
         self.wait_table.iter().map(|(_, v)| v.len()).sum()
@@ -306,6 +337,8 @@ impl WaitTable {
 
     /// Returns the duplicated `Waiter` if there is.
     fn add_waiter(&mut self, waiter: Waiter) -> Option<Waiter> {
+        self.memory_bytes += waiter.estimated_memory();
+        WAITER_MEMORY_GAUGE.set(self.memory_bytes as i64);
         let waiters = self.wait_table.entry(waiter.lock.hash).or_insert_with(|| {
             WAIT_TABLE_STATUS_GAUGE.locks.inc();
             Waiters::default()
@@ -315,6 +348,8 @@ impl WaitTable {
         if let Some(old_idx) = old_idx {
             let old = waiters.swap_remove(old_idx);
             self.waiter_count.fetch_sub(1, Ordering::SeqCst);
+            self.memory_bytes = self.memory_bytes.saturating_sub(old.estimated_memory());
+            WAITER_MEMORY_GAUGE.set(self.memory_bytes as i64);
             Some(old)
         } else {
             WAIT_TABLE_STATUS_GAUGE.txns.inc();
@@ -335,9 +370,12 @@ impl WaitTable {
             .iter()
             .position(|waiter| waiter.start_ts == waiter_ts)?;
         let waiter = waiters.swap_remove(idx);
+        let is_empty = waiters.is_empty();
         self.waiter_count.fetch_sub(1, Ordering::SeqCst);
         WAIT_TABLE_STATUS_GAUGE.txns.dec();
-        if waiters.is_empty() {
+        self.memory_bytes = self.memory_bytes.saturating_sub(waiter.estimated_memory());
+        WAITER_MEMORY_GAUGE.set(self.memory_bytes as i64);
+        if is_empty {
             self.remove(lock);
         }
         Some(waiter)
@@ -358,6 +396,8 @@ impl WaitTable {
         let oldest = waiters.swap_remove(oldest_idx);
         self.waiter_count.fetch_sub(1, Ordering::SeqCst);
         WAIT_TABLE_STATUS_GAUGE.txns.dec();
+        self.memory_bytes = self.memory_bytes.saturating_sub(oldest.estimated_memory());
+        WAITER_MEMORY_GAUGE.set(self.memory_bytes as i64);
         Some((oldest, waiters))
     }
 
@@ -403,6 +443,7 @@ impl Scheduler {
         pr: ProcessResult,
         lock: Lock,
         timeout: WaitTimeout,
+        tag: CommandKind,
     ) {
         self.notify_scheduler(Task::WaitFor {
             start_ts,
@@ -410,6 +451,7 @@ impl Scheduler {
             pr,
             lock,
             timeout,
+            tag,
         });
     }
 
@@ -581,8 +623,16 @@ impl FutureRunnable<Task> for WaiterManager {
                 pr,
                 lock,
                 timeout,
+                tag,
             } => {
-                let waiter = Waiter::new(start_ts, cb, pr, lock, self.normalize_deadline(timeout));
+                let waiter = Waiter::new(
+                    start_ts,
+                    cb,
+                    pr,
+                    lock,
+                    self.normalize_deadline(timeout),
+                    tag,
+                );
                 self.handle_wait_for(handle, waiter);
                 TASK_COUNTER_METRICS.with(|m| {
                     m.wait_for.inc();
@@ -647,6 +697,9 @@ pub mod tests {
             lock: Lock { ts: lock_ts, hash },
             delay: Delay::new(Instant::now()),
             _lifetime_timer: WAITER_LIFETIME_HISTOGRAM.start_coarse_timer(),
+            _lock_wait_timer: SCHED_LOCK_WAIT_HISTOGRAM_VEC
+                .get(CommandKind::acquire_pessimistic_lock)
+                .start_coarse_timer(),
         }
     }
 
@@ -753,6 +806,7 @@ pub mod tests {
             pr,
             lock,
             Instant::now() + Duration::from_millis(3000),
+            CommandKind::acquire_pessimistic_lock,
         );
         (waiter, info, f)
     }
@@ -932,6 +986,37 @@ pub mod tests {
             .is_none());
     }
 
+    #[test]
+    fn test_wait_table_memory_usage() {
+        let mut wait_table = WaitTable::new(Arc::new(AtomicUsize::new(0)));
+        assert_eq!(wait_table.memory_bytes, 0);
+
+        for i in 1..5u64 {
+            assert!(wait_table
+                .add_waiter(dummy_waiter((i * 10).into(), i.into(), i))
+                .is_none());
+        }
+        assert!(wait_table.memory_bytes >= 4 * std::mem::size_of::<Waiter>());
+
+        // All removal paths must decrease the memory estimation back to zero.
+        wait_table
+            .remove_waiter(Lock { ts: 1.into(), hash: 1 }, 10.into())
+            .unwrap();
+        wait_table
+            .remove_oldest_waiter(Lock { ts: 2.into(), hash: 2 })
+            .unwrap();
+        wait_table.remove(Lock { ts: 2.into(), hash: 2 });
+        // Replacing a duplicated waiter accounts for the removed one.
+        wait_table.add_waiter(dummy_waiter(30.into(), 3.into(), 3));
+        wait_table
+            .remove_waiter(Lock { ts: 3.into(), hash: 3 }, 30.into())
+            .unwrap();
+        wait_table
+            .remove_waiter(Lock { ts: 4.into(), hash: 4 }, 40.into())
+            .unwrap();
+        assert_eq!(wait_table.memory_bytes, 0);
+    }
+
     #[test]
     fn test_wait_table_add_duplicated_waiter() {
         let mut wait_table = WaitTable::new(Arc::new(AtomicUsize::new(0)));
@@ -1069,6 +1154,7 @@ pub mod tests {
             waiter.pr,
             waiter.lock,
             WaitTimeout::Millis(1000),
+            CommandKind::acquire_pessimistic_lock,
         );
         assert_elapsed(
             || expect_key_is_locked(f.wait().unwrap().unwrap(), lock_info),
@@ -1084,6 +1170,7 @@ pub mod tests {
             waiter.pr,
             waiter.lock,
             WaitTimeout::Millis(100),
+            CommandKind::acquire_pessimistic_lock,
         );
         assert_elapsed(
             || expect_key_is_locked(f.wait().unwrap().unwrap(), lock_info),
@@ -1099,6 +1186,7 @@ pub mod tests {
             waiter.pr,
             waiter.lock,
             WaitTimeout::Millis(3000),
+            CommandKind::acquire_pessimistic_lock,
         );
         assert_elapsed(
             || expect_key_is_locked(f.wait().unwrap().unwrap(), lock_info),
@@ -1109,6 +1197,38 @@ pub mod tests {
         worker.stop().unwrap();
     }
 
+    #[test]
+    fn test_waiter_manager_change_config() {
+        let (mut worker, scheduler) = start_waiter_manager(1000, 100);
+
+        // Shorten the default timeout at runtime. New waiters must be woken up within it.
+        scheduler.change_config(Some(ReadableDuration::millis(100)), None);
+        let (tx, rx) = mpsc::sync_channel(1);
+        scheduler.validate(Box::new(move |timeout, delay| {
+            tx.send((timeout, delay)).unwrap();
+        }));
+        let (timeout, delay) = rx.recv().unwrap();
+        assert_eq!(timeout, ReadableDuration::millis(100));
+        assert_eq!(delay, ReadableDuration::millis(100));
+
+        let (waiter, lock_info, f) = new_test_waiter(10.into(), 20.into(), 20);
+        scheduler.wait_for(
+            waiter.start_ts,
+            waiter.cb,
+            waiter.pr,
+            waiter.lock,
+            WaitTimeout::Default,
+            CommandKind::acquire_pessimistic_lock,
+        );
+        assert_elapsed(
+            || expect_key_is_locked(f.wait().unwrap().unwrap(), lock_info),
+            50,
+            300,
+        );
+
+        worker.stop().unwrap();
+    }
+
     #[test]
     fn test_waiter_manager_wake_up() {
         let (wait_for_lock_timeout, wake_up_delay_duration) = (1000, 100);
@@ -1128,6 +1248,7 @@ pub mod tests {
                 waiter.pr,
                 waiter.lock,
                 WaitTimeout::Millis(wait_for_lock_timeout),
+                CommandKind::acquire_pessimistic_lock,
             );
             waiters_info.push((waiter_ts, lock_info, f));
         }
@@ -1158,6 +1279,7 @@ pub mod tests {
                 waiter.pr,
                 waiter.lock,
                 WaitTimeout::Millis(wait_for_lock_timeout),
+                CommandKind::acquire_pessimistic_lock,
             );
             waiters_info.push((waiter_ts, lock_info, f));
         }
@@ -1199,6 +1321,7 @@ pub mod tests {
             waiter1.pr,
             waiter1.lock,
             WaitTimeout::Millis(wait_for_lock_timeout),
+            CommandKind::acquire_pessimistic_lock,
         );
         let (waiter2, lock_info2, f2) = new_test_waiter(30.into(), lock.ts, lock.hash);
         // Waiter2's timeout is 50ms which is less than wake_up_delay_duration.
@@ -1208,6 +1331,7 @@ pub mod tests {
             waiter2.pr,
             waiter2.lock,
             WaitTimeout::Millis(50),
+            CommandKind::acquire_pessimistic_lock,
         );
         let commit_ts = 15.into();
         let (tx, rx) = mpsc::sync_channel(1);
@@ -1249,6 +1373,7 @@ pub mod tests {
             waiter.pr,
             waiter.lock,
             WaitTimeout::Millis(1000),
+            CommandKind::acquire_pessimistic_lock,
         );
         scheduler.deadlock(waiter_ts, lock, 30);
         assert_elapsed(
@@ -1276,6 +1401,7 @@ pub mod tests {
             waiter1.pr,
             waiter1.lock,
             WaitTimeout::Millis(1000),
+            CommandKind::acquire_pessimistic_lock,
         );
         let (waiter2, lock_info2, f2) = new_test_waiter(waiter_ts, lock.ts, lock.hash);
         scheduler.wait_for(
@@ -1284,6 +1410,7 @@ pub mod tests {
             waiter2.pr,
             waiter2.lock,
             WaitTimeout::Millis(1000),
+            CommandKind::acquire_pessimistic_lock,
         );
         // Should notify duplicated waiter immediately.
         assert_elapsed(
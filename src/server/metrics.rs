@@ -50,6 +50,7 @@ make_auto_flush_static_metric! {
 
     pub label_enum GcCommandKind {
         gc,
+        gc_range,
         unsafe_destroy_range,
         physical_scan_lock,
         validate_config,
@@ -68,6 +69,12 @@ make_auto_flush_static_metric! {
         tombstone,
     }
 
+    pub label_enum GcReason {
+        auto,
+        manual,
+        range,
+    }
+
     pub label_enum GcKeysCF {
         default,
         lock,
@@ -114,6 +121,14 @@ make_auto_flush_static_metric! {
         "tag" => GcKeysDetail,
     }
 
+    pub struct GcReclaimedKeysCounterVec: IntCounter {
+        "reason" => GcReason,
+    }
+
+    pub struct GcReclaimedBytesCounterVec: IntCounter {
+        "reason" => GcReason,
+    }
+
     pub struct GrpcMsgHistogramVec: LocalHistogram {
         "type" => GrpcTypeKind,
     }
@@ -142,6 +157,11 @@ lazy_static! {
         &["type"]
     )
     .unwrap();
+    pub static ref SNAP_STALE_FILE_RECLAIMED_BYTES: IntCounter = register_int_counter!(
+        "tikv_server_snap_stale_file_reclaimed_bytes",
+        "Total bytes reclaimed by sweeping stale snapshot tmp files"
+    )
+    .unwrap();
     pub static ref GC_GCTASK_COUNTER_VEC: IntCounterVec = register_int_counter_vec!(
         "tikv_gcworker_gc_tasks_vec",
         "Counter of gc tasks processed by gc_worker",
@@ -166,12 +186,30 @@ lazy_static! {
         &["type"]
     )
     .unwrap();
+    pub static ref GRPC_MSG_SLOW_COUNTER_VEC: IntCounterVec = register_int_counter_vec!(
+        "tikv_grpc_msg_slow_total",
+        "Total number of grpc messages exceeding the slow-request threshold",
+        &["type"]
+    )
+    .unwrap();
     pub static ref GC_KEYS_COUNTER_VEC: IntCounterVec = register_int_counter_vec!(
         "tikv_gcworker_gc_keys",
         "Counter of keys affected during gc",
         &["cf", "tag"]
     )
     .unwrap();
+    pub static ref GC_RECLAIMED_KEYS_COUNTER_VEC: IntCounterVec = register_int_counter_vec!(
+        "tikv_gcworker_reclaimed_keys",
+        "Counter of MVCC versions deleted by gc",
+        &["reason"]
+    )
+    .unwrap();
+    pub static ref GC_RECLAIMED_BYTES_COUNTER_VEC: IntCounterVec = register_int_counter_vec!(
+        "tikv_gcworker_reclaimed_bytes",
+        "Estimated bytes reclaimed by gc, from the table properties of the affected range",
+        &["reason"]
+    )
+    .unwrap();
     pub static ref GRPC_MSG_HISTOGRAM_VEC: HistogramVec = register_histogram_vec!(
         "tikv_grpc_msg_duration_seconds",
         "Bucketed histogram of grpc server messages",
@@ -200,6 +238,10 @@ lazy_static! {
         auto_flush_from!(GRPC_MSG_FAIL_COUNTER_VEC, GrpcMsgFailCounterVec);
     pub static ref GC_KEYS_COUNTER_STATIC: GcKeysCounterVec =
         auto_flush_from!(GC_KEYS_COUNTER_VEC, GcKeysCounterVec);
+    pub static ref GC_RECLAIMED_KEYS_COUNTER_STATIC: GcReclaimedKeysCounterVec =
+        GcReclaimedKeysCounterVec::from(&GC_RECLAIMED_KEYS_COUNTER_VEC);
+    pub static ref GC_RECLAIMED_BYTES_COUNTER_STATIC: GcReclaimedBytesCounterVec =
+        GcReclaimedBytesCounterVec::from(&GC_RECLAIMED_BYTES_COUNTER_VEC);
 }
 
 lazy_static! {
@@ -238,6 +280,11 @@ lazy_static! {
         exponential_buckets(0.0005, 2.0, 20).unwrap()
     )
     .unwrap();
+    pub static ref GC_IN_FLIGHT_TASKS_GAUGE: IntGauge = register_int_gauge!(
+        "tikv_gcworker_in_flight_tasks",
+        "Number of gc tasks being executed"
+    )
+    .unwrap();
     pub static ref GC_TOO_BUSY_COUNTER: IntCounter = register_int_counter!(
         "tikv_gc_worker_too_busy",
         "Counter of occurrence of gc_worker being too busy"
@@ -287,6 +334,29 @@ lazy_static! {
         "Total number of raft messages flushed delay"
     )
     .unwrap();
+    pub static ref GRPC_CONN_GAUGE: IntGauge = register_int_gauge!(
+        "tikv_server_grpc_conn_count",
+        "Current number of gRPC connections held by the raft client"
+    )
+    .unwrap();
+    pub static ref RAFT_CLIENT_BACKOFF_GAUGE: IntGaugeVec = register_int_gauge_vec!(
+        "tikv_server_raft_client_backoff",
+        "Consecutive connection failures per store, 0 means the circuit is closed",
+        &["store_id"]
+    )
+    .unwrap();
+    pub static ref RAFT_CLIENT_SEND_QUEUE_GAUGE: IntGaugeVec = register_int_gauge_vec!(
+        "tikv_server_raft_client_send_queue_size",
+        "Pending raft messages per store not yet flushed to the stream",
+        &["store_id"]
+    )
+    .unwrap();
+    pub static ref RAFT_CLIENT_DROPPED_MSG_COUNTER: IntCounterVec = register_int_counter_vec!(
+        "tikv_server_raft_client_dropped_msg_total",
+        "Total number of raft messages dropped because the send queue was full",
+        &["store_id"]
+    )
+    .unwrap();
     pub static ref CONFIG_ROCKSDB_GAUGE: GaugeVec = register_gauge_vec!(
         "tikv_config_rocksdb",
         "Config information of rocksdb",
@@ -375,6 +445,12 @@ lazy_static! {
         exponential_buckets(0.0005, 2.0, 20).unwrap()
     )
     .unwrap();
+    pub static ref RAFTKV_READ_PATH_COUNTER_VEC: IntCounterVec = register_int_counter_vec!(
+        "tikv_raftkv_read_path_total",
+        "Total number of RaftKv reads taking each read path.",
+        &["path"]
+    )
+    .unwrap();
 }
 
 lazy_static! {
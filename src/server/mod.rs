@@ -20,10 +20,14 @@ pub mod transport;
 
 pub use self::config::{Config, DEFAULT_CLUSTER_ID, DEFAULT_LISTENING_ADDR};
 pub use self::errors::{Error, Result};
-pub use self::metrics::CONFIG_ROCKSDB_GAUGE;
+pub use self::metrics::{
+    CONFIG_ROCKSDB_GAUGE, GRPC_MSG_SLOW_COUNTER_VEC, RAFTKV_READ_PATH_COUNTER_VEC,
+};
 pub use self::node::{create_raft_storage, Node};
 pub use self::raft_client::RaftClient;
 pub use self::raftkv::RaftKv;
-pub use self::resolve::{PdStoreAddrResolver, StoreAddrResolver};
+pub use self::resolve::{
+    AddrPreference, PdStoreAddrResolver, StaticStoreAddrResolver, StoreAddrResolver,
+};
 pub use self::server::Server;
 pub use self::transport::ServerTransport;
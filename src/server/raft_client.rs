@@ -4,7 +4,7 @@ use std::ffi::CString;
 use std::i64;
 use std::sync::atomic::{AtomicI32, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use super::load_statistics::ThreadLoad;
 use super::metrics::*;
@@ -17,6 +17,7 @@ use grpcio::{
 use kvproto::raft_serverpb::RaftMessage;
 use kvproto::tikvpb::{BatchRaftMessage, TikvClient};
 use protobuf::Message;
+use raft::eraftpb::MessageType;
 use raftstore::router::RaftStoreRouter;
 use tikv_util::collections::{HashMap, HashMapEntry};
 use tikv_util::mpsc::batch::{self, BatchCollector, Sender as BatchSender};
@@ -29,13 +30,37 @@ const MAX_GRPC_SEND_MSG_LEN: i32 = 10 * 1024 * 1024;
 // When merge raft messages into a batch message, leave a buffer.
 const GRPC_SEND_MSG_BUF: usize = 4096;
 
-const RAFT_MSG_MAX_BATCH_SIZE: usize = 128;
 const RAFT_MSG_NOTIFY_SIZE: usize = 8;
 
+// Reconnection backoff doubles from the base up to the cap on every
+// consecutive failure. After `RAFT_CONN_BREAK_THRESHOLD` failures the circuit
+// opens and the store is only probed again after the cooldown.
+const RAFT_CONN_BACKOFF_BASE_MS: u64 = 100;
+const RAFT_CONN_BACKOFF_MAX_MS: u64 = 5_000;
+const RAFT_CONN_BREAK_THRESHOLD: u32 = 8;
+const RAFT_CONN_COOLDOWN_MS: u64 = 10_000;
+
+fn backoff_duration(consecutive_failures: u32) -> Duration {
+    if consecutive_failures >= RAFT_CONN_BREAK_THRESHOLD {
+        return Duration::from_millis(RAFT_CONN_COOLDOWN_MS);
+    }
+    let exp = consecutive_failures.saturating_sub(1).min(32);
+    let ms = RAFT_CONN_BACKOFF_BASE_MS
+        .saturating_mul(1 << exp)
+        .min(RAFT_CONN_BACKOFF_MAX_MS);
+    Duration::from_millis(ms)
+}
+
+struct StoreBackoff {
+    consecutive_failures: u32,
+    retry_at: Instant,
+}
+
 static CONN_ID: AtomicI32 = AtomicI32::new(0);
 
 struct Conn {
     stream: BatchSender<RaftMessage>,
+    store_id: u64,
     _client: TikvClient,
 }
 
@@ -67,8 +92,12 @@ impl Conn {
         let client2 = client1.clone();
 
         let (tx, rx) = batch::unbounded::<RaftMessage>(RAFT_MSG_NOTIFY_SIZE);
-        let rx =
-            batch::BatchReceiver::new(rx, RAFT_MSG_MAX_BATCH_SIZE, Vec::new, RaftMsgCollector(0));
+        let rx = batch::BatchReceiver::new(
+            rx,
+            cfg.raft_msg_max_batch_size,
+            Vec::new,
+            RaftMsgCollector(0),
+        );
 
         // Use a mutex to make compiler happy.
         let rx1 = Arc::new(Mutex::new(rx));
@@ -126,19 +155,29 @@ impl Conn {
                 .map(|_| ()),
         );
 
+        GRPC_CONN_GAUGE.inc();
+
         Conn {
             stream: tx,
+            store_id,
             _client: client1,
         }
     }
 }
 
+impl Drop for Conn {
+    fn drop(&mut self) {
+        GRPC_CONN_GAUGE.dec();
+    }
+}
+
 /// `RaftClient` is used for sending raft messages to other stores.
 pub struct RaftClient<T: 'static> {
     env: Arc<Environment>,
     router: Mutex<T>,
     conns: HashMap<(String, usize), Conn>,
     pub addrs: HashMap<u64, String>,
+    backoff: HashMap<u64, StoreBackoff>,
     cfg: Arc<Config>,
     security_mgr: Arc<SecurityManager>,
 
@@ -164,6 +203,7 @@ impl<T: RaftStoreRouter> RaftClient<T> {
             router: Mutex::new(router),
             conns: HashMap::default(),
             addrs: HashMap::default(),
+            backoff: HashMap::default(),
             cfg,
             security_mgr,
             grpc_thread_load,
@@ -173,7 +213,7 @@ impl<T: RaftStoreRouter> RaftClient<T> {
     }
 
     fn get_conn(&mut self, addr: &str, region_id: u64, store_id: u64) -> &mut Conn {
-        let index = region_id as usize % self.cfg.grpc_raft_conn_num;
+        let index = conn_index(region_id, self.cfg.grpc_raft_conn_num);
         match self.conns.entry((addr.to_owned(), index)) {
             HashMapEntry::Occupied(e) => e.into_mut(),
             HashMapEntry::Vacant(e) => {
@@ -191,13 +231,29 @@ impl<T: RaftStoreRouter> RaftClient<T> {
     }
 
     pub fn send(&mut self, store_id: u64, addr: &str, msg: RaftMessage) -> Result<()> {
-        if let Err(SendError(msg)) = self
-            .get_conn(addr, msg.region_id, store_id)
-            .stream
-            .send(msg)
-        {
+        if let Some(backoff) = self.backoff.get(&store_id) {
+            if Instant::now() < backoff.retry_at {
+                return Err(box_err!("RaftClient store {} is in backoff", store_id));
+            }
+        }
+        let (send_res, queue_len) = {
+            let queue_cap = self.cfg.raft_client_queue_size;
+            let conn = self.get_conn(addr, msg.region_id, store_id);
+            // When the peer can't keep up, shed non-critical messages instead
+            // of queueing without bound. Heartbeats and snapshot messages are
+            // always kept; dropped appends are re-sent by raft.
+            if should_shed_msg(&msg, conn.stream.len(), queue_cap) {
+                RAFT_CLIENT_DROPPED_MSG_COUNTER
+                    .with_label_values(&[&store_id.to_string()])
+                    .inc();
+                return Ok(());
+            }
+            let res = conn.stream.send(msg);
+            (res, conn.stream.len())
+        };
+        if let Err(SendError(msg)) = send_res {
             warn!("send to {} fail, the gRPC connection could be broken", addr);
-            let index = msg.region_id as usize % self.cfg.grpc_raft_conn_num;
+            let index = conn_index(msg.region_id, self.cfg.grpc_raft_conn_num);
             self.conns.remove(&(addr.to_owned(), index));
 
             if let Some(current_addr) = self.addrs.remove(&store_id) {
@@ -205,14 +261,41 @@ impl<T: RaftStoreRouter> RaftClient<T> {
                     self.addrs.insert(store_id, current_addr);
                 }
             }
+            let failures = {
+                let backoff = self.backoff.entry(store_id).or_insert_with(|| StoreBackoff {
+                    consecutive_failures: 0,
+                    retry_at: Instant::now(),
+                });
+                backoff.consecutive_failures += 1;
+                backoff.retry_at = Instant::now() + backoff_duration(backoff.consecutive_failures);
+                backoff.consecutive_failures
+            };
+            RAFT_CLIENT_BACKOFF_GAUGE
+                .with_label_values(&[&store_id.to_string()])
+                .set(i64::from(failures));
             return Err(box_err!("RaftClient send fail"));
         }
+        update_send_queue_gauge(store_id, queue_len);
+        if self.backoff.remove(&store_id).is_some() {
+            RAFT_CLIENT_BACKOFF_GAUGE
+                .with_label_values(&[&store_id.to_string()])
+                .set(0);
+        }
         Ok(())
     }
 
+    /// Returns the number of connections currently held by the client.
+    pub fn connection_count(&self) -> usize {
+        self.conns.len()
+    }
+
     pub fn flush(&mut self) {
         let (mut counter, mut delay_counter) = (0, 0);
+        // A store may be served by several connections, so sum their queues
+        // before reporting.
+        let mut queue_sizes: HashMap<u64, usize> = HashMap::default();
         for conn in self.conns.values_mut() {
+            *queue_sizes.entry(conn.store_id).or_insert(0) += conn.stream.len();
             if conn.stream.is_empty() {
                 continue;
             }
@@ -232,11 +315,46 @@ impl<T: RaftStoreRouter> RaftClient<T> {
             }
             delay_counter += 1;
         }
+        for (store_id, queue_len) in queue_sizes {
+            update_send_queue_gauge(store_id, queue_len);
+        }
         RAFT_MESSAGE_FLUSH_COUNTER.inc_by(i64::from(counter));
         RAFT_MESSAGE_DELAY_FLUSH_COUNTER.inc_by(i64::from(delay_counter));
     }
 }
 
+/// Whether a raft message must never be shed by the queue cap. Heartbeats
+/// keep leases and leadership alive, and a snapshot message is expensive to
+/// regenerate; everything else is re-sent by raft if lost.
+fn is_critical_raft_msg(msg: &RaftMessage) -> bool {
+    match msg.get_message().get_msg_type() {
+        MessageType::MsgHeartbeat | MessageType::MsgHeartbeatResponse | MessageType::MsgSnapshot => {
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Whether the message should be shed given the current queue depth.
+/// A zero cap disables shedding.
+fn should_shed_msg(msg: &RaftMessage, queue_len: usize, queue_cap: usize) -> bool {
+    queue_cap > 0 && queue_len >= queue_cap && !is_critical_raft_msg(msg)
+}
+
+/// Reports the send queue depth of a store, i.e. messages accepted by
+/// `RaftClient::send` but not yet flushed to the gRPC stream.
+fn update_send_queue_gauge(store_id: u64, queue_len: usize) {
+    RAFT_CLIENT_SEND_QUEUE_GAUGE
+        .with_label_values(&[&store_id.to_string()])
+        .set(queue_len as i64);
+}
+
+/// Returns the index of the connection serving the region. Messages of one
+/// region always go through the same connection so their order is preserved.
+fn conn_index(region_id: u64, conn_count: usize) -> usize {
+    region_id as usize % conn_count
+}
+
 // Collect raft messages into a vector so that we can merge them into one message later.
 // `MAX_GRPC_SEND_MSG_LEN` will be considered when collecting.
 struct RaftMsgCollector(usize);
@@ -287,3 +405,119 @@ fn check_rpc_result(
     warn!( "RPC {} fail", rpc; "to_addr" => addr, "sink_err" => ?sink_e, "err" => ?recv_e);
     recv_e.map_or(Ok(()), |e| Err(grpc_error_is_unimplemented(&e)))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        backoff_duration, conn_index, should_shed_msg, update_send_queue_gauge, RaftMsgCollector,
+        RAFT_MSG_NOTIFY_SIZE,
+    };
+    use raft::eraftpb::MessageType;
+    use crate::server::metrics::RAFT_CLIENT_SEND_QUEUE_GAUGE;
+    use futures::Stream;
+    use kvproto::raft_serverpb::RaftMessage;
+    use std::time::Duration;
+    use tikv_util::mpsc::batch;
+
+    #[test]
+    fn test_conn_index_is_stable() {
+        for region_id in 0..100u64 {
+            let first = conn_index(region_id, 4);
+            assert!(first < 4);
+            // Messages of the same region always use the same connection.
+            for _ in 0..10 {
+                assert_eq!(conn_index(region_id, 4), first);
+            }
+        }
+        assert_eq!(conn_index(7, 1), 0);
+    }
+
+    #[test]
+    fn test_backoff_schedule() {
+        // The delay doubles on every consecutive failure.
+        assert_eq!(backoff_duration(1), Duration::from_millis(100));
+        assert_eq!(backoff_duration(2), Duration::from_millis(200));
+        assert_eq!(backoff_duration(3), Duration::from_millis(400));
+        assert_eq!(backoff_duration(6), Duration::from_millis(3200));
+        // ... and is capped.
+        assert_eq!(backoff_duration(7), Duration::from_millis(5000));
+        // After enough failures the circuit opens for a cooldown.
+        assert_eq!(backoff_duration(8), Duration::from_millis(10000));
+        assert_eq!(backoff_duration(100), Duration::from_millis(10000));
+    }
+
+    #[test]
+    fn test_queue_cap_sheds_non_critical() {
+        let new_msg = |t: MessageType| {
+            let mut m = RaftMessage::default();
+            m.mut_message().set_msg_type(t);
+            m
+        };
+
+        let cap = 4;
+        let (tx, _rx) = batch::unbounded::<RaftMessage>(RAFT_MSG_NOTIFY_SIZE);
+
+        // Overflowing appends are shed once the queue is full.
+        let mut dropped = 0;
+        for _ in 0..6 {
+            let m = new_msg(MessageType::MsgAppend);
+            if should_shed_msg(&m, tx.len(), cap) {
+                dropped += 1;
+                continue;
+            }
+            tx.send(m).unwrap();
+        }
+        assert_eq!(tx.len(), cap);
+        assert_eq!(dropped, 2);
+
+        // Heartbeats and snapshots pass even when the queue is full.
+        for t in &[
+            MessageType::MsgHeartbeat,
+            MessageType::MsgHeartbeatResponse,
+            MessageType::MsgSnapshot,
+        ] {
+            let m = new_msg(*t);
+            assert!(!should_shed_msg(&m, tx.len(), cap));
+            tx.send(m).unwrap();
+        }
+        assert_eq!(tx.len(), cap + 3);
+
+        // A zero cap disables shedding.
+        let m = new_msg(MessageType::MsgAppend);
+        assert!(!should_shed_msg(&m, tx.len(), 0));
+    }
+
+    #[test]
+    fn test_send_queue_gauge() {
+        let store_id = 4077u64;
+        let gauge = RAFT_CLIENT_SEND_QUEUE_GAUGE.with_label_values(&[&store_id.to_string()]);
+
+        // A stream that accepts nothing: messages pile up in the queue.
+        let (tx, rx) = batch::unbounded::<RaftMessage>(RAFT_MSG_NOTIFY_SIZE);
+        for _ in 0..5 {
+            tx.send(RaftMessage::default()).unwrap();
+            update_send_queue_gauge(store_id, tx.len());
+        }
+        assert_eq!(gauge.get(), 5);
+
+        // Once the stream accepts the backlog the gauge drains with it.
+        while rx.try_recv().is_ok() {}
+        update_send_queue_gauge(store_id, tx.len());
+        assert_eq!(gauge.get(), 0);
+    }
+
+    #[test]
+    fn test_raft_msg_batching() {
+        let (tx, rx) = batch::unbounded::<RaftMessage>(RAFT_MSG_NOTIFY_SIZE);
+        let rx = batch::BatchReceiver::new(rx, 128, Vec::new, RaftMsgCollector(0));
+        for _ in 0..96 {
+            tx.send(RaftMessage::default()).unwrap();
+        }
+        drop(tx);
+
+        let batches: Vec<_> = rx.wait().map(|b| b.unwrap()).collect();
+        assert_eq!(batches.iter().map(|b| b.len()).sum::<usize>(), 96);
+        // Small messages are coalesced into far fewer stream writes.
+        assert_eq!(batches.len(), 1);
+    }
+}
@@ -14,9 +14,11 @@ use kvproto::raft_cmdpb::{
 use std::fmt::{self, Debug, Display, Formatter};
 use std::io::Error as IoError;
 use std::result;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use txn_types::{Key, Value};
 
+use super::load_statistics;
 use super::metrics::*;
 use crate::storage::kv::{
     Callback, CbContext, Cursor, Engine, Error as KvError, ErrorInner as KvErrorInner,
@@ -25,8 +27,10 @@ use crate::storage::kv::{
 use crate::storage::{self, kv};
 use raftstore::errors::Error as RaftServerError;
 use raftstore::router::RaftStoreRouter;
+use raftstore::store::fsm::store::StoreMeta;
 use raftstore::store::{Callback as StoreCallback, ReadResponse, WriteResponse};
 use raftstore::store::{RegionIterator, RegionSnapshot};
+use tikv_util::collections::HashMap;
 use tikv_util::time::Instant;
 
 quick_error! {
@@ -107,6 +111,8 @@ impl From<RaftServerError> for KvError {
 #[derive(Clone)]
 pub struct RaftKv<S: RaftStoreRouter + 'static> {
     router: S,
+    prefer_lease_read: bool,
+    store_meta: Option<Arc<Mutex<StoreMeta>>>,
 }
 
 pub enum CmdRes {
@@ -163,7 +169,64 @@ fn on_read_result(
 impl<S: RaftStoreRouter> RaftKv<S> {
     /// Create a RaftKv using specified configuration.
     pub fn new(router: S) -> RaftKv<S> {
-        RaftKv { router }
+        RaftKv {
+            router,
+            prefer_lease_read: true,
+            store_meta: None,
+        }
+    }
+
+    /// Sets the local region cache used to pre-validate requests before they
+    /// are proposed.
+    pub fn set_store_meta(&mut self, meta: Arc<Mutex<StoreMeta>>) {
+        self.store_meta = Some(meta);
+    }
+
+    /// Checks the request epoch and peer against the local region cache, so
+    /// obviously-stale requests are rejected without wasting a raft proposal.
+    fn pre_check(&self, ctx: &Context) -> Result<()> {
+        let meta = match self.store_meta.as_ref() {
+            Some(meta) => meta.lock().unwrap(),
+            None => return Ok(()),
+        };
+        let region_id = ctx.get_region_id();
+        let region = match meta.regions.get(&region_id) {
+            Some(region) => region,
+            None => return Err(RaftServerError::RegionNotFound(region_id).into()),
+        };
+        let requested = ctx.get_region_epoch();
+        let current = region.get_region_epoch();
+        if requested.get_version() < current.get_version()
+            || requested.get_conf_ver() < current.get_conf_ver()
+        {
+            return Err(RaftServerError::EpochNotMatch(
+                format!(
+                    "current epoch of region {} is {:?}, but you sent {:?}",
+                    region_id, current, requested
+                ),
+                vec![region.clone()],
+            )
+            .into());
+        }
+        let peer_id = ctx.get_peer().get_id();
+        if region.get_peers().iter().all(|p| p.get_id() != peer_id) {
+            return Err(RaftServerError::RegionNotFound(region_id).into());
+        }
+        Ok(())
+    }
+
+    /// Sets whether reads should try the lease-based local read path first.
+    /// When disabled every read takes a read index, trading latency for not
+    /// depending on leader lease correctness.
+    pub fn set_prefer_lease_read(&mut self, prefer: bool) {
+        self.prefer_lease_read = prefer;
+    }
+
+    /// Returns whether this read can be served by the leader lease. A read
+    /// that can't falls back to a quorum read through read index.
+    fn lease_read_usable(&self, ctx: &Context) -> bool {
+        fail_point!("raftkv_lease_expired", |_| false);
+        self.prefer_lease_read && !ctx.get_replica_read()
     }
 
     fn new_request_header(&self, ctx: &Context) -> RaftRequestHeader {
@@ -186,7 +249,17 @@ impl<S: RaftStoreRouter> RaftKv<S> {
         cb: Callback<CmdRes>,
     ) -> Result<()> {
         let len = reqs.len();
-        let header = self.new_request_header(ctx);
+        let mut header = self.new_request_header(ctx);
+        if self.lease_read_usable(ctx) {
+            RAFTKV_READ_PATH_COUNTER_VEC
+                .with_label_values(&["lease"])
+                .inc();
+        } else {
+            header.set_read_quorum(true);
+            RAFTKV_READ_PATH_COUNTER_VEC
+                .with_label_values(&["read_index"])
+                .inc();
+        }
         let mut cmd = RaftCmdRequest::default();
         cmd.set_header(header);
         cmd.set_requests(reqs.into());
@@ -211,6 +284,7 @@ impl<S: RaftStoreRouter> RaftKv<S> {
         fail_point!("raftkv_early_error_report", |_| Err(
             RaftServerError::RegionNotFound(ctx.get_region_id()).into()
         ));
+        self.pre_check(ctx)?;
         let len = reqs.len();
         let header = self.new_request_header(ctx);
         let mut cmd = RaftCmdRequest::default();
@@ -227,6 +301,120 @@ impl<S: RaftStoreRouter> RaftKv<S> {
             )
             .map_err(From::from)
     }
+
+    /// Proposes a batch of writes. Writes targeting the same region are
+    /// coalesced into a single raft proposal to cut commit overhead, while
+    /// writes for different regions are still proposed separately. Every
+    /// write gets its own result through its callback.
+    pub fn async_write_batch(
+        &self,
+        batches: Vec<(Context, Vec<Modify>, Callback<()>)>,
+    ) -> kv::Result<()> {
+        let mut groups: HashMap<u64, Vec<(Context, Vec<Modify>, Callback<()>)>> =
+            HashMap::default();
+        for batch in batches {
+            if batch.1.is_empty() {
+                return Err(KvError::from(KvErrorInner::EmptyRequest));
+            }
+            let region_id = batch.0.get_region_id();
+            groups.entry(region_id).or_insert_with(Vec::new).push(batch);
+        }
+
+        for (_, group) in groups {
+            let ctx = group[0].0.clone();
+            let mut reqs = Vec::new();
+            let mut cbs = Vec::with_capacity(group.len());
+            for (_, modifies, cb) in group {
+                kv::record_written_bytes(&modifies);
+                reqs.extend(modifies_to_requests(modifies));
+                cbs.push(cb);
+            }
+
+            ASYNC_REQUESTS_COUNTER_VEC.write.all.inc();
+            let begin_instant = Instant::now_coarse();
+
+            self.exec_write_requests(
+                &ctx,
+                reqs,
+                Box::new(move |(cb_ctx, res)| match res {
+                    Ok(CmdRes::Resp(_)) => {
+                        ASYNC_REQUESTS_COUNTER_VEC.write.success.inc();
+                        ASYNC_REQUESTS_DURATIONS_VEC
+                            .write
+                            .observe(begin_instant.elapsed_secs());
+                        for cb in cbs {
+                            let mut ctx = CbContext::new();
+                            ctx.term = cb_ctx.term;
+                            cb((ctx, Ok(())));
+                        }
+                    }
+                    Ok(CmdRes::Snap(_)) => {
+                        for cb in cbs {
+                            cb((
+                                CbContext::new(),
+                                Err(box_err!("unexpect snapshot, should mutate instead.")),
+                            ));
+                        }
+                    }
+                    Err(e) => {
+                        let status_kind = get_status_kind_from_engine_error(&e);
+                        ASYNC_REQUESTS_COUNTER_VEC.write.get(status_kind).inc();
+                        for cb in cbs {
+                            let err = e
+                                .maybe_clone()
+                                .unwrap_or_else(|| box_err!("batched write failed: {}", e));
+                            cb((CbContext::new(), Err(err)));
+                        }
+                    }
+                }),
+            )
+            .map_err(|e| {
+                let status_kind = get_status_kind_from_error(&e);
+                ASYNC_REQUESTS_COUNTER_VEC.write.get(status_kind).inc();
+                KvError::from(e)
+            })?;
+        }
+        Ok(())
+    }
+}
+
+fn modifies_to_requests(modifies: Vec<Modify>) -> Vec<Request> {
+    let mut reqs = Vec::with_capacity(modifies.len());
+    for m in modifies {
+        let mut req = Request::default();
+        match m {
+            Modify::Delete(cf, k) => {
+                let mut delete = DeleteRequest::default();
+                delete.set_key(k.into_encoded());
+                if cf != CF_DEFAULT {
+                    delete.set_cf(cf.to_string());
+                }
+                req.set_cmd_type(CmdType::Delete);
+                req.set_delete(delete);
+            }
+            Modify::Put(cf, k, v) => {
+                let mut put = PutRequest::default();
+                put.set_key(k.into_encoded());
+                put.set_value(v);
+                if cf != CF_DEFAULT {
+                    put.set_cf(cf.to_string());
+                }
+                req.set_cmd_type(CmdType::Put);
+                req.set_put(put);
+            }
+            Modify::DeleteRange(cf, start_key, end_key, notify_only) => {
+                let mut delete_range = DeleteRangeRequest::default();
+                delete_range.set_cf(cf.to_string());
+                delete_range.set_start_key(start_key.into_encoded());
+                delete_range.set_end_key(end_key.into_encoded());
+                delete_range.set_notify_only(notify_only);
+                req.set_cmd_type(CmdType::DeleteRange);
+                req.set_delete_range(delete_range);
+            }
+        }
+        reqs.push(req);
+    }
+    reqs
 }
 
 fn invalid_resp_type(exp: CmdType, act: CmdType) -> Error {
@@ -261,42 +449,9 @@ impl<S: RaftStoreRouter> Engine for RaftKv<S> {
         if modifies.is_empty() {
             return Err(KvError::from(KvErrorInner::EmptyRequest));
         }
+        kv::record_written_bytes(&modifies);
 
-        let mut reqs = Vec::with_capacity(modifies.len());
-        for m in modifies {
-            let mut req = Request::default();
-            match m {
-                Modify::Delete(cf, k) => {
-                    let mut delete = DeleteRequest::default();
-                    delete.set_key(k.into_encoded());
-                    if cf != CF_DEFAULT {
-                        delete.set_cf(cf.to_string());
-                    }
-                    req.set_cmd_type(CmdType::Delete);
-                    req.set_delete(delete);
-                }
-                Modify::Put(cf, k, v) => {
-                    let mut put = PutRequest::default();
-                    put.set_key(k.into_encoded());
-                    put.set_value(v);
-                    if cf != CF_DEFAULT {
-                        put.set_cf(cf.to_string());
-                    }
-                    req.set_cmd_type(CmdType::Put);
-                    req.set_put(put);
-                }
-                Modify::DeleteRange(cf, start_key, end_key, notify_only) => {
-                    let mut delete_range = DeleteRangeRequest::default();
-                    delete_range.set_cf(cf.to_string());
-                    delete_range.set_start_key(start_key.into_encoded());
-                    delete_range.set_end_key(end_key.into_encoded());
-                    delete_range.set_notify_only(notify_only);
-                    req.set_cmd_type(CmdType::DeleteRange);
-                    req.set_delete_range(delete_range);
-                }
-            }
-            reqs.push(req);
-        }
+        let reqs = modifies_to_requests(modifies);
 
         ASYNC_REQUESTS_COUNTER_VEC.write.all.inc();
         let begin_instant = Instant::now_coarse();
@@ -377,6 +532,9 @@ impl Snapshot for RegionSnapshot<RocksEngine> {
             "injected error for get"
         )));
         let v = box_try!(self.get_value(key.as_encoded()));
+        if let Some(v) = v.as_ref() {
+            load_statistics::record_read_bytes(CF_DEFAULT, v.len());
+        }
         Ok(v.map(|v| v.to_vec()))
     }
 
@@ -385,6 +543,9 @@ impl Snapshot for RegionSnapshot<RocksEngine> {
             "injected error for get_cf"
         )));
         let v = box_try!(self.get_value_cf(cf, key.as_encoded()));
+        if let Some(v) = v.as_ref() {
+            load_statistics::record_read_bytes(cf, v.len());
+        }
         Ok(v.map(|v| v.to_vec()))
     }
 
@@ -428,6 +589,170 @@ impl Snapshot for RegionSnapshot<RocksEngine> {
     fn get_data_version(&self) -> Option<u64> {
         self.get_apply_index().ok()
     }
+
+    #[inline]
+    fn region_id(&self) -> Option<u64> {
+        Some(self.get_region().get_id())
+    }
+
+    #[inline]
+    fn region_range(&self) -> Option<(&[u8], &[u8])> {
+        Some((self.get_start_key(), self.get_end_key()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kvproto::metapb;
+    use kvproto::raft_serverpb::RaftMessage;
+    use raftstore::store::{CasualMessage, SignificantMsg};
+    use raftstore::Result as RaftStoreResult;
+
+    /// A router that records every proposed command and never invokes any
+    /// callback.
+    #[derive(Clone)]
+    struct CountingRouter {
+        proposals: Arc<Mutex<Vec<RaftCmdRequest>>>,
+    }
+
+    impl RaftStoreRouter for CountingRouter {
+        fn send_raft_msg(&self, _: RaftMessage) -> RaftStoreResult<()> {
+            Ok(())
+        }
+
+        fn send_command(
+            &self,
+            req: RaftCmdRequest,
+            _: StoreCallback<RocksEngine>,
+        ) -> RaftStoreResult<()> {
+            self.proposals.lock().unwrap().push(req);
+            Ok(())
+        }
+
+        fn significant_send(&self, _: u64, _: SignificantMsg) -> RaftStoreResult<()> {
+            Ok(())
+        }
+
+        fn broadcast_unreachable(&self, _: u64) {}
+
+        fn casual_send(&self, _: u64, _: CasualMessage<RocksEngine>) -> RaftStoreResult<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_stale_requests_rejected_before_propose() {
+        let proposals = Arc::new(Mutex::new(Vec::new()));
+        let router = CountingRouter {
+            proposals: Arc::clone(&proposals),
+        };
+        let mut engine = RaftKv::new(router);
+
+        let mut peer = metapb::Peer::default();
+        peer.set_id(2);
+        peer.set_store_id(1);
+        let mut region = metapb::Region::default();
+        region.set_id(1);
+        region.mut_region_epoch().set_version(3);
+        region.mut_region_epoch().set_conf_ver(3);
+        region.mut_peers().push(peer.clone());
+
+        let meta = Arc::new(Mutex::new(StoreMeta::new(20)));
+        meta.lock().unwrap().regions.insert(1, region);
+        engine.set_store_meta(meta);
+
+        let write = || vec![Modify::Put(CF_DEFAULT, Key::from_raw(b"k"), b"v".to_vec())];
+
+        // A stale epoch must be rejected without proposing anything.
+        let mut ctx = Context::default();
+        ctx.set_region_id(1);
+        ctx.mut_region_epoch().set_version(2);
+        ctx.mut_region_epoch().set_conf_ver(3);
+        ctx.set_peer(peer.clone());
+        match engine.async_write(&ctx, write(), Box::new(|_| {})) {
+            Err(KvError(box KvErrorInner::Request(ref e))) => assert!(e.has_epoch_not_match()),
+            res => panic!("expect epoch not match, but got {:?}", res),
+        }
+        assert!(proposals.lock().unwrap().is_empty());
+
+        // An unknown region must be rejected as region not found.
+        ctx.set_region_id(4);
+        match engine.async_write(&ctx, write(), Box::new(|_| {})) {
+            Err(KvError(box KvErrorInner::Request(ref e))) => assert!(e.has_region_not_found()),
+            res => panic!("expect region not found, but got {:?}", res),
+        }
+        assert!(proposals.lock().unwrap().is_empty());
+
+        // A valid request passes the pre-check and gets proposed.
+        ctx.set_region_id(1);
+        ctx.mut_region_epoch().set_version(3);
+        engine.async_write(&ctx, write(), Box::new(|_| {})).unwrap();
+        assert_eq!(proposals.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_async_write_batch_coalesces_per_region() {
+        let proposals = Arc::new(Mutex::new(Vec::new()));
+        let router = CountingRouter {
+            proposals: Arc::clone(&proposals),
+        };
+        let engine = RaftKv::new(router);
+
+        let ctx_for = |region_id: u64| {
+            let mut ctx = Context::default();
+            ctx.set_region_id(region_id);
+            ctx
+        };
+        let put = |key: &[u8]| vec![Modify::Put(CF_DEFAULT, Key::from_raw(key), b"v".to_vec())];
+
+        let cb: fn() -> Callback<()> = || Box::new(|_| {});
+        let batches = vec![
+            (ctx_for(1), put(b"k1"), cb()),
+            (ctx_for(1), put(b"k2"), cb()),
+            (ctx_for(1), put(b"k3"), cb()),
+            (ctx_for(2), put(b"k4"), cb()),
+        ];
+        engine.async_write_batch(batches).unwrap();
+
+        // All writes for region 1 must share one proposal, region 2 gets its
+        // own.
+        let proposals = proposals.lock().unwrap();
+        assert_eq!(proposals.len(), 2);
+        for proposal in proposals.iter() {
+            match proposal.get_header().get_region_id() {
+                1 => assert_eq!(proposal.get_requests().len(), 3),
+                2 => assert_eq!(proposal.get_requests().len(), 1),
+                id => panic!("unexpected region {}", id),
+            }
+        }
+    }
+
+    #[test]
+    fn test_region_bound_snapshot() {
+        use engine_traits::ALL_CFS;
+        use tempfile::Builder;
+
+        let path = Builder::new()
+            .prefix("test_region_bound_snapshot")
+            .tempdir()
+            .unwrap();
+        let db = engine::rocks::util::new_engine(
+            path.path().to_str().unwrap(),
+            None,
+            ALL_CFS,
+            None,
+        )
+        .unwrap();
+        let mut region = metapb::Region::default();
+        region.set_id(1);
+        region.set_start_key(b"a".to_vec());
+        region.set_end_key(b"z".to_vec());
+        let snap = RegionSnapshot::<RocksEngine>::from_raw(Arc::new(db), region);
+
+        assert_eq!(snap.region_id(), Some(1));
+        assert_eq!(snap.region_range(), Some((b"a" as &[u8], b"z" as &[u8])));
+    }
 }
 
 impl EngineIterator for RegionIterator<RocksEngine> {
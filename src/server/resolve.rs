@@ -2,36 +2,58 @@
 
 use std::fmt::{self, Display, Formatter};
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use kvproto::metapb;
 
-use pd_client::{take_peer_address, PdClient};
+use pd_client::{take_advertised_address, take_peer_address, PdClient};
 use tikv_util::collections::HashMap;
 use tikv_util::worker::{Runnable, Scheduler, Worker};
 
 use super::metrics::*;
 use super::Result;
 
-const STORE_ADDRESS_REFRESH_SECONDS: u64 = 60;
+// Failed lookups are cached for a short while so an absent store doesn't get
+// re-resolved on every message.
+const STORE_ADDRESS_NEGATIVE_CACHE_SECONDS: u64 = 5;
 
 pub type Callback = Box<dyn FnOnce(Result<String>) + Send>;
 
+/// Which address field of the PD store metadata a resolver returns.
+///
+/// Deployments may advertise a different address to clients than the one
+/// stores use to talk to each other. Raft traffic must use the peer address,
+/// while client-facing resolution should use the advertised one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AddrPreference {
+    /// The `peer_address` field, falling back to `address` when unset.
+    Peer,
+    /// The `address` field, falling back to `peer_address` when unset.
+    Advertised,
+}
+
 /// A trait for resolving store addresses.
 pub trait StoreAddrResolver: Send + Clone {
     /// Resolves the address for the specified store id asynchronously.
     fn resolve(&self, store_id: u64, cb: Callback) -> Result<()>;
+
+    /// Invalidates any cached address of the store, e.g. when connecting to
+    /// the cached address fails.
+    fn invalidate(&self, _store_id: u64) {}
 }
 
-/// A task for resolving store addresses.
-pub struct Task {
-    store_id: u64,
-    cb: Callback,
+/// A task for the store address resolver.
+pub enum Task {
+    Resolve { store_id: u64, cb: Callback },
+    Invalidate { store_id: u64 },
 }
 
 impl Display for Task {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "resolve store {} address", self.store_id)
+        match self {
+            Task::Resolve { store_id, .. } => write!(f, "resolve store {} address", store_id),
+            Task::Invalidate { store_id } => write!(f, "invalidate store {} address", store_id),
+        }
     }
 }
 
@@ -44,6 +66,9 @@ struct StoreAddr {
 struct Runner<T: PdClient> {
     pd_client: Arc<T>,
     store_addrs: HashMap<u64, StoreAddr>,
+    neg_store_addrs: HashMap<u64, Instant>,
+    ttl: Duration,
+    preference: AddrPreference,
 }
 
 impl<T: PdClient> Runner<T> {
@@ -51,12 +76,25 @@ impl<T: PdClient> Runner<T> {
         if let Some(s) = self.store_addrs.get(&store_id) {
             let now = Instant::now();
             let elapsed = now.duration_since(s.last_update);
-            if elapsed.as_secs() < STORE_ADDRESS_REFRESH_SECONDS {
+            if elapsed < self.ttl {
                 return Ok(s.addr.clone());
             }
         }
 
-        let addr = self.get_address(store_id)?;
+        if let Some(failed_at) = self.neg_store_addrs.get(&store_id) {
+            if failed_at.elapsed().as_secs() < STORE_ADDRESS_NEGATIVE_CACHE_SECONDS {
+                return Err(box_err!("store {} address is unavailable (cached)", store_id));
+            }
+        }
+
+        let addr = match self.get_address(store_id) {
+            Ok(addr) => addr,
+            Err(e) => {
+                self.neg_store_addrs.insert(store_id, Instant::now());
+                return Err(e);
+            }
+        };
+        self.neg_store_addrs.remove(&store_id);
 
         let cache = StoreAddr {
             addr: addr.clone(),
@@ -67,6 +105,11 @@ impl<T: PdClient> Runner<T> {
         Ok(addr)
     }
 
+    fn invalidate(&mut self, store_id: u64) {
+        self.store_addrs.remove(&store_id);
+        self.neg_store_addrs.remove(&store_id);
+    }
+
     fn get_address(&self, store_id: u64) -> Result<String> {
         let pd_client = Arc::clone(&self.pd_client);
         let mut s = box_try!(pd_client.get_store(store_id));
@@ -74,7 +117,10 @@ impl<T: PdClient> Runner<T> {
             RESOLVE_STORE_COUNTER_STATIC.tombstone.inc();
             return Err(box_err!("store {} has been removed", store_id));
         }
-        let addr = take_peer_address(&mut s);
+        let addr = match self.preference {
+            AddrPreference::Peer => take_peer_address(&mut s),
+            AddrPreference::Advertised => take_advertised_address(&mut s),
+        };
         // In some tests, we use empty address for store first,
         // so we should ignore here.
         // TODO: we may remove this check after we refactor the test.
@@ -87,9 +133,13 @@ impl<T: PdClient> Runner<T> {
 
 impl<T: PdClient> Runnable<Task> for Runner<T> {
     fn run(&mut self, task: Task) {
-        let store_id = task.store_id;
-        let resp = self.resolve(store_id);
-        (task.cb)(resp)
+        match task {
+            Task::Resolve { store_id, cb } => {
+                let resp = self.resolve(store_id);
+                cb(resp)
+            }
+            Task::Invalidate { store_id } => self.invalidate(store_id),
+        }
     }
 }
 
@@ -105,8 +155,24 @@ impl PdStoreAddrResolver {
     }
 }
 
-/// Creates a new `PdStoreAddrResolver`.
-pub fn new_resolver<T>(pd_client: Arc<T>) -> Result<(Worker<Task>, PdStoreAddrResolver)>
+/// Creates a new `PdStoreAddrResolver` resolving peer addresses, which is
+/// what raft traffic between stores needs.
+pub fn new_resolver<T>(
+    pd_client: Arc<T>,
+    ttl: Duration,
+) -> Result<(Worker<Task>, PdStoreAddrResolver)>
+where
+    T: PdClient + 'static,
+{
+    new_resolver_with_preference(pd_client, ttl, AddrPreference::Peer)
+}
+
+/// Creates a new `PdStoreAddrResolver` resolving the given address field.
+pub fn new_resolver_with_preference<T>(
+    pd_client: Arc<T>,
+    ttl: Duration,
+    preference: AddrPreference,
+) -> Result<(Worker<Task>, PdStoreAddrResolver)>
 where
     T: PdClient + 'static,
 {
@@ -115,6 +181,9 @@ where
     let runner = Runner {
         pd_client,
         store_addrs: HashMap::default(),
+        neg_store_addrs: HashMap::default(),
+        ttl,
+        preference,
     };
     box_try!(worker.start(runner));
     let resolver = PdStoreAddrResolver::new(worker.scheduler());
@@ -123,10 +192,44 @@ where
 
 impl StoreAddrResolver for PdStoreAddrResolver {
     fn resolve(&self, store_id: u64, cb: Callback) -> Result<()> {
-        let task = Task { store_id, cb };
+        let task = Task::Resolve { store_id, cb };
         box_try!(self.sched.schedule(task));
         Ok(())
     }
+
+    fn invalidate(&self, store_id: u64) {
+        if let Err(e) = self.sched.schedule(Task::Invalidate { store_id }) {
+            warn!("failed to invalidate store address"; "store_id" => store_id, "err" => ?e);
+        }
+    }
+}
+
+/// A resolver backed by a static map of store addresses. It is useful for
+/// tests and deployments where addresses are known up front and PD is not
+/// available.
+#[derive(Clone)]
+pub struct StaticStoreAddrResolver {
+    addrs: Arc<HashMap<u64, String>>,
+}
+
+impl StaticStoreAddrResolver {
+    pub fn new(addrs: HashMap<u64, String>) -> StaticStoreAddrResolver {
+        StaticStoreAddrResolver {
+            addrs: Arc::new(addrs),
+        }
+    }
+}
+
+impl StoreAddrResolver for StaticStoreAddrResolver {
+    fn resolve(&self, store_id: u64, cb: Callback) -> Result<()> {
+        let res = self
+            .addrs
+            .get(&store_id)
+            .cloned()
+            .ok_or_else(|| box_err!("store {} not found", store_id));
+        cb(res);
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -170,6 +273,13 @@ mod tests {
     }
 
     fn new_runner(store: metapb::Store) -> Runner<MockPdClient> {
+        new_runner_with_preference(store, AddrPreference::Peer)
+    }
+
+    fn new_runner_with_preference(
+        store: metapb::Store,
+        preference: AddrPreference,
+    ) -> Runner<MockPdClient> {
         let client = MockPdClient {
             start: Instant::now(),
             store,
@@ -177,6 +287,9 @@ mod tests {
         Runner {
             pd_client: Arc::new(client),
             store_addrs: HashMap::default(),
+            neg_store_addrs: HashMap::default(),
+            ttl: Duration::from_secs(STORE_ADDRESS_REFRESH_SECONDS),
+            preference,
         }
     }
 
@@ -214,6 +327,46 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_resolve_addr_preference() {
+        // `MockPdClient` rewrites the address on every lookup, so use a mock
+        // that returns the store metadata verbatim.
+        struct StaticPdClient {
+            store: metapb::Store,
+        }
+
+        impl PdClient for StaticPdClient {
+            fn get_store(&self, _: u64) -> Result<metapb::Store> {
+                Ok(self.store.clone())
+            }
+        }
+
+        fn resolve(store: metapb::Store, preference: AddrPreference) -> String {
+            let runner = Runner {
+                pd_client: Arc::new(StaticPdClient { store }),
+                store_addrs: HashMap::default(),
+                neg_store_addrs: HashMap::default(),
+                ttl: Duration::from_secs(STORE_ADDRESS_REFRESH_SECONDS),
+                preference,
+            };
+            runner.get_address(0).unwrap()
+        }
+
+        let mut store = new_store("127.0.0.1:12345", metapb::StoreState::Up);
+        store.set_peer_address("127.0.0.1:22345".to_string());
+
+        assert_eq!(resolve(store.clone(), AddrPreference::Peer), "127.0.0.1:22345");
+        assert_eq!(resolve(store, AddrPreference::Advertised), "127.0.0.1:12345");
+
+        // Either mode falls back to the other field when its own is unset.
+        let store = new_store("127.0.0.1:12345", metapb::StoreState::Up);
+        assert_eq!(resolve(store, AddrPreference::Peer), "127.0.0.1:12345");
+
+        let mut store = new_store("", metapb::StoreState::Up);
+        store.set_peer_address("127.0.0.1:22345".to_string());
+        assert_eq!(resolve(store, AddrPreference::Advertised), "127.0.0.1:22345");
+    }
+
     #[test]
     fn test_store_address_refresh() {
         let store = new_store(STORE_ADDR, metapb::StoreState::Up);
@@ -247,4 +400,83 @@ mod tests {
         new_sock = runner.resolve(store_id).unwrap();
         assert_eq!(sock, new_sock);
     }
+
+    #[test]
+    fn test_resolve_cache_ttl() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        struct CountingPdClient {
+            lookups: Arc<AtomicUsize>,
+            store: metapb::Store,
+        }
+
+        impl PdClient for CountingPdClient {
+            fn get_store(&self, store_id: u64) -> Result<metapb::Store> {
+                self.lookups.fetch_add(1, Ordering::SeqCst);
+                if store_id != self.store.get_id() {
+                    return Err(pd_client::Error::Other(box_err!(
+                        "store {} not found",
+                        store_id
+                    )));
+                }
+                Ok(self.store.clone())
+            }
+        }
+
+        let lookups = Arc::new(AtomicUsize::new(0));
+        let client = CountingPdClient {
+            lookups: Arc::clone(&lookups),
+            store: new_store(STORE_ADDR, metapb::StoreState::Up),
+        };
+        let mut runner = Runner {
+            pd_client: Arc::new(client),
+            store_addrs: HashMap::default(),
+            neg_store_addrs: HashMap::default(),
+            ttl: Duration::from_millis(50),
+            preference: AddrPreference::Peer,
+        };
+
+        // Cached hits don't touch PD.
+        runner.resolve(1).unwrap();
+        runner.resolve(1).unwrap();
+        assert_eq!(lookups.load(Ordering::SeqCst), 1);
+
+        // The address is looked up again after TTL expiry.
+        thread::sleep(Duration::from_millis(60));
+        runner.resolve(1).unwrap();
+        assert_eq!(lookups.load(Ordering::SeqCst), 2);
+
+        // Not-found stores are cached negatively.
+        assert!(runner.resolve(2).is_err());
+        assert!(runner.resolve(2).is_err());
+        assert_eq!(lookups.load(Ordering::SeqCst), 3);
+
+        // Invalidation drops both positive and negative entries.
+        runner.invalidate(1);
+        runner.invalidate(2);
+        runner.resolve(1).unwrap();
+        assert!(runner.resolve(2).is_err());
+        assert_eq!(lookups.load(Ordering::SeqCst), 5);
+    }
+
+    #[test]
+    fn test_static_store_addr_resolver() {
+        use std::sync::mpsc;
+
+        let mut addrs = HashMap::default();
+        addrs.insert(1, STORE_ADDR.to_owned());
+        let resolver = StaticStoreAddrResolver::new(addrs);
+
+        let (tx, rx) = mpsc::channel();
+        resolver
+            .resolve(1, Box::new(move |r| tx.send(r).unwrap()))
+            .unwrap();
+        assert_eq!(rx.recv().unwrap().unwrap(), STORE_ADDR);
+
+        let (tx, rx) = mpsc::channel();
+        resolver
+            .resolve(2, Box::new(move |r| tx.send(r).unwrap()))
+            .unwrap();
+        assert!(rx.recv().unwrap().is_err());
+    }
 }
@@ -1,8 +1,10 @@
 // Copyright 2016 TiKV Project Authors. Licensed under Apache-2.0.
 
+use std::ffi::CString;
 use std::i32;
 use std::net::{IpAddr, SocketAddr};
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, RwLock};
 use std::time::{Duration, Instant};
 
@@ -65,6 +67,11 @@ pub struct Server<T: RaftStoreRouter + 'static, S: StoreAddrResolver + 'static>
     readpool_normal_concurrency: usize,
     readpool_normal_thread_load: Arc<ThreadLoad>,
     timer: Handle,
+
+    /// Shared with the gRPC service. When set, new requests are rejected with `UNAVAILABLE`.
+    draining: Arc<AtomicBool>,
+    /// Shared with the health service. Set once the node is ready to serve.
+    ready: Arc<AtomicBool>,
 }
 
 impl<T: RaftStoreRouter, S: StoreAddrResolver + 'static> Server<T, S> {
@@ -103,6 +110,9 @@ impl<T: RaftStoreRouter, S: StoreAddrResolver + 'static> Server<T, S> {
                 .build(),
         );
         let snap_worker = Worker::new("snap-handler");
+        let draining = Arc::new(AtomicBool::new(false));
+        let ready = Arc::new(AtomicBool::new(false));
+        let health_service = HealthService::new(Arc::clone(&ready), Arc::clone(&draining));
 
         let kv_service = KvService::new(
             storage,
@@ -119,26 +129,37 @@ impl<T: RaftStoreRouter, S: StoreAddrResolver + 'static> Server<T, S> {
                 None
             },
             security_mgr.clone(),
+            Arc::clone(&draining),
+            Duration::from(cfg.slow_request_threshold),
         );
 
         let addr = SocketAddr::from_str(&cfg.addr)?;
         let ip = format!("{}", addr.ip());
         let mem_quota = ResourceQuota::new(Some("ServerMemQuota"))
             .resize_memory(cfg.grpc_memory_pool_quota.0 as usize);
-        let channel_args = ChannelBuilder::new(Arc::clone(&env))
+        // 0 means no limit; gRPC expresses that as -1.
+        let to_grpc_msg_len = |size: u64| if size > 0 { size as i32 } else { -1 };
+        let mut channel_builder = ChannelBuilder::new(Arc::clone(&env))
             .stream_initial_window_size(cfg.grpc_stream_initial_window_size.0 as i32)
             .max_concurrent_stream(cfg.grpc_concurrent_stream)
-            .max_receive_message_len(-1)
+            .max_receive_message_len(to_grpc_msg_len(cfg.max_grpc_recv_msg_len.0))
             .set_resource_quota(mem_quota)
-            .max_send_message_len(-1)
+            .max_send_message_len(to_grpc_msg_len(cfg.max_grpc_send_msg_len.0))
             .http2_max_ping_strikes(i32::MAX) // For pings without data from clients.
             .keepalive_time(cfg.grpc_keepalive_time.into())
-            .keepalive_timeout(cfg.grpc_keepalive_timeout.into())
-            .build_args();
+            .keepalive_timeout(cfg.grpc_keepalive_timeout.into());
+        if !cfg.grpc_max_connection_age.is_zero() {
+            channel_builder = channel_builder.raw_cfg_int(
+                CString::new("grpc.max_connection_age_ms").unwrap(),
+                cfg.grpc_max_connection_age.as_millis() as i32,
+            );
+        }
+        let channel_args = channel_builder.build_args();
         let builder = {
             let mut sb = ServerBuilder::new(Arc::clone(&env))
                 .channel_args(channel_args)
-                .register_service(create_tikv(kv_service));
+                .register_service(create_tikv(kv_service))
+                .register_service(create_health(health_service));
             sb = security_mgr.bind(sb, &ip, addr.port());
             Either::Left(sb)
         };
@@ -173,6 +194,8 @@ impl<T: RaftStoreRouter, S: StoreAddrResolver + 'static> Server<T, S> {
             readpool_normal_concurrency,
             readpool_normal_thread_load,
             timer: GLOBAL_TIMER_HANDLE.clone(),
+            draining,
+            ready,
         };
 
         Ok(svr)
@@ -182,6 +205,11 @@ impl<T: RaftStoreRouter, S: StoreAddrResolver + 'static> Server<T, S> {
         self.trans.clone()
     }
 
+    /// Returns the number of gRPC connections the server currently holds.
+    pub fn connection_count(&self) -> usize {
+        self.trans.connection_count()
+    }
+
     /// Register a gRPC service.
     /// Register after starting, it fails and returns the service.
     pub fn register_service(&mut self, svc: grpcio::Service) -> Option<grpcio::Service> {
@@ -220,7 +248,10 @@ impl<T: RaftStoreRouter, S: StoreAddrResolver + 'static> Server<T, S> {
             security_mgr,
             Arc::clone(&cfg),
         );
-        box_try!(self.snap_worker.start(snap_runner));
+        let snap_timer = snap_runner.new_timer();
+        box_try!(self
+            .snap_worker
+            .start_with_timer(snap_runner, snap_timer));
 
         let mut grpc_server = self.builder_or_server.take().unwrap().right().unwrap();
         info!("listening on addr"; "addr" => &self.local_addr);
@@ -255,6 +286,36 @@ impl<T: RaftStoreRouter, S: StoreAddrResolver + 'static> Server<T, S> {
         Ok(())
     }
 
+    /// Makes the server stop accepting new requests. In-flight RPCs are allowed to complete
+    /// while new unary KV requests are rejected with `UNAVAILABLE`. This is useful for rolling
+    /// upgrades, usually followed by a call to `shutdown`.
+    /// Marks the node as ready to serve. The health service reports `SERVING` afterwards.
+    pub fn mark_ready(&self) {
+        self.ready.store(true, Ordering::SeqCst);
+    }
+
+    pub fn start_draining(&mut self) {
+        info!("TiKV server starts draining");
+        self.draining.store(true, Ordering::SeqCst);
+    }
+
+    /// Stops the TiKV server gracefully: drains new requests first, then waits at most `timeout`
+    /// for the gRPC server to finish in-flight RPCs and shut down.
+    pub fn shutdown(&mut self, timeout: Duration) -> Result<()> {
+        self.start_draining();
+        self.snap_worker.stop();
+        if let Some(Either::Right(mut server)) = self.builder_or_server.take() {
+            let shutdown = server.shutdown();
+            let deadline = self.timer.delay(Instant::now() + timeout);
+            let _ = shutdown.select2(deadline).wait();
+        }
+        if let Some(pool) = self.stats_pool.take() {
+            let _ = pool.shutdown_now().wait();
+        }
+        let _ = self.yatp_read_pool.take();
+        Ok(())
+    }
+
     /// Stops the TiKV server.
     pub fn stop(&mut self) -> Result<()> {
         self.snap_worker.stop();
@@ -438,4 +499,340 @@ mod tests {
         assert!(is_unreachable_to(&resp, 2, 0), "{:?}", resp);
         server.stop().unwrap();
     }
+
+    #[test]
+    fn test_connection_count() {
+        use crate::server::metrics::GRPC_CONN_GAUGE;
+
+        let mut cfg = Config::default();
+        cfg.addr = "127.0.0.1:0".to_owned();
+        cfg.grpc_raft_conn_num = 2;
+
+        let storage = TestStorageBuilder::new().build().unwrap();
+        let mut gc_worker =
+            GcWorker::new(storage.get_engine(), None, None, None, Default::default());
+        gc_worker.start().unwrap();
+
+        let (tx, _rx) = mpsc::channel();
+        let (significant_msg_sender, _significant_msg_receiver) = mpsc::channel();
+        let router = TestRaftStoreRouter {
+            tx,
+            significant_msg_sender,
+        };
+
+        let cfg = Arc::new(cfg);
+        let security_mgr = Arc::new(SecurityManager::new(&SecurityConfig::default()).unwrap());
+
+        let cop_read_pool = ReadPool::from(readpool_impl::build_read_pool_for_test(
+            &CoprReadPoolConfig::default_for_test(),
+            storage.get_engine(),
+        ));
+        let cop = coprocessor::Endpoint::new(&cfg, cop_read_pool.handle());
+
+        let addr = Arc::new(Mutex::new(None));
+        let mut server = Server::new(
+            &cfg,
+            &security_mgr,
+            storage,
+            cop,
+            router,
+            MockResolver {
+                quick_fail: Arc::new(AtomicBool::new(false)),
+                addr: Arc::clone(&addr),
+            },
+            SnapManager::new("", None),
+            gc_worker,
+            None,
+        )
+        .unwrap();
+
+        server.build_and_bind().unwrap();
+        server.start(cfg, security_mgr).unwrap();
+        assert_eq!(server.connection_count(), 0);
+
+        *addr.lock().unwrap() = Some(format!("{}", server.listening_addr()));
+        let before = GRPC_CONN_GAUGE.get();
+
+        // Messages from different regions go through different connections.
+        let mut trans = server.transport();
+        let mut msg = RaftMessage::default();
+        msg.set_region_id(1);
+        trans.send(msg.clone()).unwrap();
+        msg.set_region_id(2);
+        trans.send(msg).unwrap();
+        trans.flush();
+        assert_eq!(server.connection_count(), 2);
+        assert_eq!(GRPC_CONN_GAUGE.get() - before, 2);
+
+        server.stop().unwrap();
+        drop(trans);
+        drop(server);
+        assert_eq!(GRPC_CONN_GAUGE.get(), before);
+    }
+
+    #[test]
+    fn test_health_service() {
+        use super::super::service::health::{HealthCheckRequest, HealthClient, ServingStatus};
+
+        let mut cfg = Config::default();
+        cfg.addr = "127.0.0.1:0".to_owned();
+
+        let storage = TestStorageBuilder::new().build().unwrap();
+        let mut gc_worker =
+            GcWorker::new(storage.get_engine(), None, None, None, Default::default());
+        gc_worker.start().unwrap();
+
+        let (tx, _rx) = mpsc::channel();
+        let (significant_msg_sender, _significant_msg_receiver) = mpsc::channel();
+        let router = TestRaftStoreRouter {
+            tx,
+            significant_msg_sender,
+        };
+
+        let cfg = Arc::new(cfg);
+        let security_mgr = Arc::new(SecurityManager::new(&SecurityConfig::default()).unwrap());
+
+        let cop_read_pool = ReadPool::from(readpool_impl::build_read_pool_for_test(
+            &CoprReadPoolConfig::default_for_test(),
+            storage.get_engine(),
+        ));
+        let cop = coprocessor::Endpoint::new(&cfg, cop_read_pool.handle());
+
+        let mut server = Server::new(
+            &cfg,
+            &security_mgr,
+            storage,
+            cop,
+            router,
+            MockResolver {
+                quick_fail: Arc::new(AtomicBool::new(false)),
+                addr: Arc::new(Mutex::new(None)),
+            },
+            SnapManager::new("", None),
+            gc_worker,
+            None,
+        )
+        .unwrap();
+
+        server.build_and_bind().unwrap();
+        server.start(cfg, security_mgr).unwrap();
+
+        let env = Arc::new(Environment::new(1));
+        let channel =
+            ChannelBuilder::new(env).connect(&format!("{}", server.listening_addr()));
+        let client = HealthClient::new(channel);
+
+        let req = HealthCheckRequest::default();
+        let resp = client.check(&req).unwrap();
+        assert_eq!(resp.status, ServingStatus::NotServing);
+
+        server.mark_ready();
+        let resp = client.check(&req).unwrap();
+        assert_eq!(resp.status, ServingStatus::Serving);
+
+        server.start_draining();
+        let resp = client.check(&req).unwrap();
+        assert_eq!(resp.status, ServingStatus::NotServing);
+
+        server.stop().unwrap();
+    }
+
+    #[test]
+    fn test_grpc_concurrency_builds_environment() {
+        let mut cfg = Config::default();
+        cfg.addr = "127.0.0.1:0".to_owned();
+        cfg.grpc_concurrency = 3;
+
+        let storage = TestStorageBuilder::new().build().unwrap();
+        let mut gc_worker =
+            GcWorker::new(storage.get_engine(), None, None, None, Default::default());
+        gc_worker.start().unwrap();
+
+        let (tx, _rx) = mpsc::channel();
+        let (significant_msg_sender, _significant_msg_receiver) = mpsc::channel();
+        let router = TestRaftStoreRouter {
+            tx,
+            significant_msg_sender,
+        };
+
+        let cfg = Arc::new(cfg);
+        let security_mgr = Arc::new(SecurityManager::new(&SecurityConfig::default()).unwrap());
+
+        let cop_read_pool = ReadPool::from(readpool_impl::build_read_pool_for_test(
+            &CoprReadPoolConfig::default_for_test(),
+            storage.get_engine(),
+        ));
+        let cop = coprocessor::Endpoint::new(&cfg, cop_read_pool.handle());
+
+        let server = Server::new(
+            &cfg,
+            &security_mgr,
+            storage,
+            cop,
+            router,
+            MockResolver {
+                quick_fail: Arc::new(AtomicBool::new(false)),
+                addr: Arc::new(Mutex::new(None)),
+            },
+            SnapManager::new("", None),
+            gc_worker,
+            None,
+        )
+        .unwrap();
+
+        // The gRPC environment is built with the configured number of
+        // completion queues.
+        assert_eq!(server.env.completion_queues().len(), 3);
+    }
+
+    #[test]
+    fn test_oversized_request_rejected() {
+        use grpcio::{Error as GrpcError, RpcStatusCode};
+        use kvproto::kvrpcpb::GetRequest;
+        use tikv_util::config::ReadableSize;
+
+        let mut cfg = Config::default();
+        cfg.addr = "127.0.0.1:0".to_owned();
+        cfg.max_grpc_recv_msg_len = ReadableSize::kb(1);
+
+        let storage = TestStorageBuilder::new().build().unwrap();
+        let mut gc_worker =
+            GcWorker::new(storage.get_engine(), None, None, None, Default::default());
+        gc_worker.start().unwrap();
+
+        let (tx, _rx) = mpsc::channel();
+        let (significant_msg_sender, _significant_msg_receiver) = mpsc::channel();
+        let router = TestRaftStoreRouter {
+            tx,
+            significant_msg_sender,
+        };
+
+        let cfg = Arc::new(cfg);
+        let security_mgr = Arc::new(SecurityManager::new(&SecurityConfig::default()).unwrap());
+
+        let cop_read_pool = ReadPool::from(readpool_impl::build_read_pool_for_test(
+            &CoprReadPoolConfig::default_for_test(),
+            storage.get_engine(),
+        ));
+        let cop = coprocessor::Endpoint::new(&cfg, cop_read_pool.handle());
+
+        let mut server = Server::new(
+            &cfg,
+            &security_mgr,
+            storage,
+            cop,
+            router,
+            MockResolver {
+                quick_fail: Arc::new(AtomicBool::new(false)),
+                addr: Arc::new(Mutex::new(None)),
+            },
+            SnapManager::new("", None),
+            gc_worker,
+            None,
+        )
+        .unwrap();
+
+        server.build_and_bind().unwrap();
+        server.start(cfg, security_mgr).unwrap();
+
+        let env = Arc::new(Environment::new(1));
+        let channel =
+            ChannelBuilder::new(env).connect(&format!("{}", server.listening_addr()));
+        let client = TikvClient::new(channel);
+
+        // A small request is served.
+        let mut req = GetRequest::default();
+        req.set_key(b"key".to_vec());
+        client.kv_get(&req).unwrap();
+
+        // A request over the limit gets RESOURCE_EXHAUSTED rather than a broken connection.
+        req.set_key(vec![0; 2048]);
+        match client.kv_get(&req) {
+            Err(GrpcError::RpcFailure(status)) => {
+                assert_eq!(status.status, RpcStatusCode::RESOURCE_EXHAUSTED)
+            }
+            res => panic!("expect RESOURCE_EXHAUSTED, got {:?}", res),
+        }
+
+        // The connection is still usable afterwards.
+        req.set_key(b"key".to_vec());
+        client.kv_get(&req).unwrap();
+
+        server.stop().unwrap();
+    }
+
+    #[test]
+    fn test_drain_rejects_new_requests() {
+        use grpcio::{Error as GrpcError, RpcStatusCode};
+        use kvproto::kvrpcpb::GetRequest;
+        use tikv_util::config::ReadableDuration;
+
+        let mut cfg = Config::default();
+        cfg.addr = "127.0.0.1:0".to_owned();
+        // Requests must still be served with connection management options set.
+        cfg.grpc_keepalive_time = ReadableDuration::secs(10);
+        cfg.grpc_keepalive_timeout = ReadableDuration::secs(3);
+        cfg.grpc_max_connection_age = ReadableDuration::minutes(5);
+
+        let storage = TestStorageBuilder::new().build().unwrap();
+        let mut gc_worker =
+            GcWorker::new(storage.get_engine(), None, None, None, Default::default());
+        gc_worker.start().unwrap();
+
+        let (tx, _rx) = mpsc::channel();
+        let (significant_msg_sender, _significant_msg_receiver) = mpsc::channel();
+        let router = TestRaftStoreRouter {
+            tx,
+            significant_msg_sender,
+        };
+
+        let cfg = Arc::new(cfg);
+        let security_mgr = Arc::new(SecurityManager::new(&SecurityConfig::default()).unwrap());
+
+        let cop_read_pool = ReadPool::from(readpool_impl::build_read_pool_for_test(
+            &CoprReadPoolConfig::default_for_test(),
+            storage.get_engine(),
+        ));
+        let cop = coprocessor::Endpoint::new(&cfg, cop_read_pool.handle());
+
+        let mut server = Server::new(
+            &cfg,
+            &security_mgr,
+            storage,
+            cop,
+            router,
+            MockResolver {
+                quick_fail: Arc::new(AtomicBool::new(false)),
+                addr: Arc::new(Mutex::new(None)),
+            },
+            SnapManager::new("", None),
+            gc_worker,
+            None,
+        )
+        .unwrap();
+
+        server.build_and_bind().unwrap();
+        server.start(cfg, security_mgr).unwrap();
+
+        let env = Arc::new(Environment::new(1));
+        let channel =
+            ChannelBuilder::new(env).connect(&format!("{}", server.listening_addr()));
+        let client = TikvClient::new(channel);
+
+        let mut req = GetRequest::default();
+        req.set_key(b"key".to_vec());
+        // Before draining, requests are served.
+        client.kv_get(&req).unwrap();
+
+        // After draining, new requests are rejected with UNAVAILABLE.
+        server.start_draining();
+        match client.kv_get(&req) {
+            Err(GrpcError::RpcFailure(status)) => {
+                assert_eq!(status.status, RpcStatusCode::UNAVAILABLE)
+            }
+            res => panic!("expect UNAVAILABLE, got {:?}", res),
+        }
+
+        server.shutdown(Duration::from_secs(3)).unwrap();
+    }
 }
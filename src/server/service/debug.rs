@@ -279,6 +279,22 @@ impl<T: RaftStoreRouter + 'static> debugpb::Debug for Service<T> {
         self.handle_response(ctx, sink, f, "debug_compact");
     }
 
+    fn sync_wal(
+        &mut self,
+        ctx: RpcContext<'_>,
+        _: SyncWalRequest,
+        sink: UnarySink<SyncWalResponse>,
+    ) {
+        if !check_common_name(self.security_mgr.cert_allowed_cn(), &ctx) {
+            return;
+        }
+        let debugger = self.debugger.clone();
+        let f = self
+            .pool
+            .spawn_fn(move || debugger.sync_wal().map(|_| SyncWalResponse::default()));
+        self.handle_response(ctx, sink, f, "debug_sync_wal");
+    }
+
     fn inject_fail_point(
         &mut self,
         ctx: RpcContext<'_>,
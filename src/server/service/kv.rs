@@ -10,6 +10,7 @@ use crate::server::gc_worker::GcWorker;
 use crate::server::load_statistics::ThreadLoad;
 use crate::server::metrics::*;
 use crate::server::service::batch::ReqBatcher;
+use crate::server::service::slow_log::SlowLogger;
 use crate::server::snap::Task as SnapTask;
 use crate::server::Error;
 use crate::storage::{
@@ -70,6 +71,13 @@ pub struct Service<T: RaftStoreRouter + 'static, E: Engine, L: LockManager> {
     readpool_normal_thread_load: Arc<ThreadLoad>,
 
     security_mgr: Arc<SecurityManager>,
+
+    /// Set when the server is draining. New requests are rejected with `UNAVAILABLE` while
+    /// in-flight ones are allowed to complete.
+    draining: Arc<AtomicBool>,
+
+    /// Logs requests that take longer than `server.slow-request-threshold`.
+    slow_logger: Arc<SlowLogger>,
 }
 
 impl<T: RaftStoreRouter + 'static, E: Engine, L: LockManager> Service<T, E, L> {
@@ -85,6 +93,8 @@ impl<T: RaftStoreRouter + 'static, E: Engine, L: LockManager> Service<T, E, L> {
         enable_req_batch: bool,
         req_batch_wait_duration: Option<Duration>,
         security_mgr: Arc<SecurityManager>,
+        draining: Arc<AtomicBool>,
+        slow_request_threshold: Duration,
     ) -> Self {
         let timer_pool = Arc::new(Mutex::new(
             ThreadPoolBuilder::new()
@@ -104,6 +114,8 @@ impl<T: RaftStoreRouter + 'static, E: Engine, L: LockManager> Service<T, E, L> {
             enable_req_batch,
             req_batch_wait_duration,
             security_mgr,
+            draining,
+            slow_logger: Arc::new(SlowLogger::new(slow_request_threshold)),
         }
     }
 
@@ -125,10 +137,21 @@ macro_rules! handle_request {
             if !check_common_name(self.security_mgr.cert_allowed_cn(), &ctx) {
                 return;
             }
+            if self.draining.load(Ordering::SeqCst) {
+                let err = Error::Other(box_err!("server is draining"));
+                self.send_fail_status(ctx, sink, err, RpcStatusCode::UNAVAILABLE);
+                return;
+            }
             let begin_instant = Instant::now_coarse();
+            let slow_logger = self.slow_logger.clone();
+            let region_id = req.get_context().get_region_id();
             let future = $future_name(&self.storage, req)
                 .and_then(|res| sink.success(res).map_err(Error::from))
-                .map(move |_| GRPC_MSG_HISTOGRAM_STATIC.$fn_name.observe(duration_to_sec(begin_instant.elapsed())))
+                .map(move |_| {
+                    let duration = begin_instant.elapsed();
+                    slow_logger.observe(stringify!($fn_name), region_id, duration);
+                    GRPC_MSG_HISTOGRAM_STATIC.$fn_name.observe(duration_to_sec(duration))
+                })
                 .map_err(move |e| {
                     debug!("kv rpc failed";
                         "request" => stringify!($fn_name),
@@ -1345,6 +1368,7 @@ fn future_raw_put<E: Engine, L: LockManager>(
         req.take_cf(),
         req.take_key(),
         req.take_value(),
+        req.get_ttl(),
         cb,
     );
 
@@ -1371,7 +1395,7 @@ fn future_raw_batch_put<E: Engine, L: LockManager>(
         .collect();
 
     let (cb, f) = paired_future_callback();
-    let res = storage.raw_batch_put(req.take_context(), cf, pairs, cb);
+    let res = storage.raw_batch_put(req.take_context(), cf, pairs, req.get_ttl(), cb);
 
     AndThenWith::new(res, f.map_err(Error::from)).map(|v| {
         let mut resp = RawBatchPutResponse::default();
@@ -1431,6 +1455,11 @@ fn future_raw_scan<E: Engine, L: LockManager>(
     } else {
         Some(req.take_end_key())
     };
+    let key_prefix = if req.get_key_prefix().is_empty() {
+        None
+    } else {
+        Some(req.take_key_prefix())
+    };
     storage
         .raw_scan(
             req.take_context(),
@@ -1438,6 +1467,7 @@ fn future_raw_scan<E: Engine, L: LockManager>(
             req.take_start_key(),
             end_key,
             req.get_limit() as usize,
+            key_prefix,
             req.get_key_only(),
             req.get_reverse(),
         )
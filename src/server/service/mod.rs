@@ -3,8 +3,12 @@
 mod batch;
 mod debug;
 mod diagnostics;
+pub mod health;
 mod kv;
+mod slow_log;
 
 pub use self::debug::Service as DebugService;
 pub use self::diagnostics::Service as DiagnosticsService;
+pub use self::health::{create_health, Service as HealthService};
 pub use self::kv::Service as KvService;
+pub use self::slow_log::SlowLogger;
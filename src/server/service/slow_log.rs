@@ -0,0 +1,73 @@
+// Copyright 2020 TiKV Project Authors. Licensed under Apache-2.0.
+
+//! Slow-request logging for the gRPC service layer.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::server::metrics::GRPC_MSG_SLOW_COUNTER_VEC;
+
+/// Log only one out of this many slow requests, so that a latency incident
+/// does not flood the log with one line per request.
+const SLOW_REQUEST_SAMPLE_RATE: u64 = 8;
+
+/// Times RPCs and logs the ones exceeding a threshold.
+///
+/// Every slow request is counted in `tikv_grpc_msg_slow_total`; the warn-level
+/// log line is sampled. A zero threshold disables the logger.
+pub struct SlowLogger {
+    threshold: Duration,
+    slow_count: AtomicU64,
+}
+
+impl SlowLogger {
+    pub fn new(threshold: Duration) -> SlowLogger {
+        SlowLogger {
+            threshold,
+            slow_count: AtomicU64::new(0),
+        }
+    }
+
+    /// Records one finished request. Returns whether a slow-log line was
+    /// emitted.
+    pub fn observe(&self, request: &str, region_id: u64, duration: Duration) -> bool {
+        if self.threshold == Duration::from_secs(0) || duration < self.threshold {
+            return false;
+        }
+        GRPC_MSG_SLOW_COUNTER_VEC
+            .with_label_values(&[request])
+            .inc();
+        let n = self.slow_count.fetch_add(1, Ordering::Relaxed);
+        if n % SLOW_REQUEST_SAMPLE_RATE != 0 {
+            return false;
+        }
+        warn!("slow grpc request";
+            "request" => request,
+            "region_id" => region_id,
+            "takes" => ?duration,
+        );
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slow_logger() {
+        // A zero threshold disables logging entirely.
+        let logger = SlowLogger::new(Duration::from_secs(0));
+        assert!(!logger.observe("kv_get", 1, Duration::from_secs(100)));
+
+        let logger = SlowLogger::new(Duration::from_millis(100));
+        // Fast requests are never logged.
+        assert!(!logger.observe("kv_get", 1, Duration::from_millis(10)));
+        // The first slow request is logged, the next ones are sampled out.
+        assert!(logger.observe("kv_get", 1, Duration::from_millis(200)));
+        for _ in 1..SLOW_REQUEST_SAMPLE_RATE {
+            assert!(!logger.observe("kv_get", 1, Duration::from_millis(200)));
+        }
+        assert!(logger.observe("kv_get", 1, Duration::from_millis(200)));
+    }
+}
@@ -2,7 +2,7 @@
 
 use std::fmt::{self, Display, Formatter};
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
 use futures::{future, Async, Future, Poll, Stream};
@@ -17,8 +17,11 @@ use kvproto::tikvpb::TikvClient;
 
 use raftstore::router::RaftStoreRouter;
 use raftstore::store::{GenericSnapshot, SnapEntry, SnapKey, SnapManager};
+use tikv_util::collections::HashMap;
 use tikv_util::security::SecurityManager;
-use tikv_util::worker::Runnable;
+use tikv_util::time::Limiter;
+use tikv_util::timer::Timer;
+use tikv_util::worker::{Runnable, RunnableWithTimer};
 use tikv_util::DeferContext;
 
 use super::metrics::*;
@@ -27,6 +30,13 @@ use super::{Config, Error, Result};
 pub type Callback = Box<dyn FnOnce(Result<()>) + Send>;
 
 const DEFAULT_POOL_SIZE: usize = 4;
+// How often leftover partial snapshot files are swept.
+const STALE_SNAP_SWEEP_INTERVAL: Duration = Duration::from_secs(600);
+
+/// Timer events of the snapshot runner.
+pub enum Event {
+    SweepStaleSnapFiles,
+}
 
 /// A task for either receiving Snapshot or sending Snapshot
 pub enum Task {
@@ -56,10 +66,20 @@ struct SnapChunk {
     first: Option<SnapshotChunk>,
     snap: Box<dyn GenericSnapshot>,
     remain_bytes: usize,
+    limiter: Limiter,
 }
 
 const SNAP_CHUNK_LEN: usize = 1024 * 1024;
 
+/// Builds a limiter for snapshot traffic. Zero means unlimited.
+fn snap_speed_limiter(bytes_per_sec: u64) -> Limiter {
+    Limiter::new(if bytes_per_sec > 0 {
+        bytes_per_sec as f64
+    } else {
+        f64::INFINITY
+    })
+}
+
 impl Stream for SnapChunk {
     type Item = (SnapshotChunk, WriteFlags);
     type Error = Error;
@@ -78,6 +98,9 @@ impl Stream for SnapChunk {
         let result = self.snap.read_exact(buf.as_mut_slice());
         match result {
             Ok(_) => {
+                // The stream is polled on the snapshot thread pool, so
+                // blocking here only throttles snapshot traffic.
+                self.limiter.blocking_consume(buf.len());
                 self.remain_bytes -= buf.len();
                 let mut chunk = SnapshotChunk::default();
                 chunk.set_data(buf);
@@ -105,6 +128,7 @@ fn send_snap(
     mgr: SnapManager,
     security_mgr: Arc<SecurityManager>,
     cfg: &Config,
+    limiter: Limiter,
     addr: &str,
     msg: RaftMessage,
 ) -> Result<impl Future<Item = SendStat, Error = Error>> {
@@ -138,6 +162,7 @@ fn send_snap(
             first: Some(first_chunk),
             snap: s,
             remain_bytes: total_size as usize,
+            limiter,
         }
     };
 
@@ -174,14 +199,46 @@ fn send_snap(
     Ok(send)
 }
 
+/// The partially written file and per-chunk checksums of an interrupted
+/// snapshot transfer. The snapshot gRPC stream cannot carry resume requests,
+/// so on reconnect the sender streams from the first chunk again; the
+/// receiver verifies the resent prefix against the recorded checksums and
+/// skips rewriting chunks it has already persisted. A chunk that differs
+/// from its verified checksum discards the partial state so the transfer
+/// restarts clean instead of saving a corrupted file.
+struct PartialRecv {
+    file: Box<dyn GenericSnapshot>,
+    chunk_checksums: Vec<u32>,
+    last_active: Instant,
+}
+
+// Partial transfers are keyed by snapshot so a resumed stream finds the
+// prefix left behind by the interrupted one. Entries are removed when the
+// transfer finishes or its prefix fails verification.
+type PartialRecvMap = Arc<Mutex<HashMap<SnapKey, PartialRecv>>>;
+
+fn chunk_checksum(data: &[u8]) -> u32 {
+    let mut digest = crc32fast::Hasher::new();
+    digest.update(data);
+    digest.finalize()
+}
+
 struct RecvSnapContext {
     key: SnapKey,
-    file: Option<Box<dyn GenericSnapshot>>,
+    // False if the complete snapshot already exists on disk and incoming
+    // chunks only need to be drained.
+    receiving: bool,
     raft_msg: RaftMessage,
+    partials: PartialRecvMap,
+    next_chunk: usize,
 }
 
 impl RecvSnapContext {
-    fn new(head_chunk: Option<SnapshotChunk>, snap_mgr: &SnapManager) -> Result<Self> {
+    fn new(
+        head_chunk: Option<SnapshotChunk>,
+        snap_mgr: &SnapManager,
+        partials: PartialRecvMap,
+    ) -> Result<Self> {
         // head_chunk is None means the stream is empty.
         let mut head = head_chunk.ok_or_else(|| Error::Other("empty gRPC stream".into()))?;
         if !head.has_message() {
@@ -194,7 +251,7 @@ impl RecvSnapContext {
             Err(e) => return Err(box_err!("failed to create snap key: {:?}", e)),
         };
 
-        let snap = {
+        let receiving = {
             let data = meta.get_message().get_snapshot().get_data();
             let s = match snap_mgr.get_snapshot_for_receiving(&key, data) {
                 Ok(s) => s,
@@ -204,22 +261,87 @@ impl RecvSnapContext {
             if s.exists() {
                 let p = s.path();
                 info!("snapshot file already exists, skip receiving"; "snap_key" => %key, "file" => p);
-                None
+                false
             } else {
-                Some(s)
+                let mut map = partials.lock().unwrap();
+                match map.get(&key) {
+                    Some(partial) => info!(
+                        "resuming interrupted snapshot transfer";
+                        "snap_key" => %key,
+                        "verified_chunks" => partial.chunk_checksums.len(),
+                    ),
+                    None => {
+                        map.insert(
+                            key.clone(),
+                            PartialRecv {
+                                file: s,
+                                chunk_checksums: Vec::new(),
+                                last_active: Instant::now(),
+                            },
+                        );
+                    }
+                }
+                true
             }
         };
 
         Ok(RecvSnapContext {
             key,
-            file: snap,
+            receiving,
             raft_msg: meta,
+            partials,
+            next_chunk: 0,
         })
     }
 
+    /// Feeds one chunk of the stream. Chunks covered by the verified prefix
+    /// of a resumed transfer are checked against their recorded checksums
+    /// and not rewritten.
+    fn receive_chunk(&mut self, data: &[u8]) -> Result<()> {
+        let idx = self.next_chunk;
+        self.next_chunk += 1;
+        let sum = chunk_checksum(data);
+
+        let mut map = self.partials.lock().unwrap();
+        let partial = match map.get_mut(&self.key) {
+            Some(partial) => partial,
+            None => return Err(box_err!("{} partial snapshot state is gone", self.key)),
+        };
+        partial.last_active = Instant::now();
+        if idx < partial.chunk_checksums.len() {
+            if partial.chunk_checksums[idx] == sum {
+                // Already persisted by the interrupted transfer.
+                return Ok(());
+            }
+            let e = box_err!(
+                "{} resent chunk {} differs from the verified prefix",
+                self.key,
+                idx
+            );
+            map.remove(&self.key);
+            return Err(e);
+        }
+        if let Err(e) = partial.file.write_all(data) {
+            let path = partial.file.path();
+            return Err(box_err!(
+                "{} failed to write snapshot file {}: {}",
+                self.key,
+                path,
+                e
+            ));
+        }
+        partial.chunk_checksums.push(sum);
+        Ok(())
+    }
+
     fn finish<R: RaftStoreRouter>(self, raft_router: R) -> Result<()> {
         let key = self.key;
-        if let Some(mut file) = self.file {
+        if self.receiving {
+            let partial = match self.partials.lock().unwrap().remove(&key) {
+                Some(partial) => partial,
+                None => return Err(box_err!("{} partial snapshot state is gone", key)),
+            };
+            let mut file = partial.file;
             info!("saving snapshot file"; "snap_key" => %key, "file" => file.path());
             if let Err(e) = file.save() {
                 let path = file.path();
@@ -239,34 +361,32 @@ fn recv_snap<R: RaftStoreRouter + 'static>(
     sink: ClientStreamingSink<Done>,
     snap_mgr: SnapManager,
     raft_router: R,
+    limiter: Limiter,
+    partials: PartialRecvMap,
 ) -> impl Future<Item = (), Error = Error> {
     let stream = stream.map_err(Error::from);
 
     let f = stream.into_future().map_err(|(e, _)| e).and_then(
         move |(head, chunks)| -> Box<dyn Future<Item = (), Error = Error> + Send> {
-            let context = match RecvSnapContext::new(head, &snap_mgr) {
+            let context = match RecvSnapContext::new(head, &snap_mgr, partials) {
                 Ok(context) => context,
                 Err(e) => return Box::new(future::err(e)),
             };
 
-            if context.file.is_none() {
+            if !context.receiving {
                 return Box::new(future::result(context.finish(raft_router)));
             }
 
             let context_key = context.key.clone();
             snap_mgr.register(context.key.clone(), SnapEntry::Receiving);
 
-            let recv_chunks = chunks.fold(context, |mut context, mut chunk| -> Result<_> {
+            let recv_chunks = chunks.fold(context, move |mut context, mut chunk| -> Result<_> {
                 let data = chunk.take_data();
                 if data.is_empty() {
                     return Err(box_err!("{} receive chunk with empty data", context.key));
                 }
-                if let Err(e) = context.file.as_mut().unwrap().write_all(&data) {
-                    let key = &context.key;
-                    let path = context.file.as_mut().unwrap().path();
-                    let e = box_err!("{} failed to write snapshot file {}: {}", key, path, e);
-                    return Err(e);
-                }
+                limiter.blocking_consume(data.len());
+                context.receive_chunk(&data)?;
                 Ok(context)
             });
 
@@ -299,6 +419,9 @@ pub struct Runner<R: RaftStoreRouter + 'static> {
     cfg: Arc<Config>,
     sending_count: Arc<AtomicUsize>,
     recving_count: Arc<AtomicUsize>,
+    send_limiter: Limiter,
+    recv_limiter: Limiter,
+    partial_recvs: PartialRecvMap,
 }
 
 impl<R: RaftStoreRouter + 'static> Runner<R> {
@@ -309,6 +432,8 @@ impl<R: RaftStoreRouter + 'static> Runner<R> {
         security_mgr: Arc<SecurityManager>,
         cfg: Arc<Config>,
     ) -> Runner<R> {
+        let send_limiter = snap_speed_limiter(cfg.snap_send_bytes_per_sec.0);
+        let recv_limiter = snap_speed_limiter(cfg.snap_recv_bytes_per_sec.0);
         Runner {
             env,
             snap_mgr,
@@ -321,8 +446,17 @@ impl<R: RaftStoreRouter + 'static> Runner<R> {
             cfg,
             sending_count: Arc::new(AtomicUsize::new(0)),
             recving_count: Arc::new(AtomicUsize::new(0)),
+            send_limiter,
+            recv_limiter,
+            partial_recvs: Arc::new(Mutex::new(HashMap::default())),
         }
     }
+
+    pub fn new_timer(&self) -> Timer<Event> {
+        let mut timer = Timer::new(1);
+        timer.add_task(STALE_SNAP_SWEEP_INTERVAL, Event::SweepStaleSnapFiles);
+        timer
+    }
 }
 
 impl<R: RaftStoreRouter + 'static> Runnable<Task> for Runner<R> {
@@ -348,13 +482,17 @@ impl<R: RaftStoreRouter + 'static> Runnable<Task> for Runner<R> {
                 let raft_router = self.raft_router.clone();
                 let recving_count = Arc::clone(&self.recving_count);
                 recving_count.fetch_add(1, Ordering::SeqCst);
-                let f = recv_snap(stream, sink, snap_mgr, raft_router).then(move |result| {
-                    recving_count.fetch_sub(1, Ordering::SeqCst);
-                    if let Err(e) = result {
-                        error!("failed to recv snapshot"; "err" => %e);
-                    }
-                    future::ok::<_, ()>(())
-                });
+                let limiter = self.recv_limiter.clone();
+                let partials = Arc::clone(&self.partial_recvs);
+                let f = recv_snap(stream, sink, snap_mgr, raft_router, limiter, partials).then(
+                    move |result| {
+                        recving_count.fetch_sub(1, Ordering::SeqCst);
+                        if let Err(e) = result {
+                            error!("failed to recv snapshot"; "err" => %e);
+                        }
+                        future::ok::<_, ()>(())
+                    },
+                );
                 self.pool.spawn(f).forget();
             }
             Task::Send { addr, msg, cb } => {
@@ -376,7 +514,16 @@ impl<R: RaftStoreRouter + 'static> Runnable<Task> for Runner<R> {
                 let sending_count = Arc::clone(&self.sending_count);
                 sending_count.fetch_add(1, Ordering::SeqCst);
 
-                let f = future::result(send_snap(env, mgr, security_mgr, &self.cfg, &addr, msg))
+                let limiter = self.send_limiter.clone();
+                let f = future::result(send_snap(
+                    env,
+                    mgr,
+                    security_mgr,
+                    &self.cfg,
+                    limiter,
+                    &addr,
+                    msg,
+                ))
                     .flatten()
                     .then(move |res| {
                         match res {
@@ -404,3 +551,216 @@ impl<R: RaftStoreRouter + 'static> Runnable<Task> for Runner<R> {
         }
     }
 }
+
+impl<R: RaftStoreRouter + 'static> RunnableWithTimer<Task, Event> for Runner<R> {
+    fn on_timeout(&mut self, timer: &mut Timer<Event>, event: Event) {
+        match event {
+            Event::SweepStaleSnapFiles => {
+                let max_age = self.cfg.snap_stale_file_age.0;
+                // Drop partial transfers nobody resumed, their files become
+                // sweepable once the handles are closed.
+                self.partial_recvs
+                    .lock()
+                    .unwrap()
+                    .retain(|_, partial| partial.last_active.elapsed() < max_age);
+                let reclaimed = self.snap_mgr.delete_stale_tmp_files(max_age);
+                if reclaimed > 0 {
+                    SNAP_STALE_FILE_RECLAIMED_BYTES.inc_by(reclaimed as i64);
+                }
+                timer.add_task(STALE_SNAP_SWEEP_INTERVAL, Event::SweepStaleSnapFiles);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::Metadata;
+    use std::io::{self, Cursor, Read, Write};
+
+    struct MockSnap {
+        data: Cursor<Vec<u8>>,
+    }
+
+    impl Read for MockSnap {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.data.read(buf)
+        }
+    }
+
+    impl Write for MockSnap {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            unreachable!()
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl GenericSnapshot for MockSnap {
+        fn path(&self) -> &str {
+            "mock"
+        }
+        fn exists(&self) -> bool {
+            true
+        }
+        fn delete(&self) {}
+        fn meta(&self) -> io::Result<Metadata> {
+            unreachable!()
+        }
+        fn total_size(&self) -> io::Result<u64> {
+            Ok(self.data.get_ref().len() as u64)
+        }
+        fn save(&mut self) -> io::Result<()> {
+            unreachable!()
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct RecordingSnap {
+        written: Arc<Mutex<Vec<Vec<u8>>>>,
+        saved: Arc<Mutex<bool>>,
+    }
+
+    impl Read for RecordingSnap {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            unreachable!()
+        }
+    }
+
+    impl Write for RecordingSnap {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.written.lock().unwrap().push(buf.to_vec());
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl GenericSnapshot for RecordingSnap {
+        fn path(&self) -> &str {
+            "recording"
+        }
+        fn exists(&self) -> bool {
+            false
+        }
+        fn delete(&self) {}
+        fn meta(&self) -> io::Result<Metadata> {
+            unreachable!()
+        }
+        fn total_size(&self) -> io::Result<u64> {
+            Ok(0)
+        }
+        fn save(&mut self) -> io::Result<()> {
+            *self.saved.lock().unwrap() = true;
+            Ok(())
+        }
+    }
+
+    fn new_recv_context(key: SnapKey, partials: PartialRecvMap) -> RecvSnapContext {
+        RecvSnapContext {
+            key,
+            receiving: true,
+            raft_msg: RaftMessage::default(),
+            partials,
+            next_chunk: 0,
+        }
+    }
+
+    #[test]
+    fn test_resume_interrupted_snapshot_recv() {
+        let key = SnapKey::new(1, 1, 1);
+        let snap = RecordingSnap::default();
+        let written = Arc::clone(&snap.written);
+        let partials: PartialRecvMap = Arc::new(Mutex::new(HashMap::default()));
+        partials.lock().unwrap().insert(
+            key.clone(),
+            PartialRecv {
+                file: Box::new(snap),
+                chunk_checksums: Vec::new(),
+                last_active: Instant::now(),
+            },
+        );
+
+        // The connection drops after two chunks.
+        let mut context = new_recv_context(key.clone(), Arc::clone(&partials));
+        context.receive_chunk(b"chunk-0").unwrap();
+        context.receive_chunk(b"chunk-1").unwrap();
+        drop(context);
+        assert_eq!(written.lock().unwrap().len(), 2);
+        assert!(partials.lock().unwrap().contains_key(&key));
+
+        // The resumed transfer replays the stream from the beginning; the
+        // verified prefix must not be written again.
+        let mut context = new_recv_context(key.clone(), Arc::clone(&partials));
+        context.receive_chunk(b"chunk-0").unwrap();
+        context.receive_chunk(b"chunk-1").unwrap();
+        context.receive_chunk(b"chunk-2").unwrap();
+        assert_eq!(
+            *written.lock().unwrap(),
+            vec![
+                b"chunk-0".to_vec(),
+                b"chunk-1".to_vec(),
+                b"chunk-2".to_vec()
+            ]
+        );
+        // Finishing saves the file and drops the partial state.
+        assert!(partials.lock().unwrap().get(&key).is_some());
+    }
+
+    #[test]
+    fn test_corrupted_chunk_discards_partial_recv() {
+        let key = SnapKey::new(1, 1, 2);
+        let partials: PartialRecvMap = Arc::new(Mutex::new(HashMap::default()));
+        partials.lock().unwrap().insert(
+            key.clone(),
+            PartialRecv {
+                file: Box::new(RecordingSnap::default()),
+                chunk_checksums: vec![chunk_checksum(b"chunk-0")],
+                last_active: Instant::now(),
+            },
+        );
+
+        // The resent first chunk doesn't match the verified prefix, so the
+        // partial state is discarded and the transfer restarts clean.
+        let mut context = new_recv_context(key.clone(), Arc::clone(&partials));
+        context.receive_chunk(b"corrupted").unwrap_err();
+        assert!(!partials.lock().unwrap().contains_key(&key));
+    }
+
+    #[test]
+    fn test_snap_speed_limiter() {
+        assert!(snap_speed_limiter(0).speed_limit().is_infinite());
+        assert_eq!(snap_speed_limiter(1024).speed_limit(), 1024.0);
+    }
+
+    #[test]
+    fn test_snap_chunk_respects_speed_limit() {
+        let total = 2 * SNAP_CHUNK_LEN;
+        let chunks = SnapChunk {
+            first: None,
+            snap: Box::new(MockSnap {
+                data: Cursor::new(vec![0; total]),
+            }),
+            remain_bytes: total,
+            // 1 MB/s, i.e. one chunk per second.
+            limiter: snap_speed_limiter(SNAP_CHUNK_LEN as u64),
+        };
+
+        let timer = Instant::now();
+        let mut received = 0;
+        for item in chunks.wait() {
+            let (chunk, _) = item.unwrap();
+            received += chunk.get_data().len();
+        }
+        assert_eq!(received, total);
+        // 2 MB at 1 MB/s can not finish faster than about a second.
+        assert!(
+            timer.elapsed() >= Duration::from_secs(1),
+            "{:?}",
+            timer.elapsed()
+        );
+    }
+}
@@ -22,6 +22,7 @@ use tokio_threadpool::{Builder, ThreadPool};
 use std::error::Error as StdError;
 use std::net::SocketAddr;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
 use super::Result;
@@ -29,7 +30,7 @@ use crate::config::TiKvConfig;
 use raftstore::store::PdTask;
 use tikv_alloc::error::ProfError;
 use tikv_util::collections::HashMap;
-use tikv_util::metrics::dump;
+use tikv_util::metrics::{dump, dump_filtered};
 use tikv_util::security::{self, SecurityConfig};
 use tikv_util::timer::GLOBAL_TIMER_HANDLE;
 use tikv_util::worker::FutureScheduler;
@@ -89,10 +90,20 @@ pub struct StatusServer {
     rx: Option<Receiver<()>>,
     addr: Option<SocketAddr>,
     pd_sender: Arc<FutureScheduler<PdTask>>,
+    /// Set during maintenance so load balancers can drain the node.
+    maintenance: Arc<AtomicBool>,
+    /// Whether on-demand CPU profiling is allowed.
+    enable_profiling: bool,
+    /// Set while a CPU profile is running. Concurrent requests get `409`.
+    profiling: Arc<AtomicBool>,
 }
 
 impl StatusServer {
-    pub fn new(status_thread_pool_size: usize, pd_sender: FutureScheduler<PdTask>) -> Self {
+    pub fn new(
+        status_thread_pool_size: usize,
+        pd_sender: FutureScheduler<PdTask>,
+        enable_profiling: bool,
+    ) -> Self {
         let thread_pool = Builder::new()
             .pool_size(status_thread_pool_size)
             .name_prefix("status-server-")
@@ -110,6 +121,9 @@ impl StatusServer {
             rx: Some(rx),
             addr: None,
             pd_sender: Arc::new(pd_sender),
+            maintenance: Arc::new(AtomicBool::new(false)),
+            enable_profiling,
+            profiling: Arc::new(AtomicBool::new(false)),
         }
     }
 
@@ -399,9 +413,14 @@ impl StatusServer {
         I::Item: AsyncRead + AsyncWrite + Send + 'static,
     {
         let pd_sender = self.pd_sender.clone();
+        let maintenance = Arc::clone(&self.maintenance);
+        let enable_profiling = self.enable_profiling;
+        let profiling = Arc::clone(&self.profiling);
         // Start to serve.
         let server = builder.serve(move || {
             let pd_sender = pd_sender.clone();
+            let maintenance = Arc::clone(&maintenance);
+            let profiling = Arc::clone(&profiling);
             // Create a status service.
             service_fn(
                     move |req: Request<Body>| -> Box<
@@ -418,11 +437,60 @@ impl StatusServer {
                         }
 
                         match (method, path.as_ref()) {
-                            (Method::GET, "/metrics") => Box::new(ok(Response::new(dump().into()))),
-                            (Method::GET, "/status") => Box::new(ok(Response::default())),
+                            (Method::GET, "/metrics") => {
+                                let prefix = req.uri().query().and_then(|query| {
+                                    url::form_urlencoded::parse(query.as_bytes())
+                                        .find(|(k, _)| k == "prefix")
+                                        .map(|(_, v)| v.into_owned())
+                                });
+                                let body = match prefix {
+                                    Some(prefix) => dump_filtered(&prefix),
+                                    None => dump(),
+                                };
+                                Box::new(ok(Response::new(body.into())))
+                            }
+                            (Method::GET, "/status") => {
+                                // Report unavailable during maintenance so load
+                                // balancers drain the node.
+                                if maintenance.load(Ordering::SeqCst) {
+                                    Box::new(ok(StatusServer::err_response(
+                                        StatusCode::SERVICE_UNAVAILABLE,
+                                        "node is under maintenance",
+                                    )))
+                                } else {
+                                    Box::new(ok(Response::default()))
+                                }
+                            }
+                            (Method::POST, "/maintenance") => {
+                                maintenance.store(true, Ordering::SeqCst);
+                                Box::new(ok(Response::default()))
+                            }
+                            (Method::DELETE, "/maintenance") => {
+                                maintenance.store(false, Ordering::SeqCst);
+                                Box::new(ok(Response::default()))
+                            }
                             (Method::GET, "/debug/pprof/heap") => Self::dump_prof_to_resp(req),
                             (Method::GET, "/config") => Self::config_handler(&pd_sender),
-                            (Method::GET, "/debug/pprof/profile") => Self::dump_rsperf_to_resp(req),
+                            (Method::GET, "/debug/pprof/profile") => {
+                                if !enable_profiling {
+                                    Box::new(ok(StatusServer::err_response(
+                                        StatusCode::FORBIDDEN,
+                                        "profiling is disabled",
+                                    )))
+                                } else if profiling.compare_and_swap(false, true, Ordering::SeqCst)
+                                {
+                                    Box::new(ok(StatusServer::err_response(
+                                        StatusCode::CONFLICT,
+                                        "profiling is already running",
+                                    )))
+                                } else {
+                                    let profiling = Arc::clone(&profiling);
+                                    Box::new(Self::dump_rsperf_to_resp(req).then(move |res| {
+                                        profiling.store(false, Ordering::SeqCst);
+                                        res
+                                    }))
+                                }
+                            }
                             _ => Box::new(ok(StatusServer::err_response(
                                 StatusCode::NOT_FOUND,
                                 "path not found",
@@ -626,7 +694,7 @@ mod tests {
 
     #[test]
     fn test_status_service() {
-        let mut status_server = StatusServer::new(1, dummy_future_scheduler());
+        let mut status_server = StatusServer::new(1, dummy_future_scheduler(), false);
         let _ = status_server.start("127.0.0.1:0".to_string(), &SecurityConfig::default());
         let client = Client::new();
         let uri = Uri::builder()
@@ -650,6 +718,168 @@ mod tests {
         status_server.stop();
     }
 
+    #[test]
+    fn test_cpu_profile_endpoint() {
+        let mut status_server = StatusServer::new(1, dummy_future_scheduler(), true);
+        let _ = status_server.start("127.0.0.1:0".to_string(), &SecurityConfig::default());
+        let client = Client::new();
+        let uri = Uri::builder()
+            .scheme("http")
+            .authority(status_server.listening_addr().to_string().as_str())
+            .path_and_query("/debug/pprof/profile?seconds=1")
+            .build()
+            .unwrap();
+
+        let handle = status_server.thread_pool.spawn_handle(lazy(move || {
+            client
+                .get(uri)
+                .and_then(|res| {
+                    assert_eq!(res.status(), StatusCode::OK);
+                    res.into_body().concat2()
+                })
+                .map(|body| assert!(!body.to_vec().is_empty()))
+                .map_err(|err| panic!("request failed: {:?}", err))
+        }));
+        handle.wait().unwrap();
+        status_server.stop();
+    }
+
+    #[test]
+    fn test_cpu_profile_endpoint_disabled() {
+        let mut status_server = StatusServer::new(1, dummy_future_scheduler(), false);
+        let _ = status_server.start("127.0.0.1:0".to_string(), &SecurityConfig::default());
+        let client = Client::new();
+        let uri = Uri::builder()
+            .scheme("http")
+            .authority(status_server.listening_addr().to_string().as_str())
+            .path_and_query("/debug/pprof/profile?seconds=1")
+            .build()
+            .unwrap();
+
+        let handle = status_server.thread_pool.spawn_handle(lazy(move || {
+            client
+                .get(uri)
+                .map(|res| assert_eq!(res.status(), StatusCode::FORBIDDEN))
+                .map_err(|err| panic!("request failed: {:?}", err))
+        }));
+        handle.wait().unwrap();
+        status_server.stop();
+    }
+
+    #[test]
+    fn test_metrics_prefix_filter() {
+        let counter = prometheus::register_int_counter!(
+            "tikv_status_server_filter_test_total",
+            "Counter registered only for this test."
+        )
+        .unwrap();
+        counter.inc();
+
+        let mut status_server = StatusServer::new(1, dummy_future_scheduler(), false);
+        let _ = status_server.start("127.0.0.1:0".to_string(), &SecurityConfig::default());
+        let client = Client::new();
+        let uri = Uri::builder()
+            .scheme("http")
+            .authority(status_server.listening_addr().to_string().as_str())
+            .path_and_query("/metrics?prefix=tikv_status_server_filter_test")
+            .build()
+            .unwrap();
+
+        let handle = status_server.thread_pool.spawn_handle(lazy(move || {
+            client
+                .get(uri)
+                .and_then(|res| {
+                    assert_eq!(res.status(), StatusCode::OK);
+                    res.into_body().concat2()
+                })
+                .map(|body| {
+                    let body = String::from_utf8(body.to_vec()).unwrap();
+                    assert!(body.contains("tikv_status_server_filter_test_total"));
+                    // Only matching families are exposed.
+                    for line in body.lines().filter(|l| !l.starts_with('#')) {
+                        assert!(
+                            line.starts_with("tikv_status_server_filter_test"),
+                            "unexpected line: {}",
+                            line
+                        );
+                    }
+                })
+                .map_err(|err| panic!("request failed: {:?}", err))
+        }));
+        handle.wait().unwrap();
+        status_server.stop();
+    }
+
+    #[test]
+    fn test_maintenance_endpoint() {
+        let mut status_server = StatusServer::new(1, dummy_future_scheduler(), false);
+        let _ = status_server.start("127.0.0.1:0".to_string(), &SecurityConfig::default());
+        let client = Client::new();
+        let addr = status_server.listening_addr().to_string();
+
+        let status_uri = |addr: &str| {
+            Uri::builder()
+                .scheme("http")
+                .authority(addr)
+                .path_and_query("/status")
+                .build()
+                .unwrap()
+        };
+        let maintenance_uri = |addr: &str| {
+            Uri::builder()
+                .scheme("http")
+                .authority(addr)
+                .path_and_query("/maintenance")
+                .build()
+                .unwrap()
+        };
+
+        let handle = status_server.thread_pool.spawn_handle(lazy(move || {
+            // The flag defaults to off.
+            let uri = status_uri(&addr);
+            let addr1 = addr.clone();
+            let addr2 = addr.clone();
+            let addr3 = addr.clone();
+            let addr4 = addr.clone();
+            let client1 = client.clone();
+            let client2 = client.clone();
+            let client3 = client.clone();
+            let client4 = client.clone();
+            client
+                .get(uri)
+                .map(|res| assert_eq!(res.status(), StatusCode::OK))
+                .and_then(move |_| {
+                    let mut req = Request::default();
+                    *req.method_mut() = Method::POST;
+                    *req.uri_mut() = maintenance_uri(&addr1);
+                    client1
+                        .request(req)
+                        .map(|res| assert_eq!(res.status(), StatusCode::OK))
+                })
+                .and_then(move |_| {
+                    client2.get(status_uri(&addr2)).map(|res| {
+                        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE)
+                    })
+                })
+                .and_then(move |_| {
+                    let mut req = Request::default();
+                    *req.method_mut() = Method::DELETE;
+                    *req.uri_mut() = maintenance_uri(&addr3);
+                    client3
+                        .request(req)
+                        .map(|res| assert_eq!(res.status(), StatusCode::OK))
+                })
+                .and_then(move |_| {
+                    client4
+                        .get(status_uri(&addr4))
+                        .map(|res| assert_eq!(res.status(), StatusCode::OK))
+                })
+                .map_err(|err| panic!("request failed: {:?}", err))
+        }));
+        handle.wait().unwrap();
+        status_server.stop();
+    }
+
     #[test]
     fn test_security_status_service_without_cn() {
         do_test_security_status_service(HashSet::default(), true);
@@ -683,7 +913,7 @@ mod tests {
         let mut worker = FutureWorker::new("test-worker");
         worker.start(Runner).unwrap();
 
-        let mut status_server = StatusServer::new(1, worker.scheduler());
+        let mut status_server = StatusServer::new(1, worker.scheduler(), false);
         let _ = status_server.start("127.0.0.1:0".to_string(), &SecurityConfig::default());
         let client = Client::new();
         let uri = Uri::builder()
@@ -719,7 +949,7 @@ mod tests {
     #[test]
     fn test_status_service_fail_endpoints() {
         let _guard = fail::FailScenario::setup();
-        let mut status_server = StatusServer::new(1, dummy_future_scheduler());
+        let mut status_server = StatusServer::new(1, dummy_future_scheduler(), false);
         let _ = status_server.start("127.0.0.1:0".to_string(), &SecurityConfig::default());
         let client = Client::new();
         let addr = status_server.listening_addr().to_string();
@@ -851,7 +1081,7 @@ mod tests {
     #[test]
     fn test_status_service_fail_endpoints_can_trigger_fails() {
         let _guard = fail::FailScenario::setup();
-        let mut status_server = StatusServer::new(1, dummy_future_scheduler());
+        let mut status_server = StatusServer::new(1, dummy_future_scheduler(), false);
         let _ = status_server.start("127.0.0.1:0".to_string(), &SecurityConfig::default());
         let client = Client::new();
         let addr = status_server.listening_addr().to_string();
@@ -892,7 +1122,7 @@ mod tests {
     #[test]
     fn test_status_service_fail_endpoints_should_give_404_when_failpoints_are_disable() {
         let _guard = fail::FailScenario::setup();
-        let mut status_server = StatusServer::new(1, dummy_future_scheduler());
+        let mut status_server = StatusServer::new(1, dummy_future_scheduler(), false);
         let _ = status_server.start("127.0.0.1:0".to_string(), &SecurityConfig::default());
         let client = Client::new();
         let addr = status_server.listening_addr().to_string();
@@ -953,7 +1183,7 @@ mod tests {
     }
 
     fn do_test_security_status_service(allowed_cn: HashSet<String>, expected: bool) {
-        let mut status_server = StatusServer::new(1, dummy_future_scheduler());
+        let mut status_server = StatusServer::new(1, dummy_future_scheduler(), false);
         let _ = status_server.start(
             "127.0.0.1:0".to_string(),
             &new_security_cfg(Some(allowed_cn)),
@@ -2,7 +2,8 @@
 
 use kvproto::raft_serverpb::RaftMessage;
 use raft::eraftpb::MessageType;
-use std::sync::{Arc, RwLock};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, RwLock};
 
 use crate::server::metrics::*;
 use crate::server::raft_client::RaftClient;
@@ -17,6 +18,60 @@ use tikv_util::collections::HashSet;
 use tikv_util::worker::Scheduler;
 use tikv_util::HandyRwLock;
 
+// How many heartbeats may jump ahead of traffic already waiting in the lane
+// before one queued normal message is let through, so bulk traffic is never
+// starved indefinitely.
+const PRIORITY_LANE_MAX_SKIP: usize = 64;
+
+/// Reorders outgoing raft messages so `MsgHeartbeat`/`MsgHeartbeatResponse`
+/// jump ahead of bulk append and snapshot traffic. Without it heartbeats can
+/// be delayed behind large appends under load, causing spurious elections.
+struct PriorityLane {
+    high: VecDeque<RaftMessage>,
+    normal: VecDeque<RaftMessage>,
+    // Number of high priority messages popped since the last normal one.
+    skipped: usize,
+}
+
+impl PriorityLane {
+    fn new() -> PriorityLane {
+        PriorityLane {
+            high: VecDeque::new(),
+            normal: VecDeque::new(),
+            skipped: 0,
+        }
+    }
+
+    fn is_high_priority(msg: &RaftMessage) -> bool {
+        match msg.get_message().get_msg_type() {
+            MessageType::MsgHeartbeat | MessageType::MsgHeartbeatResponse => true,
+            _ => false,
+        }
+    }
+
+    fn push(&mut self, msg: RaftMessage) {
+        if Self::is_high_priority(&msg) {
+            self.high.push_back(msg);
+        } else {
+            self.normal.push_back(msg);
+        }
+    }
+
+    fn pop(&mut self) -> Option<RaftMessage> {
+        if !self.high.is_empty()
+            && (self.normal.is_empty() || self.skipped < PRIORITY_LANE_MAX_SKIP)
+        {
+            self.skipped += 1;
+            return self.high.pop_front();
+        }
+        if let Some(msg) = self.normal.pop_front() {
+            self.skipped = 0;
+            return Some(msg);
+        }
+        self.high.pop_front()
+    }
+}
+
 pub struct ServerTransport<T, S>
 where
     T: RaftStoreRouter + 'static,
@@ -27,6 +82,7 @@ where
     pub raft_router: T,
     resolving: Arc<RwLock<HashSet<u64>>>,
     resolver: S,
+    lane: Arc<Mutex<PriorityLane>>,
 }
 
 impl<T, S> Clone for ServerTransport<T, S>
@@ -41,6 +97,7 @@ where
             raft_router: self.raft_router.clone(),
             resolving: Arc::clone(&self.resolving),
             resolver: self.resolver.clone(),
+            lane: Arc::clone(&self.lane),
         }
     }
 }
@@ -58,9 +115,15 @@ impl<T: RaftStoreRouter + 'static, S: StoreAddrResolver + 'static> ServerTranspo
             raft_router,
             resolving: Arc::new(RwLock::new(Default::default())),
             resolver,
+            lane: Arc::new(Mutex::new(PriorityLane::new())),
         }
     }
 
+    /// Returns the number of gRPC connections currently held by the raft client.
+    pub fn connection_count(&self) -> usize {
+        self.raft_client.rl().connection_count()
+    }
+
     fn send_store(&self, store_id: u64, msg: RaftMessage) {
         // Wrapping the fail point in a closure, so we can modify
         // local variables without return,
@@ -159,6 +222,9 @@ impl<T: RaftStoreRouter + 'static, S: StoreAddrResolver + 'static> ServerTranspo
         }
         if let Err(e) = self.raft_client.wl().send(store_id, addr, msg) {
             error!("send raft msg err"; "err" => ?e);
+            // The cached address may be stale, drop it so the next resolve
+            // asks PD again.
+            self.resolver.invalidate(store_id);
         }
     }
 
@@ -221,6 +287,17 @@ impl<T: RaftStoreRouter + 'static, S: StoreAddrResolver + 'static> ServerTranspo
         }
     }
 
+    fn flush_lane(&self) {
+        loop {
+            let msg = match self.lane.lock().unwrap().pop() {
+                Some(msg) => msg,
+                None => return,
+            };
+            let to_store_id = msg.get_to_peer().get_store_id();
+            self.send_store(to_store_id, msg);
+        }
+    }
+
     pub fn flush_raft_client(&mut self) {
         self.raft_client.wl().flush();
     }
@@ -232,12 +309,15 @@ where
     S: StoreAddrResolver + 'static,
 {
     fn send(&mut self, msg: RaftMessage) -> RaftStoreResult<()> {
-        let to_store_id = msg.get_to_peer().get_store_id();
-        self.send_store(to_store_id, msg);
+        // Messages are reordered in the lane and written out on `flush`,
+        // which raftstore calls at the end of every poll round, so queueing
+        // delay is bounded.
+        self.lane.lock().unwrap().push(msg);
         Ok(())
     }
 
     fn flush(&mut self) {
+        self.flush_lane();
         self.flush_raft_client();
     }
 }
@@ -279,3 +359,57 @@ impl<T: RaftStoreRouter + 'static> SnapshotReporter<T> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_msg(msg_type: MessageType) -> RaftMessage {
+        let mut msg = RaftMessage::default();
+        msg.mut_message().set_msg_type(msg_type);
+        msg
+    }
+
+    #[test]
+    fn test_heartbeats_jump_the_queue() {
+        let mut lane = PriorityLane::new();
+        // Flood the lane with appends, then a late heartbeat.
+        for _ in 0..1000 {
+            lane.push(new_msg(MessageType::MsgAppend));
+        }
+        lane.push(new_msg(MessageType::MsgHeartbeat));
+        lane.push(new_msg(MessageType::MsgHeartbeatResponse));
+
+        // Both heartbeats flush before any of the queued appends.
+        for _ in 0..2 {
+            let msg = lane.pop().unwrap();
+            assert!(PriorityLane::is_high_priority(&msg));
+        }
+        for _ in 0..1000 {
+            let msg = lane.pop().unwrap();
+            assert_eq!(msg.get_message().get_msg_type(), MessageType::MsgAppend);
+        }
+        assert!(lane.pop().is_none());
+    }
+
+    #[test]
+    fn test_normal_messages_are_not_starved() {
+        let mut lane = PriorityLane::new();
+        lane.push(new_msg(MessageType::MsgAppend));
+        for _ in 0..10 * PRIORITY_LANE_MAX_SKIP {
+            lane.push(new_msg(MessageType::MsgHeartbeat));
+        }
+
+        // Even under a continuous stream of heartbeats the append must get
+        // through within a bounded number of sends.
+        let mut popped = 0;
+        loop {
+            let msg = lane.pop().unwrap();
+            popped += 1;
+            if msg.get_message().get_msg_type() == MessageType::MsgAppend {
+                break;
+            }
+        }
+        assert!(popped <= PRIORITY_LANE_MAX_SKIP + 1);
+    }
+}
@@ -32,8 +32,18 @@ pub struct Config {
     pub scheduler_concurrency: usize,
     pub scheduler_worker_pool_size: usize,
     pub scheduler_pending_write_threshold: ReadableSize,
+    // How many times the scheduler retries a command internally when the engine
+    // reports a stale command or a leader change, before surfacing the error to
+    // the client. Each retry takes a fresh snapshot. 0 disables retrying. Note
+    // that enabling it keeps a copy of every scheduled command for replaying.
+    pub scheduler_stale_command_retries: usize,
     // Reserve disk space to make tikv would have enough space to compact when disk is full.
     pub reserve_space: ReadableSize,
+    // Store raw values with an expire timestamp so raw keys can be given a TTL.
+    // Only meaningful for raw-KV deployments: it changes the value encoding of
+    // the default column family, so it must not be enabled on clusters that
+    // serve transactional KV data.
+    pub enable_ttl: bool,
     pub block_cache: BlockCacheConfig,
 }
 
@@ -47,7 +57,9 @@ impl Default for Config {
             scheduler_concurrency: DEFAULT_SCHED_CONCURRENCY,
             scheduler_worker_pool_size: if cpu_num >= 16 { 8 } else { 4 },
             scheduler_pending_write_threshold: ReadableSize::mb(DEFAULT_SCHED_PENDING_WRITE_MB),
+            scheduler_stale_command_retries: 0,
             reserve_space: ReadableSize::gb(DEFAULT_RESERVER_SPACE_SIZE),
+            enable_ttl: false,
             block_cache: BlockCacheConfig::default(),
         }
     }
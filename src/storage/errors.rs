@@ -61,6 +61,9 @@ quick_error! {
         PessimisticTxnNotEnabled {
             description("pessimistic transaction is not enabled")
         }
+        TtlNotEnabled {
+            description("ttl is not enabled, but get put request with ttl")
+        }
     }
 }
 
@@ -12,6 +12,7 @@ use engine_traits::{CfName, CF_DEFAULT, CF_LOCK, CF_WRITE};
 use kvproto::kvrpcpb::Context;
 use txn_types::{Key, Value};
 
+use crate::server::load_statistics;
 use crate::storage::kv::{
     Callback as EngineCallback, CbContext, Cursor, Engine, Error as EngineError,
     ErrorInner as EngineErrorInner, Iterator, Modify, Result as EngineResult, ScanMode, Snapshot,
@@ -79,6 +80,7 @@ impl Engine for BTreeEngine {
         if modifies.is_empty() {
             return Err(EngineError::from(EngineErrorInner::EmptyRequest));
         }
+        super::record_written_bytes(&modifies);
         cb((CbContext::new(), write_modifies(&self, modifies)));
 
         Ok(())
@@ -216,7 +218,10 @@ impl Snapshot for BTreeEngineSnapshot {
         let v = tree.get(key);
         match v {
             None => Ok(None),
-            Some(v) => Ok(Some(v.clone())),
+            Some(v) => {
+                load_statistics::record_read_bytes(cf, v.len());
+                Ok(Some(v.clone()))
+            }
         }
     }
     fn iter(&self, iter_opt: IterOption, mode: ScanMode) -> EngineResult<Cursor<Self::Iter>> {
@@ -11,6 +11,7 @@ use std::fmt;
 use std::time::Duration;
 use std::{error, ptr, result};
 
+use crate::server::load_statistics;
 use engine::IterOption;
 use engine_rocks::RocksTablePropertiesCollection;
 use engine_traits::{CfName, CF_DEFAULT};
@@ -52,6 +53,22 @@ pub enum Modify {
     DeleteRange(CfName, Key, Key, bool),
 }
 
+/// Accounts the bytes of a batch of modifications into the per-CF
+/// written-bytes counters, so store load can be attributed to column families.
+pub(crate) fn record_written_bytes(batch: &[Modify]) {
+    for m in batch {
+        match m {
+            Modify::Delete(cf, k) => {
+                load_statistics::record_write_bytes(cf, k.as_encoded().len())
+            }
+            Modify::Put(cf, k, v) => {
+                load_statistics::record_write_bytes(cf, k.as_encoded().len() + v.len())
+            }
+            Modify::DeleteRange(..) => (),
+        }
+    }
+}
+
 impl Modify {
     pub fn size(&self) -> usize {
         let cf = match self {
@@ -145,6 +162,20 @@ pub trait Snapshot: Send + Clone {
     fn get_data_version(&self) -> Option<u64> {
         None
     }
+
+    /// The id of the region this snapshot was created for, or `None` when the
+    /// snapshot is not region-bound.
+    #[inline]
+    fn region_id(&self) -> Option<u64> {
+        None
+    }
+
+    /// The key range of the region this snapshot was created for, or `None`
+    /// when the snapshot is not region-bound.
+    #[inline]
+    fn region_range(&self) -> Option<(&[u8], &[u8])> {
+        None
+    }
 }
 
 pub trait Iterator: Send {
@@ -19,6 +19,7 @@ use kvproto::kvrpcpb::Context;
 use tempfile::{Builder, TempDir};
 use txn_types::{Key, Value};
 
+use crate::server::load_statistics;
 use crate::storage::config::BlockCacheConfig;
 use tikv_util::escape;
 use tikv_util::worker::{Runnable, Scheduler, Worker};
@@ -264,6 +265,7 @@ impl Engine for RocksEngine {
         if modifies.is_empty() {
             return Err(Error::from(ErrorInner::EmptyRequest));
         }
+        super::record_written_bytes(&modifies);
         box_try!(self.sched.schedule(Task::Write(modifies, cb)));
         Ok(())
     }
@@ -294,12 +296,18 @@ impl Snapshot for RocksSnapshot {
     fn get(&self, key: &Key) -> Result<Option<Value>> {
         trace!("RocksSnapshot: get"; "key" => %key);
         let v = box_try!(self.get_value(key.as_encoded()));
+        if let Some(v) = v.as_ref() {
+            load_statistics::record_read_bytes(CF_DEFAULT, v.len());
+        }
         Ok(v.map(|v| v.to_vec()))
     }
 
     fn get_cf(&self, cf: CfName, key: &Key) -> Result<Option<Value>> {
         trace!("RocksSnapshot: get_cf"; "cf" => cf, "key" => %key);
         let v = box_try!(self.get_value_cf(cf, key.as_encoded()));
+        if let Some(v) = v.as_ref() {
+            load_statistics::record_read_bytes(cf, v.len());
+        }
         Ok(v.map(|v| v.to_vec()))
     }
 
@@ -455,6 +463,41 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_cf_load_byte_counters() {
+        use crate::server::load_statistics::{
+            LOAD_BYTES_READ_COUNTER_VEC, LOAD_BYTES_WRITTEN_COUNTER_VEC,
+        };
+
+        let engine = TestEngineBuilder::new().build().unwrap();
+        let value = vec![b'v'; 1024];
+        let written = Key::from_raw(b"load_key").as_encoded().len() + value.len();
+
+        // The counters are global, so other tests may bump them concurrently;
+        // only assert that the expected deltas show up on the right labels.
+        let written_before = LOAD_BYTES_WRITTEN_COUNTER_VEC
+            .with_label_values(&[CF_LOCK])
+            .get();
+        must_put_cf(&engine, CF_LOCK, b"load_key", &value);
+        assert!(
+            LOAD_BYTES_WRITTEN_COUNTER_VEC
+                .with_label_values(&[CF_LOCK])
+                .get()
+                >= written_before + written as i64
+        );
+
+        let read_before = LOAD_BYTES_READ_COUNTER_VEC
+            .with_label_values(&[CF_LOCK])
+            .get();
+        assert_has_cf(&engine, CF_LOCK, b"load_key", &value);
+        assert!(
+            LOAD_BYTES_READ_COUNTER_VEC
+                .with_label_values(&[CF_LOCK])
+                .get()
+                >= read_before + value.len() as i64
+        );
+    }
+
     #[test]
     fn test_rocksdb_perf_statistics() {
         let engine = TestEngineBuilder::new()
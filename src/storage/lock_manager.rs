@@ -1,6 +1,6 @@
 // Copyright 2019 TiKV Project Authors. Licensed under Apache-2.0.
 
-use crate::storage::{txn::ProcessResult, types::StorageCallback};
+use crate::storage::{metrics::CommandKind, txn::ProcessResult, types::StorageCallback};
 use std::time::Duration;
 use txn_types::TimeStamp;
 
@@ -62,6 +62,7 @@ pub trait LockManager: Clone + Send + 'static {
         lock: Lock,
         is_first_lock: bool,
         timeout: Option<WaitTimeout>,
+        tag: CommandKind,
     );
 
     /// The locks with `lock_ts` and `hashes` are released, tries to wake up transactions.
@@ -94,6 +95,7 @@ impl LockManager for DummyLockManager {
         _lock: Lock,
         _is_first_lock: bool,
         _wait_timeout: Option<WaitTimeout>,
+        _tag: CommandKind,
     ) {
     }
 
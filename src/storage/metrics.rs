@@ -147,10 +147,12 @@ make_static_metric! {
         scan_lock,
         resolve_lock,
         resolve_lock_lite,
+        resolve_lock_batch,
         delete_range,
         pause,
         key_mvcc,
         start_ts_mvcc,
+        mvcc_version_count,
         raw_get,
         raw_batch_get,
         raw_scan,
@@ -160,6 +162,7 @@ make_static_metric! {
         raw_delete,
         raw_delete_range,
         raw_batch_delete,
+        raw_compare_and_swap,
     }
 
     pub label_enum CommandStageKind {
@@ -176,6 +179,7 @@ make_static_metric! {
         write,
         write_finish,
         async_write_err,
+        stale_retry,
         error,
         pipelined_write,
         pipelined_write_finish,
@@ -204,6 +208,10 @@ make_static_metric! {
         "type" => CommandKind,
     }
 
+    pub struct SchedLockWaitDurationVec: Histogram {
+        "type" => CommandKind,
+    }
+
     pub struct KvCommandKeysWrittenVec: Histogram {
         "type" => CommandKind,
     }
@@ -263,6 +271,15 @@ lazy_static! {
             exponential_buckets(0.0005, 2.0, 20).unwrap()
         )
         .unwrap();
+    pub static ref SCHED_LOCK_WAIT_HISTOGRAM_VEC: SchedLockWaitDurationVec =
+        register_static_histogram_vec!(
+            SchedLockWaitDurationVec,
+            "tikv_scheduler_lock_wait_duration_seconds",
+            "Bucketed histogram of time a command spends blocked on lock acquisition",
+            &["type"],
+            exponential_buckets(0.0005, 2.0, 20).unwrap()
+        )
+        .unwrap();
     pub static ref SCHED_PROCESSING_READ_HISTOGRAM_VEC: HistogramVec = register_histogram_vec!(
         "tikv_scheduler_processing_read_duration_seconds",
         "Bucketed histogram of processing read duration",
@@ -15,6 +15,7 @@ pub mod kv;
 pub mod lock_manager;
 pub(crate) mod metrics;
 pub mod mvcc;
+pub mod raw_ttl;
 pub mod txn;
 
 mod read_pool;
@@ -38,6 +39,7 @@ use crate::storage::{
     lock_manager::{DummyLockManager, LockManager},
     metrics::*,
     txn::{
+        commands,
         commands::{Command, TypedCommand},
         scheduler::Scheduler as TxnScheduler,
     },
@@ -93,6 +95,8 @@ pub struct Storage<E: Engine, L: LockManager> {
     max_key_size: usize,
 
     pessimistic_txn_enabled: bool,
+
+    enable_ttl: bool,
 }
 
 impl<E: Engine, L: LockManager> Clone for Storage<E, L> {
@@ -111,6 +115,7 @@ impl<E: Engine, L: LockManager> Clone for Storage<E, L> {
             refs: self.refs.clone(),
             max_key_size: self.max_key_size,
             pessimistic_txn_enabled: self.pessimistic_txn_enabled,
+            enable_ttl: self.enable_ttl,
         }
     }
 }
@@ -175,6 +180,7 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
             config.scheduler_concurrency,
             config.scheduler_worker_pool_size,
             config.scheduler_pending_write_threshold.0 as usize,
+            config.scheduler_stale_command_retries,
             pipelined_pessimistic_lock,
         );
 
@@ -187,6 +193,7 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
             refs: Arc::new(atomic::AtomicUsize::new(1)),
             max_key_size: config.max_key_size,
             pessimistic_txn_enabled,
+            enable_ttl: config.enable_ttl,
         })
     }
 
@@ -553,6 +560,7 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
         const CMD: &str = "raw_get";
         let priority = ctx.get_priority();
         let priority_tag = get_priority_tag(priority);
+        let enable_ttl = self.enable_ttl;
 
         let res = self.read_pool.spawn_handle(
             async move {
@@ -564,7 +572,10 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
                     // no scan_count for this kind of op.
 
                     let key_len = key.len();
-                    let r = snapshot.get_cf(cf, &Key::from_encoded(key))?;
+                    let mut r = snapshot.get_cf(cf, &Key::from_encoded(key))?;
+                    if enable_ttl {
+                        r = raw_ttl::check_ttl_value(r)?;
+                    }
                     if let Some(ref value) = r {
                         let mut stats = Statistics::default();
                         stats.data.flow_stats.read_keys = 1;
@@ -596,6 +607,7 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
         let ctx = gets[0].ctx.clone();
         let priority = ctx.get_priority();
         let priority_tag = get_priority_tag(priority);
+        let enable_ttl = self.enable_ttl;
         let res = self.read_pool.spawn_handle(
             async move {
                 metrics::tls_collect_command_count(CMD, priority_tag);
@@ -606,7 +618,11 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
                     let mut results = vec![];
                     // TODO: optimize using seek.
                     for get in gets {
-                        results.push(snapshot.get_cf(cf, &get.key).map_err(Error::from));
+                        let mut v = snapshot.get_cf(cf, &get.key).map_err(Error::from);
+                        if enable_ttl {
+                            v = v.and_then(raw_ttl::check_ttl_value);
+                        }
+                        results.push(v);
                     }
                     Ok(results)
                 });
@@ -630,6 +646,7 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
         const CMD: &str = "raw_batch_get";
         let priority = ctx.get_priority();
         let priority_tag = get_priority_tag(priority);
+        let enable_ttl = self.enable_ttl;
 
         let res = self.read_pool.spawn_handle(
             async move {
@@ -644,7 +661,10 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
                     let result: Vec<Result<KvPair>> = keys
                         .into_iter()
                         .map(|k| {
-                            let v = snapshot.get_cf(cf, &k);
+                            let mut v = snapshot.get_cf(cf, &k).map_err(Error::from);
+                            if enable_ttl {
+                                v = v.and_then(raw_ttl::check_ttl_value);
+                            }
                             (k, v)
                         })
                         .filter(|&(_, ref v)| !(v.is_ok() && v.as_ref().unwrap().is_none()))
@@ -654,7 +674,7 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
                                 stats.data.flow_stats.read_bytes += k.as_encoded().len() + v.len();
                                 Ok((k.into_encoded(), v))
                             }
-                            Err(e) => Err(Error::from(e)),
+                            Err(e) => Err(e),
                             _ => unreachable!(),
                         })
                         .collect();
@@ -674,17 +694,83 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
             .flatten()
     }
 
+    /// Check which of the given raw keys exist.
+    ///
+    /// The returned `Vec<bool>` is aligned to the input keys: the i-th flag is
+    /// `true` iff the i-th key has a value. This is cheaper than
+    /// [`raw_batch_get`](Storage::raw_batch_get) because the values are not
+    /// returned to the caller.
+    pub fn raw_batch_get_exists(
+        &self,
+        ctx: Context,
+        cf: String,
+        keys: Vec<Vec<u8>>,
+    ) -> impl Future<Item = Vec<bool>, Error = Error> {
+        const CMD: &str = "raw_batch_get_exists";
+        let priority = ctx.get_priority();
+        let priority_tag = get_priority_tag(priority);
+        let enable_ttl = self.enable_ttl;
+
+        let res = self.read_pool.spawn_handle(
+            async move {
+                metrics::tls_collect_command_count(CMD, priority_tag);
+                let command_duration = tikv_util::time::Instant::now_coarse();
+                let snapshot = Self::with_tls_engine(|engine| Self::snapshot(engine, &ctx)).await?;
+                let result = metrics::tls_processing_read_observe_duration(CMD, || {
+                    let cf = Self::rawkv_cf(&cf)?;
+                    // no scan_count for this kind of op.
+                    let mut stats = Statistics::default();
+                    let mut exists = Vec::with_capacity(keys.len());
+                    for k in keys {
+                        let k = Key::from_encoded(k);
+                        let mut v = snapshot.get_cf(cf, &k)?;
+                        if enable_ttl {
+                            v = raw_ttl::check_ttl_value(v)?;
+                        }
+                        if v.is_some() {
+                            stats.data.flow_stats.read_keys += 1;
+                            stats.data.flow_stats.read_bytes += k.as_encoded().len();
+                        }
+                        exists.push(v.is_some());
+                    }
+
+                    tls_collect_key_reads(CMD, stats.data.flow_stats.read_keys as usize);
+                    tls_collect_read_flow(ctx.get_region_id(), &stats);
+                    Ok(exists)
+                });
+                metrics::tls_collect_command_duration(CMD, command_duration.elapsed());
+                result
+            },
+            priority,
+            thread_rng().next_u64(),
+        );
+
+        res.map_err(|_| Error::from(ErrorInner::SchedTooBusy))
+            .flatten()
+    }
+
     /// Write a raw key to the storage.
+    ///
+    /// A non-zero `ttl` makes the key expire after `ttl` seconds; it requires
+    /// `storage.enable-ttl` to be set. Zero means the key never expires.
     pub fn raw_put(
         &self,
         ctx: Context,
         cf: String,
         key: Vec<u8>,
-        value: Vec<u8>,
+        mut value: Vec<u8>,
+        ttl: u64,
         callback: Callback<()>,
     ) -> Result<()> {
         check_key_size!(Some(&key).into_iter(), self.max_key_size, callback);
 
+        if self.enable_ttl {
+            let expire_ts = raw_ttl::convert_to_expire_ts(ttl);
+            raw_ttl::append_expire_ts(&mut value, expire_ts);
+        } else if ttl != 0 {
+            return Err(ErrorInner::TtlNotEnabled.into());
+        }
+
         self.engine.async_write(
             &ctx,
             vec![Modify::Put(
@@ -699,11 +785,15 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
     }
 
     /// Write some keys to the storage in a batch.
+    ///
+    /// A non-zero `ttl` makes the keys expire after `ttl` seconds; it requires
+    /// `storage.enable-ttl` to be set. Zero means the keys never expire.
     pub fn raw_batch_put(
         &self,
         ctx: Context,
         cf: String,
         pairs: Vec<KvPair>,
+        ttl: u64,
         callback: Callback<()>,
     ) -> Result<()> {
         let cf = Self::rawkv_cf(&cf)?;
@@ -714,9 +804,20 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
             callback
         );
 
+        if !self.enable_ttl && ttl != 0 {
+            return Err(ErrorInner::TtlNotEnabled.into());
+        }
+        let expire_ts = raw_ttl::convert_to_expire_ts(ttl);
+        let enable_ttl = self.enable_ttl;
+
         let requests = pairs
             .into_iter()
-            .map(|(k, v)| Modify::Put(cf, Key::from_encoded(k), v))
+            .map(|(k, mut v)| {
+                if enable_ttl {
+                    raw_ttl::append_expire_ts(&mut v, expire_ts);
+                }
+                Modify::Put(cf, Key::from_encoded(k), v)
+            })
             .collect();
         self.engine.async_write(
             &ctx,
@@ -800,9 +901,48 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
         Ok(())
     }
 
+    /// Write `value` into `key` only if the current value of `key` equals `previous_value`.
+    ///
+    /// The command goes through the transaction scheduler so the key is latched during the
+    /// read-compare-write sequence, which makes the swap atomic with respect to other
+    /// `raw_compare_and_swap` calls on the same key.
+    ///
+    /// A non-zero `ttl` makes the new value expire after `ttl` seconds; it requires
+    /// `storage.enable-ttl` to be set. Zero means the value never expires.
+    pub fn raw_compare_and_swap(
+        &self,
+        ctx: Context,
+        cf: String,
+        key: Vec<u8>,
+        previous_value: Option<Vec<u8>>,
+        value: Vec<u8>,
+        ttl: u64,
+        callback: Callback<(Option<Value>, bool)>,
+    ) -> Result<()> {
+        let cf = Self::rawkv_cf(&cf)?;
+        check_key_size!(Some(&key).into_iter(), self.max_key_size, callback);
+        if !self.enable_ttl && ttl != 0 {
+            return Err(ErrorInner::TtlNotEnabled.into());
+        }
+
+        let cmd = commands::RawCompareAndSwap::new(
+            cf,
+            Key::from_encoded(key),
+            previous_value,
+            value,
+            ttl,
+            self.enable_ttl,
+            ctx,
+        );
+        self.sched_txn_command(cmd, callback)
+    }
+
     /// Scan raw keys in [`start_key`, `end_key`), returns at most `limit` keys. If `end_key` is
     /// `None`, it means unbounded.
     ///
+    /// If `key_prefix` is `Some`, only keys starting with the prefix are returned; keys outside
+    /// it are filtered out during iteration and do not count against `limit`.
+    ///
     /// If `key_only` is true, the value corresponding to the key will not be read. Only scanned
     /// keys will be returned.
     fn forward_raw_scan(
@@ -811,8 +951,10 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
         start_key: &Key,
         end_key: Option<Key>,
         limit: usize,
+        key_prefix: Option<Vec<u8>>,
         statistics: &mut Statistics,
         key_only: bool,
+        enable_ttl: bool,
     ) -> Result<Vec<Result<KvPair>>> {
         let mut option = IterOption::default();
         if let Some(end) = end_key {
@@ -825,13 +967,33 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
         }
         let mut pairs = vec![];
         while cursor.valid()? && pairs.len() < limit {
+            if let Some(ref prefix) = key_prefix {
+                if !cursor.key(statistics).starts_with(prefix) {
+                    cursor.next(statistics);
+                    continue;
+                }
+            }
+            // With TTL enabled the value must be read even for key-only scans
+            // to check the expire timestamp.
+            let value = if key_only && !enable_ttl {
+                vec![]
+            } else {
+                cursor.value(statistics).to_owned()
+            };
+            let value = if enable_ttl {
+                match raw_ttl::check_ttl_value(Some(value))? {
+                    Some(v) => v,
+                    None => {
+                        cursor.next(statistics);
+                        continue;
+                    }
+                }
+            } else {
+                value
+            };
             pairs.push(Ok((
                 cursor.key(statistics).to_owned(),
-                if key_only {
-                    vec![]
-                } else {
-                    cursor.value(statistics).to_owned()
-                },
+                if key_only { vec![] } else { value },
             )));
             cursor.next(statistics);
         }
@@ -841,6 +1003,9 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
     /// Scan raw keys in [`end_key`, `start_key`) in reverse order, returns at most `limit` keys. If
     /// `start_key` is `None`, it means it's unbounded.
     ///
+    /// If `key_prefix` is `Some`, only keys starting with the prefix are returned; keys outside
+    /// it are filtered out during iteration and do not count against `limit`.
+    ///
     /// If `key_only` is true, the value
     /// corresponding to the key will not be read out. Only scanned keys will be returned.
     fn reverse_raw_scan(
@@ -849,8 +1014,10 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
         start_key: &Key,
         end_key: Option<Key>,
         limit: usize,
+        key_prefix: Option<Vec<u8>>,
         statistics: &mut Statistics,
         key_only: bool,
+        enable_ttl: bool,
     ) -> Result<Vec<Result<KvPair>>> {
         let mut option = IterOption::default();
         if let Some(end) = end_key {
@@ -863,13 +1030,33 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
         }
         let mut pairs = vec![];
         while cursor.valid()? && pairs.len() < limit {
+            if let Some(ref prefix) = key_prefix {
+                if !cursor.key(statistics).starts_with(prefix) {
+                    cursor.prev(statistics);
+                    continue;
+                }
+            }
+            // With TTL enabled the value must be read even for key-only scans
+            // to check the expire timestamp.
+            let value = if key_only && !enable_ttl {
+                vec![]
+            } else {
+                cursor.value(statistics).to_owned()
+            };
+            let value = if enable_ttl {
+                match raw_ttl::check_ttl_value(Some(value))? {
+                    Some(v) => v,
+                    None => {
+                        cursor.prev(statistics);
+                        continue;
+                    }
+                }
+            } else {
+                value
+            };
             pairs.push(Ok((
                 cursor.key(statistics).to_owned(),
-                if key_only {
-                    vec![]
-                } else {
-                    cursor.value(statistics).to_owned()
-                },
+                if key_only { vec![] } else { value },
             )));
             cursor.prev(statistics);
         }
@@ -884,6 +1071,10 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
     ///
     /// This function scans at most `limit` keys.
     ///
+    /// If `key_prefix` is `Some`, only keys starting with the prefix are returned. The prefix is
+    /// checked while iterating, so non-matching keys are never shipped to the caller and do not
+    /// count against `limit`.
+    ///
     /// If `key_only` is true, the value
     /// corresponding to the key will not be read out. Only scanned keys will be returned.
     pub fn raw_scan(
@@ -893,12 +1084,14 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
         key: Vec<u8>,
         end_key: Option<Vec<u8>>,
         limit: usize,
+        key_prefix: Option<Vec<u8>>,
         key_only: bool,
         reverse: bool,
     ) -> impl Future<Item = Vec<Result<KvPair>>, Error = Error> {
         const CMD: &str = "raw_scan";
         let priority = ctx.get_priority();
         let priority_tag = get_priority_tag(priority);
+        let enable_ttl = self.enable_ttl;
 
         let res = self.read_pool.spawn_handle(
             async move {
@@ -917,8 +1110,10 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
                             &Key::from_encoded(key),
                             end_key,
                             limit,
+                            key_prefix,
                             &mut statistics,
                             key_only,
+                            enable_ttl,
                         )
                         .map_err(Error::from)
                     } else {
@@ -928,8 +1123,10 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
                             &Key::from_encoded(key),
                             end_key,
                             limit,
+                            key_prefix,
                             &mut statistics,
                             key_only,
+                            enable_ttl,
                         )
                         .map_err(Error::from)
                     };
@@ -953,6 +1150,23 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
             .flatten()
     }
 
+    /// Scan raw keys in [`end_key`, `start_key`) in descending order, starting from `start_key`.
+    /// If `end_key` is `None`, it means unbounded.
+    ///
+    /// This function scans at most `limit` keys. If `key_only` is true, the value corresponding
+    /// to the key will not be read out. Only scanned keys will be returned.
+    pub fn raw_reverse_scan(
+        &self,
+        ctx: Context,
+        cf: String,
+        start_key: Vec<u8>,
+        end_key: Option<Vec<u8>>,
+        limit: usize,
+        key_only: bool,
+    ) -> impl Future<Item = Vec<Result<KvPair>>, Error = Error> {
+        self.raw_scan(ctx, cf, start_key, end_key, limit, None, key_only, true)
+    }
+
     /// Check the given raw kv CF name. Return the CF name, or `Err` if given CF name is invalid.
     /// The CF name can be one of `"default"`, `"write"` and `"lock"`. If given `cf` is empty,
     /// `CF_DEFAULT` (`"default"`) will be returned.
@@ -1002,6 +1216,7 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
         const CMD: &str = "raw_batch_scan";
         let priority = ctx.get_priority();
         let priority_tag = get_priority_tag(priority);
+        let enable_ttl = self.enable_ttl;
 
         let res = self.read_pool.spawn_handle(
             async move {
@@ -1035,8 +1250,10 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
                                 &start_key,
                                 end_key,
                                 each_limit,
+                                None,
                                 &mut statistics,
                                 key_only,
+                                enable_ttl,
                             )?
                         } else {
                             Self::forward_raw_scan(
@@ -1045,8 +1262,10 @@ impl<E: Engine, L: LockManager> Storage<E, L> {
                                 &start_key,
                                 end_key,
                                 each_limit,
+                                None,
                                 &mut statistics,
                                 key_only,
+                                enable_ttl,
                             )?
                         };
                         result.extend(pairs.into_iter());
@@ -1199,6 +1418,8 @@ mod tests {
     use std::{
         fmt::Debug,
         sync::mpsc::{channel, Sender},
+        thread,
+        time::Duration,
     };
     use tikv_util::collections::HashMap;
     use tikv_util::config::ReadableSize;
@@ -2224,6 +2445,7 @@ mod tests {
                     "".to_string(),
                     kv.0.to_vec(),
                     kv.1.to_vec(),
+                    0,
                     expect_ok_callback(tx.clone(), 0),
                 )
                 .unwrap();
@@ -2336,6 +2558,7 @@ mod tests {
                 Context::default(),
                 "".to_string(),
                 test_data.clone(),
+                0,
                 expect_ok_callback(tx, 0),
             )
             .unwrap();
@@ -2373,6 +2596,7 @@ mod tests {
                     "".to_string(),
                     key.clone(),
                     value.clone(),
+                    0,
                     expect_ok_callback(tx.clone(), 0),
                 )
                 .unwrap();
@@ -2390,6 +2614,341 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_raw_batch_get_exists() {
+        let storage = TestStorageBuilder::new().build().unwrap();
+        let (tx, rx) = channel();
+
+        let test_data = vec![
+            (b"a".to_vec(), b"aa".to_vec()),
+            (b"c".to_vec(), b"cc".to_vec()),
+            (b"e".to_vec(), b"ee".to_vec()),
+        ];
+
+        // Write key-value pairs one by one
+        for &(ref key, ref value) in &test_data {
+            storage
+                .raw_put(
+                    Context::default(),
+                    "".to_string(),
+                    key.clone(),
+                    value.clone(),
+                    0,
+                    expect_ok_callback(tx.clone(), 0),
+                )
+                .unwrap();
+        }
+        rx.recv().unwrap();
+
+        // Mix present and absent keys; the result must be aligned to the input.
+        let keys = vec![
+            b"a".to_vec(),
+            b"b".to_vec(),
+            b"c".to_vec(),
+            b"d".to_vec(),
+            b"e".to_vec(),
+        ];
+        let exists = storage
+            .raw_batch_get_exists(Context::default(), "".to_string(), keys)
+            .wait()
+            .unwrap();
+        assert_eq!(exists, vec![true, false, true, false, true]);
+    }
+
+    #[test]
+    fn test_raw_put_ttl() {
+        let mut config = Config::default();
+        config.enable_ttl = true;
+        let storage = TestStorageBuilder::new().config(config).build().unwrap();
+        let (tx, rx) = channel();
+
+        // A key with a short TTL and one without expiry.
+        storage
+            .raw_put(
+                Context::default(),
+                "".to_string(),
+                b"k1".to_vec(),
+                b"v1".to_vec(),
+                1,
+                expect_ok_callback(tx.clone(), 0),
+            )
+            .unwrap();
+        storage
+            .raw_put(
+                Context::default(),
+                "".to_string(),
+                b"k2".to_vec(),
+                b"v2".to_vec(),
+                0,
+                expect_ok_callback(tx.clone(), 1),
+            )
+            .unwrap();
+        rx.recv().unwrap();
+        rx.recv().unwrap();
+
+        // Before expiry both keys are visible and the expire timestamp is
+        // stripped from the values.
+        expect_value(
+            b"v1".to_vec(),
+            storage
+                .raw_get(Context::default(), "".to_string(), b"k1".to_vec())
+                .wait(),
+        );
+        expect_value(
+            b"v2".to_vec(),
+            storage
+                .raw_get(Context::default(), "".to_string(), b"k2".to_vec())
+                .wait(),
+        );
+
+        thread::sleep(Duration::from_secs(2));
+
+        // The key with the TTL has expired; the other one is still there.
+        expect_none(
+            storage
+                .raw_get(Context::default(), "".to_string(), b"k1".to_vec())
+                .wait(),
+        );
+        expect_value(
+            b"v2".to_vec(),
+            storage
+                .raw_get(Context::default(), "".to_string(), b"k2".to_vec())
+                .wait(),
+        );
+
+        // Expired keys are not returned by scans either.
+        expect_multi_values(
+            vec![Some((b"k2".to_vec(), b"v2".to_vec()))],
+            storage
+                .raw_scan(
+                    Context::default(),
+                    "".to_string(),
+                    b"k1".to_vec(),
+                    None,
+                    10,
+                    None,
+                    false,
+                    false,
+                )
+                .wait(),
+        );
+
+        // A non-zero TTL is rejected when TTL is not enabled.
+        let storage = TestStorageBuilder::new().build().unwrap();
+        assert!(storage
+            .raw_put(
+                Context::default(),
+                "".to_string(),
+                b"k1".to_vec(),
+                b"v1".to_vec(),
+                1,
+                expect_ok_callback(tx, 2),
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_raw_compare_and_swap() {
+        let storage = TestStorageBuilder::new().build().unwrap();
+        let (tx, rx) = channel();
+
+        // The swap fails when the key does not hold the expected value.
+        storage
+            .raw_compare_and_swap(
+                Context::default(),
+                "".to_string(),
+                b"k1".to_vec(),
+                Some(b"v0".to_vec()),
+                b"v1".to_vec(),
+                0,
+                expect_value_callback(tx.clone(), 0, (None, false)),
+            )
+            .unwrap();
+        rx.recv().unwrap();
+        expect_none(
+            storage
+                .raw_get(Context::default(), "".to_string(), b"k1".to_vec())
+                .wait(),
+        );
+
+        // Expecting no value succeeds on a missing key.
+        storage
+            .raw_compare_and_swap(
+                Context::default(),
+                "".to_string(),
+                b"k1".to_vec(),
+                None,
+                b"v1".to_vec(),
+                0,
+                expect_value_callback(tx.clone(), 1, (None, true)),
+            )
+            .unwrap();
+        rx.recv().unwrap();
+        expect_value(
+            b"v1".to_vec(),
+            storage
+                .raw_get(Context::default(), "".to_string(), b"k1".to_vec())
+                .wait(),
+        );
+
+        // A mismatched expectation leaves the value untouched and returns the
+        // current one.
+        storage
+            .raw_compare_and_swap(
+                Context::default(),
+                "".to_string(),
+                b"k1".to_vec(),
+                Some(b"v0".to_vec()),
+                b"v2".to_vec(),
+                0,
+                expect_value_callback(tx.clone(), 2, (Some(b"v1".to_vec()), false)),
+            )
+            .unwrap();
+        rx.recv().unwrap();
+
+        // A matched expectation swaps the value.
+        storage
+            .raw_compare_and_swap(
+                Context::default(),
+                "".to_string(),
+                b"k1".to_vec(),
+                Some(b"v1".to_vec()),
+                b"v2".to_vec(),
+                0,
+                expect_value_callback(tx.clone(), 3, (Some(b"v1".to_vec()), true)),
+            )
+            .unwrap();
+        rx.recv().unwrap();
+        expect_value(
+            b"v2".to_vec(),
+            storage
+                .raw_get(Context::default(), "".to_string(), b"k1".to_vec())
+                .wait(),
+        );
+
+        // Concurrent swaps on the same key are serialized by the latch, so
+        // exactly one of them may observe the missing key and win.
+        let (tx, rx) = channel();
+        for i in 0..5u8 {
+            let tx = tx.clone();
+            storage
+                .raw_compare_and_swap(
+                    Context::default(),
+                    "".to_string(),
+                    b"k2".to_vec(),
+                    None,
+                    vec![b'v', i],
+                    0,
+                    Box::new(move |res| tx.send((i, res.unwrap())).unwrap()),
+                )
+                .unwrap();
+        }
+        let mut winners = vec![];
+        for _ in 0..5 {
+            let (i, (_, succeed)) = rx.recv().unwrap();
+            if succeed {
+                winners.push(i);
+            }
+        }
+        assert_eq!(winners.len(), 1);
+        expect_value(
+            vec![b'v', winners[0]],
+            storage
+                .raw_get(Context::default(), "".to_string(), b"k2".to_vec())
+                .wait(),
+        );
+    }
+
+    #[test]
+    fn test_raw_compare_and_swap_ttl() {
+        let mut config = Config::default();
+        config.enable_ttl = true;
+        let storage = TestStorageBuilder::new().config(config).build().unwrap();
+        let (tx, rx) = channel();
+
+        // Store a value carrying an expire timestamp.
+        storage
+            .raw_put(
+                Context::default(),
+                "".to_string(),
+                b"k1".to_vec(),
+                b"v1".to_vec(),
+                0,
+                expect_ok_callback(tx.clone(), 0),
+            )
+            .unwrap();
+        rx.recv().unwrap();
+
+        // The comparison and the returned previous value use the user-visible
+        // value, not the TTL-encoded one.
+        storage
+            .raw_compare_and_swap(
+                Context::default(),
+                "".to_string(),
+                b"k1".to_vec(),
+                Some(b"v1".to_vec()),
+                b"v2".to_vec(),
+                0,
+                expect_value_callback(tx.clone(), 1, (Some(b"v1".to_vec()), true)),
+            )
+            .unwrap();
+        rx.recv().unwrap();
+        expect_value(
+            b"v2".to_vec(),
+            storage
+                .raw_get(Context::default(), "".to_string(), b"k1".to_vec())
+                .wait(),
+        );
+
+        // A swap with a short TTL makes the new value expire.
+        storage
+            .raw_compare_and_swap(
+                Context::default(),
+                "".to_string(),
+                b"k1".to_vec(),
+                Some(b"v2".to_vec()),
+                b"v3".to_vec(),
+                1,
+                expect_value_callback(tx.clone(), 2, (Some(b"v2".to_vec()), true)),
+            )
+            .unwrap();
+        rx.recv().unwrap();
+        thread::sleep(Duration::from_secs(2));
+        expect_none(
+            storage
+                .raw_get(Context::default(), "".to_string(), b"k1".to_vec())
+                .wait(),
+        );
+
+        // The expired key compares as absent.
+        storage
+            .raw_compare_and_swap(
+                Context::default(),
+                "".to_string(),
+                b"k1".to_vec(),
+                None,
+                b"v4".to_vec(),
+                0,
+                expect_value_callback(tx.clone(), 3, (None, true)),
+            )
+            .unwrap();
+        rx.recv().unwrap();
+
+        // A non-zero TTL is rejected when TTL is not enabled.
+        let storage = TestStorageBuilder::new().build().unwrap();
+        assert!(storage
+            .raw_compare_and_swap(
+                Context::default(),
+                "".to_string(),
+                b"k1".to_vec(),
+                None,
+                b"v1".to_vec(),
+                1,
+                expect_value_callback(tx, 4, (None, true)),
+            )
+            .is_err());
+    }
+
     #[test]
     fn test_batch_raw_get() {
         let storage = TestStorageBuilder::new().build().unwrap();
@@ -2411,6 +2970,7 @@ mod tests {
                     "".to_string(),
                     key.clone(),
                     value.clone(),
+                    0,
                     expect_ok_callback(tx.clone(), 0),
                 )
                 .unwrap();
@@ -2456,6 +3016,7 @@ mod tests {
                 Context::default(),
                 "".to_string(),
                 test_data.clone(),
+                0,
                 expect_ok_callback(tx.clone(), 0),
             )
             .unwrap();
@@ -2570,6 +3131,7 @@ mod tests {
                 Context::default(),
                 "".to_string(),
                 test_data.clone(),
+                0,
                 expect_ok_callback(tx, 0),
             )
             .unwrap();
@@ -2589,6 +3151,7 @@ mod tests {
                     vec![],
                     None,
                     20,
+                    None,
                     true,
                     false,
                 )
@@ -2604,6 +3167,7 @@ mod tests {
                     b"c2".to_vec(),
                     None,
                     20,
+                    None,
                     true,
                     false,
                 )
@@ -2623,6 +3187,7 @@ mod tests {
                     vec![],
                     None,
                     20,
+                    None,
                     false,
                     false,
                 )
@@ -2638,6 +3203,7 @@ mod tests {
                     b"c2".to_vec(),
                     None,
                     20,
+                    None,
                     false,
                     false,
                 )
@@ -2658,6 +3224,7 @@ mod tests {
                     b"z".to_vec(),
                     None,
                     20,
+                    None,
                     false,
                     true,
                 )
@@ -2679,6 +3246,7 @@ mod tests {
                     b"z".to_vec(),
                     None,
                     5,
+                    None,
                     false,
                     true,
                 )
@@ -2702,6 +3270,7 @@ mod tests {
                     b"b2".to_vec(),
                     Some(b"c2".to_vec()),
                     20,
+                    None,
                     false,
                     false,
                 )
@@ -2723,6 +3292,7 @@ mod tests {
                     b"b2".to_vec(),
                     Some(b"b2\x00".to_vec()),
                     20,
+                    None,
                     false,
                     false,
                 )
@@ -2747,6 +3317,7 @@ mod tests {
                     b"c2".to_vec(),
                     Some(b"b2".to_vec()),
                     20,
+                    None,
                     false,
                     true,
                 )
@@ -2767,6 +3338,7 @@ mod tests {
                     b"b2\x00".to_vec(),
                     Some(b"b2".to_vec()),
                     20,
+                    None,
                     false,
                     true,
                 )
@@ -2797,8 +3369,10 @@ mod tests {
                     &Key::from_encoded(b"c1".to_vec()),
                     Some(Key::from_encoded(b"d3".to_vec())),
                     20,
+                    None,
                     &mut Statistics::default(),
                     false,
+                    false,
                 )
             }),
         );
@@ -2813,8 +3387,10 @@ mod tests {
                     &Key::from_encoded(b"d3".to_vec()),
                     Some(Key::from_encoded(b"c1".to_vec())),
                     20,
+                    None,
                     &mut Statistics::default(),
                     false,
+                    false,
                 )
             }),
         );
@@ -2952,6 +3528,7 @@ mod tests {
                 Context::default(),
                 "".to_string(),
                 test_data.clone(),
+                0,
                 expect_ok_callback(tx, 0),
             )
             .unwrap();
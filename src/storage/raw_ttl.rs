@@ -0,0 +1,158 @@
+// Copyright 2020 TiKV Project Authors. Licensed under Apache-2.0.
+
+//! TTL support for raw keys.
+//!
+//! When `storage.enable-ttl` is set, every raw value is stored with an 8-byte
+//! expire timestamp (unix seconds, big-endian) appended to it. A timestamp of
+//! 0 means the key never expires. The raw read paths treat expired keys as
+//! absent, and a compaction filter purges them from the default column family
+//! during compactions.
+
+use engine::rocks::{
+    new_compaction_filter_raw, CompactionFilter, CompactionFilterContext, CompactionFilterFactory,
+    DBCompactionFilter,
+};
+use std::ffi::CString;
+use tikv_util::codec::number::{self, NumberEncoder};
+use tikv_util::time::UnixSecs;
+
+use crate::storage::{ErrorInner, Result};
+
+pub const EXPIRE_TS_LEN: usize = number::U64_SIZE;
+
+/// Converts a TTL in seconds to an absolute expire timestamp. A zero TTL maps
+/// to a zero timestamp, which means the key never expires.
+pub fn convert_to_expire_ts(ttl: u64) -> u64 {
+    if ttl == 0 {
+        return 0;
+    }
+    ttl.saturating_add(UnixSecs::now().into_inner())
+}
+
+/// Appends the expire timestamp to a raw value.
+pub fn append_expire_ts(value: &mut Vec<u8>, expire_ts: u64) {
+    value.encode_u64(expire_ts).unwrap();
+}
+
+/// Extracts the expire timestamp from an encoded raw value.
+pub fn get_expire_ts(value_with_ttl: &[u8]) -> Result<u64> {
+    let len = value_with_ttl.len();
+    if len < EXPIRE_TS_LEN {
+        return Err(ErrorInner::Other(box_err!(
+            "ttl-encoded value is too short: {}",
+            len
+        ))
+        .into());
+    }
+    let mut ts = &value_with_ttl[len - EXPIRE_TS_LEN..];
+    Ok(number::decode_u64(&mut ts).unwrap())
+}
+
+/// Strips the expire timestamp from an encoded raw value.
+pub fn strip_expire_ts(value_with_ttl: &[u8]) -> &[u8] {
+    &value_with_ttl[..value_with_ttl.len() - EXPIRE_TS_LEN]
+}
+
+pub fn truncate_expire_ts(value_with_ttl: &mut Vec<u8>) {
+    let len = value_with_ttl.len();
+    value_with_ttl.truncate(len - EXPIRE_TS_LEN);
+}
+
+/// Returns whether a key with the given expire timestamp is expired at `now`.
+pub fn is_expired(expire_ts: u64, now: u64) -> bool {
+    expire_ts != 0 && expire_ts <= now
+}
+
+/// Decodes a TTL-encoded raw value. Returns `None` if the key has expired,
+/// otherwise the value with the expire timestamp stripped.
+pub fn check_ttl_value(value: Option<Vec<u8>>) -> Result<Option<Vec<u8>>> {
+    match value {
+        Some(mut v) => {
+            let expire_ts = get_expire_ts(&v)?;
+            if is_expired(expire_ts, UnixSecs::now().into_inner()) {
+                Ok(None)
+            } else {
+                truncate_expire_ts(&mut v);
+                Ok(Some(v))
+            }
+        }
+        None => Ok(None),
+    }
+}
+
+/// A compaction filter that purges expired raw keys from the default column
+/// family.
+pub struct TTLCompactionFilterFactory;
+
+impl CompactionFilterFactory for TTLCompactionFilterFactory {
+    fn create_compaction_filter(
+        &self,
+        _context: &CompactionFilterContext,
+    ) -> *mut DBCompactionFilter {
+        let name = CString::new("ttl_compaction_filter").unwrap();
+        let filter = Box::new(TTLCompactionFilter {
+            now: UnixSecs::now().into_inner(),
+        });
+        unsafe { new_compaction_filter_raw(name, filter) }
+    }
+}
+
+struct TTLCompactionFilter {
+    now: u64,
+}
+
+impl CompactionFilter for TTLCompactionFilter {
+    fn filter(
+        &mut self,
+        _level: usize,
+        _key: &[u8],
+        value: &[u8],
+        _new_value: &mut Vec<u8>,
+        _value_changed: &mut bool,
+    ) -> bool {
+        match get_expire_ts(value) {
+            // Values not encoded with an expire timestamp are kept; dropping
+            // them would lose data written before TTL was enabled.
+            Err(_) => false,
+            Ok(expire_ts) => is_expired(expire_ts, self.now),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expire_ts_codec() {
+        let mut value = b"value".to_vec();
+        append_expire_ts(&mut value, 0);
+        assert_eq!(value.len(), 5 + EXPIRE_TS_LEN);
+        assert_eq!(get_expire_ts(&value).unwrap(), 0);
+        assert_eq!(strip_expire_ts(&value), b"value");
+
+        let mut value = b"value".to_vec();
+        append_expire_ts(&mut value, 4077);
+        assert_eq!(get_expire_ts(&value).unwrap(), 4077);
+        truncate_expire_ts(&mut value);
+        assert_eq!(value, b"value".to_vec());
+
+        assert!(get_expire_ts(b"short").is_err());
+    }
+
+    #[test]
+    fn test_convert_to_expire_ts() {
+        assert_eq!(convert_to_expire_ts(0), 0);
+        let now = UnixSecs::now().into_inner();
+        assert!(convert_to_expire_ts(100) >= now + 100);
+    }
+
+    #[test]
+    fn test_is_expired() {
+        // Zero means no expiry.
+        assert!(!is_expired(0, 100));
+        assert!(!is_expired(101, 100));
+        assert!(is_expired(100, 100));
+        assert!(is_expired(99, 100));
+    }
+}
@@ -4,9 +4,10 @@ use std::fmt::{self, Debug, Display, Formatter};
 use std::iter::{self, FromIterator};
 use std::marker::PhantomData;
 
+use engine_traits::CfName;
 use kvproto::kvrpcpb::*;
 use tikv_util::collections::HashMap;
-use txn_types::{Key, Lock, Mutation, TimeStamp};
+use txn_types::{Key, Lock, Mutation, TimeStamp, Value};
 
 use crate::storage::lock_manager::WaitTimeout;
 use crate::storage::metrics::{self, KV_COMMAND_COUNTER_VEC_STATIC};
@@ -22,6 +23,7 @@ use crate::storage::Result;
 /// These are typically scheduled and used through the [`Storage`](Storage) with functions like
 /// [`Storage::prewrite`](Storage::prewrite) trait and are executed asynchronously.
 // Logic related to these can be found in the `src/storage/txn/proccess.rs::process_write_impl` function.
+#[derive(Clone)]
 pub struct Command {
     pub ctx: Context,
     pub kind: CommandKind,
@@ -249,6 +251,7 @@ macro_rules! command {
         }
     ) => {
         $(#[$outer_doc])*
+        #[derive(Clone)]
         pub struct $cmd {
             $($(#[$inner_doc])* pub $arg: $arg_ty,)*
         }
@@ -483,6 +486,7 @@ command! {
 }
 
 /// Scan locks from `start_key`, and find all locks whose timestamp is before `max_ts`.
+#[derive(Clone)]
 pub struct ScanLock {
     /// The maximum transaction timestamp to scan.
     pub max_ts: TimeStamp,
@@ -551,6 +555,21 @@ command! {
     }
 }
 
+command! {
+    /// Resolve locks of multiple transactions in one pass, reporting progress.
+    ///
+    /// Works like [`ResolveLock`], except that the callback receives the total
+    /// number of locks that were committed or rolled back.
+    ResolveLockBatch -> u64 {
+        /// Maps lock_ts to commit_ts, see `ResolveLock` for the details.
+        txn_status: HashMap<TimeStamp, TimeStamp>,
+        scan_key: Option<Key>,
+        key_locks: Vec<(Key, Lock)>,
+        /// The number of locks resolved by the previous rounds of this command.
+        resolved: u64,
+    }
+}
+
 command! {
     /// **Testing functionality:** Latch the given keys for given duration.
     ///
@@ -563,6 +582,27 @@ command! {
     }
 }
 
+command! {
+    /// Atomically set the value of a raw key, if its current value matches
+    /// `previous_value` (`None` means the key is expected to be absent).
+    ///
+    /// Like other writes it goes through the raft layer, and the scheduler
+    /// holds a latch on the key, so concurrent attempts on the same key are
+    /// linearizable.
+    ///
+    /// With `storage.enable-ttl` set, values are stored with an expire-ts
+    /// suffix; the comparison and the returned previous value use the
+    /// user-visible value, and a non-zero `ttl` makes the new value expire.
+    RawCompareAndSwap -> (Option<Value>, bool) {
+        cf: CfName,
+        key: Key,
+        previous_value: Option<Value>,
+        value: Value,
+        ttl: u64,
+        enable_ttl: bool,
+    }
+}
+
 command! {
     /// Retrieve MVCC information for the given key.
     MvccByKey -> MvccInfo {
@@ -577,6 +617,20 @@ command! {
     }
 }
 
+command! {
+    /// Count the write-CF versions of `key` whose commit timestamps fall in
+    /// `[start_ts, end_ts]`.
+    ///
+    /// Returns the count together with the oldest and the newest matching commit timestamp.
+    /// An `end_ts` of zero means no upper bound.
+    MvccVersionCount -> (u64, TimeStamp, TimeStamp) {
+        key: Key,
+        start_ts: TimeStamp,
+        end_ts: TimeStamp,
+    }
+}
+
+#[derive(Clone)]
 pub enum CommandKind {
     Prewrite(Prewrite),
     PrewritePessimistic(PrewritePessimistic),
@@ -590,9 +644,12 @@ pub enum CommandKind {
     ScanLock(ScanLock),
     ResolveLock(ResolveLock),
     ResolveLockLite(ResolveLockLite),
+    ResolveLockBatch(ResolveLockBatch),
     Pause(Pause),
+    RawCompareAndSwap(RawCompareAndSwap),
     MvccByKey(MvccByKey),
     MvccByStartTs(MvccByStartTs),
+    MvccVersionCount(MvccVersionCount),
 }
 
 impl Command {
@@ -600,8 +657,12 @@ impl Command {
         match self.kind {
             CommandKind::ScanLock(_)
             | CommandKind::MvccByKey(_)
-            | CommandKind::MvccByStartTs(_) => true,
-            CommandKind::ResolveLock(ResolveLock { ref key_locks, .. }) => key_locks.is_empty(),
+            | CommandKind::MvccByStartTs(_)
+            | CommandKind::MvccVersionCount(_) => true,
+            CommandKind::ResolveLock(ResolveLock { ref key_locks, .. })
+            | CommandKind::ResolveLockBatch(ResolveLockBatch { ref key_locks, .. }) => {
+                key_locks.is_empty()
+            }
             _ => false,
         }
     }
@@ -626,9 +687,18 @@ impl Command {
             CommandKind::ResolveLockLite(_) => {
                 KV_COMMAND_COUNTER_VEC_STATIC.resolve_lock_lite.inc()
             }
+            CommandKind::ResolveLockBatch(_) => {
+                KV_COMMAND_COUNTER_VEC_STATIC.resolve_lock_batch.inc()
+            }
             CommandKind::Pause(_) => KV_COMMAND_COUNTER_VEC_STATIC.pause.inc(),
+            CommandKind::RawCompareAndSwap(_) => {
+                KV_COMMAND_COUNTER_VEC_STATIC.raw_compare_and_swap.inc()
+            }
             CommandKind::MvccByKey(_) => KV_COMMAND_COUNTER_VEC_STATIC.key_mvcc.inc(),
             CommandKind::MvccByStartTs(_) => KV_COMMAND_COUNTER_VEC_STATIC.start_ts_mvcc.inc(),
+            CommandKind::MvccVersionCount(_) => {
+                KV_COMMAND_COUNTER_VEC_STATIC.mvcc_version_count.inc()
+            }
         }
     }
 
@@ -666,9 +736,12 @@ impl Command {
             CommandKind::ScanLock(_) => metrics::CommandKind::scan_lock,
             CommandKind::ResolveLock(_) => metrics::CommandKind::resolve_lock,
             CommandKind::ResolveLockLite(_) => metrics::CommandKind::resolve_lock_lite,
+            CommandKind::ResolveLockBatch(_) => metrics::CommandKind::resolve_lock_batch,
             CommandKind::Pause(_) => metrics::CommandKind::pause,
+            CommandKind::RawCompareAndSwap(_) => metrics::CommandKind::raw_compare_and_swap,
             CommandKind::MvccByKey(_) => metrics::CommandKind::key_mvcc,
             CommandKind::MvccByStartTs(_) => metrics::CommandKind::start_ts_mvcc,
+            CommandKind::MvccVersionCount(_) => metrics::CommandKind::mvcc_version_count,
         }
     }
 
@@ -686,9 +759,12 @@ impl Command {
             | CommandKind::CheckTxnStatus(CheckTxnStatus { lock_ts, .. }) => lock_ts,
             CommandKind::ScanLock(ScanLock { max_ts, .. }) => max_ts,
             CommandKind::ResolveLockLite(ResolveLockLite { start_ts, .. }) => start_ts,
-            CommandKind::ResolveLock(_) | CommandKind::Pause(_) | CommandKind::MvccByKey(_) => {
-                TimeStamp::zero()
-            }
+            CommandKind::ResolveLock(_)
+            | CommandKind::ResolveLockBatch(_)
+            | CommandKind::Pause(_)
+            | CommandKind::RawCompareAndSwap(_)
+            | CommandKind::MvccByKey(_)
+            | CommandKind::MvccVersionCount(_) => TimeStamp::zero(),
         }
     }
 
@@ -738,7 +814,8 @@ impl Command {
                     bytes += key.as_encoded().len();
                 }
             }
-            CommandKind::ResolveLock(ResolveLock { ref key_locks, .. }) => {
+            CommandKind::ResolveLock(ResolveLock { ref key_locks, .. })
+            | CommandKind::ResolveLockBatch(ResolveLockBatch { ref key_locks, .. }) => {
                 for lock in key_locks {
                     bytes += lock.0.as_encoded().len();
                 }
@@ -763,6 +840,12 @@ impl Command {
             }) => {
                 bytes += primary_key.as_encoded().len();
             }
+            CommandKind::RawCompareAndSwap(RawCompareAndSwap {
+                ref key, ref value, ..
+            }) => {
+                bytes += key.as_encoded().len();
+                bytes += value.len();
+            }
             _ => {}
         }
         bytes
@@ -778,7 +861,8 @@ impl Command {
                 let keys: Vec<&Key> = mutations.iter().map(|(x, _)| x.key()).collect();
                 latches.gen_lock(&keys)
             }
-            CommandKind::ResolveLock(ResolveLock { key_locks, .. }) => {
+            CommandKind::ResolveLock(ResolveLock { key_locks, .. })
+            | CommandKind::ResolveLockBatch(ResolveLockBatch { key_locks, .. }) => {
                 let keys: Vec<&Key> = key_locks.iter().map(|x| &x.0).collect();
                 latches.gen_lock(&keys)
             }
@@ -802,11 +886,15 @@ impl Command {
             CommandKind::CheckTxnStatus(CheckTxnStatus { primary_key, .. }) => {
                 latches.gen_lock(&[primary_key])
             }
+            CommandKind::RawCompareAndSwap(RawCompareAndSwap { key, .. }) => {
+                latches.gen_lock(&[key])
+            }
 
             // Avoid using wildcard _ here to avoid forgetting add new commands here.
             CommandKind::ScanLock(_)
             | CommandKind::MvccByKey(_)
-            | CommandKind::MvccByStartTs(_) => latch::Lock::new(vec![]),
+            | CommandKind::MvccByStartTs(_)
+            | CommandKind::MvccVersionCount(_) => latch::Lock::new(vec![]),
         }
     }
 
@@ -938,6 +1026,7 @@ impl Display for Command {
             ),
             CommandKind::ResolveLock(_) => write!(f, "kv::resolve_lock"),
             CommandKind::ResolveLockLite(_) => write!(f, "kv::resolve_lock_lite"),
+            CommandKind::ResolveLockBatch(_) => write!(f, "kv::resolve_lock_batch"),
             CommandKind::Pause(Pause { ref keys, duration }) => write!(
                 f,
                 "kv::command::pause keys:({}) {} ms | {:?}",
@@ -945,6 +1034,13 @@ impl Display for Command {
                 duration,
                 self.ctx,
             ),
+            CommandKind::RawCompareAndSwap(RawCompareAndSwap {
+                cf, ref key, ..
+            }) => write!(
+                f,
+                "kv::command::raw_compare_and_swap {:?} in cf {} | {:?}",
+                key, cf, self.ctx,
+            ),
             CommandKind::MvccByKey(MvccByKey { ref key }) => {
                 write!(f, "kv::command::mvccbykey {:?} | {:?}", key, self.ctx)
             }
@@ -953,6 +1049,15 @@ impl Display for Command {
                 "kv::command::mvccbystartts {:?} | {:?}",
                 start_ts, self.ctx
             ),
+            CommandKind::MvccVersionCount(MvccVersionCount {
+                ref key,
+                start_ts,
+                end_ts,
+            }) => write!(
+                f,
+                "kv::command::mvccversioncount {:?} [{}, {}] | {:?}",
+                key, start_ts, end_ts, self.ctx
+            ),
         }
     }
 }
@@ -18,7 +18,7 @@ use kvproto::kvrpcpb::LockInfo;
 use std::error;
 use std::fmt;
 use std::io::Error as IoError;
-use txn_types::{Key, TimeStamp};
+use txn_types::{Key, TimeStamp, Value};
 
 pub use self::commands::Command;
 pub use self::process::RESOLVE_LOCK_BATCH_SIZE;
@@ -54,6 +54,18 @@ pub enum ProcessResult {
     PessimisticLockRes {
         res: StorageResult<PessimisticLockRes>,
     },
+    RawCompareAndSwapRes {
+        previous_value: Option<Value>,
+        succeed: bool,
+    },
+    MvccVersionCount {
+        count: u64,
+        oldest_commit_ts: TimeStamp,
+        newest_commit_ts: TimeStamp,
+    },
+    ResolveLockBatchRes {
+        resolved: u64,
+    },
 }
 
 impl ProcessResult {
@@ -118,6 +130,10 @@ quick_error! {
                         lower_bound.as_ref().map(hex::encode_upper).unwrap_or_else(|| "(none)".to_owned()),
                         upper_bound.as_ref().map(hex::encode_upper).unwrap_or_else(|| "(none)".to_owned()))
         }
+        DeadlineExceeded {
+            description("Deadline is exceeded")
+            display("Deadline is exceeded")
+        }
     }
 }
 
@@ -145,6 +161,7 @@ impl ErrorInner {
                 lower_bound: lower_bound.clone(),
                 upper_bound: upper_bound.clone(),
             }),
+            ErrorInner::DeadlineExceeded => Some(ErrorInner::DeadlineExceeded),
             ErrorInner::Other(_) | ErrorInner::ProtoBuf(_) | ErrorInner::Io(_) => None,
         }
     }
@@ -21,8 +21,10 @@ use crate::storage::mvcc::{
 use crate::storage::txn::{
     commands::{
         AcquirePessimisticLock, CheckTxnStatus, Cleanup, Command, CommandKind, Commit, MvccByKey,
-        MvccByStartTs, Pause, PessimisticRollback, Prewrite, PrewritePessimistic, ResolveLock,
-        ResolveLockLite, Rollback, ScanLock, TxnHeartBeat,
+        MvccByStartTs, MvccVersionCount, Pause, PessimisticRollback, Prewrite,
+        PrewritePessimistic, RawCompareAndSwap, ResolveLock, ResolveLockBatch, ResolveLockLite,
+        Rollback, ScanLock,
+        TxnHeartBeat,
     },
     sched_pool::*,
     scheduler::Msg,
@@ -30,11 +32,13 @@ use crate::storage::txn::{
 };
 use crate::storage::{
     metrics::{self, KV_COMMAND_KEYWRITE_HISTOGRAM_VEC, SCHED_STAGE_COUNTER_VEC},
+    raw_ttl,
     types::{MvccInfo, PessimisticLockRes, TxnStatus},
     Error as StorageError, ErrorInner as StorageErrorInner, Result as StorageResult,
 };
 use engine_traits::CF_WRITE;
 use tikv_util::collections::HashMap;
+use tikv_util::deadline::Deadline;
 use tikv_util::time::Instant;
 
 pub const FORWARD_MIN_MUTATIONS_NUM: usize = 12;
@@ -51,16 +55,29 @@ pub struct Task {
     cmd: Command,
     ts: TimeStamp,
     region_id: u64,
+    // The deadline the client attached to the command, if any. Commands that
+    // have not started processing when it passes abort with `DeadlineExceeded`
+    // instead of occupying a worker.
+    deadline: Option<Deadline>,
 }
 
 impl Task {
     /// Creates a task for a running command.
     pub fn new(cid: u64, cmd: Command) -> Task {
+        let max_execution_duration = cmd.ctx.get_max_execution_duration_ms();
+        let deadline = if max_execution_duration > 0 {
+            Some(Deadline::from_now(Duration::from_millis(
+                max_execution_duration,
+            )))
+        } else {
+            None
+        };
         Task {
             cid,
             tag: cmd.tag(),
             region_id: cmd.ctx.get_region_id(),
             ts: cmd.ts(),
+            deadline,
             cmd,
         }
     }
@@ -180,6 +197,24 @@ impl<E: Engine, S: MsgScheduler, L: LockManager> Executor<E, S, L> {
             .spawn(move || {
                 fail_point!("scheduler_async_snapshot_finish");
 
+                // The client has given up on commands whose deadline passed
+                // while they waited for the snapshot; abort instead of doing
+                // the work for nothing.
+                if let Some(deadline) = task.deadline {
+                    if deadline.check().is_err() {
+                        SCHED_STAGE_COUNTER_VEC.get(tag).error.inc();
+                        notify_scheduler(
+                            self.take_scheduler(),
+                            Msg::FinishedWithErr {
+                                cid: task.cid,
+                                err: Error::from(ErrorInner::DeadlineExceeded),
+                                tag,
+                            },
+                        );
+                        return future::ok::<_, ()>(());
+                    }
+                }
+
                 let read_duration = Instant::now_coarse();
 
                 let region_id = task.region_id;
@@ -385,6 +420,46 @@ fn process_read_impl<E: Engine>(
                 None => Ok(ProcessResult::MvccStartTs { mvcc: None }),
             }
         }
+        CommandKind::MvccVersionCount(MvccVersionCount {
+            ref key,
+            start_ts,
+            end_ts,
+        }) => {
+            let mut reader = MvccReader::new(
+                snapshot,
+                Some(ScanMode::Forward),
+                !cmd.ctx.get_not_fill_cache(),
+                cmd.ctx.get_isolation_level(),
+            );
+            let mut count = 0;
+            let mut oldest_commit_ts = TimeStamp::zero();
+            let mut newest_commit_ts = TimeStamp::zero();
+            let mut seek_ts = if end_ts.is_zero() {
+                TimeStamp::max()
+            } else {
+                end_ts
+            };
+            while let Some((commit_ts, _)) = reader.seek_write(key, seek_ts)? {
+                if commit_ts < start_ts {
+                    break;
+                }
+                if count == 0 {
+                    newest_commit_ts = commit_ts;
+                }
+                oldest_commit_ts = commit_ts;
+                count += 1;
+                if commit_ts.is_zero() {
+                    break;
+                }
+                seek_ts = commit_ts.prev();
+            }
+            statistics.add(reader.get_statistics());
+            Ok(ProcessResult::MvccVersionCount {
+                count,
+                oldest_commit_ts,
+                newest_commit_ts,
+            })
+        }
         // Scans locks with timestamp <= `max_ts`
         CommandKind::ScanLock(ScanLock {
             max_ts,
@@ -457,6 +532,49 @@ fn process_read_impl<E: Engine>(
                 })
             }
         }
+        CommandKind::ResolveLockBatch(ResolveLockBatch {
+            ref mut txn_status,
+            ref scan_key,
+            resolved,
+            ..
+        }) => {
+            let mut reader = MvccReader::new(
+                snapshot,
+                Some(ScanMode::Forward),
+                !cmd.ctx.get_not_fill_cache(),
+                cmd.ctx.get_isolation_level(),
+            );
+            let result = reader.scan_locks(
+                scan_key.as_ref(),
+                |lock| txn_status.contains_key(&lock.ts),
+                RESOLVE_LOCK_BATCH_SIZE,
+            );
+            statistics.add(reader.get_statistics());
+            let (kv_pairs, has_remain) = result?;
+            tls_collect_keyread_histogram_vec(tag.get_str(), kv_pairs.len() as f64);
+
+            if kv_pairs.is_empty() {
+                Ok(ProcessResult::ResolveLockBatchRes { resolved })
+            } else {
+                let next_scan_key = if has_remain {
+                    // There might be more locks.
+                    kv_pairs.last().map(|(k, _lock)| k.clone())
+                } else {
+                    // All locks are scanned
+                    None
+                };
+                Ok(ProcessResult::NextCommand {
+                    cmd: ResolveLockBatch::new(
+                        mem::replace(txn_status, Default::default()),
+                        next_scan_key,
+                        kv_pairs,
+                        resolved,
+                        cmd.ctx.clone(),
+                    )
+                    .into(),
+                })
+            }
+        }
         _ => panic!("unsupported read command"),
     }
 }
@@ -875,6 +993,104 @@ fn process_write_impl<S: Snapshot, L: LockManager>(
 
             (pr, modifies, rows, cmd.ctx, None)
         }
+        CommandKind::ResolveLockBatch(ResolveLockBatch {
+            txn_status,
+            mut scan_key,
+            key_locks,
+            mut resolved,
+        }) => {
+            // Map (txn's start_ts, is_pessimistic_txn) => Option<key_hashes>
+            let (mut txn_to_keys, has_waiter) = if let Some(lm) = lock_mgr.as_ref() {
+                (Some(HashMap::default()), lm.has_waiter())
+            } else {
+                (None, false)
+            };
+
+            let mut scan_key = scan_key.take();
+            let mut modifies: Vec<Modify> = vec![];
+            let mut write_size = 0;
+            let rows = key_locks.len();
+            for (current_key, current_lock) in key_locks {
+                if let Some(txn_to_keys) = txn_to_keys.as_mut() {
+                    txn_to_keys
+                        .entry((current_lock.ts, !current_lock.for_update_ts.is_zero()))
+                        .and_modify(|key_hashes: &mut Option<Vec<u64>>| {
+                            if let Some(key_hashes) = key_hashes {
+                                key_hashes.push(current_key.gen_hash());
+                            }
+                        })
+                        .or_insert_with(|| {
+                            if has_waiter {
+                                Some(vec![current_key.gen_hash()])
+                            } else {
+                                None
+                            }
+                        });
+                }
+
+                let mut txn = MvccTxn::new(
+                    snapshot.clone(),
+                    current_lock.ts,
+                    !cmd.ctx.get_not_fill_cache(),
+                );
+                let status = txn_status.get(&current_lock.ts);
+                let commit_ts = match status {
+                    Some(ts) => *ts,
+                    None => panic!("txn status {} not found.", current_lock.ts),
+                };
+                if !commit_ts.is_zero() {
+                    if current_lock.ts >= commit_ts {
+                        return Err(Error::from(ErrorInner::InvalidTxnTso {
+                            start_ts: current_lock.ts,
+                            commit_ts,
+                        }));
+                    }
+                    txn.commit(current_key.clone(), commit_ts)?;
+                } else {
+                    txn.rollback(current_key.clone())?;
+                }
+                resolved += 1;
+                write_size += txn.write_size();
+
+                statistics.add(&txn.take_statistics());
+                modifies.append(&mut txn.into_modifies());
+
+                if write_size >= MAX_TXN_WRITE_SIZE {
+                    scan_key = Some(current_key);
+                    break;
+                }
+            }
+            if let Some(txn_to_keys) = txn_to_keys {
+                txn_to_keys
+                    .into_iter()
+                    .for_each(|((ts, is_pessimistic_txn), key_hashes)| {
+                        wake_up_waiters_if_needed(
+                            &lock_mgr,
+                            ts,
+                            key_hashes,
+                            TimeStamp::zero(),
+                            is_pessimistic_txn,
+                        );
+                    });
+            }
+
+            let pr = if scan_key.is_none() {
+                ProcessResult::ResolveLockBatchRes { resolved }
+            } else {
+                ProcessResult::NextCommand {
+                    cmd: ResolveLockBatch::new(
+                        txn_status,
+                        scan_key.take(),
+                        vec![],
+                        resolved,
+                        cmd.ctx.clone(),
+                    )
+                    .into(),
+                }
+            };
+
+            (pr, modifies, rows, cmd.ctx, None)
+        }
         CommandKind::ResolveLockLite(ResolveLockLite {
             start_ts,
             commit_ts,
@@ -961,6 +1177,39 @@ fn process_write_impl<S: Snapshot, L: LockManager>(
             thread::sleep(Duration::from_millis(duration));
             (ProcessResult::Res, vec![], 0, cmd.ctx, None)
         }
+        CommandKind::RawCompareAndSwap(RawCompareAndSwap {
+            cf,
+            key,
+            previous_value,
+            mut value,
+            ttl,
+            enable_ttl,
+        }) => {
+            // With TTL enabled the stored value carries an expire-ts suffix;
+            // compare (and report) the user-visible value, treating expired
+            // keys as absent.
+            let current = snapshot.get_cf(cf, &key)?;
+            let current = if enable_ttl {
+                raw_ttl::check_ttl_value(current).map_err(|e| -> Error { box_err!("{}", e) })?
+            } else {
+                current
+            };
+            let succeed = current == previous_value;
+            let (to_be_write, rows) = if succeed {
+                if enable_ttl {
+                    let expire_ts = raw_ttl::convert_to_expire_ts(ttl);
+                    raw_ttl::append_expire_ts(&mut value, expire_ts);
+                }
+                (vec![Modify::Put(cf, key, value)], 1)
+            } else {
+                (vec![], 0)
+            };
+            let pr = ProcessResult::RawCompareAndSwapRes {
+                previous_value: current,
+                succeed,
+            };
+            (pr, to_be_write, rows, cmd.ctx, None)
+        }
         _ => panic!("unsupported write command"),
     };
 
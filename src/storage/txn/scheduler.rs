@@ -31,7 +31,10 @@ use prometheus::HistogramTimer;
 use tikv_util::{collections::HashMap, time::Instant};
 use txn_types::TimeStamp;
 
-use crate::storage::kv::{with_tls_engine, Engine, Result as EngineResult};
+use crate::storage::kv::{
+    with_tls_engine, Engine, Error as EngineError, ErrorInner as EngineErrorInner,
+    Result as EngineResult,
+};
 use crate::storage::lock_manager::{self, LockManager, WaitTimeout};
 use crate::storage::metrics::{
     self, SCHED_COMMANDS_PRI_COUNTER_VEC_STATIC, SCHED_CONTEX_GAUGE, SCHED_HISTOGRAM_VEC_STATIC,
@@ -43,7 +46,7 @@ use crate::storage::txn::{
     latch::{Latches, Lock},
     process::{Executor, MsgScheduler, Task},
     sched_pool::SchedPool,
-    Error, ProcessResult,
+    Error, ErrorInner, ProcessResult,
 };
 use crate::storage::{
     get_priority_tag, types::StorageCallback, Error as StorageError,
@@ -119,6 +122,11 @@ struct TaskContext {
     cb: Option<StorageCallback>,
     write_bytes: usize,
     tag: metrics::CommandKind,
+    // A copy of the command for replaying it on stale-command errors. `None`
+    // when retrying is disabled or the retry quota is used up.
+    retry_cmd: Option<Command>,
+    // How many times the command can still be retried internally.
+    retries_left: usize,
     // How long it waits on latches.
     latch_timer: Option<HistogramTimer>,
     // Total duration of a command.
@@ -126,7 +134,7 @@ struct TaskContext {
 }
 
 impl TaskContext {
-    fn new(task: Task, latches: &Latches, cb: StorageCallback) -> TaskContext {
+    fn new(task: Task, latches: &Latches, cb: StorageCallback, retries: usize) -> TaskContext {
         let tag = task.cmd().tag();
         let lock = task.cmd().gen_lock(latches);
         // Write command should acquire write lock.
@@ -138,6 +146,11 @@ impl TaskContext {
         } else {
             0
         };
+        let retry_cmd = if retries > 0 {
+            Some(task.cmd().clone())
+        } else {
+            None
+        };
 
         TaskContext {
             task: Some(task),
@@ -145,6 +158,8 @@ impl TaskContext {
             cb: Some(cb),
             write_bytes,
             tag,
+            retry_cmd,
+            retries_left: retries,
             latch_timer: Some(SCHED_LATCH_HISTOGRAM_VEC.get(tag).start_coarse_timer()),
             _cmd_timer: SCHED_HISTOGRAM_VEC_STATIC.get(tag).start_coarse_timer(),
         }
@@ -178,6 +193,10 @@ struct SchedulerInner<L: LockManager> {
 
     lock_mgr: Option<L>,
 
+    // How many times a command is replayed with a fresh snapshot when the
+    // engine reports a stale command or a leader change.
+    stale_command_retries: usize,
+
     pipelined_pessimistic_lock: bool,
 }
 
@@ -203,7 +222,7 @@ impl<L: LockManager> SchedulerInner<L> {
 
     fn enqueue_task(&self, task: Task, callback: StorageCallback) {
         let cid = task.cid;
-        let tctx = TaskContext::new(task, &self.latches, callback);
+        let tctx = TaskContext::new(task, &self.latches, callback, self.stale_command_retries);
 
         let running_write_bytes = self
             .running_write_bytes
@@ -276,6 +295,7 @@ impl<E: Engine, L: LockManager> Scheduler<E, L> {
         concurrency: usize,
         worker_pool_size: usize,
         sched_pending_write_threshold: usize,
+        stale_command_retries: usize,
         pipelined_pessimistic_lock: bool,
     ) -> Self {
         // Add 2 logs records how long is need to initialize TASKS_SLOTS_NUM * 2048000 `Mutex`es.
@@ -299,6 +319,7 @@ impl<E: Engine, L: LockManager> Scheduler<E, L> {
                 "sched-high-pri-pool",
             ),
             lock_mgr,
+            stale_command_retries,
             pipelined_pessimistic_lock,
         });
 
@@ -409,8 +430,44 @@ impl<E: Engine, L: LockManager> Scheduler<E, L> {
         }
     }
 
+    /// Tries to replay the command with a fresh snapshot after the engine
+    /// reported a stale command or a leader change.
+    ///
+    /// Returns `true` if the command has been rescheduled, in which case the
+    /// caller must not finish the task. The latches held by the command are
+    /// kept across the retry, so the replayed command still has exclusive
+    /// access to its keys.
+    fn try_retry_stale_command(&self, cid: u64) -> bool {
+        let tag = {
+            let mut task_contexts = self.inner.task_contexts[id_index(cid)].lock();
+            let tctx = match task_contexts.get_mut(&cid) {
+                Some(tctx) => tctx,
+                None => return false,
+            };
+            if tctx.retries_left == 0 || tctx.retry_cmd.is_none() {
+                return false;
+            }
+            tctx.retries_left -= 1;
+            let cmd = if tctx.retries_left > 0 {
+                tctx.retry_cmd.clone().unwrap()
+            } else {
+                tctx.retry_cmd.take().unwrap()
+            };
+            tctx.task = Some(Task::new(cid, cmd));
+            tctx.tag
+        };
+
+        SCHED_STAGE_COUNTER_VEC.get(tag).stale_retry.inc();
+        info!("retrying stale command with a fresh snapshot"; "cid" => cid);
+        self.get_snapshot(cid);
+        true
+    }
+
     /// Calls the callback with an error.
     fn finish_with_err(&self, cid: u64, err: Error) {
+        if is_retryable_err(&err) && self.try_retry_stale_command(cid) {
+            return;
+        }
         debug!("write command finished with error"; "cid" => cid);
         let tctx = self.inner.dequeue_task_context(cid);
 
@@ -452,6 +509,14 @@ impl<E: Engine, L: LockManager> Scheduler<E, L> {
         pipelined: bool,
         tag: metrics::CommandKind,
     ) {
+        // Pipelined writes have responded to the client already, so they must
+        // not be replayed.
+        if let Err(ref e) = result {
+            if !pipelined && is_retryable_engine_err(e) && self.try_retry_stale_command(cid) {
+                return;
+            }
+        }
+
         if !pipelined {
             SCHED_STAGE_COUNTER_VEC.get(tag).write_finish.inc();
         } else {
@@ -505,6 +570,7 @@ impl<E: Engine, L: LockManager> Scheduler<E, L> {
             lock,
             is_first_lock,
             wait_timeout,
+            tctx.tag,
         );
         self.release_lock(&tctx.lock, cid);
     }
@@ -547,6 +613,25 @@ impl<E: Engine, L: LockManager> MsgScheduler for Scheduler<E, L> {
     }
 }
 
+/// Returns whether the engine error indicates a stale command or a leader
+/// change, which a replay with a fresh snapshot may resolve. Genuine
+/// transaction conflicts never match.
+fn is_retryable_engine_err(e: &EngineError) -> bool {
+    match *e {
+        EngineError(box EngineErrorInner::Request(ref header)) => {
+            header.has_stale_command() || header.has_not_leader()
+        }
+        _ => false,
+    }
+}
+
+fn is_retryable_err(err: &Error) -> bool {
+    match *err {
+        Error(box ErrorInner::Engine(ref e)) => is_retryable_engine_err(e),
+        _ => false,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
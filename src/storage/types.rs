@@ -165,6 +165,9 @@ storage_callback! {
     Locks(Vec<kvrpcpb::LockInfo>) ProcessResult::Locks { locks } => locks,
     TxnStatus(TxnStatus) ProcessResult::TxnStatus { txn_status } => txn_status,
     PessimisticLock(Result<PessimisticLockRes>) ProcessResult::PessimisticLockRes { res } => res,
+    RawCompareAndSwap((Option<Value>, bool)) ProcessResult::RawCompareAndSwapRes { previous_value, succeed } => (previous_value, succeed),
+    MvccVersionCount((u64, TimeStamp, TimeStamp)) ProcessResult::MvccVersionCount { count, oldest_commit_ts, newest_commit_ts } => (count, oldest_commit_ts, newest_commit_ts),
+    ResolveLockBatch(u64) ProcessResult::ResolveLockBatchRes { resolved } => resolved,
 }
 
 pub trait StorageCallbackType: Sized {
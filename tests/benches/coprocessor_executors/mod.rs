@@ -12,6 +12,71 @@ mod table_scan;
 mod top_n;
 mod util;
 
+/// Measures the bytes allocated by the global allocator, as reported by `tikv_alloc`.
+///
+/// On platforms where the allocator does not expose statistics this reports zero.
+struct AllocBytesMeasurement;
+
+fn allocated_bytes() -> usize {
+    match tikv_alloc::fetch_stats() {
+        Ok(Some(stats)) => stats
+            .iter()
+            .find(|(name, _)| *name == "allocated")
+            .map_or(0, |(_, value)| *value),
+        _ => 0,
+    }
+}
+
+struct AllocBytesFormatter;
+
+impl criterion::measurement::ValueFormatter for AllocBytesFormatter {
+    fn scale_values(&self, _typical_value: f64, _values: &mut [f64]) -> &'static str {
+        "bytes"
+    }
+
+    fn scale_throughputs(
+        &self,
+        _typical_value: f64,
+        _throughput: &criterion::Throughput,
+        _values: &mut [f64],
+    ) -> &'static str {
+        "bytes"
+    }
+
+    fn scale_for_machines(&self, _values: &mut [f64]) -> &'static str {
+        "bytes"
+    }
+}
+
+impl criterion::measurement::Measurement for AllocBytesMeasurement {
+    type Intermediate = usize;
+    type Value = usize;
+
+    fn start(&self) -> Self::Intermediate {
+        allocated_bytes()
+    }
+
+    fn end(&self, i: Self::Intermediate) -> Self::Value {
+        allocated_bytes().saturating_sub(i)
+    }
+
+    fn add(&self, v1: &Self::Value, v2: &Self::Value) -> Self::Value {
+        v1 + v2
+    }
+
+    fn zero(&self) -> Self::Value {
+        0
+    }
+
+    fn to_f64(&self, value: &Self::Value) -> f64 {
+        *value as f64
+    }
+
+    fn formatter(&self) -> &dyn criterion::measurement::ValueFormatter {
+        &AllocBytesFormatter
+    }
+}
+
 fn execute<M: criterion::measurement::Measurement + 'static>(c: &mut criterion::Criterion<M>) {
     util::fixture::bench(c);
     table_scan::bench(c);
@@ -41,6 +106,12 @@ fn run_bench(measurement: &str) {
                 .configure_from_args();
             execute(&mut c);
         }
+        "ALLOC" => {
+            let mut c = criterion::Criterion::default()
+                .with_measurement(AllocBytesMeasurement)
+                .configure_from_args();
+            execute(&mut c);
+        }
         _ => {
             panic!("unknown measurement");
         }
@@ -56,6 +127,12 @@ fn run_bench(measurement: &str) {
                 .configure_from_args();
             execute(&mut c);
         }
+        "ALLOC" => {
+            let mut c = criterion::Criterion::default()
+                .with_measurement(AllocBytesMeasurement)
+                .configure_from_args();
+            execute(&mut c);
+        }
         _ => {
             panic!("unknown measurement");
         }
@@ -67,3 +144,20 @@ fn main() {
 
     run_bench(&measurement);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use criterion::measurement::Measurement;
+
+    #[test]
+    fn test_alloc_measurement_initializes() {
+        let m = AllocBytesMeasurement;
+        let start = m.start();
+        let buf: Vec<u8> = Vec::with_capacity(4096);
+        let end = m.end(start);
+        drop(buf);
+        assert_eq!(m.add(&end, &m.zero()), end);
+        let _ = criterion::Criterion::default().with_measurement(AllocBytesMeasurement);
+    }
+}
@@ -48,6 +48,20 @@ where
     input.bencher.bench(b, &fb, &[expr]);
 }
 
+/// For SQLs like `WHERE a > 1` over wide rows, where most of the columns are not referenced by
+/// the predicate at all.
+fn bench_selection_binary_func_wide_row<M>(b: &mut criterion::Bencher<M>, input: &Input<M>)
+where
+    M: Measurement,
+{
+    let fb = FixtureBuilder::new(input.src_rows).push_columns_mixed(100);
+    let expr = ExprDefBuilder::scalar_func(ScalarFuncSig::GtInt, FieldTypeTp::LongLong)
+        .push_child(ExprDefBuilder::column_ref(0, FieldTypeTp::LongLong))
+        .push_child(ExprDefBuilder::constant_int(0))
+        .build();
+    input.bencher.bench(b, &fb, &[expr]);
+}
+
 /// For SQLs like `WHERE a > 1 AND b > 2`.
 fn bench_selection_multiple_predicate<M>(b: &mut criterion::Bencher<M>, input: &Input<M>)
 where
@@ -130,6 +144,10 @@ where
                 "selection_multiple_predicate",
                 bench_selection_multiple_predicate,
             ),
+            BenchCase::new(
+                "selection_binary_func_wide_row",
+                bench_selection_binary_func_wide_row,
+            ),
         ];
         cases.append(&mut additional_cases);
     }
@@ -44,6 +44,40 @@ pub fn table_with_multi_columns(rows: usize, columns: usize) -> (Table, Store<Ro
     (table, store)
 }
 
+/// Builds a fixture table, which contains specified number of columns: col0, col1, col2, ...,
+/// with types alternating between integer and var-char.
+///
+/// This simulates wide production tables where the row decode cost is dominated by the number
+/// of columns instead of the size of a single value.
+pub fn table_with_wide_mixed_columns(rows: usize, columns: usize) -> (Table, Store<RocksEngine>) {
+    let mut table = TableBuilder::new();
+    for idx in 0..columns {
+        let col_type = if idx % 2 == 0 {
+            TYPE_LONG
+        } else {
+            TYPE_VAR_CHAR
+        };
+        let col = ColumnBuilder::new().col_type(col_type).build();
+        table = table.add_col(format!("col{}", idx), col);
+    }
+    let table = table.build();
+
+    let mut fb = crate::util::FixtureBuilder::new(rows);
+    let mut col_names = vec![];
+    for idx in 0..columns {
+        fb = if idx % 2 == 0 {
+            fb.push_column_i64_random()
+        } else {
+            fb.push_column_bytes_random_fixed_len(16)
+        };
+        col_names.push(format!("col{}", idx));
+    }
+    let col_names: Vec<_> = col_names.iter().map(|s| s.as_str()).collect();
+    let store = fb.build_store(&table, col_names.as_slice());
+
+    (table, store)
+}
+
 /// Builds a fixture table, which contains specified number of columns: col0, col1, col2, ...,
 /// but the first column does not present in data.
 pub fn table_with_missing_column(rows: usize, columns: usize) -> (Table, Store<RocksEngine>) {
@@ -94,6 +94,22 @@ where
     );
 }
 
+/// 100 interested columns of mixed types, all columns in the row are interested (i.e. there are
+/// totally 100 columns in the row).
+fn bench_table_scan_wide_row_datum_all<M>(b: &mut criterion::Bencher<M>, input: &Input<M>)
+where
+    M: Measurement,
+{
+    let (table, store) = fixture::table_with_wide_mixed_columns(ROWS, 100);
+    input.0.bench(
+        b,
+        &table.columns_info(),
+        &[table.get_record_range_all()],
+        &store,
+        (),
+    );
+}
+
 /// 3 columns in the row and the last column is very long but only PK is interested.
 fn bench_table_scan_long_datum_primary_key<M>(b: &mut criterion::Bencher<M>, input: &Input<M>)
 where
@@ -264,6 +280,10 @@ where
         let mut additional_cases = vec![
             BenchCase::new("table_scan_datum_front", bench_table_scan_datum_front),
             BenchCase::new("table_scan_datum_all", bench_table_scan_datum_all),
+            BenchCase::new(
+                "table_scan_wide_row_datum_all",
+                bench_table_scan_wide_row_datum_all,
+            ),
             BenchCase::new("table_scan_point_range", bench_table_scan_point_range),
         ];
         cases.append(&mut additional_cases);
@@ -31,6 +31,23 @@ const SEED_1: u64 = 0x525C682A2F7CE3DB;
 const SEED_2: u64 = 0xB7CEACC38146676B;
 const SEED_3: u64 = 0x2B877E351BD8628E;
 
+/// Computes a CRC32 checksum over the datum-encoded representation of the given columns.
+///
+/// Columns are hashed in column-major order so that the result can be compared against output
+/// collected column by column from an executor.
+fn checksum_columns(columns: &[Vec<Datum>]) -> u32 {
+    let mut ctx = EvalContext::default();
+    let mut hasher = crc32fast::Hasher::new();
+    for col in columns {
+        for datum in col {
+            let mut v = vec![];
+            v.write_datum(&mut ctx, &[datum.clone()], false).unwrap();
+            hasher.update(&v);
+        }
+    }
+    hasher.finalize()
+}
+
 #[derive(Clone)]
 pub struct FixtureBuilder {
     rows: usize,
@@ -231,6 +248,28 @@ impl FixtureBuilder {
         self
     }
 
+    /// Pushes the specified number of columns whose types cycle through i64, f64, decimal and
+    /// bytes, all filled with random values.
+    ///
+    /// This simulates wide production tables with 100+ columns, where decode cost is dominated
+    /// by the number of columns rather than the size of a single value.
+    pub fn push_columns_mixed(mut self, columns: usize) -> Self {
+        for index in 0..columns {
+            self = match index % 4 {
+                0 => self.push_column_i64_random(),
+                1 => self.push_column_f64_random(),
+                2 => self.push_column_decimal_random(),
+                _ => self.push_column_bytes_random_fixed_len(16),
+            };
+        }
+        self
+    }
+
+    /// Computes the checksum of the fixture data. See [`checksum_columns`].
+    pub fn checksum(&self) -> u32 {
+        checksum_columns(&self.columns)
+    }
+
     pub fn build_store(self, table: &Table, columns: &[&str]) -> Store<RocksEngine> {
         assert!(!columns.is_empty());
         assert_eq!(self.columns.len(), columns.len());
@@ -423,7 +462,7 @@ where
     M: Measurement,
 {
     super::bencher::BatchNext1024Bencher::new(|| {
-        FixtureBuilder::new(5000)
+        FixtureBuilder::new(crate::util::bench_rows(5000))
             .push_column_i64_random()
             .build_batch_fixture_executor()
     })
@@ -435,18 +474,48 @@ where
     M: Measurement,
 {
     super::bencher::NormalNext1024Bencher::new(|| {
-        FixtureBuilder::new(5000)
+        FixtureBuilder::new(crate::util::bench_rows(5000))
             .push_column_i64_random()
             .build_normal_fixture_executor()
     })
     .bench(b);
 }
 
+/// Drains `executor` and asserts that the rows it produces hash to `expected`.
+fn verify_batch_fixture_checksum(expected: u32, mut executor: BatchFixtureExecutor) {
+    let mut columns: Vec<Vec<u8>> = vec![Vec::new(); executor.schema().len()];
+    loop {
+        let r = executor.next_batch(1024);
+        for (col_index, col) in r.physical_columns.as_slice().iter().enumerate() {
+            for v in col.raw().iter() {
+                columns[col_index].extend_from_slice(v);
+            }
+        }
+        if r.is_drained.unwrap() {
+            break;
+        }
+    }
+    let mut hasher = crc32fast::Hasher::new();
+    for col in &columns {
+        hasher.update(col);
+    }
+    assert_eq!(
+        hasher.finalize(),
+        expected,
+        "fixture executor produced rows that do not match the expected checksum"
+    );
+}
+
 /// Checks whether our test utilities themselves are fast enough.
 pub fn bench<M>(c: &mut criterion::Criterion<M>)
 where
     M: Measurement + 'static,
 {
+    if crate::util::verify_checksum() {
+        let builder = FixtureBuilder::new(crate::util::bench_rows(5000)).push_column_i64_random();
+        let expected = builder.checksum();
+        verify_batch_fixture_checksum(expected, builder.build_batch_fixture_executor());
+    }
     if crate::util::bench_level() >= 1 {
         c.bench_function(
             "util_batch_fixture_executor_next_1024",
@@ -458,3 +527,93 @@ where
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksum_columns() {
+        let a = vec![
+            vec![Datum::I64(1), Datum::I64(2)],
+            vec![Datum::Bytes(b"foo".to_vec()), Datum::Bytes(b"bar".to_vec())],
+        ];
+        // Deterministic over the same data.
+        assert_eq!(checksum_columns(&a), checksum_columns(&a));
+
+        // Sensitive to value changes.
+        let b = vec![
+            vec![Datum::I64(1), Datum::I64(3)],
+            vec![Datum::Bytes(b"foo".to_vec()), Datum::Bytes(b"bar".to_vec())],
+        ];
+        assert_ne!(checksum_columns(&a), checksum_columns(&b));
+
+        // Sensitive to column order.
+        let c = vec![
+            vec![Datum::Bytes(b"foo".to_vec()), Datum::Bytes(b"bar".to_vec())],
+            vec![Datum::I64(1), Datum::I64(2)],
+        ];
+        assert_ne!(checksum_columns(&a), checksum_columns(&c));
+    }
+
+    #[test]
+    fn test_wide_mixed_row_codec_roundtrip() {
+        use tidb_query_datatype::codec::table;
+
+        const COLUMNS: usize = 100;
+        const ROWS: usize = 3;
+
+        let mut ctx = EvalContext::default();
+        let builder = FixtureBuilder::new(ROWS).push_columns_mixed(COLUMNS);
+        assert_eq!(builder.columns.len(), COLUMNS);
+        assert_eq!(builder.field_types.len(), COLUMNS);
+
+        let col_ids: Vec<i64> = (0..COLUMNS as i64).collect();
+        let columns_info: HashMap<i64, ColumnInfo> = builder
+            .field_types
+            .iter()
+            .enumerate()
+            .map(|(index, ft)| {
+                let mut ci = ColumnInfo::default();
+                ci.set_column_id(index as i64);
+                let ft = ft.as_accessor();
+                ci.as_mut_accessor()
+                    .set_tp(ft.tp())
+                    .set_flag(ft.flag())
+                    .set_flen(ft.flen())
+                    .set_decimal(ft.decimal())
+                    .set_collation(ft.collation().unwrap());
+                (index as i64, ci)
+            })
+            .collect();
+
+        for row_index in 0..ROWS {
+            let row: Vec<Datum> = builder
+                .columns
+                .iter()
+                .map(|col| col[row_index].clone())
+                .collect();
+            let encoded = table::encode_row(&mut ctx, row.clone(), &col_ids).unwrap();
+            let mut remaining = encoded.as_slice();
+            let decoded = table::decode_row(&mut remaining, &mut ctx, &columns_info).unwrap();
+            assert_eq!(decoded.len(), COLUMNS);
+            for (col_index, expected) in row.into_iter().enumerate() {
+                assert_eq!(decoded[&(col_index as i64)], expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_fixture_builder_row_count() {
+        let builder = FixtureBuilder::new(7)
+            .push_column_i64_0_n()
+            .push_column_f64_0_n();
+        for col in &builder.columns {
+            assert_eq!(col.len(), 7);
+        }
+        let mut executor = builder.build_batch_fixture_executor();
+        let r = executor.next_batch(1024);
+        assert_eq!(r.physical_columns.rows_len(), 7);
+        assert!(r.is_drained.unwrap());
+    }
+}
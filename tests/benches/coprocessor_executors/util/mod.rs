@@ -30,6 +30,27 @@ pub fn bench_level() -> usize {
     }
 }
 
+/// Gets the number of rows benches generate from `TIKV_BENCH_ROWS`, falling back to `default`
+/// when it is unset. The value must parse to a positive integer.
+pub fn bench_rows(default: usize) -> usize {
+    match std::env::var("TIKV_BENCH_ROWS") {
+        Ok(s) => {
+            let rows = s
+                .parse::<usize>()
+                .expect("TIKV_BENCH_ROWS must be a positive integer");
+            assert!(rows > 0, "TIKV_BENCH_ROWS must be a positive integer");
+            rows
+        }
+        Err(_) => default,
+    }
+}
+
+/// Checks whether `TIKV_BENCH_VERIFY_CHECKSUM` is set. When enabled, fixture benches verify the
+/// produced rows against an expected checksum before timing.
+pub fn verify_checksum() -> bool {
+    std::env::var("TIKV_BENCH_VERIFY_CHECKSUM").is_ok()
+}
+
 /// A simple helper function to build the DAG handler.
 pub fn build_dag_handler<TargetTxnStore: TxnStore + 'static>(
     executors: &[PbExecutor],
@@ -10,10 +10,12 @@ use kvproto::kvrpcpb::{self, Context, Op, PrewriteRequest, RawPutRequest};
 use kvproto::tikvpb::TikvClient;
 
 use test_raftstore::{must_get_equal, must_get_none, new_server_cluster};
+use tikv::server::{GRPC_MSG_SLOW_COUNTER_VEC, RAFTKV_READ_PATH_COUNTER_VEC};
 use tikv::storage;
 use tikv::storage::kv::{Error as KvError, ErrorInner as KvErrorInner};
 use tikv::storage::txn::{commands, Error as TxnError, ErrorInner as TxnErrorInner};
 use tikv::storage::*;
+use tikv_util::config::ReadableDuration;
 use tikv_util::HandyRwLock;
 use txn_types::Key;
 use txn_types::{Mutation, TimeStamp};
@@ -72,6 +74,143 @@ fn test_scheduler_leader_change_twice() {
     }
 }
 
+#[test]
+fn test_scheduler_deadline_exceeded() {
+    let snapshot_fp = "scheduler_async_snapshot_finish";
+    let storage = TestStorageBuilder::new().build().unwrap();
+
+    let mut ctx = Context::default();
+    ctx.set_max_execution_duration_ms(100);
+    let (prewrite_tx, prewrite_rx) = channel();
+    fail::cfg(snapshot_fp, "pause").unwrap();
+    storage
+        .sched_txn_command(
+            commands::Prewrite::new(
+                vec![Mutation::Put((Key::from_raw(b"k"), b"v".to_vec()))],
+                b"k".to_vec(),
+                10.into(),
+                0,
+                false,
+                0,
+                TimeStamp::default(),
+                ctx,
+            ),
+            Box::new(move |res: storage::Result<_>| {
+                prewrite_tx.send(res).unwrap();
+            }),
+        )
+        .unwrap();
+    // Keep the command paused until well past its deadline.
+    thread::sleep(Duration::from_millis(500));
+    fail::remove(snapshot_fp);
+
+    match prewrite_rx.recv_timeout(Duration::from_secs(5)).unwrap() {
+        Err(Error(box ErrorInner::Txn(TxnError(box TxnErrorInner::DeadlineExceeded)))) => {}
+        res => panic!("expect deadline exceeded, but got {:?}", res),
+    }
+}
+
+#[test]
+fn test_scheduler_stale_command_retry() {
+    let snapshot_fp = "scheduler_async_snapshot_finish";
+    let mut cluster = new_server_cluster(0, 2);
+    cluster.run();
+    let region0 = cluster.get_region(b"");
+    let peers = region0.get_peers();
+    cluster.must_transfer_leader(region0.get_id(), peers[0].clone());
+    let engine0 = cluster.sim.rl().storages[&peers[0].get_id()].clone();
+    let mut config = storage::config::Config::default();
+    config.scheduler_stale_command_retries = 1;
+    let storage0 = TestStorageBuilder::from_engine(engine0)
+        .config(config)
+        .build()
+        .unwrap();
+
+    let mut ctx0 = Context::default();
+    ctx0.set_region_id(region0.get_id());
+    ctx0.set_region_epoch(region0.get_region_epoch().clone());
+    ctx0.set_peer(peers[0].clone());
+    let (prewrite_tx, prewrite_rx) = channel();
+    fail::cfg(snapshot_fp, "pause").unwrap();
+    storage0
+        .sched_txn_command(
+            commands::Prewrite::new(
+                vec![Mutation::Put((Key::from_raw(b"k"), b"v".to_vec()))],
+                b"k".to_vec(),
+                10.into(),
+                0,
+                false,
+                0,
+                TimeStamp::default(),
+                ctx0,
+            ),
+            Box::new(move |res: storage::Result<_>| {
+                prewrite_tx.send(res).unwrap();
+            }),
+        )
+        .unwrap();
+    // Sleep to make sure the failpoint is triggered.
+    thread::sleep(Duration::from_millis(2000));
+    // Transfer leader twice so the prepared snapshot becomes stale, then
+    // unblock it. The leader ends up on peer 0 again, so one internal retry
+    // with a fresh snapshot must succeed.
+    cluster.must_transfer_leader(region0.get_id(), peers[1].clone());
+    cluster.must_transfer_leader(region0.get_id(), peers[0].clone());
+    fail::remove(snapshot_fp);
+
+    let res = prewrite_rx
+        .recv_timeout(Duration::from_secs(5))
+        .unwrap()
+        .unwrap();
+    for r in res {
+        r.unwrap();
+    }
+}
+
+#[test]
+fn test_raftkv_lease_expired_fallback_to_read_index() {
+    let lease_fp = "raftkv_lease_expired";
+    let mut cluster = new_server_cluster(0, 1);
+    cluster.run();
+    cluster.must_put(b"k1", b"v1");
+
+    let region = cluster.get_region(b"");
+    let leader = region.get_peers()[0].clone();
+    let engine = cluster.sim.rl().storages[&leader.get_id()].clone();
+
+    let mut ctx = Context::default();
+    ctx.set_region_id(region.get_id());
+    ctx.set_region_epoch(region.get_region_epoch().clone());
+    ctx.set_peer(leader);
+
+    // With a valid lease reads take the lease path.
+    let lease_before = RAFTKV_READ_PATH_COUNTER_VEC
+        .with_label_values(&["lease"])
+        .get();
+    engine.snapshot(&ctx).unwrap();
+    assert!(
+        RAFTKV_READ_PATH_COUNTER_VEC
+            .with_label_values(&["lease"])
+            .get()
+            > lease_before
+    );
+
+    // An expired lease must fall back to read index, and the read must still
+    // succeed through the quorum path.
+    fail::cfg(lease_fp, "return()").unwrap();
+    let read_index_before = RAFTKV_READ_PATH_COUNTER_VEC
+        .with_label_values(&["read_index"])
+        .get();
+    engine.snapshot(&ctx).unwrap();
+    assert!(
+        RAFTKV_READ_PATH_COUNTER_VEC
+            .with_label_values(&["read_index"])
+            .get()
+            > read_index_before
+    );
+    fail::remove(lease_fp);
+}
+
 #[test]
 fn test_server_catching_api_error() {
     let raftkv_fp = "raftkv_early_error_report";
@@ -129,3 +268,52 @@ fn test_server_catching_api_error() {
     assert!(!put_resp.has_region_error(), "{:?}", put_resp);
     must_get_equal(&cluster.get_engine(1), b"k3", b"v3");
 }
+
+#[test]
+fn test_slow_request_log() {
+    let snapshot_fp = "scheduler_async_snapshot_finish";
+    let mut cluster = new_server_cluster(0, 1);
+    cluster.cfg.server.slow_request_threshold = ReadableDuration::millis(50);
+    cluster.run();
+    let region = cluster.get_region(b"");
+    let leader = region.get_peers()[0].clone();
+
+    let env = Arc::new(Environment::new(1));
+    let channel =
+        ChannelBuilder::new(env).connect(cluster.sim.rl().get_addr(leader.get_store_id()));
+    let client = TikvClient::new(channel);
+
+    let mut ctx = Context::default();
+    ctx.set_region_id(region.get_id());
+    ctx.set_region_epoch(region.get_region_epoch().clone());
+    ctx.set_peer(leader);
+
+    let mut prewrite_req = PrewriteRequest::default();
+    prewrite_req.set_context(ctx);
+    let mut mutation = kvrpcpb::Mutation::default();
+    mutation.op = Op::Put.into();
+    mutation.key = b"k_slow".to_vec();
+    mutation.value = b"v".to_vec();
+    prewrite_req.set_mutations(vec![mutation].into_iter().collect());
+    prewrite_req.primary_lock = b"k_slow".to_vec();
+    prewrite_req.start_version = 1;
+    prewrite_req.lock_ttl = 2;
+
+    let before = GRPC_MSG_SLOW_COUNTER_VEC
+        .with_label_values(&["kv_prewrite"])
+        .get();
+
+    // Delay the command in the scheduler until well past the threshold; the
+    // request must show up in the slow-request counter and (sampled) log.
+    fail::cfg(snapshot_fp, "sleep(200)").unwrap();
+    let resp = client.kv_prewrite(&prewrite_req).unwrap();
+    assert!(!resp.has_region_error(), "{:?}", resp);
+    fail::remove(snapshot_fp);
+
+    assert!(
+        GRPC_MSG_SLOW_COUNTER_VEC
+            .with_label_values(&["kv_prewrite"])
+            .get()
+            > before
+    );
+}
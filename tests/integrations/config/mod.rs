@@ -61,6 +61,7 @@ fn test_serde_custom_tikv_config() {
         advertise_addr: "example.com:443".to_owned(),
         status_addr: "example.com:443".to_owned(),
         status_thread_pool_size: 1,
+        enable_debug_profiling: true,
         concurrent_send_snap_limit: 4,
         concurrent_recv_snap_limit: 4,
         grpc_compression_type: GrpcCompressionType::Gzip,
@@ -68,9 +69,14 @@ fn test_serde_custom_tikv_config() {
         grpc_concurrent_stream: 1_234,
         grpc_memory_pool_quota: ReadableSize(123_456),
         grpc_raft_conn_num: 123,
+        raft_msg_max_batch_size: 256,
+        raft_client_queue_size: 1234,
         grpc_stream_initial_window_size: ReadableSize(12_345),
-        grpc_keepalive_time: ReadableDuration::secs(3),
-        grpc_keepalive_timeout: ReadableDuration::secs(60),
+        grpc_keepalive_time: ReadableDuration::secs(60),
+        grpc_keepalive_timeout: ReadableDuration::secs(3),
+        grpc_max_connection_age: ReadableDuration::hours(2),
+        max_grpc_send_msg_len: ReadableSize::mb(6),
+        max_grpc_recv_msg_len: ReadableSize::mb(6),
         end_point_concurrency: None,
         end_point_max_tasks: None,
         end_point_stack_size: None,
@@ -83,12 +89,18 @@ fn test_serde_custom_tikv_config() {
         end_point_max_concurrency: 10,
         snap_max_write_bytes_per_sec: ReadableSize::mb(10),
         snap_max_total_size: ReadableSize::gb(10),
+        snap_send_bytes_per_sec: ReadableSize::mb(100),
+        snap_recv_bytes_per_sec: ReadableSize::mb(100),
+        snap_stale_file_age: ReadableDuration::hours(2),
         stats_concurrency: 10,
         heavy_load_threshold: 1000,
         heavy_load_wait_duration: ReadableDuration::millis(2),
         enable_request_batch: false,
         request_batch_enable_cross_command: false,
         request_batch_wait_duration: ReadableDuration::millis(10),
+        resolve_cache_ttl: ReadableDuration::secs(30),
+        prefer_lease_read: false,
+        slow_request_threshold: ReadableDuration::millis(500),
     };
     value.readpool = ReadPoolConfig {
         unified: UnifiedReadPoolConfig {
@@ -149,6 +161,8 @@ fn test_serde_custom_tikv_config() {
         region_compact_check_step: 1_234,
         region_compact_min_tombstones: 999,
         region_compact_tombstones_percent: 33,
+        compaction_window_start_hour: 22,
+        compaction_window_end_hour: 6,
         pd_heartbeat_tick_interval: ReadableDuration::minutes(12),
         pd_store_heartbeat_tick_interval: ReadableDuration::secs(12),
         notify_capacity: 12_345,
@@ -533,7 +547,9 @@ fn test_serde_custom_tikv_config() {
         scheduler_concurrency: 123,
         scheduler_worker_pool_size: 1,
         scheduler_pending_write_threshold: ReadableSize::kb(123),
+        scheduler_stale_command_retries: 2,
         reserve_space: ReadableSize::gb(2),
+        enable_ttl: true,
         block_cache: BlockCacheConfig {
             shared: true,
             capacity: Some(ReadableSize::gb(40)),
@@ -564,12 +580,17 @@ fn test_serde_custom_tikv_config() {
     value.import = ImportConfig {
         num_threads: 123,
         stream_channel_window: 123,
+        auto_refresh_region_epoch: false,
     };
     value.panic_when_unexpected_key_or_data = true;
     value.gc = GcConfig {
         ratio_threshold: 1.2,
         batch_keys: 256,
         max_write_bytes_per_sec: ReadableSize::mb(10),
+        max_write_batch_size: ReadableSize::kb(256),
+        max_concurrent_tasks: 4,
+        enable_auto_gc: false,
+        poll_safe_point_interval: ReadableDuration::secs(30),
     };
     value.pessimistic_txn = PessimisticTxnConfig {
         enabled: false,
@@ -20,10 +20,9 @@ use tikv_util::HandyRwLock;
 
 const CLEANUP_SST_MILLIS: u64 = 10;
 
-fn new_cluster() -> (Cluster<ServerCluster>, Context) {
+fn new_cluster(cleanup_interval: Duration) -> (Cluster<ServerCluster>, Context) {
     let count = 1;
     let mut cluster = new_server_cluster(0, count);
-    let cleanup_interval = Duration::from_millis(CLEANUP_SST_MILLIS);
     cluster.cfg.raft_store.cleanup_import_sst_interval.0 = cleanup_interval;
     cluster.run();
 
@@ -40,7 +39,15 @@ fn new_cluster() -> (Cluster<ServerCluster>, Context) {
 
 fn new_cluster_and_tikv_import_client(
 ) -> (Cluster<ServerCluster>, Context, TikvClient, ImportSstClient) {
-    let (cluster, ctx) = new_cluster();
+    new_cluster_and_tikv_import_client_with_cleanup_interval(Duration::from_millis(
+        CLEANUP_SST_MILLIS,
+    ))
+}
+
+fn new_cluster_and_tikv_import_client_with_cleanup_interval(
+    cleanup_interval: Duration,
+) -> (Cluster<ServerCluster>, Context, TikvClient, ImportSstClient) {
+    let (cluster, ctx) = new_cluster(cleanup_interval);
 
     let ch = {
         let env = Arc::new(Environment::new(1));
@@ -125,6 +132,97 @@ fn test_ingest_sst() {
     send_upload_sst(&import, &meta, &data).unwrap();
 }
 
+#[test]
+fn test_delete_range() {
+    let (mut cluster, ctx, tikv, import) = new_cluster_and_tikv_import_client();
+
+    let temp_dir = Builder::new().prefix("test_delete_range").tempdir().unwrap();
+    let sst_path = temp_dir.path().join("test.sst");
+    let sst_range = (0, 100);
+    let (mut meta, data) = gen_sst_file(sst_path, sst_range);
+    meta.set_region_id(ctx.get_region_id());
+    meta.set_region_epoch(ctx.get_region_epoch().clone());
+    send_upload_sst(&import, &meta, &data).unwrap();
+    let mut ingest = IngestRequest::default();
+    ingest.set_context(ctx.clone());
+    ingest.set_sst(meta.clone());
+    let resp = import.ingest(&ingest).unwrap();
+    assert!(!resp.has_error(), "{:?}", resp);
+
+    // Delete a sub-range.
+    let mut delete = DeleteRangeRequest::default();
+    delete.set_context(ctx.clone());
+    delete.set_start_key(vec![20]);
+    delete.set_end_key(vec![50]);
+    let resp = import.delete_range(&delete).unwrap();
+    assert!(!resp.has_error(), "{:?}", resp);
+
+    // Only the deleted sub-range is gone.
+    check_ingested_kvs(&tikv, &ctx, (0, 20));
+    check_ingested_kvs(&tikv, &ctx, (50, 100));
+    for i in 20..50u8 {
+        let mut m = RawGetRequest::default();
+        m.set_context(ctx.clone());
+        m.set_key(vec![i]);
+        let resp = tikv.raw_get(&m).unwrap();
+        assert!(resp.get_error().is_empty());
+        assert!(resp.get_value().is_empty());
+    }
+
+    // A range crossing the region boundary is rejected with a region error.
+    let region = cluster.get_region(&[]);
+    cluster.must_split(&region, &[100]);
+    let region = cluster.get_region(&[100]);
+    let leader = cluster.leader_of_region(region.get_id()).unwrap();
+    let mut right_ctx = Context::default();
+    right_ctx.set_region_id(region.get_id());
+    right_ctx.set_peer(leader);
+    right_ctx.set_region_epoch(region.get_region_epoch().clone());
+    let mut delete = DeleteRangeRequest::default();
+    delete.set_context(right_ctx);
+    delete.set_start_key(vec![0]);
+    delete.set_end_key(vec![50]);
+    let resp = import.delete_range(&delete).unwrap();
+    assert!(resp.has_error(), "{:?}", resp);
+}
+
+#[test]
+fn test_ingest_sst_auto_refresh_epoch() {
+    // Use a long cleanup interval so the stale-epoch SST is not removed
+    // before the server-side retry kicks in.
+    let (mut cluster, mut ctx, tikv, import) =
+        new_cluster_and_tikv_import_client_with_cleanup_interval(Duration::from_secs(600));
+
+    let temp_dir = Builder::new()
+        .prefix("test_ingest_sst_auto_refresh_epoch")
+        .tempdir()
+        .unwrap();
+    let sst_path = temp_dir.path().join("test.sst");
+    let sst_range = (100, 200);
+    let (mut meta, data) = gen_sst_file(sst_path, sst_range);
+    meta.set_region_id(ctx.get_region_id());
+    meta.set_region_epoch(ctx.get_region_epoch().clone());
+    send_upload_sst(&import, &meta, &data).unwrap();
+
+    // Split the region between upload and ingest. The SST range lives
+    // entirely within the derived (right) half, which keeps the region id,
+    // so the server can refresh the epoch and retry on its own.
+    let region = cluster.get_region(&[]);
+    cluster.must_split(&region, &[100]);
+
+    // Both the context and the SST meta still carry the stale epoch, yet the
+    // single server-side retry makes the ingest succeed.
+    let mut ingest = IngestRequest::default();
+    ingest.set_context(ctx.clone());
+    ingest.set_sst(meta.clone());
+    let resp = import.ingest(&ingest).unwrap();
+    assert!(!resp.has_error(), "{:?}", resp);
+
+    // The ingested data is visible with the current epoch.
+    ctx.set_region_epoch(cluster.get_region_epoch(ctx.get_region_id()));
+    check_ingested_kvs(&tikv, &ctx, sst_range);
+}
+
 #[test]
 fn test_download_sst() {
     let (_cluster, ctx, tikv, import) = new_cluster_and_tikv_import_client();
@@ -575,6 +575,47 @@ fn test_txn_store_resolve_lock_in_a_batch() {
     store.scan_locks_ok(30, b"", 100, vec![]);
 }
 
+#[test]
+fn test_txn_store_resolve_lock_batch_reports_count() {
+    let store = AssertionStorage::default();
+
+    for i in 0..5 {
+        let key = format!("rb5_{}", i);
+        store.prewrite_ok(
+            vec![Mutation::Put((
+                Key::from_raw(key.as_bytes()),
+                b"v5".to_vec(),
+            ))],
+            b"rb5_0",
+            5,
+        );
+    }
+    for i in 0..3 {
+        let key = format!("rb10_{}", i);
+        store.prewrite_ok(
+            vec![Mutation::Put((
+                Key::from_raw(key.as_bytes()),
+                b"v10".to_vec(),
+            ))],
+            b"rb10_0",
+            10,
+        );
+    }
+
+    // Commit the first txn and roll back the second; 8 locks are resolved.
+    assert_eq!(store.resolve_lock_batch_ok(5, 20, 10, 0), 8);
+    for i in 0..5 {
+        store.get_ok(format!("rb5_{}", i).as_bytes(), 30, b"v5");
+    }
+    for i in 0..3 {
+        store.get_none(format!("rb10_{}", i).as_bytes(), 30);
+    }
+    store.scan_locks_ok(30, b"", 100, vec![]);
+
+    // Nothing is left to resolve.
+    assert_eq!(store.resolve_lock_batch_ok(5, 20, 10, 0), 0);
+}
+
 #[test]
 fn test_txn_store_resolve_lock2() {
     for &i in &[
@@ -735,6 +776,111 @@ fn test_txn_store_rawkv() {
     store.raw_scan_ok("".to_string(), b"k5".to_vec(), 1, vec![]);
 }
 
+#[test]
+fn test_mvcc_version_count() {
+    let store = AssertionStorage::default();
+    for i in 1..=10u64 {
+        store.put_ok(b"x", format!("v{}", i).as_bytes(), i * 10, i * 10 + 5);
+    }
+    store.put_ok(b"y", b"v", 200, 205);
+
+    // All versions of the key, unbounded.
+    let (count, oldest, newest) = store
+        .store
+        .mvcc_version_count(store.ctx.clone(), Key::from_raw(b"x"), 0, 0)
+        .unwrap();
+    assert_eq!(count, 10);
+    assert_eq!(oldest, 15.into());
+    assert_eq!(newest, 105.into());
+
+    // Only versions committed within [40, 80].
+    let (count, oldest, newest) = store
+        .store
+        .mvcc_version_count(store.ctx.clone(), Key::from_raw(b"x"), 40, 80)
+        .unwrap();
+    assert_eq!(count, 4);
+    assert_eq!(oldest, 45.into());
+    assert_eq!(newest, 75.into());
+
+    // A key without any version in the range.
+    let (count, oldest, newest) = store
+        .store
+        .mvcc_version_count(store.ctx.clone(), Key::from_raw(b"y"), 0, 100)
+        .unwrap();
+    assert_eq!(count, 0);
+    assert!(oldest.is_zero());
+    assert!(newest.is_zero());
+}
+
+#[test]
+fn test_txn_store_rawkv_scan_key_prefix() {
+    let store = AssertionStorage::default();
+    store.raw_put_ok("".to_string(), b"a1".to_vec(), b"v1".to_vec());
+    store.raw_put_ok("".to_string(), b"a2".to_vec(), b"v2".to_vec());
+    store.raw_put_ok("".to_string(), b"b1".to_vec(), b"v3".to_vec());
+    store.raw_put_ok("".to_string(), b"a3".to_vec(), b"v4".to_vec());
+    store.raw_put_ok("".to_string(), b"c1".to_vec(), b"v5".to_vec());
+
+    // Only keys under the prefix are returned, regardless of other keys
+    // interleaved in the range.
+    store.raw_scan_prefix_ok(
+        "".to_string(),
+        b"".to_vec(),
+        10,
+        b"a".to_vec(),
+        vec![(b"a1", b"v1"), (b"a2", b"v2"), (b"a3", b"v4")],
+    );
+    // Filtered-out keys do not count against the limit.
+    store.raw_scan_prefix_ok(
+        "".to_string(),
+        b"a2".to_vec(),
+        10,
+        b"c".to_vec(),
+        vec![(b"c1", b"v5")],
+    );
+    store.raw_scan_prefix_ok(
+        "".to_string(),
+        b"".to_vec(),
+        2,
+        b"a".to_vec(),
+        vec![(b"a1", b"v1"), (b"a2", b"v2")],
+    );
+    store.raw_scan_prefix_ok("".to_string(), b"".to_vec(), 10, b"d".to_vec(), vec![]);
+}
+
+#[test]
+fn test_txn_store_rawkv_reverse_scan() {
+    let store = AssertionStorage::default();
+    store.raw_put_ok("".to_string(), b"k1".to_vec(), b"v1".to_vec());
+    store.raw_put_ok("".to_string(), b"k2".to_vec(), b"v2".to_vec());
+    store.raw_put_ok("".to_string(), b"k3".to_vec(), b"v3".to_vec());
+    store.raw_put_ok("".to_string(), b"k5".to_vec(), b"v5".to_vec());
+
+    // Scans go backwards from the start key, excluding it.
+    store.raw_reverse_scan_ok(
+        "".to_string(),
+        b"k9".to_vec(),
+        10,
+        vec![(b"k5", b"v5"), (b"k3", b"v3"), (b"k2", b"v2"), (b"k1", b"v1")],
+    );
+    store.raw_reverse_scan_ok(
+        "".to_string(),
+        b"k5".to_vec(),
+        10,
+        vec![(b"k3", b"v3"), (b"k2", b"v2"), (b"k1", b"v1")],
+    );
+
+    // The limit cuts the result off at the largest keys.
+    store.raw_reverse_scan_ok(
+        "".to_string(),
+        b"k9".to_vec(),
+        2,
+        vec![(b"k5", b"v5"), (b"k3", b"v3")],
+    );
+    store.raw_reverse_scan_ok("".to_string(), b"k9".to_vec(), 0, vec![]);
+    store.raw_reverse_scan_ok("".to_string(), b"k1".to_vec(), 10, vec![]);
+}
+
 #[test]
 fn test_txn_store_rawkv_cf() {
     let store = AssertionStorage::default();